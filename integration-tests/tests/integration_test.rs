@@ -21,6 +21,8 @@ pub struct OrderConfirmed {
 pub struct LoyaltyDto {
     pub customer_id: String,
     pub current_points: f32,
+    pub pending_points: f32,
+    pub available_points: f32,
     pub transactions: Vec<LoyaltyAccountTransaction>,
 }
 
@@ -40,7 +42,7 @@ async fn when_event_published_points_are_added() {
 
     info!("Customer under test is {}", &customer_under_test);
 
-    produce_event(&customer_under_test, 100.00).await;
+    let order_id = produce_event(&customer_under_test, 100.00).await;
 
     std::thread::sleep(Duration::from_secs(5));
 
@@ -51,7 +53,10 @@ async fn when_event_published_points_are_added() {
     let client = reqwest::Client::new();
 
     let get_points = client
-        .get(format!("{}/loyalty/{}", api_endpoint, customer_under_test))
+        .get(format!(
+            "{}/programmes/default/loyalty/{}",
+            api_endpoint, customer_under_test
+        ))
         .send()
         .await
         .expect("Loyalty Account should exist");
@@ -61,12 +66,29 @@ async fn when_event_published_points_are_added() {
 
     assert_eq!(account.customer_id, customer_under_test);
     assert!(account.current_points > 0.0);
-    assert_eq!(account.transactions.len(), 1);
+
+    // A new account is granted the signup bonus, which can be spent straight away, while the
+    // points earned on the order are pending until the return window has passed
+    assert_eq!(account.transactions.len(), 2);
+    assert!(account
+        .transactions
+        .iter()
+        .any(|transaction| transaction.order_number == "SIGNUP-BONUS"));
+    let earned = account
+        .transactions
+        .iter()
+        .find(|transaction| transaction.order_number == order_id)
+        .expect("Order should have earned points");
+    assert_eq!(account.pending_points, earned.change);
+    assert!(account.available_points >= 5.0);
 
     let spend_points = client
-        .post(format!("{}/loyalty/{}/spend", api_endpoint, customer_under_test))
+        .post(format!(
+            "{}/programmes/default/loyalty/{}/spend",
+            api_endpoint, customer_under_test
+        ))
         .header("Content-Type", "application/json")
-        .body(serde_json::json!({"customerId": customer_under_test, "orderNumber": "ORD999", "spend": 5}).to_string())
+        .body(serde_json::json!({"programmeId": "default", "customerId": customer_under_test, "orderNumber": "ORD999", "spend": 5}).to_string())
         .send()
         .await
         .expect("Spend points should be successful");
@@ -80,10 +102,12 @@ async fn when_event_published_points_are_added() {
     assert!(
         account_after_spend.current_points < account.current_points
     );
-    assert_eq!(account_after_spend.transactions.len(), 2);
+    assert_eq!(account_after_spend.available_points, account.available_points - 5.0);
+    assert_eq!(account_after_spend.pending_points, account.pending_points);
+    assert_eq!(account_after_spend.transactions.len(), 3);
 }
 
-async fn produce_event(customer_under_test: &str, order_value: f32) {
+async fn produce_event(customer_under_test: &str, order_value: f32) -> String {
     let username = std::env::var("KAFKA_USERNAME");
     let password = std::env::var("KAFKA_PASSWORD");
     let broker = std::env::var("BROKER").expect("Broker should be set");
@@ -134,4 +158,6 @@ async fn produce_event(customer_under_test: &str, order_value: f32) {
         Ok(_) => info!("Publish success"),
         Err((e, _)) => tracing::error!("Kafka publish failed: {}", e),
     }
+
+    data.order_id
 }
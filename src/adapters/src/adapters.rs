@@ -1,12 +1,17 @@
+use std::ops::{Deref, DerefMut};
+use std::sync::{Arc, Mutex};
 use std::{env, time::Duration};
 
 use async_trait::async_trait;
 use chrono::DateTime;
 use momento::{cache::GetResponse, CacheClient, CredentialProvider};
-use sqlx::PgPool;
+use sqlx::pool::PoolConnection;
+use sqlx::{Connection, PgConnection, PgPool, Postgres, Transaction};
 use tracing::{info, warn};
 
-use loyalty_core::{LoyaltyAccount, LoyaltyAccountTransaction, LoyaltyErrors, LoyaltyPoints};
+use loyalty_core::{
+    AccountStatus, LoyaltyAccount, LoyaltyAccountTransaction, LoyaltyErrors, LoyaltyPoints,
+};
 
 pub struct ApplicationAdapters<T: LoyaltyPoints + Send + Sync> {
     pub loyalty_points: T,
@@ -22,9 +27,44 @@ impl<T: LoyaltyPoints + Send + Sync> ApplicationAdapters<T> {
 }
 
 pub struct PostgresLoyaltyPoints {
-    db: PgPool,
+    pub(crate) db: PgPool,
     cache_client: Option<CacheClient>,
     cache_name: String,
+    /// Set while every change is made in one transaction, see `begin_message`.
+    pub(crate) transaction: Option<Arc<SharedTransaction>>,
+}
+
+/// A transaction every query runs in, in place of the pool. Accounts written to it are only
+/// cached once it has committed.
+pub(crate) struct SharedTransaction {
+    pub(crate) connection: tokio::sync::Mutex<Option<Transaction<'static, Postgres>>>,
+    pub(crate) written: Mutex<Vec<(String, String)>>,
+}
+
+/// A connection from the pool, or the shared transaction.
+pub(crate) enum DbConnection<'a> {
+    Pooled(PoolConnection<Postgres>),
+    Shared(tokio::sync::MappedMutexGuard<'a, Transaction<'static, Postgres>>),
+}
+
+impl Deref for DbConnection<'_> {
+    type Target = PgConnection;
+
+    fn deref(&self) -> &PgConnection {
+        match self {
+            DbConnection::Pooled(connection) => connection,
+            DbConnection::Shared(transaction) => transaction,
+        }
+    }
+}
+
+impl DerefMut for DbConnection<'_> {
+    fn deref_mut(&mut self) -> &mut PgConnection {
+        match self {
+            DbConnection::Pooled(connection) => connection,
+            DbConnection::Shared(transaction) => transaction,
+        }
+    }
 }
 
 impl PostgresLoyaltyPoints {
@@ -38,9 +78,69 @@ impl PostgresLoyaltyPoints {
             db: database_pool,
             cache_client,
             cache_name,
+            transaction: None,
         })
     }
 
+    /// A copy sharing the pool and cache, so other tasks can use the database without opening
+    /// connections of their own.
+    pub fn shared(&self) -> Self {
+        Self {
+            db: self.db.clone(),
+            cache_client: self.cache_client.clone(),
+            cache_name: self.cache_name.clone(),
+            transaction: None,
+        }
+    }
+
+    /// A copy sharing the pool and cache whose queries all run in `transaction`.
+    pub(crate) fn with_transaction(&self, transaction: Transaction<'static, Postgres>) -> Self {
+        Self {
+            db: self.db.clone(),
+            cache_client: self.cache_client.clone(),
+            cache_name: self.cache_name.clone(),
+            transaction: Some(Arc::new(SharedTransaction {
+                connection: tokio::sync::Mutex::new(Some(transaction)),
+                written: Mutex::new(vec![]),
+            })),
+        }
+    }
+
+    /// The connection to query on, which is the shared transaction if there is one. Only one
+    /// query can hold it at a time, so it must be let go of before calling another adapter.
+    pub(crate) async fn connection(&self) -> Result<DbConnection<'_>, LoyaltyErrors> {
+        let map_err =
+            |e: sqlx::Error| LoyaltyErrors::DatabaseError(format!("Database Error: {:?}", e));
+
+        match &self.transaction {
+            Some(shared) => {
+                let guard = shared.connection.lock().await;
+
+                tokio::sync::MutexGuard::try_map(guard, Option::as_mut)
+                    .map(DbConnection::Shared)
+                    .map_err(|_| {
+                        LoyaltyErrors::DatabaseError("Transaction already finished".to_string())
+                    })
+            }
+            None => self
+                .db
+                .acquire()
+                .await
+                .map(DbConnection::Pooled)
+                .map_err(map_err),
+        }
+    }
+
+    /// Checks the database can be reached, for readiness checks.
+    pub async fn ping(&self) -> Result<(), LoyaltyErrors> {
+        sqlx::query("SELECT 1")
+            .execute(&self.db)
+            .await
+            .map_err(|e| LoyaltyErrors::DatabaseError(e.to_string()))?;
+
+        Ok(())
+    }
+
     async fn configure_cache_client() -> (Option<CacheClient>, String) {
         let key = env::var("MOMENTO_API_KEY");
         let cache_name = env::var("CACHE_NAME").unwrap_or(String::from(""));
@@ -77,12 +177,22 @@ impl PostgresLoyaltyPoints {
         (cache_client, cache_name)
     }
 
+    /// Customer ids are only unique within a programme, so cache entries are keyed by both.
+    fn cache_key(programme_id: &str, customer_id: &str) -> String {
+        format!("{}#{}", programme_id, customer_id)
+    }
+
     #[tracing::instrument(name = "cache_get", skip(self))]
-    async fn cache_get(&self, customer_id: &str) -> Result<String, ()> {
+    async fn cache_get(&self, programme_id: &str, customer_id: &str) -> Result<String, ()> {
+        // The cache only holds what has been committed
+        if self.transaction.is_some() {
+            return Err(());
+        }
+
         match &self.cache_client {
             Some(cache_client) => {
                 let get_result = cache_client
-                    .get(&self.cache_name, customer_id)
+                    .get(&self.cache_name, Self::cache_key(programme_id, customer_id))
                     .await
                     .map_err(|_e| LoyaltyErrors::AccountNotFound());
 
@@ -108,34 +218,70 @@ impl PostgresLoyaltyPoints {
     }
 
     #[tracing::instrument(name = "cache_put", skip(self, account))]
-    async fn cache_put(&self, account: &LoyaltyAccount) {
-        match &self.cache_client {
-            Some(cache_client) => {
-                let cache_data = serde_json::to_string(account).unwrap_or(String::from(""));
+    pub(crate) async fn cache_put(&self, account: &LoyaltyAccount) {
+        if let Some(shared) = &self.transaction {
+            shared.written.lock().unwrap().push((
+                account.programme_id().to_string(),
+                account.customer_id().to_string(),
+            ));
+            return;
+        }
 
-                match cache_client
-                    .set(&self.cache_name, account.customer_id(), cache_data)
-                    .await
-                {
-                    Ok(_) => info!("Successfully cached"),
-                    Err(e) => tracing::error!("Error: {}", e),
-                }
+        if let Some(cache_client) = &self.cache_client {
+            let cache_data = serde_json::to_string(account).unwrap_or(String::from(""));
+
+            match cache_client
+                .set(
+                    &self.cache_name,
+                    Self::cache_key(account.programme_id(), account.customer_id()),
+                    cache_data,
+                )
+                .await
+            {
+                Ok(_) => info!("Successfully cached"),
+                Err(e) => tracing::error!("Error: {}", e),
             }
-            None => {}
-        };
+        }
+    }
+
+    #[tracing::instrument(name = "cache_delete", skip(self))]
+    pub(crate) async fn cache_delete(&self, programme_id: &str, customer_id: &str) {
+        if let Some(shared) = &self.transaction {
+            shared
+                .written
+                .lock()
+                .unwrap()
+                .push((programme_id.to_string(), customer_id.to_string()));
+            return;
+        }
+
+        if let Some(cache_client) = &self.cache_client {
+            match cache_client
+                .delete(&self.cache_name, Self::cache_key(programme_id, customer_id))
+                .await
+            {
+                Ok(_) => info!("Successfully removed from cache"),
+                Err(e) => tracing::error!("Error: {}", e),
+            }
+        }
     }
 
     #[tracing::instrument(name = "db_get", skip(self))]
-    async fn db_get(&self, customer_id: &str) -> anyhow::Result<LoyaltyAccount, LoyaltyErrors> {
+    async fn db_get(
+        &self,
+        programme_id: &str,
+        customer_id: &str,
+    ) -> anyhow::Result<LoyaltyAccount, LoyaltyErrors> {
         let account = sqlx::query!(
             r#"
-            SELECT customer_id, current_points
+            SELECT programme_id, customer_id, current_points, status, merged_into
             FROM loyalty
-            WHERE customer_id = $1
+            WHERE programme_id = $1 AND customer_id = $2
             "#,
+            programme_id,
             customer_id,
         )
-        .fetch_optional(&self.db)
+        .fetch_optional(&mut *self.connection().await?)
         .await;
 
         match account {
@@ -143,13 +289,14 @@ impl PostgresLoyaltyPoints {
                 Some(data) => {
                     let transactions = sqlx::query!(
                         r#"
-                        SELECT customer_id, date_epoch, order_number, change
+                        SELECT customer_id, date_epoch, order_number, change, transfer_id, metadata, vests_epoch, recorded_epoch
                         FROM loyalty_transaction
-                        WHERE customer_id = $1
+                        WHERE programme_id = $1 AND customer_id = $2
                         "#,
+                        programme_id,
                         customer_id,
                     )
-                    .fetch_all(&self.db)
+                    .fetch_all(&mut *self.connection().await?)
                     .await;
 
                     let loyalty_transactions = match transactions {
@@ -161,16 +308,31 @@ impl PostgresLoyaltyPoints {
                                     row.order_number.clone().unwrap(),
                                     row.change.unwrap(),
                                 )
+                                .with_transfer_id(row.transfer_id.clone())
+                                .with_metadata(
+                                    row.metadata
+                                        .as_deref()
+                                        .and_then(|metadata| serde_json::from_str(metadata).ok())
+                                        .unwrap_or_default(),
+                                )
+                                .with_vests_at(
+                                    row.vests_epoch.and_then(DateTime::from_timestamp_millis),
+                                )
+                                .with_recorded_at(
+                                    row.recorded_epoch.and_then(DateTime::from_timestamp_millis),
+                                )
                             })
                             .collect(),
                         Err(_) => vec![],
                     };
 
                     let found_account = LoyaltyAccount::from(
+                        data.programme_id,
                         data.customer_id.unwrap(),
                         data.current_points.unwrap(),
                         loyalty_transactions,
-                    )?;
+                    )?
+                    .with_status(data.status.parse::<AccountStatus>()?, data.merged_into);
 
                     let _ = &self.cache_put(&found_account).await;
 
@@ -186,42 +348,104 @@ impl PostgresLoyaltyPoints {
     }
 }
 
+/// Inserts every transaction in one statement, one row per element of the arrays.
+async fn insert_transactions(
+    connection: &mut PgConnection,
+    account: &LoyaltyAccount,
+    transactions: &[LoyaltyAccountTransaction],
+) -> Result<(), sqlx::Error> {
+    let mut dates = vec![];
+    let mut order_numbers = vec![];
+    let mut changes = vec![];
+    let mut metadata = vec![];
+    let mut vests = vec![];
+    let mut recorded = vec![];
+
+    for transaction in transactions {
+        dates.push(transaction.date().timestamp_millis());
+        order_numbers.push(transaction.order_number());
+        changes.push(transaction.change());
+        metadata.push(match transaction.metadata().is_empty() {
+            true => None,
+            false => serde_json::to_string(transaction.metadata()).ok(),
+        });
+        vests.push(transaction.vests_at().map(|date| date.timestamp_millis()));
+        recorded.push(transaction.recorded_at().timestamp_millis());
+    }
+
+    sqlx::query!(
+        r#"
+    INSERT INTO loyalty_transaction ( programme_id, customer_id, date_epoch, order_number, change, metadata, vests_epoch, recorded_epoch )
+    SELECT $1, $2, *
+    FROM UNNEST( $3::bigint[], $4::text[], $5::real[], $6::text[], $7::bigint[], $8::bigint[] )
+        "#,
+        account.programme_id(),
+        account.customer_id(),
+        &dates,
+        &order_numbers,
+        &changes,
+        &metadata as &[Option<String>],
+        &vests as &[Option<i64>],
+        &recorded
+    )
+    .execute(connection)
+    .await?;
+
+    Ok(())
+}
+
 #[async_trait]
 impl LoyaltyPoints for PostgresLoyaltyPoints {
-    #[tracing::instrument(name = "db_new_account", skip(self))]
+    #[tracing::instrument(name = "db_new_account", skip(self, account, transactions), fields(programme_id=account.programme_id(), customer_id=account.customer_id(), transactions=transactions.len()))]
     async fn new_account(
         &self,
-        customer_id: String,
-    ) -> anyhow::Result<LoyaltyAccount, LoyaltyErrors> {
-        let account = LoyaltyAccount::new(customer_id)?;
+        account: &LoyaltyAccount,
+        transactions: Vec<LoyaltyAccountTransaction>,
+    ) -> anyhow::Result<(), LoyaltyErrors> {
+        let map_err = |e: sqlx::Error| LoyaltyErrors::DatabaseError(format!("Database Error: {:?}", e));
+
+        let mut connection = self.connection().await?;
+        let mut db_transaction = connection.begin().await.map_err(map_err)?;
 
-        let _rec = sqlx::query!(
+        sqlx::query!(
             r#"
-    INSERT INTO loyalty ( customer_id, current_points )
-    VALUES ( $1, $2 )
+    INSERT INTO loyalty ( programme_id, customer_id, current_points )
+    VALUES ( $1, $2, $3 )
             "#,
+            account.programme_id(),
             account.customer_id(),
             account.current_points()
         )
-        .fetch_one(&self.db)
-        .await;
+        .execute(&mut *db_transaction)
+        .await
+        .map_err(map_err)?;
+
+        insert_transactions(&mut db_transaction, account, &transactions)
+            .await
+            .map_err(map_err)?;
+
+        db_transaction.commit().await.map_err(map_err)?;
 
-        Ok(account)
+        Ok(())
     }
 
     #[tracing::instrument(name = "retrieve", skip(self))]
-    async fn retrieve(&self, customer_id: &str) -> anyhow::Result<LoyaltyAccount, LoyaltyErrors> {
+    async fn retrieve(
+        &self,
+        programme_id: &str,
+        customer_id: &str,
+    ) -> anyhow::Result<LoyaltyAccount, LoyaltyErrors> {
         info!("Searching for customer data {}", customer_id);
 
-        let cache_val = &self.cache_get(customer_id).await;
-
-        match cache_val {
-            Ok(account) => {
-                let parsed_account = serde_json::from_str(account).unwrap();
-                Ok(parsed_account)
+        if let Ok(cached) = &self.cache_get(programme_id, customer_id).await {
+            // An entry that can't be parsed is read from the database instead, which replaces it
+            match serde_json::from_str(cached) {
+                Ok(account) => return Ok(account),
+                Err(e) => warn!("Failure parsing cached account, treating as a miss: {:?}", e),
             }
-            Err(_) => self.db_get(customer_id).await,
         }
+
+        self.db_get(programme_id, customer_id).await
     }
 
     #[tracing::instrument(name = "db_add_transaction", skip(self, account, transaction))]
@@ -232,19 +456,32 @@ impl LoyaltyPoints for PostgresLoyaltyPoints {
     ) -> anyhow::Result<(), LoyaltyErrors> {
         info!("Opening DB transaction");
 
-        let db_transaction = self.db.begin().await.unwrap();
+        let mut connection = self.connection().await?;
+        let mut db_transaction = connection
+            .begin()
+            .await
+            .map_err(|e| LoyaltyErrors::DatabaseError(format!("Database Error: {:?}", e)))?;
+
+        let metadata = match transaction.metadata().is_empty() {
+            true => None,
+            false => serde_json::to_string(transaction.metadata()).ok(),
+        };
 
         let insert_res = sqlx::query!(
             r#"
-    INSERT INTO loyalty_transaction ( customer_id, date_epoch, order_number, change )
-    VALUES ( $1, $2, $3, $4 )
+    INSERT INTO loyalty_transaction ( programme_id, customer_id, date_epoch, order_number, change, metadata, vests_epoch, recorded_epoch )
+    VALUES ( $1, $2, $3, $4, $5, $6, $7, $8 )
             "#,
+            account.programme_id(),
             account.customer_id(),
             transaction.date().timestamp_millis(),
             transaction.order_number(),
-            transaction.change()
+            transaction.change(),
+            metadata,
+            transaction.vests_at().map(|date| date.timestamp_millis()),
+            transaction.recorded_at().timestamp_millis()
         )
-        .execute(&self.db)
+        .execute(&mut *db_transaction)
         .await;
 
         if insert_res.is_err() {
@@ -257,33 +494,463 @@ impl LoyaltyPoints for PostgresLoyaltyPoints {
 
         info!("Inserted transaction");
 
+        // Apply the change relative to the stored balance so concurrent writes are not lost. A
+        // debit can't take the balance below zero, unless the account is expected to go negative,
+        // as when a refund claws back points that were already spent
         let update_res = sqlx::query!(
             r#"
     UPDATE loyalty
-    SET current_points = $1
-    WHERE customer_id = $2
+    SET current_points = current_points + $1
+    WHERE programme_id = $2 AND customer_id = $3 AND (current_points + $1 >= 0 OR $4)
             "#,
-            account.current_points(),
-            account.customer_id()
+            transaction.change(),
+            account.programme_id(),
+            account.customer_id(),
+            *account.current_points() < 0.0
         )
-        .execute(&self.db)
+        .execute(&mut *db_transaction)
         .await;
 
-        if update_res.is_err() {
-            let _ = db_transaction.rollback().await;
-            return Err(LoyaltyErrors::DatabaseError(
-                "Failure updating account".to_string(),
-            ));
+        match update_res {
+            Ok(updated) if updated.rows_affected() == 1 => {}
+            Ok(_) => {
+                let _ = db_transaction.rollback().await;
+                return Err(LoyaltyErrors::PointsNotAvailable(format!(
+                    "Current points not enough to cover transaction for {}",
+                    account.customer_id()
+                )));
+            }
+            Err(_) => {
+                let _ = db_transaction.rollback().await;
+                return Err(LoyaltyErrors::DatabaseError(
+                    "Failure updating account".to_string(),
+                ));
+            }
         }
 
         info!("Updated account");
 
-        let _ = db_transaction.commit().await;
+        db_transaction
+            .commit()
+            .await
+            .map_err(|e| LoyaltyErrors::DatabaseError(format!("Database Error: {:?}", e)))?;
 
-        let _ = &self.cache_put(account).await;
+        drop(connection);
+
+        // The stored balance may include concurrent writes the account in memory doesn't
+        let _ = &self
+            .cache_delete(account.programme_id(), account.customer_id())
+            .await;
 
         info!("Committed");
 
         return Ok(());
     }
+
+    #[tracing::instrument(name = "db_add_transactions", skip(self, account, transactions), fields(transactions=transactions.len()))]
+    async fn add_transactions(
+        &self,
+        account: &LoyaltyAccount,
+        transactions: Vec<LoyaltyAccountTransaction>,
+    ) -> anyhow::Result<(), LoyaltyErrors> {
+        let map_err = |e: sqlx::Error| LoyaltyErrors::DatabaseError(format!("Database Error: {:?}", e));
+
+        let mut connection = self.connection().await?;
+        let mut db_transaction = connection.begin().await.map_err(map_err)?;
+
+        insert_transactions(&mut db_transaction, account, &transactions)
+            .await
+            .map_err(map_err)?;
+
+        // Applied relative to the stored balance, as in `add_transaction`
+        let updated = sqlx::query!(
+            r#"
+    UPDATE loyalty
+    SET current_points = current_points + $1
+    WHERE programme_id = $2 AND customer_id = $3 AND (current_points + $1 >= 0 OR $4)
+            "#,
+            transactions.iter().map(|t| t.change()).sum::<f32>(),
+            account.programme_id(),
+            account.customer_id(),
+            *account.current_points() < 0.0
+        )
+        .execute(&mut *db_transaction)
+        .await
+        .map_err(map_err)?;
+
+        if updated.rows_affected() != 1 {
+            let _ = db_transaction.rollback().await;
+
+            return Err(LoyaltyErrors::PointsNotAvailable(format!(
+                "Current points not enough to cover transactions for {}",
+                account.customer_id()
+            )));
+        }
+
+        db_transaction.commit().await.map_err(map_err)?;
+
+        let _ = &self
+            .cache_delete(account.programme_id(), account.customer_id())
+            .await;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "db_transfer", skip(self, source, target, debit, credit), fields(transfer_id=debit.transfer_id()))]
+    async fn transfer(
+        &self,
+        source: &LoyaltyAccount,
+        target: &LoyaltyAccount,
+        debit: LoyaltyAccountTransaction,
+        credit: LoyaltyAccountTransaction,
+    ) -> anyhow::Result<(), LoyaltyErrors> {
+        let map_err = |e: sqlx::Error| LoyaltyErrors::DatabaseError(format!("Database Error: {:?}", e));
+
+        let mut connection = self.connection().await?;
+        let mut db_transaction = connection.begin().await.map_err(map_err)?;
+
+        for (account, transaction) in [(source, &debit), (target, &credit)] {
+            sqlx::query!(
+                r#"
+    INSERT INTO loyalty_transaction ( programme_id, customer_id, date_epoch, order_number, change, transfer_id, recorded_epoch )
+    VALUES ( $1, $2, $3, $4, $5, $6, $7 )
+                "#,
+                account.programme_id(),
+                account.customer_id(),
+                transaction.date().timestamp_millis(),
+                transaction.order_number(),
+                transaction.change(),
+                transaction.transfer_id(),
+                transaction.recorded_at().timestamp_millis()
+            )
+            .execute(&mut *db_transaction)
+            .await
+            .map_err(map_err)?;
+
+            // Apply the change relative to the stored balance so concurrent writes are not lost,
+            // refusing any debit that would take the balance below zero
+            let updated = sqlx::query!(
+                r#"
+    UPDATE loyalty
+    SET current_points = current_points + $1
+    WHERE programme_id = $2 AND customer_id = $3 AND current_points + $1 >= 0
+                "#,
+                transaction.change(),
+                account.programme_id(),
+                account.customer_id()
+            )
+            .execute(&mut *db_transaction)
+            .await
+            .map_err(map_err)?;
+
+            if updated.rows_affected() != 1 {
+                let _ = db_transaction.rollback().await;
+
+                return Err(LoyaltyErrors::PointsNotAvailable(format!(
+                    "Current points not enough to cover transfer for {}",
+                    account.customer_id()
+                )));
+            }
+        }
+
+        db_transaction.commit().await.map_err(map_err)?;
+
+        let _ = &self
+            .cache_delete(source.programme_id(), source.customer_id())
+            .await;
+        let _ = &self
+            .cache_delete(target.programme_id(), target.customer_id())
+            .await;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "db_update_status", skip(self, account))]
+    async fn update_status(&self, account: &LoyaltyAccount) -> anyhow::Result<(), LoyaltyErrors> {
+        sqlx::query!(
+            r#"
+    UPDATE loyalty
+    SET status = $1, merged_into = $2
+    WHERE programme_id = $3 AND customer_id = $4
+            "#,
+            account.status().to_string(),
+            account.merged_into(),
+            account.programme_id(),
+            account.customer_id()
+        )
+        .execute(&mut *self.connection().await?)
+        .await
+        .map_err(|e| LoyaltyErrors::DatabaseError(format!("Database Error: {:?}", e)))?;
+
+        let _ = &self.cache_put(account).await;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "db_merge_accounts", skip(self, source, target), fields(source=source.customer_id(), target=target.customer_id()))]
+    async fn merge_accounts(
+        &self,
+        source: &LoyaltyAccount,
+        target: &LoyaltyAccount,
+    ) -> anyhow::Result<(), LoyaltyErrors> {
+        let map_err = |e: sqlx::Error| LoyaltyErrors::DatabaseError(format!("Database Error: {:?}", e));
+
+        let mut connection = self.connection().await?;
+        let mut db_transaction = connection.begin().await.map_err(map_err)?;
+
+        sqlx::query!(
+            r#"
+    UPDATE loyalty_transaction
+    SET customer_id = $1
+    WHERE programme_id = $2 AND customer_id = $3
+            "#,
+            target.customer_id(),
+            source.programme_id(),
+            source.customer_id()
+        )
+        .execute(&mut *db_transaction)
+        .await
+        .map_err(map_err)?;
+
+        sqlx::query!(
+            r#"
+    UPDATE loyalty
+    SET current_points = $1, status = $2, merged_into = $3
+    WHERE programme_id = $4 AND customer_id = $5
+            "#,
+            source.current_points(),
+            source.status().to_string(),
+            source.merged_into(),
+            source.programme_id(),
+            source.customer_id()
+        )
+        .execute(&mut *db_transaction)
+        .await
+        .map_err(map_err)?;
+
+        sqlx::query!(
+            r#"
+    UPDATE loyalty
+    SET current_points = $1
+    WHERE programme_id = $2 AND customer_id = $3
+            "#,
+            target.current_points(),
+            target.programme_id(),
+            target.customer_id()
+        )
+        .execute(&mut *db_transaction)
+        .await
+        .map_err(map_err)?;
+
+        // Point any earlier tombstones at the new target so redirects never chain
+        sqlx::query!(
+            r#"
+    UPDATE loyalty
+    SET merged_into = $1
+    WHERE programme_id = $2 AND merged_into = $3
+            "#,
+            target.customer_id(),
+            source.programme_id(),
+            source.customer_id()
+        )
+        .execute(&mut *db_transaction)
+        .await
+        .map_err(map_err)?;
+
+        db_transaction.commit().await.map_err(map_err)?;
+
+        let _ = &self.cache_put(source).await;
+        let _ = &self.cache_put(target).await;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "db_erase", skip(self, customer_id, account))]
+    async fn erase(
+        &self,
+        customer_id: &str,
+        account: &LoyaltyAccount,
+    ) -> anyhow::Result<(), LoyaltyErrors> {
+        let map_err = |e: sqlx::Error| LoyaltyErrors::DatabaseError(format!("Database Error: {:?}", e));
+
+        let mut connection = self.connection().await?;
+        let mut db_transaction = connection.begin().await.map_err(map_err)?;
+
+        sqlx::query!(
+            r#"
+    UPDATE loyalty_transaction
+    SET customer_id = $1
+    WHERE programme_id = $2 AND customer_id = $3
+            "#,
+            account.customer_id(),
+            account.programme_id(),
+            customer_id
+        )
+        .execute(&mut *db_transaction)
+        .await
+        .map_err(map_err)?;
+
+        sqlx::query!(
+            r#"
+    UPDATE loyalty
+    SET customer_id = $1, status = $2
+    WHERE programme_id = $3 AND customer_id = $4
+            "#,
+            account.customer_id(),
+            account.status().to_string(),
+            account.programme_id(),
+            customer_id
+        )
+        .execute(&mut *db_transaction)
+        .await
+        .map_err(map_err)?;
+
+        sqlx::query!(
+            r#"
+    UPDATE loyalty
+    SET merged_into = $1
+    WHERE programme_id = $2 AND merged_into = $3
+            "#,
+            account.customer_id(),
+            account.programme_id(),
+            customer_id
+        )
+        .execute(&mut *db_transaction)
+        .await
+        .map_err(map_err)?;
+
+        sqlx::query!(
+            r#"
+    UPDATE loyalty_adjustment
+    SET customer_id = $1
+    WHERE programme_id = $2 AND customer_id = $3
+            "#,
+            account.customer_id(),
+            account.programme_id(),
+            customer_id
+        )
+        .execute(&mut *db_transaction)
+        .await
+        .map_err(map_err)?;
+
+        sqlx::query!(
+            r#"
+    UPDATE loyalty_campaign
+    SET customer_ids = array_replace(customer_ids, $3, $1)
+    WHERE programme_id = $2 AND $3 = ANY(customer_ids)
+            "#,
+            account.customer_id(),
+            account.programme_id(),
+            customer_id
+        )
+        .execute(&mut *db_transaction)
+        .await
+        .map_err(map_err)?;
+
+        sqlx::query!(
+            r#"
+    UPDATE loyalty_redemption
+    SET customer_id = $1
+    WHERE programme_id = $2 AND customer_id = $3
+            "#,
+            account.customer_id(),
+            account.programme_id(),
+            customer_id
+        )
+        .execute(&mut *db_transaction)
+        .await
+        .map_err(map_err)?;
+
+        sqlx::query!(
+            r#"
+    UPDATE loyalty_customer_profile
+    SET customer_id = $1, display_name = '', email = '', birthday = NULL, marketing_opt_in = FALSE
+    WHERE programme_id = $2 AND customer_id = $3
+            "#,
+            account.customer_id(),
+            account.programme_id(),
+            customer_id
+        )
+        .execute(&mut *db_transaction)
+        .await
+        .map_err(map_err)?;
+
+        sqlx::query!(
+            r#"
+    UPDATE loyalty_referral_code
+    SET customer_id = $1
+    WHERE programme_id = $2 AND customer_id = $3
+            "#,
+            account.customer_id(),
+            account.programme_id(),
+            customer_id
+        )
+        .execute(&mut *db_transaction)
+        .await
+        .map_err(map_err)?;
+
+        sqlx::query!(
+            r#"
+    UPDATE loyalty_referral
+    SET referee_id = CASE WHEN referee_id = $3 THEN $1 ELSE referee_id END,
+        referrer_id = CASE WHEN referrer_id = $3 THEN $1 ELSE referrer_id END
+    WHERE programme_id = $2 AND (referee_id = $3 OR referrer_id = $3)
+            "#,
+            account.customer_id(),
+            account.programme_id(),
+            customer_id
+        )
+        .execute(&mut *db_transaction)
+        .await
+        .map_err(map_err)?;
+
+        sqlx::query!(
+            r#"
+    UPDATE loyalty_household_transaction
+    SET customer_id = $1
+    WHERE customer_id = $3
+      AND household_id IN (SELECT household_id FROM loyalty_household WHERE programme_id = $2)
+            "#,
+            account.customer_id(),
+            account.programme_id(),
+            customer_id
+        )
+        .execute(&mut *db_transaction)
+        .await
+        .map_err(map_err)?;
+
+        sqlx::query!(
+            r#"
+    UPDATE loyalty_household_member
+    SET customer_id = $1
+    WHERE programme_id = $2 AND customer_id = $3
+            "#,
+            account.customer_id(),
+            account.programme_id(),
+            customer_id
+        )
+        .execute(&mut *db_transaction)
+        .await
+        .map_err(map_err)?;
+
+        sqlx::query!(
+            r#"
+    UPDATE loyalty_flagged_spend
+    SET customer_id = $1
+    WHERE programme_id = $2 AND customer_id = $3
+            "#,
+            account.customer_id(),
+            account.programme_id(),
+            customer_id
+        )
+        .execute(&mut *db_transaction)
+        .await
+        .map_err(map_err)?;
+
+        db_transaction.commit().await.map_err(map_err)?;
+
+        let _ = &self.cache_delete(account.programme_id(), customer_id).await;
+
+        Ok(())
+    }
 }
\ No newline at end of file
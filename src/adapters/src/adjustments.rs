@@ -0,0 +1,205 @@
+use async_trait::async_trait;
+use chrono::DateTime;
+use loyalty_core::{
+    AdjustmentStatus, LoyaltyAccount, LoyaltyAccountTransaction, LoyaltyErrors, PointsAdjustment,
+    PointsAdjustments, ReasonCode,
+};
+use sqlx::{Connection, Postgres, Transaction};
+use tracing::info;
+
+use crate::PostgresLoyaltyPoints;
+
+struct AdjustmentRow {
+    programme_id: String,
+    adjustment_id: String,
+    customer_id: String,
+    points: f32,
+    reason_code: String,
+    note: String,
+    requested_by: String,
+    status: String,
+    reviewed_by: Option<String>,
+    created_epoch: i64,
+    reviewed_epoch: Option<i64>,
+}
+
+impl TryFrom<AdjustmentRow> for PointsAdjustment {
+    type Error = LoyaltyErrors;
+
+    fn try_from(row: AdjustmentRow) -> Result<Self, Self::Error> {
+        Ok(PointsAdjustment::from(
+            row.programme_id,
+            row.adjustment_id,
+            row.customer_id,
+            row.points,
+            row.reason_code.parse::<ReasonCode>()?,
+            row.note,
+            row.requested_by,
+            row.status.parse::<AdjustmentStatus>()?,
+            row.reviewed_by,
+            DateTime::from_timestamp_millis(row.created_epoch).unwrap(),
+            row.reviewed_epoch.and_then(DateTime::from_timestamp_millis),
+        ))
+    }
+}
+
+fn map_err(e: sqlx::Error) -> LoyaltyErrors {
+    LoyaltyErrors::DatabaseError(format!("Database Error: {:?}", e))
+}
+
+async fn upsert_adjustment(
+    db_transaction: &mut Transaction<'_, Postgres>,
+    adjustment: &PointsAdjustment,
+) -> Result<(), LoyaltyErrors> {
+    sqlx::query!(
+        r#"
+    INSERT INTO loyalty_adjustment ( programme_id, adjustment_id, customer_id, points, reason_code, note, requested_by, status, reviewed_by, created_epoch, reviewed_epoch )
+    VALUES ( $1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11 )
+    ON CONFLICT (adjustment_id) DO UPDATE
+    SET status = EXCLUDED.status, reviewed_by = EXCLUDED.reviewed_by, reviewed_epoch = EXCLUDED.reviewed_epoch
+        "#,
+        adjustment.programme_id(),
+        adjustment.adjustment_id(),
+        adjustment.customer_id(),
+        adjustment.points(),
+        adjustment.reason_code().to_string(),
+        adjustment.note(),
+        adjustment.requested_by(),
+        adjustment.status().to_string(),
+        adjustment.reviewed_by(),
+        adjustment.created_at().timestamp_millis(),
+        adjustment.reviewed_at().map(|date| date.timestamp_millis())
+    )
+    .execute(&mut **db_transaction)
+    .await
+    .map_err(map_err)?;
+
+    Ok(())
+}
+
+#[async_trait]
+impl PointsAdjustments for PostgresLoyaltyPoints {
+    #[tracing::instrument(name = "db_save_adjustment", skip(self, adjustment), fields(adjustment_id=adjustment.adjustment_id()))]
+    async fn save_adjustment(&self, adjustment: &PointsAdjustment) -> Result<(), LoyaltyErrors> {
+        let mut connection = self.connection().await?;
+        let mut db_transaction = connection.begin().await.map_err(map_err)?;
+
+        upsert_adjustment(&mut db_transaction, adjustment).await?;
+
+        db_transaction.commit().await.map_err(map_err)?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "db_retrieve_adjustment", skip(self))]
+    async fn retrieve_adjustment(
+        &self,
+        programme_id: &str,
+        adjustment_id: &str,
+    ) -> Result<PointsAdjustment, LoyaltyErrors> {
+        let row = sqlx::query_as!(
+            AdjustmentRow,
+            r#"
+            SELECT programme_id, adjustment_id, customer_id, points, reason_code, note, requested_by, status, reviewed_by, created_epoch, reviewed_epoch
+            FROM loyalty_adjustment
+            WHERE programme_id = $1 AND adjustment_id = $2
+            "#,
+            programme_id,
+            adjustment_id,
+        )
+        .fetch_optional(&mut *self.connection().await?)
+        .await
+        .map_err(map_err)?;
+
+        match row {
+            Some(row) => row.try_into(),
+            None => Err(LoyaltyErrors::AdjustmentNotFound(format!(
+                "Adjustment {} not found",
+                adjustment_id
+            ))),
+        }
+    }
+
+    #[tracing::instrument(name = "db_list_pending_adjustments", skip(self))]
+    async fn list_pending_adjustments(
+        &self,
+        programme_id: &str,
+    ) -> Result<Vec<PointsAdjustment>, LoyaltyErrors> {
+        let rows = sqlx::query_as!(
+            AdjustmentRow,
+            r#"
+            SELECT programme_id, adjustment_id, customer_id, points, reason_code, note, requested_by, status, reviewed_by, created_epoch, reviewed_epoch
+            FROM loyalty_adjustment
+            WHERE programme_id = $1 AND status = 'Pending'
+            ORDER BY created_epoch
+            "#,
+            programme_id,
+        )
+        .fetch_all(&mut *self.connection().await?)
+        .await
+        .map_err(map_err)?;
+
+        rows.into_iter().map(|row| row.try_into()).collect()
+    }
+
+    #[tracing::instrument(name = "db_apply_adjustment", skip(self, account, transaction, adjustment), fields(adjustment_id=adjustment.adjustment_id()))]
+    async fn apply_adjustment(
+        &self,
+        account: &LoyaltyAccount,
+        transaction: LoyaltyAccountTransaction,
+        adjustment: &PointsAdjustment,
+    ) -> Result<(), LoyaltyErrors> {
+        let mut connection = self.connection().await?;
+        let mut db_transaction = connection.begin().await.map_err(map_err)?;
+
+        upsert_adjustment(&mut db_transaction, adjustment).await?;
+
+        sqlx::query!(
+            r#"
+    INSERT INTO loyalty_transaction ( programme_id, customer_id, date_epoch, order_number, change, recorded_epoch )
+    VALUES ( $1, $2, $3, $4, $5, $6 )
+            "#,
+            account.programme_id(),
+            account.customer_id(),
+            transaction.date().timestamp_millis(),
+            transaction.order_number(),
+            transaction.change(),
+            transaction.recorded_at().timestamp_millis()
+        )
+        .execute(&mut *db_transaction)
+        .await
+        .map_err(map_err)?;
+
+        let updated = sqlx::query!(
+            r#"
+    UPDATE loyalty
+    SET current_points = current_points + $1
+    WHERE programme_id = $2 AND customer_id = $3 AND current_points + $1 >= 0
+            "#,
+            transaction.change(),
+            account.programme_id(),
+            account.customer_id()
+        )
+        .execute(&mut *db_transaction)
+        .await
+        .map_err(map_err)?;
+
+        if updated.rows_affected() != 1 {
+            let _ = db_transaction.rollback().await;
+
+            return Err(LoyaltyErrors::PointsNotAvailable(
+                "Adjustment would take the balance below zero".to_string(),
+            ));
+        }
+
+        db_transaction.commit().await.map_err(map_err)?;
+
+        info!("Applied adjustment");
+
+        let _ = &self
+            .cache_delete(account.programme_id(), account.customer_id())
+            .await;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,208 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use loyalty_core::{Campaign, CampaignEligibility, CampaignReward, Campaigns, LoyaltyErrors};
+
+use crate::PostgresLoyaltyPoints;
+
+struct CampaignRow {
+    programme_id: String,
+    campaign_id: String,
+    name: String,
+    starts_epoch: i64,
+    ends_epoch: i64,
+    min_order_value: Option<f32>,
+    customer_ids: Vec<String>,
+    reward_type: String,
+    reward_value: f32,
+    budget_cap: Option<f32>,
+    points_awarded: f32,
+}
+
+impl TryFrom<CampaignRow> for Campaign {
+    type Error = LoyaltyErrors;
+
+    fn try_from(row: CampaignRow) -> Result<Self, Self::Error> {
+        let reward = match row.reward_type.as_str() {
+            "Multiplier" => CampaignReward::Multiplier(row.reward_value),
+            "FlatBonus" => CampaignReward::FlatBonus(row.reward_value),
+            other => {
+                return Err(LoyaltyErrors::InvalidValues(format!(
+                    "Unknown campaign reward type {}",
+                    other
+                )))
+            }
+        };
+
+        Ok(Campaign::from(
+            row.programme_id,
+            row.campaign_id,
+            row.name,
+            DateTime::from_timestamp_millis(row.starts_epoch).unwrap(),
+            DateTime::from_timestamp_millis(row.ends_epoch).unwrap(),
+            CampaignEligibility {
+                min_order_value: row.min_order_value,
+                customer_ids: row.customer_ids,
+            },
+            reward,
+            row.budget_cap,
+            row.points_awarded,
+        ))
+    }
+}
+
+fn reward_columns(reward: CampaignReward) -> (&'static str, f32) {
+    match reward {
+        CampaignReward::Multiplier(value) => ("Multiplier", value),
+        CampaignReward::FlatBonus(value) => ("FlatBonus", value),
+    }
+}
+
+fn map_err(e: sqlx::Error) -> LoyaltyErrors {
+    LoyaltyErrors::DatabaseError(format!("Database Error: {:?}", e))
+}
+
+#[async_trait]
+impl Campaigns for PostgresLoyaltyPoints {
+    #[tracing::instrument(name = "db_save_campaign", skip(self, campaign), fields(campaign_id=campaign.campaign_id()))]
+    async fn save_campaign(&self, campaign: &Campaign) -> Result<(), LoyaltyErrors> {
+        let (reward_type, reward_value) = reward_columns(campaign.reward());
+
+        // points_awarded is only ever changed by reserve_budget, so an update must not reset it
+        sqlx::query!(
+            r#"
+    INSERT INTO loyalty_campaign ( programme_id, campaign_id, name, starts_epoch, ends_epoch, min_order_value, customer_ids, reward_type, reward_value, budget_cap )
+    VALUES ( $1, $2, $3, $4, $5, $6, $7, $8, $9, $10 )
+    ON CONFLICT (campaign_id) DO UPDATE
+    SET name = EXCLUDED.name, starts_epoch = EXCLUDED.starts_epoch, ends_epoch = EXCLUDED.ends_epoch,
+        min_order_value = EXCLUDED.min_order_value, customer_ids = EXCLUDED.customer_ids,
+        reward_type = EXCLUDED.reward_type, reward_value = EXCLUDED.reward_value, budget_cap = EXCLUDED.budget_cap
+            "#,
+            campaign.programme_id(),
+            campaign.campaign_id(),
+            campaign.name(),
+            campaign.starts_at().timestamp_millis(),
+            campaign.ends_at().timestamp_millis(),
+            campaign.eligibility().min_order_value,
+            &campaign.eligibility().customer_ids,
+            reward_type,
+            reward_value,
+            campaign.budget_cap()
+        )
+        .execute(&mut *self.connection().await?)
+        .await
+        .map_err(map_err)?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "db_retrieve_campaign", skip(self))]
+    async fn retrieve_campaign(
+        &self,
+        programme_id: &str,
+        campaign_id: &str,
+    ) -> Result<Campaign, LoyaltyErrors> {
+        let row = sqlx::query_as!(
+            CampaignRow,
+            r#"
+            SELECT programme_id, campaign_id, name, starts_epoch, ends_epoch, min_order_value, customer_ids, reward_type, reward_value, budget_cap, points_awarded
+            FROM loyalty_campaign
+            WHERE programme_id = $1 AND campaign_id = $2
+            "#,
+            programme_id,
+            campaign_id,
+        )
+        .fetch_optional(&mut *self.connection().await?)
+        .await
+        .map_err(map_err)?;
+
+        match row {
+            Some(row) => row.try_into(),
+            None => Err(LoyaltyErrors::CampaignNotFound(format!(
+                "Campaign {} not found",
+                campaign_id
+            ))),
+        }
+    }
+
+    #[tracing::instrument(name = "db_list_campaigns", skip(self))]
+    async fn list_campaigns(&self, programme_id: &str) -> Result<Vec<Campaign>, LoyaltyErrors> {
+        let rows = sqlx::query_as!(
+            CampaignRow,
+            r#"
+            SELECT programme_id, campaign_id, name, starts_epoch, ends_epoch, min_order_value, customer_ids, reward_type, reward_value, budget_cap, points_awarded
+            FROM loyalty_campaign
+            WHERE programme_id = $1
+            ORDER BY starts_epoch
+            "#,
+            programme_id,
+        )
+        .fetch_all(&mut *self.connection().await?)
+        .await
+        .map_err(map_err)?;
+
+        rows.into_iter().map(|row| row.try_into()).collect()
+    }
+
+    #[tracing::instrument(name = "db_delete_campaign", skip(self))]
+    async fn delete_campaign(
+        &self,
+        programme_id: &str,
+        campaign_id: &str,
+    ) -> Result<(), LoyaltyErrors> {
+        sqlx::query!(
+            r#"
+    DELETE FROM loyalty_campaign
+    WHERE programme_id = $1 AND campaign_id = $2
+            "#,
+            programme_id,
+            campaign_id
+        )
+        .execute(&mut *self.connection().await?)
+        .await
+        .map_err(map_err)?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "db_active_campaigns", skip(self))]
+    async fn active_campaigns(
+        &self,
+        programme_id: &str,
+        at: DateTime<Utc>,
+    ) -> Result<Vec<Campaign>, LoyaltyErrors> {
+        let rows = sqlx::query_as!(
+            CampaignRow,
+            r#"
+            SELECT programme_id, campaign_id, name, starts_epoch, ends_epoch, min_order_value, customer_ids, reward_type, reward_value, budget_cap, points_awarded
+            FROM loyalty_campaign
+            WHERE programme_id = $1 AND starts_epoch <= $2 AND ends_epoch > $2
+            "#,
+            programme_id,
+            at.timestamp_millis(),
+        )
+        .fetch_all(&mut *self.connection().await?)
+        .await
+        .map_err(map_err)?;
+
+        rows.into_iter().map(|row| row.try_into()).collect()
+    }
+
+    #[tracing::instrument(name = "db_reserve_budget", skip(self))]
+    async fn reserve_budget(&self, campaign_id: &str, points: f32) -> Result<bool, LoyaltyErrors> {
+        // Guarding on the cap inside the update keeps concurrent orders from overspending the budget
+        let updated = sqlx::query!(
+            r#"
+    UPDATE loyalty_campaign
+    SET points_awarded = points_awarded + $1
+    WHERE campaign_id = $2 AND (budget_cap IS NULL OR points_awarded + $1 <= budget_cap)
+            "#,
+            points,
+            campaign_id
+        )
+        .execute(&mut *self.connection().await?)
+        .await
+        .map_err(map_err)?;
+
+        Ok(updated.rows_affected() == 1)
+    }
+}
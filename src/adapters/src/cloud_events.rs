@@ -0,0 +1,386 @@
+use std::collections::HashMap;
+
+use chrono::{DateTime, Utc};
+use loyalty_core::{CustomerRegistered, LoyaltyErrors, OrderConfirmed};
+use rdkafka::message::{Headers, Message};
+use serde_json::Value;
+
+use crate::events::CUSTOMER_REGISTERED_TOPIC;
+
+pub const ORDER_CONFIRMED_TYPE: &str = "com.loyalty.order.confirmed";
+pub const CUSTOMER_REGISTERED_TYPE: &str = "com.loyalty.customer.registered";
+
+const SPEC_VERSION: &str = "1.0";
+const STRUCTURED_CONTENT_TYPE: &str = "application/cloudevents+json";
+
+/// The CloudEvents attributes of an incoming message. Legacy bare payloads have no id or source,
+/// and their type is worked out from the topic they arrived on.
+#[derive(Clone, Debug)]
+pub struct EventMetadata {
+    pub id: Option<String>,
+    pub source: Option<String>,
+    pub event_type: String,
+    /// The schema version of `data`, taken from a `.v<n>` suffix on the type.
+    pub version: u32,
+    pub time: Option<DateTime<Utc>>,
+}
+
+pub enum IncomingEvent {
+    OrderConfirmed(OrderConfirmed),
+    CustomerRegistered(CustomerRegistered),
+}
+
+impl IncomingEvent {
+    pub fn programme_id(&self) -> &str {
+        match self {
+            IncomingEvent::OrderConfirmed(evt) => evt.programme_id(),
+            IncomingEvent::CustomerRegistered(evt) => evt.programme_id(),
+        }
+    }
+}
+
+/// Brings `data` written against an older schema up to the next version.
+type Upcaster = fn(Value, &EventMetadata) -> Value;
+
+/// Each schema change of an event type, keyed by the version it upcasts from. They are applied in
+/// order from the version the event was published with up to the version the handlers read.
+const UPCASTERS: &[(&str, u32, Upcaster)] = &[(ORDER_CONFIRMED_TYPE, 1, order_confirmed_v1_to_v2)];
+
+/// Orders published before `occurred_at` was added were placed when the event was raised.
+fn order_confirmed_v1_to_v2(mut data: Value, metadata: &EventMetadata) -> Value {
+    if let (Value::Object(fields), Some(time)) = (&mut data, metadata.time) {
+        fields
+            .entry("occurred_at")
+            .or_insert_with(|| Value::String(time.to_rfc3339()));
+    }
+
+    data
+}
+
+/// Collects the UTF-8 headers of a Kafka message, header names are compared case insensitively.
+pub fn message_headers<M: Message>(message: &M) -> HashMap<String, String> {
+    let Some(headers) = message.headers() else {
+        return HashMap::new();
+    };
+
+    headers
+        .iter()
+        .filter_map(|header| {
+            let value = std::str::from_utf8(header.value?).ok()?;
+            Some((header.key.to_lowercase(), value.to_string()))
+        })
+        .collect()
+}
+
+/// Decodes a message published as a CloudEvents 1.0 structured event, a Kafka binary mode event
+/// with `ce_` headers, or a legacy bare payload, upcasting the data to the current schema. The
+/// body is the payload as decoded by a `PayloadDecoder`.
+pub fn decode_event(
+    topic: &str,
+    headers: &HashMap<String, String>,
+    body: Value,
+) -> Result<(EventMetadata, IncomingEvent), LoyaltyErrors> {
+    let (metadata, data) = if headers.contains_key("ce_specversion") {
+        binary_event(headers, body)?
+    } else if is_structured(headers, &body) {
+        structured_event(body)?
+    } else {
+        legacy_event(topic, body)
+    };
+
+    let data = upcast(&metadata, data);
+
+    let invalid_data = |e: serde_json::Error| {
+        LoyaltyErrors::InvalidValues(format!(
+            "Failure parsing '{}' event data: {}",
+            metadata.event_type, e
+        ))
+    };
+
+    let event = match metadata.event_type.as_str() {
+        ORDER_CONFIRMED_TYPE => {
+            IncomingEvent::OrderConfirmed(serde_json::from_value(data).map_err(invalid_data)?)
+        }
+        CUSTOMER_REGISTERED_TYPE => {
+            IncomingEvent::CustomerRegistered(serde_json::from_value(data).map_err(invalid_data)?)
+        }
+        event_type => {
+            return Err(LoyaltyErrors::InvalidValues(format!(
+                "Unsupported event type '{}'",
+                event_type
+            )))
+        }
+    };
+
+    Ok((metadata, event))
+}
+
+fn is_structured(headers: &HashMap<String, String>, body: &Value) -> bool {
+    headers
+        .get("content-type")
+        .is_some_and(|content_type| content_type.starts_with(STRUCTURED_CONTENT_TYPE))
+        || body.get("specversion").is_some()
+}
+
+fn binary_event(
+    headers: &HashMap<String, String>,
+    body: Value,
+) -> Result<(EventMetadata, Value), LoyaltyErrors> {
+    let attribute = |name: &str| headers.get(&format!("ce_{}", name)).map(String::as_str);
+
+    let metadata = metadata(
+        attribute("specversion"),
+        attribute("type"),
+        attribute("id"),
+        attribute("source"),
+        attribute("time"),
+    )?;
+
+    Ok((metadata, body))
+}
+
+fn structured_event(mut body: Value) -> Result<(EventMetadata, Value), LoyaltyErrors> {
+    let attribute = |name: &str| body.get(name).and_then(Value::as_str);
+
+    let metadata = metadata(
+        attribute("specversion"),
+        attribute("type"),
+        attribute("id"),
+        attribute("source"),
+        attribute("time"),
+    )?;
+
+    let data = match body.get_mut("data") {
+        Some(data) => data.take(),
+        None => {
+            return Err(LoyaltyErrors::InvalidValues(
+                "Structured event has no 'data'".to_string(),
+            ))
+        }
+    };
+
+    Ok((metadata, data))
+}
+
+/// Bare payloads predate the envelope, so are the first version of the type their topic carries.
+fn legacy_event(topic: &str, body: Value) -> (EventMetadata, Value) {
+    let event_type = match topic {
+        CUSTOMER_REGISTERED_TOPIC => CUSTOMER_REGISTERED_TYPE,
+        _ => ORDER_CONFIRMED_TYPE,
+    };
+
+    let metadata = EventMetadata {
+        id: None,
+        source: None,
+        event_type: event_type.to_string(),
+        version: 1,
+        time: None,
+    };
+
+    (metadata, body)
+}
+
+fn metadata(
+    spec_version: Option<&str>,
+    event_type: Option<&str>,
+    id: Option<&str>,
+    source: Option<&str>,
+    time: Option<&str>,
+) -> Result<EventMetadata, LoyaltyErrors> {
+    if spec_version != Some(SPEC_VERSION) {
+        return Err(LoyaltyErrors::InvalidValues(format!(
+            "Unsupported CloudEvents spec version {:?}",
+            spec_version
+        )));
+    }
+
+    let (Some(event_type), Some(id), Some(source)) = (event_type, id, source) else {
+        return Err(LoyaltyErrors::InvalidValues(
+            "CloudEvents require 'type', 'id' and 'source'".to_string(),
+        ));
+    };
+
+    let time = match time {
+        Some(time) => Some(
+            DateTime::parse_from_rfc3339(time)
+                .map_err(|e| LoyaltyErrors::InvalidValues(format!("Invalid event time: {}", e)))?
+                .with_timezone(&Utc),
+        ),
+        None => None,
+    };
+
+    let (event_type, version) = match event_type
+        .rsplit_once(".v")
+        .and_then(|(name, version)| Some((name, version.parse::<u32>().ok()?)))
+    {
+        Some((name, version)) => (name.to_string(), version),
+        None => (event_type.to_string(), 1),
+    };
+
+    Ok(EventMetadata {
+        id: Some(id.to_string()),
+        source: Some(source.to_string()),
+        event_type,
+        version,
+        time,
+    })
+}
+
+fn upcast(metadata: &EventMetadata, mut data: Value) -> Value {
+    for (event_type, from_version, upcaster) in UPCASTERS {
+        if *event_type == metadata.event_type && *from_version >= metadata.version {
+            data = upcaster(data, metadata);
+        }
+    }
+
+    data
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn order_data() -> Value {
+        json!({
+            "customer_id": "james",
+            "order_id": "ORD123",
+            "order_value": 100.0
+        })
+    }
+
+    fn order_fields(event: IncomingEvent) -> Value {
+        match event {
+            IncomingEvent::OrderConfirmed(evt) => serde_json::to_value(evt).unwrap(),
+            IncomingEvent::CustomerRegistered(_) => panic!("Expected an 'OrderConfirmed' event"),
+        }
+    }
+
+    #[test]
+    fn structured_event_is_decoded_from_the_envelope() {
+        let body = json!({
+            "specversion": "1.0",
+            "type": "com.loyalty.order.confirmed.v2",
+            "id": "evt-1",
+            "source": "/orders",
+            "time": "2024-03-01T10:00:00Z",
+            "data": order_data()
+        });
+
+        let (metadata, event) = decode_event("order-completed", &HashMap::new(), body).unwrap();
+
+        assert_eq!(metadata.event_type, ORDER_CONFIRMED_TYPE);
+        assert_eq!(metadata.version, 2);
+        assert_eq!(metadata.id.as_deref(), Some("evt-1"));
+        assert_eq!(order_fields(event)["order_id"], "ORD123");
+    }
+
+    #[test]
+    fn binary_event_is_decoded_from_headers() {
+        let headers = HashMap::from([
+            ("ce_specversion".to_string(), "1.0".to_string()),
+            (
+                "ce_type".to_string(),
+                "com.loyalty.customer.registered".to_string(),
+            ),
+            ("ce_id".to_string(), "evt-2".to_string()),
+            ("ce_source".to_string(), "/customers".to_string()),
+        ]);
+
+        let (metadata, event) = decode_event(
+            "customer-registered",
+            &headers,
+            json!({"customer_id": "james"}),
+        )
+        .unwrap();
+
+        assert_eq!(metadata.event_type, CUSTOMER_REGISTERED_TYPE);
+        assert_eq!(metadata.version, 1);
+        assert_eq!(metadata.source.as_deref(), Some("/customers"));
+        assert!(matches!(event, IncomingEvent::CustomerRegistered(_)));
+    }
+
+    #[test]
+    fn legacy_payload_takes_its_type_from_the_topic() {
+        let (metadata, event) =
+            decode_event("order-completed", &HashMap::new(), order_data()).unwrap();
+
+        assert_eq!(metadata.event_type, ORDER_CONFIRMED_TYPE);
+        assert_eq!(metadata.version, 1);
+        assert!(metadata.id.is_none());
+        assert!(order_fields(event)["occurred_at"].is_null());
+
+        let (metadata, _) = decode_event(
+            "customer-registered",
+            &HashMap::new(),
+            json!({"customer_id": "james"}),
+        )
+        .unwrap();
+
+        assert_eq!(metadata.event_type, CUSTOMER_REGISTERED_TYPE);
+    }
+
+    #[test]
+    fn envelope_without_required_attributes_is_rejected() {
+        let body = json!({
+            "specversion": "1.0",
+            "type": "com.loyalty.order.confirmed",
+            "data": order_data()
+        });
+
+        let result = decode_event("order-completed", &HashMap::new(), body);
+
+        assert!(matches!(result, Err(LoyaltyErrors::InvalidValues(_))));
+    }
+
+    #[test]
+    fn unsupported_spec_version_is_rejected() {
+        let body = json!({
+            "specversion": "0.3",
+            "type": "com.loyalty.order.confirmed",
+            "id": "evt-1",
+            "source": "/orders",
+            "data": order_data()
+        });
+
+        let result = decode_event("order-completed", &HashMap::new(), body);
+
+        assert!(matches!(result, Err(LoyaltyErrors::InvalidValues(_))));
+    }
+
+    #[test]
+    fn v1_order_is_upcast_with_the_event_time() {
+        let body = json!({
+            "specversion": "1.0",
+            "type": "com.loyalty.order.confirmed.v1",
+            "id": "evt-1",
+            "source": "/orders",
+            "time": "2024-03-01T10:00:00Z",
+            "data": order_data()
+        });
+
+        let (_, event) = decode_event("order-completed", &HashMap::new(), body).unwrap();
+        let occurred_at: DateTime<Utc> =
+            serde_json::from_value(order_fields(event)["occurred_at"].clone()).unwrap();
+
+        assert_eq!(occurred_at.to_rfc3339(), "2024-03-01T10:00:00+00:00");
+    }
+
+    #[test]
+    fn order_confirmed_v1_to_v2_keeps_an_existing_occurred_at() {
+        let metadata = EventMetadata {
+            id: None,
+            source: None,
+            event_type: ORDER_CONFIRMED_TYPE.to_string(),
+            version: 1,
+            time: Some("2024-03-01T10:00:00Z".parse().unwrap()),
+        };
+        let mut data = order_data();
+        data["occurred_at"] = json!("2024-02-28T09:00:00Z");
+
+        let upcast = order_confirmed_v1_to_v2(data, &metadata);
+
+        assert_eq!(upcast["occurred_at"], "2024-02-28T09:00:00Z");
+    }
+}
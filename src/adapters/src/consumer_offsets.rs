@@ -0,0 +1,302 @@
+use async_trait::async_trait;
+use loyalty_core::LoyaltyErrors;
+use sqlx::{PgConnection, PgPool};
+
+use crate::PostgresLoyaltyPoints;
+
+fn map_err(e: sqlx::Error) -> LoyaltyErrors {
+    LoyaltyErrors::DatabaseError(format!("Database Error: {:?}", e))
+}
+
+/// Makes every change for a message in one transaction along with its offset, so a message
+/// redelivered after a crash is recognised and its changes are never made twice.
+#[async_trait]
+pub trait MessageTransactions: Sized {
+    /// Adapters making every change in a transaction that records the message as processed by
+    /// `group_id`, or `None` if it already has been.
+    async fn begin_message(
+        &self,
+        group_id: &str,
+        topic: &str,
+        partition: i32,
+        offset: i64,
+    ) -> Result<Option<Self>, LoyaltyErrors>;
+
+    /// Commits the message's changes along with its offset. Dropping the adapters instead rolls
+    /// both back.
+    async fn commit_message(self) -> Result<(), LoyaltyErrors>;
+}
+
+#[async_trait]
+impl MessageTransactions for PostgresLoyaltyPoints {
+    #[tracing::instrument(name = "db_begin_message", skip(self))]
+    async fn begin_message(
+        &self,
+        group_id: &str,
+        topic: &str,
+        partition: i32,
+        offset: i64,
+    ) -> Result<Option<Self>, LoyaltyErrors> {
+        let mut db_transaction = self.db.begin().await.map_err(map_err)?;
+
+        let recorded =
+            record_message(&mut db_transaction, group_id, topic, partition, offset).await?;
+
+        if !recorded {
+            return Ok(None);
+        }
+
+        Ok(Some(self.with_transaction(db_transaction)))
+    }
+
+    #[tracing::instrument(name = "db_commit_message", skip(self))]
+    async fn commit_message(mut self) -> Result<(), LoyaltyErrors> {
+        let Some(shared) = self.transaction.take() else {
+            return Ok(());
+        };
+
+        let db_transaction = shared.connection.lock().await.take();
+
+        if let Some(db_transaction) = db_transaction {
+            db_transaction.commit().await.map_err(map_err)?;
+        }
+
+        // Accounts written in the transaction are refreshed from the database on their next read
+        let written: Vec<_> = shared.written.lock().unwrap().drain(..).collect();
+
+        for (programme_id, customer_id) in written {
+            self.cache_delete(&programme_id, &customer_id).await;
+        }
+
+        Ok(())
+    }
+}
+
+/// Records the message as processed, unless it already was or is behind the partition's stored
+/// position. Returns whether it was recorded.
+async fn record_message(
+    connection: &mut PgConnection,
+    group_id: &str,
+    topic: &str,
+    partition: i32,
+    offset: i64,
+) -> Result<bool, LoyaltyErrors> {
+    let recorded = sqlx::query!(
+        r#"
+    INSERT INTO consumed_message ( group_id, topic, partition, message_offset )
+    SELECT $1::varchar, $2::varchar, $3::int, $4::bigint
+    WHERE NOT EXISTS (
+        SELECT 1 FROM consumer_offset
+        WHERE group_id = $1 AND topic = $2 AND partition = $3 AND next_offset > $4
+    )
+    ON CONFLICT DO NOTHING
+        "#,
+        group_id,
+        topic,
+        partition,
+        offset
+    )
+    .execute(connection)
+    .await
+    .map_err(map_err)?;
+
+    Ok(recorded.rows_affected() == 1)
+}
+
+/// The position each partition has been processed up to, kept in Postgres in place of Kafka's
+/// committed offsets. Messages are recorded as they are processed, the position only moves on
+/// once every message before it has been.
+pub struct PostgresConsumerOffsets {
+    db: PgPool,
+    group_id: String,
+}
+
+impl PostgresConsumerOffsets {
+    pub fn new(database: &PostgresLoyaltyPoints, group_id: &str) -> Self {
+        Self {
+            db: database.db.clone(),
+            group_id: group_id.to_string(),
+        }
+    }
+
+    pub fn group_id(&self) -> &str {
+        &self.group_id
+    }
+
+    /// Records a message that made no changes as processed, such as one that failed and was
+    /// moved to a retry or dead-letter topic.
+    #[tracing::instrument(name = "db_message_processed", skip(self))]
+    pub async fn processed(
+        &self,
+        topic: &str,
+        partition: i32,
+        offset: i64,
+    ) -> Result<(), LoyaltyErrors> {
+        let mut connection = self.db.acquire().await.map_err(map_err)?;
+
+        record_message(&mut connection, &self.group_id, topic, partition, offset).await?;
+
+        Ok(())
+    }
+
+    /// Moves the partition's position on to `next_offset`, forgetting the messages before it.
+    #[tracing::instrument(name = "db_advance_offset", skip(self))]
+    pub async fn advance(
+        &self,
+        topic: &str,
+        partition: i32,
+        next_offset: i64,
+    ) -> Result<(), LoyaltyErrors> {
+        let mut db_transaction = self.db.begin().await.map_err(map_err)?;
+
+        // Positions only ever move forward, a consumer that lost the partition may still be
+        // finishing earlier messages
+        sqlx::query!(
+            r#"
+    INSERT INTO consumer_offset ( group_id, topic, partition, next_offset )
+    VALUES ( $1, $2, $3, $4 )
+    ON CONFLICT (group_id, topic, partition) DO UPDATE
+    SET next_offset = GREATEST(consumer_offset.next_offset, EXCLUDED.next_offset)
+            "#,
+            self.group_id.as_str(),
+            topic,
+            partition,
+            next_offset
+        )
+        .execute(&mut *db_transaction)
+        .await
+        .map_err(map_err)?;
+
+        sqlx::query!(
+            r#"
+    DELETE FROM consumed_message
+    WHERE group_id = $1 AND topic = $2 AND partition = $3 AND message_offset < $4
+            "#,
+            self.group_id.as_str(),
+            topic,
+            partition,
+            next_offset
+        )
+        .execute(&mut *db_transaction)
+        .await
+        .map_err(map_err)?;
+
+        db_transaction.commit().await.map_err(map_err)?;
+
+        Ok(())
+    }
+
+    /// The stored position of each of `partitions` that has one.
+    #[tracing::instrument(name = "db_consumer_positions", skip(self, partitions))]
+    pub async fn positions(
+        &self,
+        partitions: &[(String, i32)],
+    ) -> Result<Vec<(String, i32, i64)>, LoyaltyErrors> {
+        let rows = sqlx::query!(
+            r#"
+    SELECT topic, partition, next_offset
+    FROM consumer_offset
+    WHERE group_id = $1
+            "#,
+            self.group_id.as_str()
+        )
+        .fetch_all(&self.db)
+        .await
+        .map_err(map_err)?;
+
+        Ok(rows
+            .into_iter()
+            .filter(|row| partitions.contains(&(row.topic.clone(), row.partition)))
+            .map(|row| (row.topic, row.partition, row.next_offset))
+            .collect())
+    }
+}
+
+// Needs a database at `DATABASE_URL`, run with `--features database-tests`
+#[cfg(all(test, feature = "database-tests"))]
+mod tests {
+    use super::*;
+
+    fn offsets(pool: PgPool) -> PostgresConsumerOffsets {
+        PostgresConsumerOffsets {
+            db: pool,
+            group_id: "loyalty".to_string(),
+        }
+    }
+
+    #[sqlx::test(migrations = "../core/migrations")]
+    async fn message_is_only_recorded_once(pool: PgPool) {
+        let mut connection = pool.acquire().await.unwrap();
+
+        let first = record_message(&mut connection, "loyalty", "orders", 0, 5)
+            .await
+            .unwrap();
+        let second = record_message(&mut connection, "loyalty", "orders", 0, 5)
+            .await
+            .unwrap();
+        let other_group = record_message(&mut connection, "reporting", "orders", 0, 5)
+            .await
+            .unwrap();
+
+        assert!(first);
+        assert!(!second);
+        assert!(other_group);
+    }
+
+    #[sqlx::test(migrations = "../core/migrations")]
+    async fn message_behind_stored_position_is_not_recorded(pool: PgPool) {
+        let offsets = offsets(pool.clone());
+        offsets.advance("orders", 0, 10).await.unwrap();
+
+        let mut connection = pool.acquire().await.unwrap();
+
+        let behind = record_message(&mut connection, "loyalty", "orders", 0, 9)
+            .await
+            .unwrap();
+        let at_position = record_message(&mut connection, "loyalty", "orders", 0, 10)
+            .await
+            .unwrap();
+
+        assert!(!behind);
+        assert!(at_position);
+    }
+
+    #[sqlx::test(migrations = "../core/migrations")]
+    async fn advance_forgets_messages_before_position(pool: PgPool) {
+        let offsets = offsets(pool.clone());
+
+        offsets.processed("orders", 0, 3).await.unwrap();
+        offsets.processed("orders", 0, 4).await.unwrap();
+        offsets.advance("orders", 0, 4).await.unwrap();
+
+        let remaining: Vec<i64> = sqlx::query_scalar(
+            "SELECT message_offset FROM consumed_message WHERE group_id = 'loyalty'",
+        )
+        .fetch_all(&pool)
+        .await
+        .unwrap();
+
+        assert_eq!(remaining, vec![4]);
+    }
+
+    #[sqlx::test(migrations = "../core/migrations")]
+    async fn positions_only_move_forward(pool: PgPool) {
+        let offsets = offsets(pool);
+
+        offsets.advance("orders", 0, 10).await.unwrap();
+        offsets.advance("orders", 0, 7).await.unwrap();
+        offsets.advance("orders", 1, 3).await.unwrap();
+        offsets.advance("customers", 0, 20).await.unwrap();
+
+        let mut positions = offsets
+            .positions(&[("orders".to_string(), 0), ("orders".to_string(), 1)])
+            .await
+            .unwrap();
+        positions.sort();
+
+        assert_eq!(
+            positions,
+            vec![("orders".to_string(), 0, 10), ("orders".to_string(), 1, 3)]
+        );
+    }
+}
@@ -0,0 +1,360 @@
+use std::{collections::HashMap, env, time::Duration};
+
+use async_trait::async_trait;
+use loyalty_core::{LoyaltyErrors, RewardEvents, RewardRedeemed};
+use rdkafka::{
+    message::{Header, OwnedHeaders},
+    producer::{FutureProducer, FutureRecord},
+};
+use tracing::info;
+
+use crate::kafka_config::configure_kafka_client;
+
+pub const ORDER_COMPLETED_TOPIC: &str = "order-completed";
+pub const CUSTOMER_REGISTERED_TOPIC: &str = "customer-registered";
+
+/// Splits a topic dedicated to one programme, such as `acme.order-completed`, into the programme
+/// and the underlying topic. Topics shared by every programme have no prefix.
+pub fn split_programme_topic(topic: &str) -> (Option<&str>, &str) {
+    match topic.split_once('.') {
+        Some((programme_id, topic)) => (Some(programme_id), topic),
+        None => (None, topic),
+    }
+}
+
+/// Events on a programme's own topic must belong to that programme, anything else is a producer
+/// misconfiguration and is rejected rather than credited to the wrong customers.
+pub fn check_topic_programme(
+    topic_programme: Option<&str>,
+    programme_id: &str,
+) -> Result<(), LoyaltyErrors> {
+    match topic_programme {
+        Some(topic_programme) if topic_programme != programme_id => {
+            Err(LoyaltyErrors::InvalidValues(format!(
+                "Event for programme '{}' received on topic for programme '{}'",
+                programme_id, topic_programme
+            )))
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Why a consumed message could not be handled.
+#[derive(Debug)]
+pub enum HandleError {
+    /// The message can never be processed, such as a payload that does not parse.
+    Invalid(String),
+    /// The message is valid but handling it failed.
+    Failed(String),
+    /// Handling failed in a way that may succeed if the message is tried again later.
+    Transient(String),
+}
+
+impl HandleError {
+    /// Classifies an error returned while handling a valid message.
+    pub fn processing(context: &str, error: &LoyaltyErrors) -> Self {
+        let message = format!("{}: {:?}", context, error);
+
+        if error.is_retryable() {
+            HandleError::Transient(message)
+        } else {
+            HandleError::Failed(message)
+        }
+    }
+
+    pub fn kind(&self) -> &'static str {
+        match self {
+            HandleError::Invalid(_) => "invalid",
+            HandleError::Failed(_) => "processing",
+            HandleError::Transient(_) => "transient",
+        }
+    }
+
+    pub fn message(&self) -> &str {
+        match self {
+            HandleError::Invalid(message)
+            | HandleError::Failed(message)
+            | HandleError::Transient(message) => message,
+        }
+    }
+
+    pub fn is_retryable(&self) -> bool {
+        matches!(self, HandleError::Transient(_))
+    }
+}
+
+/// A consumed message that could not be handled, and where it was read from.
+pub struct DeadLetter<'a> {
+    pub topic: &'a str,
+    pub partition: i32,
+    pub offset: i64,
+    pub key: Option<&'a [u8]>,
+    pub payload: &'a [u8],
+    /// The headers it was consumed with, header names in lower case.
+    pub headers: &'a HashMap<String, String>,
+    pub error: &'a HandleError,
+    /// How many times handling the message was attempted.
+    pub attempts: u32,
+}
+
+impl DeadLetter<'_> {
+    /// The original headers with the `dlq.*` headers appended.
+    fn dlq_headers(&self) -> OwnedHeaders {
+        let partition = self.partition.to_string();
+        let offset = self.offset.to_string();
+        let attempts = self.attempts.to_string();
+
+        let dlq_headers = [
+            ("dlq.original.topic", self.topic),
+            ("dlq.original.partition", &partition),
+            ("dlq.original.offset", &offset),
+            ("dlq.error.kind", self.error.kind()),
+            ("dlq.error.message", self.error.message()),
+            ("dlq.attempts", &attempts),
+        ];
+
+        self.headers
+            .iter()
+            .filter(|(key, _)| !key.starts_with("dlq.") && !key.starts_with("retry."))
+            .map(|(key, value)| (key.as_str(), value.as_str()))
+            .chain(dlq_headers)
+            .fold(OwnedHeaders::new(), |owned, (key, value)| {
+                owned.insert(Header {
+                    key,
+                    value: Some(value),
+                })
+            })
+    }
+}
+
+/// Publishes domain events to Kafka. If `BROKER` is not set every publish fails, so reward events
+/// stay in the outbox and consumed messages are not acknowledged until a broker is configured.
+pub struct KafkaEventPublisher {
+    producer: Option<FutureProducer>,
+    reward_redeemed_topic: String,
+    dead_letter_topic: String,
+}
+
+impl KafkaEventPublisher {
+    pub fn new() -> Result<Self, anyhow::Error> {
+        let reward_redeemed_topic =
+            env::var("REWARD_REDEEMED_TOPIC").unwrap_or("reward-redeemed".to_string());
+        let dead_letter_topic =
+            env::var("DEAD_LETTER_TOPIC").unwrap_or("loyalty-dead-letter".to_string());
+
+        let broker = match env::var("BROKER") {
+            Ok(broker) => broker,
+            Err(_) => {
+                info!("Broker not configured, events will not be published");
+
+                return Ok(Self {
+                    producer: None,
+                    reward_redeemed_topic,
+                    dead_letter_topic,
+                });
+            }
+        };
+
+        Ok(Self {
+            producer: Some(configure_kafka_client(&broker)?.create()?),
+            reward_redeemed_topic,
+            dead_letter_topic,
+        })
+    }
+
+    /// Republishes a message that could not be handled to the dead-letter topic, with the
+    /// original key, payload and headers untouched so it can be replayed once the cause is fixed.
+    /// Where it came from and why it failed are carried in `dlq.*` headers, replacing any from an
+    /// earlier dead-lettering, and the `retry.*` headers are dropped.
+    #[tracing::instrument(name = "publish_dead_letter", skip(self, dead_letter), fields(topic=dead_letter.topic, partition=dead_letter.partition, offset=dead_letter.offset, error_kind=dead_letter.error.kind()))]
+    pub async fn publish_dead_letter(
+        &self,
+        dead_letter: &DeadLetter<'_>,
+    ) -> Result<(), LoyaltyErrors> {
+        self.republish(
+            &self.dead_letter_topic,
+            dead_letter.key,
+            dead_letter.payload,
+            dead_letter.dlq_headers(),
+        )
+        .await
+    }
+
+    /// Publishes a consumed message to another topic unchanged, apart from its headers.
+    pub async fn republish(
+        &self,
+        topic: &str,
+        key: Option<&[u8]>,
+        payload: &[u8],
+        headers: OwnedHeaders,
+    ) -> Result<(), LoyaltyErrors> {
+        let Some(producer) = &self.producer else {
+            return Err(LoyaltyErrors::PublishError(format!(
+                "No broker configured, can't republish message to {}",
+                topic
+            )));
+        };
+
+        let mut record = FutureRecord::to(topic).payload(payload).headers(headers);
+
+        if let Some(key) = key {
+            record = record.key(key);
+        }
+
+        producer
+            .send(record, Duration::from_secs(5))
+            .await
+            .map_err(|(e, _)| {
+                LoyaltyErrors::PublishError(format!("Failure republishing message: {:?}", e))
+            })?;
+
+        Ok(())
+    }
+
+    async fn publish(&self, topic: &str, key: &str, payload: &str) -> Result<(), LoyaltyErrors> {
+        let Some(producer) = &self.producer else {
+            return Err(LoyaltyErrors::PublishError(format!(
+                "No broker configured, can't publish event for {}",
+                key
+            )));
+        };
+
+        producer
+            .send(
+                FutureRecord::to(topic).key(key).payload(payload),
+                Duration::from_secs(5),
+            )
+            .await
+            .map_err(|(e, _)| {
+                LoyaltyErrors::PublishError(format!("Failure publishing event: {:?}", e))
+            })?;
+
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl RewardEvents for KafkaEventPublisher {
+    #[tracing::instrument(name = "publish_reward_redeemed", skip(self, evt), fields(redemption_id=evt.redemption_id))]
+    async fn publish_reward_redeemed(&self, evt: &RewardRedeemed) -> Result<(), LoyaltyErrors> {
+        let payload = serde_json::to_string(evt)
+            .map_err(|e| LoyaltyErrors::InvalidValues(format!("{:?}", e)))?;
+
+        // Keyed on the customer so a customer's redemptions stay ordered on one partition
+        self.publish(&self.reward_redeemed_topic, &evt.customer_id, &payload)
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rdkafka::message::Headers;
+
+    use super::*;
+
+    fn header_values(headers: &OwnedHeaders) -> HashMap<String, String> {
+        headers
+            .iter()
+            .map(|header| {
+                (
+                    header.key.to_string(),
+                    String::from_utf8_lossy(header.value.unwrap_or_default()).to_string(),
+                )
+            })
+            .collect()
+    }
+
+    fn publisher_without_broker() -> KafkaEventPublisher {
+        KafkaEventPublisher {
+            producer: None,
+            reward_redeemed_topic: "reward-redeemed".to_string(),
+            dead_letter_topic: "loyalty-dead-letter".to_string(),
+        }
+    }
+
+    #[test]
+    fn dead_letter_keeps_original_headers() {
+        let headers = HashMap::from([
+            ("ce_type".to_string(), "order.confirmed".to_string()),
+            ("content-type".to_string(), "application/avro".to_string()),
+            ("traceparent".to_string(), "00-abc-def-01".to_string()),
+            ("retry.attempts".to_string(), "2".to_string()),
+            ("dlq.error.kind".to_string(), "stale".to_string()),
+        ]);
+        let error = HandleError::Invalid("Invalid 'OrderConfirmed' event".to_string());
+
+        let dead_letter = DeadLetter {
+            topic: "order-completed",
+            partition: 3,
+            offset: 42,
+            key: None,
+            payload: b"{}",
+            headers: &headers,
+            error: &error,
+            attempts: 1,
+        };
+
+        let values = header_values(&dead_letter.dlq_headers());
+
+        assert_eq!(values["ce_type"], "order.confirmed");
+        assert_eq!(values["content-type"], "application/avro");
+        assert_eq!(values["traceparent"], "00-abc-def-01");
+        assert_eq!(values["dlq.original.topic"], "order-completed");
+        assert_eq!(values["dlq.original.partition"], "3");
+        assert_eq!(values["dlq.original.offset"], "42");
+        assert_eq!(values["dlq.error.kind"], error.kind());
+        assert_eq!(values["dlq.attempts"], "1");
+        assert!(!values.contains_key("retry.attempts"));
+    }
+
+    #[tokio::test]
+    async fn reward_event_fails_to_publish_without_a_broker() {
+        let publisher = publisher_without_broker();
+        let evt = RewardRedeemed {
+            programme_id: "default".to_string(),
+            redemption_id: "redemption".to_string(),
+            customer_id: "james".to_string(),
+            reward_id: "reward".to_string(),
+            reward_name: "Coffee".to_string(),
+            points: 50.0,
+            redeemed_at: chrono::Utc::now(),
+        };
+
+        let result = publisher.publish_reward_redeemed(&evt).await;
+
+        assert!(matches!(result, Err(LoyaltyErrors::PublishError(_))));
+    }
+
+    #[tokio::test]
+    async fn dead_letter_fails_to_publish_without_a_broker() {
+        let publisher = publisher_without_broker();
+        let headers = HashMap::new();
+        let error = HandleError::Invalid("Invalid 'OrderConfirmed' event".to_string());
+
+        let dead_letter = DeadLetter {
+            topic: "order-completed",
+            partition: 0,
+            offset: 7,
+            key: None,
+            payload: b"{}",
+            headers: &headers,
+            error: &error,
+            attempts: 1,
+        };
+
+        let result = publisher.publish_dead_letter(&dead_letter).await;
+
+        assert!(matches!(result, Err(LoyaltyErrors::PublishError(_))));
+    }
+
+    #[tokio::test]
+    async fn retry_fails_to_publish_without_a_broker() {
+        let publisher = publisher_without_broker();
+
+        let result = publisher
+            .republish("loyalty-retry-1m", None, b"{}", OwnedHeaders::new())
+            .await;
+
+        assert!(matches!(result, Err(LoyaltyErrors::PublishError(_))));
+    }
+}
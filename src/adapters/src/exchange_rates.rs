@@ -0,0 +1,125 @@
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+    sync::RwLock,
+    time::SystemTime,
+};
+
+use loyalty_core::{ExchangeRates, LoyaltyErrors, StaticExchangeRates};
+use tracing::{info, warn};
+
+/// Rates come from `EXCHANGE_RATES_FILE` if set, reloaded as the file changes, otherwise from a
+/// JSON object in `EXCHANGE_RATES`. Without either only orders in a programme's base currency earn.
+pub fn configure_exchange_rates() -> Box<dyn ExchangeRates + Send + Sync> {
+    if let Ok(path) = std::env::var("EXCHANGE_RATES_FILE") {
+        return Box::new(FileExchangeRates::new(path).expect("Exchange rates file to be valid"));
+    }
+
+    match std::env::var("EXCHANGE_RATES") {
+        Ok(rates) => Box::new(
+            serde_json::from_str(&rates)
+                .map_err(|e| LoyaltyErrors::InvalidValues(e.to_string()))
+                .and_then(StaticExchangeRates::new)
+                .expect("'EXCHANGE_RATES' to be a JSON object of positive rates"),
+        ),
+        Err(_) => Box::new(StaticExchangeRates::default()),
+    }
+}
+
+struct LoadedRates {
+    modified: Option<SystemTime>,
+    rates: StaticExchangeRates,
+}
+
+/// Exchange rates read from a JSON file mapping currency codes to their value in a common
+/// reference currency, e.g. `{"USD": 1.0, "EUR": 1.08}`. The file is reloaded whenever it
+/// changes so rates can be updated without a redeploy.
+pub struct FileExchangeRates {
+    path: PathBuf,
+    loaded: RwLock<LoadedRates>,
+}
+
+impl FileExchangeRates {
+    pub fn new(path: impl Into<PathBuf>) -> Result<Self, LoyaltyErrors> {
+        let path = path.into();
+        let modified = Self::modified(&path);
+        let rates = Self::load(&path)?;
+
+        Ok(Self {
+            path,
+            loaded: RwLock::new(LoadedRates { modified, rates }),
+        })
+    }
+
+    fn modified(path: &Path) -> Option<SystemTime> {
+        fs::metadata(path)
+            .and_then(|metadata| metadata.modified())
+            .ok()
+    }
+
+    fn load(path: &Path) -> Result<StaticExchangeRates, LoyaltyErrors> {
+        let contents = fs::read_to_string(path).map_err(|e| {
+            LoyaltyErrors::InvalidValues(format!(
+                "Failure reading exchange rates from {}: {}",
+                path.display(),
+                e
+            ))
+        })?;
+
+        let rates: HashMap<String, f32> = serde_json::from_str(&contents).map_err(|e| {
+            LoyaltyErrors::InvalidValues(format!(
+                "Failure parsing exchange rates from {}: {}",
+                path.display(),
+                e
+            ))
+        })?;
+
+        StaticExchangeRates::new(rates)
+    }
+
+    /// Swaps in the file's rates if it has changed since it was last read. A file that fails to
+    /// load leaves the previous rates in place.
+    fn refresh(&self) {
+        let modified = Self::modified(&self.path);
+
+        if self
+            .loaded
+            .read()
+            .map(|loaded| loaded.modified == modified)
+            .unwrap_or(false)
+        {
+            return;
+        }
+
+        let mut loaded = match self.loaded.write() {
+            Ok(loaded) => loaded,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+
+        if loaded.modified == modified {
+            return;
+        }
+
+        match Self::load(&self.path) {
+            Ok(rates) => {
+                info!("Reloaded exchange rates from {}", self.path.display());
+                loaded.rates = rates;
+            }
+            Err(e) => warn!("Keeping previous exchange rates: {:?}", e),
+        }
+
+        loaded.modified = modified;
+    }
+}
+
+impl ExchangeRates for FileExchangeRates {
+    fn rate(&self, from: &str, to: &str) -> Result<f32, LoyaltyErrors> {
+        self.refresh();
+
+        match self.loaded.read() {
+            Ok(loaded) => loaded.rates.rate(from, to),
+            Err(poisoned) => poisoned.into_inner().rates.rate(from, to),
+        }
+    }
+}
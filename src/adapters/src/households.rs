@@ -0,0 +1,351 @@
+use async_trait::async_trait;
+use chrono::DateTime;
+use loyalty_core::{
+    Household, HouseholdMember, HouseholdRole, Households, LoyaltyAccountTransaction,
+    LoyaltyErrors,
+};
+use sqlx::Connection;
+
+use crate::PostgresLoyaltyPoints;
+
+struct HouseholdRow {
+    programme_id: String,
+    household_id: String,
+    name: String,
+    points: f32,
+    created_epoch: i64,
+}
+
+struct HouseholdMemberRow {
+    customer_id: String,
+    role: String,
+    spend_limit: Option<f32>,
+    contributed_points: f32,
+}
+
+impl TryFrom<HouseholdMemberRow> for HouseholdMember {
+    type Error = LoyaltyErrors;
+
+    fn try_from(row: HouseholdMemberRow) -> Result<Self, Self::Error> {
+        Ok(HouseholdMember::from(
+            row.customer_id,
+            row.role.parse::<HouseholdRole>()?,
+            row.spend_limit,
+            row.contributed_points,
+        ))
+    }
+}
+
+fn map_err(e: sqlx::Error) -> LoyaltyErrors {
+    LoyaltyErrors::DatabaseError(format!("Database Error: {:?}", e))
+}
+
+fn is_unique_violation(e: &sqlx::Error) -> bool {
+    e.as_database_error()
+        .is_some_and(|e| e.is_unique_violation())
+}
+
+#[async_trait]
+impl Households for PostgresLoyaltyPoints {
+    #[tracing::instrument(name = "db_create_household", skip(self, household), fields(household_id=household.household_id()))]
+    async fn create_household(&self, household: &Household) -> Result<(), LoyaltyErrors> {
+        let mut connection = self.connection().await?;
+        let mut db_transaction = connection.begin().await.map_err(map_err)?;
+
+        sqlx::query!(
+            r#"
+    INSERT INTO loyalty_household ( programme_id, household_id, name, points, created_epoch )
+    VALUES ( $1, $2, $3, $4, $5 )
+            "#,
+            household.programme_id(),
+            household.household_id(),
+            household.name(),
+            household.points(),
+            household.created_at().timestamp_millis()
+        )
+        .execute(&mut *db_transaction)
+        .await
+        .map_err(map_err)?;
+
+        for member in household.members() {
+            sqlx::query!(
+                r#"
+    INSERT INTO loyalty_household_member ( programme_id, customer_id, household_id, role, spend_limit, contributed_points )
+    VALUES ( $1, $2, $3, $4, $5, $6 )
+                "#,
+                household.programme_id(),
+                member.customer_id(),
+                household.household_id(),
+                member.role().to_string(),
+                member.spend_limit(),
+                member.contributed_points()
+            )
+            .execute(&mut *db_transaction)
+            .await
+            .map_err(|e| match is_unique_violation(&e) {
+                true => LoyaltyErrors::InvalidValues(format!(
+                    "{} already belongs to a household",
+                    member.customer_id()
+                )),
+                false => map_err(e),
+            })?;
+        }
+
+        db_transaction.commit().await.map_err(map_err)?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "db_retrieve_household", skip(self))]
+    async fn retrieve_household(
+        &self,
+        programme_id: &str,
+        household_id: &str,
+    ) -> Result<Household, LoyaltyErrors> {
+        let household = sqlx::query_as!(
+            HouseholdRow,
+            r#"
+            SELECT programme_id, household_id, name, points, created_epoch
+            FROM loyalty_household
+            WHERE programme_id = $1 AND household_id = $2
+            "#,
+            programme_id,
+            household_id
+        )
+        .fetch_optional(&mut *self.connection().await?)
+        .await
+        .map_err(map_err)?
+        .ok_or_else(|| {
+            LoyaltyErrors::HouseholdNotFound(format!("Household {} not found", household_id))
+        })?;
+
+        let members = sqlx::query_as!(
+            HouseholdMemberRow,
+            r#"
+            SELECT customer_id, role, spend_limit, contributed_points
+            FROM loyalty_household_member
+            WHERE household_id = $1
+            ORDER BY customer_id
+            "#,
+            household_id
+        )
+        .fetch_all(&mut *self.connection().await?)
+        .await
+        .map_err(map_err)?
+        .into_iter()
+        .map(|row| row.try_into())
+        .collect::<Result<Vec<HouseholdMember>, LoyaltyErrors>>()?;
+
+        Ok(Household::from(
+            household.programme_id,
+            household.household_id,
+            household.name,
+            household.points,
+            members,
+            DateTime::from_timestamp_millis(household.created_epoch).unwrap(),
+        ))
+    }
+
+    #[tracing::instrument(name = "db_household_for_member", skip(self))]
+    async fn household_for_member(
+        &self,
+        programme_id: &str,
+        customer_id: &str,
+    ) -> Result<Household, LoyaltyErrors> {
+        let row = sqlx::query!(
+            r#"
+            SELECT household_id
+            FROM loyalty_household_member
+            WHERE programme_id = $1 AND customer_id = $2
+            "#,
+            programme_id,
+            customer_id
+        )
+        .fetch_optional(&mut *self.connection().await?)
+        .await
+        .map_err(map_err)?;
+
+        match row {
+            Some(row) => {
+                self.retrieve_household(programme_id, &row.household_id)
+                    .await
+            }
+            None => Err(LoyaltyErrors::HouseholdNotFound(format!(
+                "{} does not belong to a household",
+                customer_id
+            ))),
+        }
+    }
+
+    #[tracing::instrument(name = "db_add_household_member", skip(self, member), fields(customer_id=member.customer_id()))]
+    async fn add_member(
+        &self,
+        household_id: &str,
+        member: &HouseholdMember,
+    ) -> Result<(), LoyaltyErrors> {
+        sqlx::query!(
+            r#"
+    INSERT INTO loyalty_household_member ( programme_id, customer_id, household_id, role, spend_limit, contributed_points )
+    SELECT programme_id, $1, household_id, $2, $3, $4
+    FROM loyalty_household
+    WHERE household_id = $5
+            "#,
+            member.customer_id(),
+            member.role().to_string(),
+            member.spend_limit(),
+            member.contributed_points(),
+            household_id
+        )
+        .execute(&mut *self.connection().await?)
+        .await
+        .map_err(|e| match is_unique_violation(&e) {
+            true => LoyaltyErrors::InvalidValues(format!(
+                "{} already belongs to a household",
+                member.customer_id()
+            )),
+            false => map_err(e),
+        })?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "db_remove_household_member", skip(self))]
+    async fn remove_member(&self, household_id: &str, customer_id: &str) -> Result<(), LoyaltyErrors> {
+        sqlx::query!(
+            r#"
+    DELETE FROM loyalty_household_member
+    WHERE household_id = $1 AND customer_id = $2
+            "#,
+            household_id,
+            customer_id
+        )
+        .execute(&mut *self.connection().await?)
+        .await
+        .map_err(map_err)?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "db_contribute_to_household", skip(self, transaction))]
+    async fn contribute(
+        &self,
+        household_id: &str,
+        customer_id: &str,
+        transaction: LoyaltyAccountTransaction,
+    ) -> Result<bool, LoyaltyErrors> {
+        let mut connection = self.connection().await?;
+        let mut db_transaction = connection.begin().await.map_err(map_err)?;
+
+        let inserted = sqlx::query!(
+            r#"
+    INSERT INTO loyalty_household_transaction ( household_id, customer_id, date_epoch, order_number, change )
+    VALUES ( $1, $2, $3, $4, $5 )
+    ON CONFLICT (household_id, order_number) DO NOTHING
+            "#,
+            household_id,
+            customer_id,
+            transaction.date().timestamp_millis(),
+            transaction.order_number(),
+            transaction.change()
+        )
+        .execute(&mut *db_transaction)
+        .await
+        .map_err(map_err)?;
+
+        if inserted.rows_affected() != 1 {
+            let _ = db_transaction.rollback().await;
+
+            return Ok(false);
+        }
+
+        // Both balances move relative to their stored values so concurrent earns from different
+        // members are never lost
+        sqlx::query!(
+            r#"
+    UPDATE loyalty_household
+    SET points = points + $1
+    WHERE household_id = $2
+            "#,
+            transaction.change(),
+            household_id
+        )
+        .execute(&mut *db_transaction)
+        .await
+        .map_err(map_err)?;
+
+        sqlx::query!(
+            r#"
+    UPDATE loyalty_household_member
+    SET contributed_points = contributed_points + $1
+    WHERE household_id = $2 AND customer_id = $3
+            "#,
+            transaction.change(),
+            household_id,
+            customer_id
+        )
+        .execute(&mut *db_transaction)
+        .await
+        .map_err(map_err)?;
+
+        db_transaction.commit().await.map_err(map_err)?;
+
+        Ok(true)
+    }
+
+    #[tracing::instrument(name = "db_spend_from_household", skip(self, transaction))]
+    async fn spend(
+        &self,
+        household_id: &str,
+        customer_id: &str,
+        transaction: LoyaltyAccountTransaction,
+    ) -> Result<(), LoyaltyErrors> {
+        let mut connection = self.connection().await?;
+        let mut db_transaction = connection.begin().await.map_err(map_err)?;
+
+        sqlx::query!(
+            r#"
+    INSERT INTO loyalty_household_transaction ( household_id, customer_id, date_epoch, order_number, change )
+    VALUES ( $1, $2, $3, $4, $5 )
+            "#,
+            household_id,
+            customer_id,
+            transaction.date().timestamp_millis(),
+            transaction.order_number(),
+            transaction.change()
+        )
+        .execute(&mut *db_transaction)
+        .await
+        .map_err(|e| match is_unique_violation(&e) {
+            true => LoyaltyErrors::TransactionExistsForOrder(format!(
+                "Transaction already exists for order {}",
+                transaction.order_number()
+            )),
+            false => map_err(e),
+        })?;
+
+        let updated = sqlx::query!(
+            r#"
+    UPDATE loyalty_household
+    SET points = points + $1
+    WHERE household_id = $2 AND points + $1 >= 0
+            "#,
+            transaction.change(),
+            household_id
+        )
+        .execute(&mut *db_transaction)
+        .await
+        .map_err(map_err)?;
+
+        if updated.rows_affected() != 1 {
+            let _ = db_transaction.rollback().await;
+
+            return Err(LoyaltyErrors::PointsNotAvailable(
+                "Household points not enough to cover this transaction".to_string(),
+            ));
+        }
+
+        db_transaction.commit().await.map_err(map_err)?;
+
+        Ok(())
+    }
+}
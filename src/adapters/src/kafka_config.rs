@@ -0,0 +1,207 @@
+use std::env;
+
+use anyhow::{anyhow, bail};
+use rdkafka::config::RDKafkaLogLevel;
+use rdkafka::ClientConfig;
+
+use crate::events::{CUSTOMER_REGISTERED_TOPIC, ORDER_COMPLETED_TOPIC};
+
+/// Prefix of environment variables passed straight through to librdkafka, so
+/// `kafka.socket.keepalive.enable=true` sets `socket.keepalive.enable`.
+const PASSTHROUGH_PREFIX: &str = "kafka.";
+
+/// Settings shared by every Kafka client, from the environment.
+///
+/// Security defaults to SASL_SSL when a SASL mechanism or `KAFKA_USERNAME` is set, and to SSL when
+/// only a client certificate is, `KAFKA_SECURITY_PROTOCOL` overrides either.
+///
+/// - `KAFKA_SASL_MECHANISM`: `PLAIN` (the default), `SCRAM-SHA-256`, `SCRAM-SHA-512` or
+///   `OAUTHBEARER`. The first three use `KAFKA_USERNAME` and `KAFKA_PASSWORD`, OAUTHBEARER fetches
+///   tokens from `KAFKA_OAUTH_TOKEN_ENDPOINT` with `KAFKA_OAUTH_CLIENT_ID`,
+///   `KAFKA_OAUTH_CLIENT_SECRET` and an optional `KAFKA_OAUTH_SCOPE`.
+/// - `KAFKA_SSL_CA_LOCATION`, `KAFKA_SSL_CERTIFICATE_LOCATION`, `KAFKA_SSL_KEY_LOCATION` and
+///   `KAFKA_SSL_KEY_PASSWORD` for mutual TLS.
+/// - `KAFKA_LOG_LEVEL`, `info` by default.
+///
+/// Any `kafka.*` variable is then applied as is, overriding everything above.
+pub fn configure_kafka_client(broker: &str) -> Result<ClientConfig, anyhow::Error> {
+    let mut config = base_config(broker)?;
+
+    apply_passthrough(&mut config);
+
+    Ok(config)
+}
+
+/// Client settings plus the consumer group, `KAFKA_AUTO_OFFSET_RESET` for where a group with no
+/// committed offset starts, and `KAFKA_SESSION_TIMEOUT_MS`, `KAFKA_HEARTBEAT_INTERVAL_MS` and
+/// `KAFKA_MAX_POLL_INTERVAL_MS` for how quickly a consumer that has gone away is noticed.
+pub fn configure_kafka_consumer(
+    broker: &str,
+    group_id: &str,
+) -> Result<ClientConfig, anyhow::Error> {
+    let mut config = base_config(broker)?;
+
+    config.set("group.id", group_id);
+
+    for (variable, property) in [
+        ("KAFKA_AUTO_OFFSET_RESET", "auto.offset.reset"),
+        ("KAFKA_SESSION_TIMEOUT_MS", "session.timeout.ms"),
+        ("KAFKA_HEARTBEAT_INTERVAL_MS", "heartbeat.interval.ms"),
+        ("KAFKA_MAX_POLL_INTERVAL_MS", "max.poll.interval.ms"),
+    ] {
+        if let Ok(value) = env::var(variable) {
+            config.set(property, value);
+        }
+    }
+
+    apply_passthrough(&mut config);
+
+    Ok(config)
+}
+
+fn base_config(broker: &str) -> Result<ClientConfig, anyhow::Error> {
+    let mut config = ClientConfig::new();
+
+    config
+        .set("bootstrap.servers", broker)
+        .set_log_level(log_level()?);
+
+    let mechanism = env::var("KAFKA_SASL_MECHANISM")
+        .ok()
+        .or_else(|| env::var("KAFKA_USERNAME").ok().map(|_| "PLAIN".to_string()));
+
+    let certificate = env::var("KAFKA_SSL_CERTIFICATE_LOCATION").ok();
+
+    let protocol = match (
+        env::var("KAFKA_SECURITY_PROTOCOL"),
+        &mechanism,
+        &certificate,
+    ) {
+        (Ok(protocol), _, _) => Some(protocol),
+        (Err(_), Some(_), _) => Some("SASL_SSL".to_string()),
+        (Err(_), None, Some(_)) => Some("SSL".to_string()),
+        (Err(_), None, None) => None,
+    };
+
+    if let Some(protocol) = protocol {
+        config.set("security.protocol", protocol);
+    }
+
+    if let Some(mechanism) = mechanism {
+        configure_sasl(&mut config, &mechanism)?;
+    }
+
+    for (variable, property) in [
+        ("KAFKA_SSL_CA_LOCATION", "ssl.ca.location"),
+        ("KAFKA_SSL_CERTIFICATE_LOCATION", "ssl.certificate.location"),
+        ("KAFKA_SSL_KEY_LOCATION", "ssl.key.location"),
+        ("KAFKA_SSL_KEY_PASSWORD", "ssl.key.password"),
+    ] {
+        if let Ok(value) = env::var(variable) {
+            config.set(property, value);
+        }
+    }
+
+    Ok(config)
+}
+
+fn configure_sasl(config: &mut ClientConfig, mechanism: &str) -> Result<(), anyhow::Error> {
+    let required = |variable: &str| {
+        env::var(variable).map_err(|_| {
+            anyhow!(
+                "'{}' must be set to use the {} SASL mechanism",
+                variable,
+                mechanism
+            )
+        })
+    };
+
+    config.set("sasl.mechanisms", mechanism);
+
+    match mechanism {
+        "PLAIN" | "SCRAM-SHA-256" | "SCRAM-SHA-512" => {
+            config
+                .set("sasl.username", required("KAFKA_USERNAME")?)
+                .set("sasl.password", required("KAFKA_PASSWORD")?);
+        }
+        "OAUTHBEARER" => {
+            config
+                .set("sasl.oauthbearer.method", "oidc")
+                .set(
+                    "sasl.oauthbearer.token.endpoint.url",
+                    required("KAFKA_OAUTH_TOKEN_ENDPOINT")?,
+                )
+                .set(
+                    "sasl.oauthbearer.client.id",
+                    required("KAFKA_OAUTH_CLIENT_ID")?,
+                )
+                .set(
+                    "sasl.oauthbearer.client.secret",
+                    required("KAFKA_OAUTH_CLIENT_SECRET")?,
+                );
+
+            if let Ok(scope) = env::var("KAFKA_OAUTH_SCOPE") {
+                config.set("sasl.oauthbearer.scope", scope);
+            }
+        }
+        other => bail!("Unsupported SASL mechanism '{}'", other),
+    }
+
+    Ok(())
+}
+
+fn log_level() -> Result<RDKafkaLogLevel, anyhow::Error> {
+    let Ok(level) = env::var("KAFKA_LOG_LEVEL") else {
+        return Ok(RDKafkaLogLevel::Info);
+    };
+
+    match level.to_lowercase().as_str() {
+        "emerg" => Ok(RDKafkaLogLevel::Emerg),
+        "alert" => Ok(RDKafkaLogLevel::Alert),
+        "critical" => Ok(RDKafkaLogLevel::Critical),
+        "error" => Ok(RDKafkaLogLevel::Error),
+        "warning" => Ok(RDKafkaLogLevel::Warning),
+        "notice" => Ok(RDKafkaLogLevel::Notice),
+        "info" => Ok(RDKafkaLogLevel::Info),
+        "debug" => Ok(RDKafkaLogLevel::Debug),
+        other => bail!("Unsupported Kafka log level '{}'", other),
+    }
+}
+
+fn apply_passthrough(config: &mut ClientConfig) {
+    for (variable, value) in env::vars() {
+        if let Some(property) = variable.strip_prefix(PASSTHROUGH_PREFIX) {
+            config.set(property, value);
+        }
+    }
+}
+
+/// The topics events are consumed from, `ORDER_COMPLETED_TOPIC` and `CUSTOMER_REGISTERED_TOPIC`
+/// override the defaults.
+#[derive(Clone, Debug)]
+pub struct Topics {
+    pub order_completed: String,
+    pub customer_registered: String,
+}
+
+impl Topics {
+    pub fn from_env() -> Self {
+        Self {
+            order_completed: env::var("ORDER_COMPLETED_TOPIC")
+                .unwrap_or(ORDER_COMPLETED_TOPIC.to_string()),
+            customer_registered: env::var("CUSTOMER_REGISTERED_TOPIC")
+                .unwrap_or(CUSTOMER_REGISTERED_TOPIC.to_string()),
+        }
+    }
+
+    /// The default name of a configured topic, which is what events are decoded by.
+    pub fn canonical<'a>(&self, topic: &'a str) -> &'a str {
+        if topic == self.order_completed {
+            ORDER_COMPLETED_TOPIC
+        } else if topic == self.customer_registered {
+            CUSTOMER_REGISTERED_TOPIC
+        } else {
+            topic
+        }
+    }
+}
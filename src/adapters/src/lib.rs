@@ -1,5 +1,36 @@
 mod adapters;
+mod adjustments;
+mod campaigns;
+mod cloud_events;
+mod consumer_offsets;
+mod events;
+mod exchange_rates;
+mod households;
+mod kafka_config;
 mod observability;
+mod payloads;
+mod profiles;
+mod programmes;
+mod referrals;
+mod rewards;
+mod spend_risk;
+mod trace_context;
 
 pub use adapters::{ApplicationAdapters, PostgresLoyaltyPoints};
-pub use observability::{dd_observability, otlp_observability, use_datadog, log_observability, use_otlp, configure_instrumentation};
\ No newline at end of file
+pub use cloud_events::{
+    decode_event, message_headers, EventMetadata, IncomingEvent, CUSTOMER_REGISTERED_TYPE,
+    ORDER_CONFIRMED_TYPE,
+};
+pub use consumer_offsets::{MessageTransactions, PostgresConsumerOffsets};
+pub use events::{
+    check_topic_programme, split_programme_topic, DeadLetter, HandleError, KafkaEventPublisher,
+    CUSTOMER_REGISTERED_TOPIC, ORDER_COMPLETED_TOPIC,
+};
+pub use exchange_rates::{configure_exchange_rates, FileExchangeRates};
+pub use kafka_config::{configure_kafka_client, configure_kafka_consumer, Topics};
+pub use observability::{dd_observability, otlp_observability, use_datadog, log_observability, use_otlp, configure_instrumentation, shutdown_instrumentation};
+pub use payloads::{
+    configure_payload_decoders, AvroDecoder, JsonDecoder, PayloadDecoder, PayloadDecoders,
+    PayloadFormat, ProtobufDecoder, SchemaRegistry,
+};
+pub use trace_context::{configure_propagation, link_message, message_span, trace_context};
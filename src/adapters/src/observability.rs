@@ -7,7 +7,9 @@ use opentelemetry_sdk::{
     trace::{Config, TracerProvider},
     Resource,
 };
-use std::env;
+use std::sync::mpsc;
+use std::time::Duration;
+use std::{env, thread};
 use tracing::{
     level_filters::LevelFilter,
     subscriber::{set_global_default, SetGlobalDefaultError},
@@ -16,6 +18,8 @@ use tracing::{
 use tracing_bunyan_formatter::{BunyanFormattingLayer, JsonStorageLayer};
 use tracing_subscriber::{layer::SubscriberExt, EnvFilter, Registry};
 
+use crate::trace_context::configure_propagation;
+
 pub fn use_datadog() -> bool {
     env::var("DD_SERVICE").is_ok()
 }
@@ -120,6 +124,8 @@ pub fn configure_instrumentation() -> Option<Result<(), SetGlobalDefaultError>>
 
     let mut subscribe: Option<Result<(), SetGlobalDefaultError>> = None;
 
+    configure_propagation();
+
     if use_otlp() {
         println!("Configuring OTLP");
         let (_, subscriber) = otlp_observability(&service_name);
@@ -135,3 +141,18 @@ pub fn configure_instrumentation() -> Option<Result<(), SetGlobalDefaultError>>
 
     subscribe
 }
+
+/// Flushes any spans still buffered by the exporter, giving up after `timeout` so a collector
+/// that can't be reached doesn't hold up shutdown.
+pub fn shutdown_instrumentation(timeout: Duration) {
+    let (sender, receiver) = mpsc::channel();
+
+    let _ = thread::spawn(move || {
+        global::shutdown_tracer_provider();
+        sender.send(()).ok()
+    });
+
+    if receiver.recv_timeout(timeout).is_err() {
+        tracing::error!("failed to shutdown OpenTelemetry");
+    }
+}
@@ -0,0 +1,651 @@
+use std::collections::HashMap;
+use std::env;
+use std::str::FromStr;
+use std::sync::{Arc, RwLock};
+
+use apache_avro::types::Value as AvroValue;
+use apache_avro::Schema;
+use async_trait::async_trait;
+use chrono::{DateTime, NaiveDate, Utc};
+use prost::Message;
+use serde_json::{json, Map, Value};
+
+use crate::events::{HandleError, CUSTOMER_REGISTERED_TOPIC, ORDER_COMPLETED_TOPIC};
+
+/// How a message payload is serialised.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum PayloadFormat {
+    Json,
+    /// Avro in the Confluent wire format, the writer's schema is fetched from the registry.
+    Avro,
+    /// Protobuf in the Confluent wire format, decoded as the message the topic carries.
+    Protobuf,
+}
+
+impl PayloadFormat {
+    /// The format a `content-type` header names, if it is one we understand.
+    pub fn from_content_type(content_type: &str) -> Option<Self> {
+        let media_type = content_type.split(';').next()?.trim().to_lowercase();
+
+        if media_type.contains("avro") {
+            Some(PayloadFormat::Avro)
+        } else if media_type.contains("protobuf") {
+            Some(PayloadFormat::Protobuf)
+        } else if media_type.contains("json") {
+            Some(PayloadFormat::Json)
+        } else {
+            None
+        }
+    }
+}
+
+impl FromStr for PayloadFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(format: &str) -> Result<Self, Self::Err> {
+        match format.to_lowercase().as_str() {
+            "json" => Ok(PayloadFormat::Json),
+            "avro" => Ok(PayloadFormat::Avro),
+            "protobuf" => Ok(PayloadFormat::Protobuf),
+            other => Err(anyhow::anyhow!("Unsupported payload format '{}'", other)),
+        }
+    }
+}
+
+/// Turns a message payload into the JSON its event envelope and data are read from.
+#[async_trait]
+pub trait PayloadDecoder: Send + Sync {
+    async fn decode(&self, topic: &str, payload: &[u8]) -> Result<Value, HandleError>;
+}
+
+/// The decoders for each payload format, picked by a message's `content-type` header or the
+/// configured default when it has none.
+pub struct PayloadDecoders {
+    default: PayloadFormat,
+    decoders: HashMap<PayloadFormat, Box<dyn PayloadDecoder>>,
+}
+
+impl PayloadDecoders {
+    /// JSON is always understood, other formats are added with `with_decoder`.
+    pub fn new(default: PayloadFormat) -> Self {
+        let mut decoders: HashMap<PayloadFormat, Box<dyn PayloadDecoder>> = HashMap::new();
+        decoders.insert(PayloadFormat::Json, Box::new(JsonDecoder));
+
+        Self { default, decoders }
+    }
+
+    pub fn with_decoder(
+        mut self,
+        format: PayloadFormat,
+        decoder: impl PayloadDecoder + 'static,
+    ) -> Self {
+        self.decoders.insert(format, Box::new(decoder));
+        self
+    }
+
+    pub async fn decode(
+        &self,
+        topic: &str,
+        headers: &HashMap<String, String>,
+        payload: &[u8],
+    ) -> Result<Value, HandleError> {
+        let format = headers
+            .get("content-type")
+            .and_then(|content_type| PayloadFormat::from_content_type(content_type))
+            .unwrap_or(self.default);
+
+        let decoder = self.decoders.get(&format).ok_or_else(|| {
+            HandleError::Invalid(format!("No decoder configured for {:?} payloads", format))
+        })?;
+
+        decoder.decode(topic, payload).await
+    }
+}
+
+impl Default for PayloadDecoders {
+    fn default() -> Self {
+        Self::new(PayloadFormat::Json)
+    }
+}
+
+/// Decoders from `PAYLOAD_FORMAT`, `json` by default, for messages without a `content-type`.
+/// Avro needs `SCHEMA_REGISTRY_URL`, with `SCHEMA_REGISTRY_USERNAME` and
+/// `SCHEMA_REGISTRY_PASSWORD` if the registry requires them.
+pub fn configure_payload_decoders() -> Result<PayloadDecoders, anyhow::Error> {
+    let default = match env::var("PAYLOAD_FORMAT") {
+        Ok(format) => format.parse()?,
+        Err(_) => PayloadFormat::Json,
+    };
+
+    let mut decoders =
+        PayloadDecoders::new(default).with_decoder(PayloadFormat::Protobuf, ProtobufDecoder);
+
+    match env::var("SCHEMA_REGISTRY_URL") {
+        Ok(url) => {
+            let mut registry = SchemaRegistry::new(url);
+
+            if let Ok(username) = env::var("SCHEMA_REGISTRY_USERNAME") {
+                registry =
+                    registry.with_credentials(username, env::var("SCHEMA_REGISTRY_PASSWORD")?);
+            }
+
+            decoders = decoders.with_decoder(PayloadFormat::Avro, AvroDecoder::new(registry));
+        }
+        Err(_) if default == PayloadFormat::Avro => {
+            anyhow::bail!("'SCHEMA_REGISTRY_URL' must be set to decode Avro payloads")
+        }
+        Err(_) => {}
+    }
+
+    Ok(decoders)
+}
+
+pub struct JsonDecoder;
+
+#[async_trait]
+impl PayloadDecoder for JsonDecoder {
+    async fn decode(&self, _topic: &str, payload: &[u8]) -> Result<Value, HandleError> {
+        serde_json::from_slice(payload)
+            .map_err(|e| HandleError::Invalid(format!("Payload is not valid JSON: {}", e)))
+    }
+}
+
+/// Splits a payload in the Confluent wire format, a zero byte and a big-endian schema id ahead of
+/// the encoded message.
+fn split_wire_format(payload: &[u8]) -> Result<(u32, &[u8]), HandleError> {
+    match payload {
+        [0, a, b, c, d, message @ ..] => Ok((u32::from_be_bytes([*a, *b, *c, *d]), message)),
+        _ => Err(HandleError::Invalid(
+            "Payload is not in the schema registry wire format".to_string(),
+        )),
+    }
+}
+
+/// Fetches schemas from a Confluent compatible schema registry by id. Ids never change what they
+/// refer to, so each is fetched once.
+pub struct SchemaRegistry {
+    url: String,
+    client: reqwest::Client,
+    credentials: Option<(String, String)>,
+    schemas: RwLock<HashMap<u32, Arc<Schema>>>,
+}
+
+impl SchemaRegistry {
+    pub fn new(url: impl Into<String>) -> Self {
+        Self {
+            url: url.into().trim_end_matches('/').to_string(),
+            client: reqwest::Client::new(),
+            credentials: None,
+            schemas: RwLock::new(HashMap::new()),
+        }
+    }
+
+    pub fn with_credentials(mut self, username: String, password: String) -> Self {
+        self.credentials = Some((username, password));
+        self
+    }
+
+    /// An unreachable registry is transient, a schema it doesn't have or can't be parsed is not.
+    pub async fn avro_schema(&self, id: u32) -> Result<Arc<Schema>, HandleError> {
+        let cached = self.schemas.read().unwrap().get(&id).cloned();
+
+        if let Some(schema) = cached {
+            return Ok(schema);
+        }
+
+        let mut request = self.client.get(format!("{}/schemas/ids/{}", self.url, id));
+
+        if let Some((username, password)) = &self.credentials {
+            request = request.basic_auth(username, Some(password));
+        }
+
+        let unreachable = |e: reqwest::Error| {
+            HandleError::Transient(format!("Schema registry unreachable: {}", e))
+        };
+
+        let response = request.send().await.map_err(unreachable)?;
+        let status = response.status();
+
+        if status.is_server_error() {
+            return Err(HandleError::Transient(format!(
+                "Schema registry returned {} for schema {}",
+                status, id
+            )));
+        }
+
+        if !status.is_success() {
+            return Err(HandleError::Invalid(format!(
+                "Schema registry returned {} for schema {}",
+                status, id
+            )));
+        }
+
+        let body: Value = serde_json::from_str(&response.text().await.map_err(unreachable)?)
+            .map_err(|e| {
+                HandleError::Invalid(format!("Invalid schema registry response: {}", e))
+            })?;
+
+        let schema = body
+            .get("schema")
+            .and_then(Value::as_str)
+            .ok_or_else(|| HandleError::Invalid(format!("Schema {} has no 'schema'", id)))?;
+
+        let schema = Arc::new(
+            Schema::parse_str(schema)
+                .map_err(|e| HandleError::Invalid(format!("Invalid Avro schema {}: {}", id, e)))?,
+        );
+
+        self.schemas.write().unwrap().insert(id, schema.clone());
+
+        Ok(schema)
+    }
+}
+
+/// Decodes Avro written with the schema the payload's id refers to.
+pub struct AvroDecoder {
+    registry: SchemaRegistry,
+}
+
+impl AvroDecoder {
+    pub fn new(registry: SchemaRegistry) -> Self {
+        Self { registry }
+    }
+}
+
+#[async_trait]
+impl PayloadDecoder for AvroDecoder {
+    async fn decode(&self, _topic: &str, payload: &[u8]) -> Result<Value, HandleError> {
+        let (id, mut datum) = split_wire_format(payload)?;
+        let schema = self.registry.avro_schema(id).await?;
+
+        let value = apache_avro::from_avro_datum(&schema, &mut datum, None)
+            .map_err(|e| HandleError::Invalid(format!("Invalid Avro payload: {}", e)))?;
+
+        avro_to_json(value)
+    }
+}
+
+/// Avro dates count days from 1970-01-01, chrono counts from 0001-01-01.
+const DAYS_FROM_CE_TO_UNIX_EPOCH: i32 = 719_163;
+
+/// Logical types are converted to the formats the JSON events use, so timestamps become RFC 3339
+/// strings and dates `YYYY-MM-DD`.
+fn avro_to_json(value: AvroValue) -> Result<Value, HandleError> {
+    let timestamp = |timestamp: Option<DateTime<Utc>>| {
+        timestamp
+            .map(|timestamp| Value::String(timestamp.to_rfc3339()))
+            .ok_or_else(|| HandleError::Invalid("Avro timestamp out of range".to_string()))
+    };
+
+    match value {
+        AvroValue::Union(_, value) => avro_to_json(*value),
+        AvroValue::Record(fields) => fields
+            .into_iter()
+            .map(|(name, value)| Ok((name, avro_to_json(value)?)))
+            .collect::<Result<Map<_, _>, _>>()
+            .map(Value::Object),
+        AvroValue::Map(entries) => entries
+            .into_iter()
+            .map(|(name, value)| Ok((name, avro_to_json(value)?)))
+            .collect::<Result<Map<_, _>, _>>()
+            .map(Value::Object),
+        AvroValue::Array(items) => items
+            .into_iter()
+            .map(avro_to_json)
+            .collect::<Result<Vec<_>, _>>()
+            .map(Value::Array),
+        AvroValue::Enum(_, symbol) => Ok(Value::String(symbol)),
+        AvroValue::TimestampMillis(millis) => timestamp(DateTime::from_timestamp_millis(millis)),
+        AvroValue::TimestampMicros(micros) => timestamp(DateTime::from_timestamp_micros(micros)),
+        AvroValue::Date(days) => days
+            .checked_add(DAYS_FROM_CE_TO_UNIX_EPOCH)
+            .and_then(NaiveDate::from_num_days_from_ce_opt)
+            .map(|date| Value::String(date.to_string()))
+            .ok_or_else(|| HandleError::Invalid("Avro date out of range".to_string())),
+        value => Value::try_from(value)
+            .map_err(|e| HandleError::Invalid(format!("Unsupported Avro value: {}", e))),
+    }
+}
+
+/// Decodes Protobuf as the message published to the topic, see `proto/events.proto`. The schema
+/// id and message indexes in the wire format are skipped, the topic decides the message.
+pub struct ProtobufDecoder;
+
+#[async_trait]
+impl PayloadDecoder for ProtobufDecoder {
+    async fn decode(&self, topic: &str, payload: &[u8]) -> Result<Value, HandleError> {
+        let (_, mut message) = split_wire_format(payload)?;
+
+        let invalid = |e: prost::DecodeError| {
+            HandleError::Invalid(format!("Invalid Protobuf payload: {}", e))
+        };
+
+        // A count of message indexes, then the indexes, picking the message out of the schema.
+        // They are zigzag encoded and never negative, so halving gives the count
+        let indexes = prost::encoding::decode_varint(&mut message).map_err(invalid)? >> 1;
+
+        for _ in 0..indexes {
+            prost::encoding::decode_varint(&mut message).map_err(invalid)?;
+        }
+
+        match topic {
+            ORDER_COMPLETED_TOPIC => Ok(OrderConfirmedMessage::decode(message)
+                .map_err(invalid)?
+                .into_json()),
+            CUSTOMER_REGISTERED_TOPIC => Ok(CustomerRegisteredMessage::decode(message)
+                .map_err(invalid)?
+                .into_json()),
+            topic => Err(HandleError::Invalid(format!(
+                "No Protobuf message for topic '{}'",
+                topic
+            ))),
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+struct OrderConfirmedMessage {
+    #[prost(string, tag = "1")]
+    programme_id: String,
+    #[prost(string, tag = "2")]
+    customer_id: String,
+    #[prost(string, tag = "3")]
+    order_id: String,
+    #[prost(float, tag = "4")]
+    order_value: f32,
+    #[prost(string, optional, tag = "5")]
+    currency: Option<String>,
+    #[prost(message, optional, tag = "6")]
+    occurred_at: Option<prost_types::Timestamp>,
+}
+
+impl OrderConfirmedMessage {
+    fn into_json(self) -> Value {
+        let mut data = json!({
+            "customer_id": self.customer_id,
+            "order_id": self.order_id,
+            "order_value": self.order_value,
+            "currency": self.currency,
+            "occurred_at": self.occurred_at.and_then(timestamp_to_json),
+        });
+
+        set_programme(&mut data, self.programme_id);
+
+        data
+    }
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+struct CustomerRegisteredMessage {
+    #[prost(string, tag = "1")]
+    programme_id: String,
+    #[prost(string, tag = "2")]
+    customer_id: String,
+    #[prost(string, tag = "3")]
+    display_name: String,
+    #[prost(string, tag = "4")]
+    email: String,
+    /// `YYYY-MM-DD`, empty if the customer didn't give one.
+    #[prost(string, tag = "5")]
+    birthday: String,
+    #[prost(bool, tag = "6")]
+    marketing_opt_in: bool,
+    #[prost(message, optional, tag = "7")]
+    registered_at: Option<prost_types::Timestamp>,
+    #[prost(string, optional, tag = "8")]
+    referral_code: Option<String>,
+}
+
+impl CustomerRegisteredMessage {
+    fn into_json(self) -> Value {
+        let mut data = json!({
+            "customer_id": self.customer_id,
+            "display_name": self.display_name,
+            "email": self.email,
+            "birthday": Some(self.birthday).filter(|birthday| !birthday.is_empty()),
+            "marketing_opt_in": self.marketing_opt_in,
+            "registered_at": self.registered_at.and_then(timestamp_to_json),
+            "referral_code": self.referral_code,
+        });
+
+        set_programme(&mut data, self.programme_id);
+
+        data
+    }
+}
+
+/// Proto3 can't tell an empty string from a missing one, so an empty programme is left out and
+/// the event belongs to the default programme like a JSON event without one.
+fn set_programme(data: &mut Value, programme_id: String) {
+    if let (Value::Object(fields), false) = (data, programme_id.is_empty()) {
+        fields.insert("programme_id".to_string(), Value::String(programme_id));
+    }
+}
+
+fn timestamp_to_json(timestamp: prost_types::Timestamp) -> Option<Value> {
+    let time = DateTime::from_timestamp(timestamp.seconds, timestamp.nanos.try_into().ok()?)?;
+
+    Some(Value::String(time.to_rfc3339()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wiremock::matchers::{method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    const ORDER_SCHEMA: &str = r#"{
+        "type": "record",
+        "name": "OrderConfirmed",
+        "fields": [
+            {"name": "customer_id", "type": "string"},
+            {"name": "order_id", "type": "string"},
+            {"name": "order_value", "type": "float"},
+            {"name": "currency", "type": ["null", "string"], "default": null},
+            {"name": "occurred_at", "type": {"type": "long", "logicalType": "timestamp-millis"}}
+        ]
+    }"#;
+
+    fn framed(id: u32, message: &[u8]) -> Vec<u8> {
+        let mut payload = vec![0];
+        payload.extend_from_slice(&id.to_be_bytes());
+        payload.extend_from_slice(message);
+        payload
+    }
+
+    async fn registry_with_schema(id: u32, schema: &str) -> MockServer {
+        let registry = MockServer::start().await;
+
+        Mock::given(method("GET"))
+            .and(path(format!("/schemas/ids/{}", id)))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({ "schema": schema })))
+            .expect(1)
+            .mount(&registry)
+            .await;
+
+        registry
+    }
+
+    fn avro_order() -> Vec<u8> {
+        let schema = Schema::parse_str(ORDER_SCHEMA).unwrap();
+
+        let order = AvroValue::Record(vec![
+            (
+                "customer_id".to_string(),
+                AvroValue::String("james".to_string()),
+            ),
+            (
+                "order_id".to_string(),
+                AvroValue::String("ORD1".to_string()),
+            ),
+            ("order_value".to_string(), AvroValue::Float(12.5)),
+            (
+                "currency".to_string(),
+                AvroValue::Union(1, Box::new(AvroValue::String("EUR".to_string()))),
+            ),
+            (
+                "occurred_at".to_string(),
+                AvroValue::TimestampMillis(1_700_000_000_000),
+            ),
+        ]);
+
+        apache_avro::to_avro_datum(&schema, order).unwrap()
+    }
+
+    #[tokio::test]
+    async fn avro_is_decoded_with_the_schema_from_the_registry() {
+        let registry = registry_with_schema(7, ORDER_SCHEMA).await;
+        let decoder = AvroDecoder::new(SchemaRegistry::new(registry.uri()));
+
+        let data = decoder
+            .decode(ORDER_COMPLETED_TOPIC, &framed(7, &avro_order()))
+            .await
+            .unwrap();
+
+        assert_eq!(
+            data,
+            json!({
+                "customer_id": "james",
+                "order_id": "ORD1",
+                "order_value": 12.5,
+                "currency": "EUR",
+                "occurred_at": "2023-11-14T22:13:20+00:00",
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn schemas_are_only_fetched_once() {
+        // The mock expects exactly one request, checked when the server is dropped
+        let registry = registry_with_schema(7, ORDER_SCHEMA).await;
+        let decoder = AvroDecoder::new(SchemaRegistry::new(registry.uri()));
+
+        for _ in 0..3 {
+            decoder
+                .decode(ORDER_COMPLETED_TOPIC, &framed(7, &avro_order()))
+                .await
+                .unwrap();
+        }
+    }
+
+    #[tokio::test]
+    async fn unknown_schema_is_invalid() {
+        let registry = MockServer::start().await;
+
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(404))
+            .mount(&registry)
+            .await;
+
+        let decoder = AvroDecoder::new(SchemaRegistry::new(registry.uri()));
+
+        let result = decoder
+            .decode(ORDER_COMPLETED_TOPIC, &framed(9, &avro_order()))
+            .await;
+
+        assert!(matches!(result, Err(HandleError::Invalid(_))));
+    }
+
+    #[tokio::test]
+    async fn registry_outage_is_transient() {
+        let registry = MockServer::start().await;
+
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(503))
+            .mount(&registry)
+            .await;
+
+        let decoder = AvroDecoder::new(SchemaRegistry::new(registry.uri()));
+
+        let result = decoder
+            .decode(ORDER_COMPLETED_TOPIC, &framed(7, &avro_order()))
+            .await;
+
+        assert!(matches!(result, Err(HandleError::Transient(_))));
+    }
+
+    #[tokio::test]
+    async fn payload_without_wire_format_is_invalid() {
+        let decoder = AvroDecoder::new(SchemaRegistry::new("http://localhost:1"));
+
+        let result = decoder.decode(ORDER_COMPLETED_TOPIC, b"{}").await;
+
+        assert!(matches!(result, Err(HandleError::Invalid(_))));
+    }
+
+    #[tokio::test]
+    async fn protobuf_is_decoded_as_the_topic_message() {
+        let order = OrderConfirmedMessage {
+            programme_id: String::new(),
+            customer_id: "james".to_string(),
+            order_id: "ORD1".to_string(),
+            order_value: 12.5,
+            currency: None,
+            occurred_at: Some(prost_types::Timestamp {
+                seconds: 1_700_000_000,
+                nanos: 0,
+            }),
+        };
+
+        // A single zero stands for the first message in the schema
+        let mut message = vec![0];
+        message.extend(order.encode_to_vec());
+
+        let data = ProtobufDecoder
+            .decode(ORDER_COMPLETED_TOPIC, &framed(3, &message))
+            .await
+            .unwrap();
+
+        assert_eq!(
+            data,
+            json!({
+                "customer_id": "james",
+                "order_id": "ORD1",
+                "order_value": 12.5,
+                "currency": null,
+                "occurred_at": "2023-11-14T22:13:20+00:00",
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn content_type_header_picks_the_decoder() {
+        let decoders = PayloadDecoders::new(PayloadFormat::Json)
+            .with_decoder(PayloadFormat::Protobuf, ProtobufDecoder);
+
+        let headers = HashMap::from([(
+            "content-type".to_string(),
+            "application/x-protobuf".to_string(),
+        )]);
+
+        let message = CustomerRegisteredMessage {
+            programme_id: "acme".to_string(),
+            customer_id: "james".to_string(),
+            ..Default::default()
+        };
+
+        let mut payload = vec![0];
+        payload.extend(message.encode_to_vec());
+
+        let data = decoders
+            .decode(CUSTOMER_REGISTERED_TOPIC, &headers, &framed(3, &payload))
+            .await
+            .unwrap();
+
+        assert_eq!(data["programme_id"], "acme");
+        assert_eq!(data["customer_id"], "james");
+    }
+
+    #[tokio::test]
+    async fn format_without_a_decoder_is_invalid() {
+        let decoders = PayloadDecoders::default();
+
+        let headers = HashMap::from([("content-type".to_string(), "avro/binary".to_string())]);
+
+        let result = decoders
+            .decode(ORDER_COMPLETED_TOPIC, &headers, &framed(7, &avro_order()))
+            .await;
+
+        assert!(matches!(result, Err(HandleError::Invalid(_))));
+    }
+}
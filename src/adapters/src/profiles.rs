@@ -0,0 +1,112 @@
+use async_trait::async_trait;
+use chrono::{DateTime, NaiveDate};
+use loyalty_core::{CustomerProfile, CustomerProfiles, LoyaltyErrors};
+
+use crate::PostgresLoyaltyPoints;
+
+struct CustomerProfileRow {
+    programme_id: String,
+    customer_id: String,
+    display_name: String,
+    email: String,
+    birthday: Option<NaiveDate>,
+    marketing_opt_in: bool,
+    joined_epoch: i64,
+}
+
+impl From<CustomerProfileRow> for CustomerProfile {
+    fn from(row: CustomerProfileRow) -> Self {
+        CustomerProfile::from(
+            row.programme_id,
+            row.customer_id,
+            row.display_name,
+            row.email,
+            row.birthday,
+            row.marketing_opt_in,
+            DateTime::from_timestamp_millis(row.joined_epoch).unwrap(),
+        )
+    }
+}
+
+fn map_err(e: sqlx::Error) -> LoyaltyErrors {
+    LoyaltyErrors::DatabaseError(format!("Database Error: {:?}", e))
+}
+
+#[async_trait]
+impl CustomerProfiles for PostgresLoyaltyPoints {
+    #[tracing::instrument(name = "db_save_profile", skip(self, profile), fields(customer_id=profile.customer_id()))]
+    async fn save_profile(&self, profile: &CustomerProfile) -> Result<(), LoyaltyErrors> {
+        sqlx::query!(
+            r#"
+    INSERT INTO loyalty_customer_profile ( programme_id, customer_id, display_name, email, birthday, marketing_opt_in, joined_epoch )
+    VALUES ( $1, $2, $3, $4, $5, $6, $7 )
+    ON CONFLICT (programme_id, customer_id) DO UPDATE
+    SET display_name = EXCLUDED.display_name, email = EXCLUDED.email, birthday = EXCLUDED.birthday, marketing_opt_in = EXCLUDED.marketing_opt_in
+            "#,
+            profile.programme_id(),
+            profile.customer_id(),
+            profile.display_name(),
+            profile.email(),
+            profile.birthday() as Option<NaiveDate>,
+            profile.marketing_opt_in(),
+            profile.joined_at().timestamp_millis()
+        )
+        .execute(&mut *self.connection().await?)
+        .await
+        .map_err(map_err)?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "db_retrieve_profile", skip(self))]
+    async fn retrieve_profile(
+        &self,
+        programme_id: &str,
+        customer_id: &str,
+    ) -> Result<CustomerProfile, LoyaltyErrors> {
+        let row = sqlx::query_as!(
+            CustomerProfileRow,
+            r#"
+            SELECT programme_id, customer_id, display_name, email, birthday as "birthday: NaiveDate", marketing_opt_in, joined_epoch
+            FROM loyalty_customer_profile
+            WHERE programme_id = $1 AND customer_id = $2
+            "#,
+            programme_id,
+            customer_id,
+        )
+        .fetch_optional(&mut *self.connection().await?)
+        .await
+        .map_err(map_err)?;
+
+        match row {
+            Some(row) => Ok(row.into()),
+            None => Err(LoyaltyErrors::ProfileNotFound(format!(
+                "Profile for {} not found",
+                customer_id
+            ))),
+        }
+    }
+
+    #[tracing::instrument(name = "db_profiles_with_birthday", skip(self))]
+    async fn profiles_with_birthday(
+        &self,
+        month: u32,
+        day: u32,
+    ) -> Result<Vec<CustomerProfile>, LoyaltyErrors> {
+        let rows = sqlx::query_as!(
+            CustomerProfileRow,
+            r#"
+            SELECT programme_id, customer_id, display_name, email, birthday as "birthday: NaiveDate", marketing_opt_in, joined_epoch
+            FROM loyalty_customer_profile
+            WHERE EXTRACT(MONTH FROM birthday)::int = $1 AND EXTRACT(DAY FROM birthday)::int = $2
+            "#,
+            month as i32,
+            day as i32,
+        )
+        .fetch_all(&mut *self.connection().await?)
+        .await
+        .map_err(map_err)?;
+
+        Ok(rows.into_iter().map(|row| row.into()).collect())
+    }
+}
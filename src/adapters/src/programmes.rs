@@ -0,0 +1,95 @@
+use async_trait::async_trait;
+use chrono::DateTime;
+use loyalty_core::{LoyaltyErrors, Programme, Programmes};
+
+use crate::PostgresLoyaltyPoints;
+
+struct ProgrammeRow {
+    programme_id: String,
+    name: String,
+    earn_rate: f32,
+    base_currency: String,
+    created_epoch: i64,
+}
+
+impl From<ProgrammeRow> for Programme {
+    fn from(row: ProgrammeRow) -> Self {
+        Programme::from(
+            row.programme_id,
+            row.name,
+            row.earn_rate,
+            row.base_currency,
+            DateTime::from_timestamp_millis(row.created_epoch).unwrap(),
+        )
+    }
+}
+
+fn map_err(e: sqlx::Error) -> LoyaltyErrors {
+    LoyaltyErrors::DatabaseError(format!("Database Error: {:?}", e))
+}
+
+#[async_trait]
+impl Programmes for PostgresLoyaltyPoints {
+    #[tracing::instrument(name = "db_save_programme", skip(self, programme), fields(programme_id=programme.programme_id()))]
+    async fn save_programme(&self, programme: &Programme) -> Result<(), LoyaltyErrors> {
+        sqlx::query!(
+            r#"
+    INSERT INTO loyalty_programme ( programme_id, name, earn_rate, base_currency, created_epoch )
+    VALUES ( $1, $2, $3, $4, $5 )
+    ON CONFLICT (programme_id) DO UPDATE
+    SET name = EXCLUDED.name, earn_rate = EXCLUDED.earn_rate
+            "#,
+            programme.programme_id(),
+            programme.name(),
+            programme.earn_rate(),
+            programme.base_currency(),
+            programme.created_at().timestamp_millis()
+        )
+        .execute(&mut *self.connection().await?)
+        .await
+        .map_err(map_err)?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "db_retrieve_programme", skip(self))]
+    async fn retrieve_programme(&self, programme_id: &str) -> Result<Programme, LoyaltyErrors> {
+        let row = sqlx::query_as!(
+            ProgrammeRow,
+            r#"
+            SELECT programme_id, name, earn_rate, base_currency, created_epoch
+            FROM loyalty_programme
+            WHERE programme_id = $1
+            "#,
+            programme_id,
+        )
+        .fetch_optional(&mut *self.connection().await?)
+        .await
+        .map_err(map_err)?;
+
+        match row {
+            Some(row) => Ok(row.into()),
+            None => Err(LoyaltyErrors::ProgrammeNotFound(format!(
+                "Programme {} not found",
+                programme_id
+            ))),
+        }
+    }
+
+    #[tracing::instrument(name = "db_list_programmes", skip(self))]
+    async fn list_programmes(&self) -> Result<Vec<Programme>, LoyaltyErrors> {
+        let rows = sqlx::query_as!(
+            ProgrammeRow,
+            r#"
+            SELECT programme_id, name, earn_rate, base_currency, created_epoch
+            FROM loyalty_programme
+            ORDER BY programme_id
+            "#,
+        )
+        .fetch_all(&mut *self.connection().await?)
+        .await
+        .map_err(map_err)?;
+
+        Ok(rows.into_iter().map(|row| row.into()).collect())
+    }
+}
@@ -0,0 +1,272 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use loyalty_core::{
+    LoyaltyAccount, LoyaltyErrors, Referral, ReferralBonuses, ReferralStatus, Referrals,
+};
+use sqlx::Connection;
+
+use crate::PostgresLoyaltyPoints;
+
+struct ReferralRow {
+    programme_id: String,
+    referrer_id: String,
+    referee_id: String,
+    referral_code: String,
+    status: String,
+    created_epoch: i64,
+    rewarded_epoch: Option<i64>,
+}
+
+impl TryFrom<ReferralRow> for Referral {
+    type Error = LoyaltyErrors;
+
+    fn try_from(row: ReferralRow) -> Result<Self, Self::Error> {
+        Ok(Referral::from(
+            row.programme_id,
+            row.referrer_id,
+            row.referee_id,
+            row.referral_code,
+            row.status.parse::<ReferralStatus>()?,
+            DateTime::from_timestamp_millis(row.created_epoch).unwrap(),
+            row.rewarded_epoch.and_then(DateTime::from_timestamp_millis),
+        ))
+    }
+}
+
+fn map_err(e: sqlx::Error) -> LoyaltyErrors {
+    LoyaltyErrors::DatabaseError(format!("Database Error: {:?}", e))
+}
+
+#[async_trait]
+impl Referrals for PostgresLoyaltyPoints {
+    #[tracing::instrument(name = "db_assign_referral_code", skip(self))]
+    async fn assign_referral_code(
+        &self,
+        programme_id: &str,
+        customer_id: &str,
+        referral_code: &str,
+    ) -> Result<String, LoyaltyErrors> {
+        sqlx::query!(
+            r#"
+    INSERT INTO loyalty_referral_code ( programme_id, customer_id, referral_code )
+    VALUES ( $1, $2, $3 )
+    ON CONFLICT (programme_id, customer_id) DO NOTHING
+            "#,
+            programme_id,
+            customer_id,
+            referral_code
+        )
+        .execute(&mut *self.connection().await?)
+        .await
+        .map_err(map_err)?;
+
+        let row = sqlx::query!(
+            r#"
+            SELECT referral_code
+            FROM loyalty_referral_code
+            WHERE programme_id = $1 AND customer_id = $2
+            "#,
+            programme_id,
+            customer_id
+        )
+        .fetch_one(&mut *self.connection().await?)
+        .await
+        .map_err(map_err)?;
+
+        Ok(row.referral_code)
+    }
+
+    #[tracing::instrument(name = "db_customer_for_referral_code", skip(self))]
+    async fn customer_for_referral_code(
+        &self,
+        programme_id: &str,
+        referral_code: &str,
+    ) -> Result<String, LoyaltyErrors> {
+        let row = sqlx::query!(
+            r#"
+            SELECT customer_id
+            FROM loyalty_referral_code
+            WHERE programme_id = $1 AND referral_code = $2
+            "#,
+            programme_id,
+            referral_code
+        )
+        .fetch_optional(&mut *self.connection().await?)
+        .await
+        .map_err(map_err)?;
+
+        match row {
+            Some(row) => Ok(row.customer_id),
+            None => Err(LoyaltyErrors::ReferralNotFound(format!(
+                "Referral code {} not found",
+                referral_code
+            ))),
+        }
+    }
+
+    #[tracing::instrument(name = "db_save_referral", skip(self, referral), fields(referee_id=referral.referee_id()))]
+    async fn save_referral(&self, referral: &Referral) -> Result<(), LoyaltyErrors> {
+        sqlx::query!(
+            r#"
+    INSERT INTO loyalty_referral ( programme_id, referee_id, referrer_id, referral_code, status, created_epoch, rewarded_epoch )
+    VALUES ( $1, $2, $3, $4, $5, $6, $7 )
+            "#,
+            referral.programme_id(),
+            referral.referee_id(),
+            referral.referrer_id(),
+            referral.referral_code(),
+            referral.status().to_string(),
+            referral.created_at().timestamp_millis(),
+            referral.rewarded_at().map(|date| date.timestamp_millis())
+        )
+        .execute(&mut *self.connection().await?)
+        .await
+        .map_err(map_err)?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "db_retrieve_referral", skip(self))]
+    async fn retrieve_referral(
+        &self,
+        programme_id: &str,
+        referee_id: &str,
+    ) -> Result<Referral, LoyaltyErrors> {
+        let row = sqlx::query_as!(
+            ReferralRow,
+            r#"
+            SELECT programme_id, referrer_id, referee_id, referral_code, status, created_epoch, rewarded_epoch
+            FROM loyalty_referral
+            WHERE programme_id = $1 AND referee_id = $2
+            "#,
+            programme_id,
+            referee_id
+        )
+        .fetch_optional(&mut *self.connection().await?)
+        .await
+        .map_err(map_err)?;
+
+        match row {
+            Some(row) => row.try_into(),
+            None => Err(LoyaltyErrors::ReferralNotFound(format!(
+                "Referral for {} not found",
+                referee_id
+            ))),
+        }
+    }
+
+    #[tracing::instrument(name = "db_list_referrals", skip(self))]
+    async fn list_referrals(
+        &self,
+        programme_id: &str,
+        referrer_id: &str,
+    ) -> Result<Vec<Referral>, LoyaltyErrors> {
+        let rows = sqlx::query_as!(
+            ReferralRow,
+            r#"
+            SELECT programme_id, referrer_id, referee_id, referral_code, status, created_epoch, rewarded_epoch
+            FROM loyalty_referral
+            WHERE programme_id = $1 AND referrer_id = $2
+            ORDER BY created_epoch
+            "#,
+            programme_id,
+            referrer_id
+        )
+        .fetch_all(&mut *self.connection().await?)
+        .await
+        .map_err(map_err)?;
+
+        rows.into_iter().map(|row| row.try_into()).collect()
+    }
+
+    #[tracing::instrument(name = "db_reward_referral", skip(self, referral, referee, referrer, bonuses), fields(referee_id=referral.referee_id()))]
+    async fn reward_referral<'a>(
+        &self,
+        referral: &Referral,
+        referee: &LoyaltyAccount,
+        referrer: Option<&'a LoyaltyAccount>,
+        bonuses: ReferralBonuses,
+    ) -> Result<bool, LoyaltyErrors> {
+        let mut connection = self.connection().await?;
+        let mut db_transaction = connection.begin().await.map_err(map_err)?;
+
+        // Only the first writer moves the referral out of pending, a redelivered order finds it
+        // already rewarded and leaves the balances alone
+        let updated = sqlx::query!(
+            r#"
+    UPDATE loyalty_referral
+    SET status = $1, rewarded_epoch = $2
+    WHERE programme_id = $3 AND referee_id = $4 AND status = $5
+            "#,
+            referral.status().to_string(),
+            referral.rewarded_at().unwrap_or(Utc::now()).timestamp_millis(),
+            referral.programme_id(),
+            referral.referee_id(),
+            ReferralStatus::Pending.to_string()
+        )
+        .execute(&mut *db_transaction)
+        .await
+        .map_err(map_err)?;
+
+        if updated.rows_affected() != 1 {
+            let _ = db_transaction.rollback().await;
+
+            return Ok(false);
+        }
+
+        let credits = [
+            (Some(referee), bonuses.referee_bonus),
+            (referrer, bonuses.referrer_bonus),
+        ];
+
+        for (account, transaction) in credits {
+            let (Some(account), Some(transaction)) = (account, transaction) else {
+                continue;
+            };
+
+            sqlx::query!(
+                r#"
+    INSERT INTO loyalty_transaction ( programme_id, customer_id, date_epoch, order_number, change, recorded_epoch )
+    VALUES ( $1, $2, $3, $4, $5, $6 )
+                "#,
+                account.programme_id(),
+                account.customer_id(),
+                transaction.date().timestamp_millis(),
+                transaction.order_number(),
+                transaction.change(),
+                transaction.recorded_at().timestamp_millis()
+            )
+            .execute(&mut *db_transaction)
+            .await
+            .map_err(map_err)?;
+
+            sqlx::query!(
+                r#"
+    UPDATE loyalty
+    SET current_points = current_points + $1
+    WHERE programme_id = $2 AND customer_id = $3
+                "#,
+                transaction.change(),
+                account.programme_id(),
+                account.customer_id()
+            )
+            .execute(&mut *db_transaction)
+            .await
+            .map_err(map_err)?;
+        }
+
+        db_transaction.commit().await.map_err(map_err)?;
+
+        let _ = &self
+            .cache_delete(referee.programme_id(), referee.customer_id())
+            .await;
+
+        if let Some(referrer) = referrer {
+            let _ = &self
+                .cache_delete(referrer.programme_id(), referrer.customer_id())
+                .await;
+        }
+
+        Ok(true)
+    }
+}
@@ -0,0 +1,362 @@
+use async_trait::async_trait;
+use chrono::DateTime;
+use loyalty_core::{
+    FulfilmentStatus, LoyaltyAccount, LoyaltyAccountTransaction, LoyaltyErrors, Redemption,
+    Reward, RewardOutbox, RewardRedeemed, Rewards,
+};
+use sqlx::Connection;
+use tracing::info;
+
+use crate::PostgresLoyaltyPoints;
+
+struct RewardRow {
+    programme_id: String,
+    reward_id: String,
+    name: String,
+    description: String,
+    points_cost: f32,
+    stock: i32,
+    available_from_epoch: Option<i64>,
+    available_until_epoch: Option<i64>,
+}
+
+impl From<RewardRow> for Reward {
+    fn from(row: RewardRow) -> Self {
+        Reward::from(
+            row.programme_id,
+            row.reward_id,
+            row.name,
+            row.description,
+            row.points_cost,
+            row.stock,
+            row.available_from_epoch.and_then(DateTime::from_timestamp_millis),
+            row.available_until_epoch.and_then(DateTime::from_timestamp_millis),
+        )
+    }
+}
+
+struct RedemptionRow {
+    programme_id: String,
+    redemption_id: String,
+    customer_id: String,
+    reward_id: String,
+    points: f32,
+    status: String,
+    created_epoch: i64,
+}
+
+impl TryFrom<RedemptionRow> for Redemption {
+    type Error = LoyaltyErrors;
+
+    fn try_from(row: RedemptionRow) -> Result<Self, Self::Error> {
+        Ok(Redemption::from(
+            row.programme_id,
+            row.redemption_id,
+            row.customer_id,
+            row.reward_id,
+            row.points,
+            row.status.parse::<FulfilmentStatus>()?,
+            DateTime::from_timestamp_millis(row.created_epoch).unwrap(),
+        ))
+    }
+}
+
+fn map_err(e: sqlx::Error) -> LoyaltyErrors {
+    LoyaltyErrors::DatabaseError(format!("Database Error: {:?}", e))
+}
+
+#[async_trait]
+impl Rewards for PostgresLoyaltyPoints {
+    #[tracing::instrument(name = "db_save_reward", skip(self, reward), fields(reward_id=reward.reward_id()))]
+    async fn save_reward(&self, reward: &Reward) -> Result<(), LoyaltyErrors> {
+        sqlx::query!(
+            r#"
+    INSERT INTO loyalty_reward ( programme_id, reward_id, name, description, points_cost, stock, available_from_epoch, available_until_epoch )
+    VALUES ( $1, $2, $3, $4, $5, $6, $7, $8 )
+            "#,
+            reward.programme_id(),
+            reward.reward_id(),
+            reward.name(),
+            reward.description(),
+            reward.points_cost(),
+            reward.stock(),
+            reward.available_from().map(|date| date.timestamp_millis()),
+            reward.available_until().map(|date| date.timestamp_millis())
+        )
+        .execute(&mut *self.connection().await?)
+        .await
+        .map_err(map_err)?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "db_retrieve_reward", skip(self))]
+    async fn retrieve_reward(
+        &self,
+        programme_id: &str,
+        reward_id: &str,
+    ) -> Result<Reward, LoyaltyErrors> {
+        let row = sqlx::query_as!(
+            RewardRow,
+            r#"
+            SELECT programme_id, reward_id, name, description, points_cost, stock, available_from_epoch, available_until_epoch
+            FROM loyalty_reward
+            WHERE programme_id = $1 AND reward_id = $2
+            "#,
+            programme_id,
+            reward_id,
+        )
+        .fetch_optional(&mut *self.connection().await?)
+        .await
+        .map_err(map_err)?;
+
+        match row {
+            Some(row) => Ok(row.into()),
+            None => Err(LoyaltyErrors::RewardNotFound(format!(
+                "Reward {} not found",
+                reward_id
+            ))),
+        }
+    }
+
+    #[tracing::instrument(name = "db_list_rewards", skip(self))]
+    async fn list_rewards(&self, programme_id: &str) -> Result<Vec<Reward>, LoyaltyErrors> {
+        let rows = sqlx::query_as!(
+            RewardRow,
+            r#"
+            SELECT programme_id, reward_id, name, description, points_cost, stock, available_from_epoch, available_until_epoch
+            FROM loyalty_reward
+            WHERE programme_id = $1
+            ORDER BY points_cost
+            "#,
+            programme_id,
+        )
+        .fetch_all(&mut *self.connection().await?)
+        .await
+        .map_err(map_err)?;
+
+        Ok(rows.into_iter().map(|row| row.into()).collect())
+    }
+
+    #[tracing::instrument(name = "db_redeem", skip(self, account, transaction, redemption, event), fields(redemption_id=redemption.redemption_id()))]
+    async fn redeem(
+        &self,
+        account: &LoyaltyAccount,
+        transaction: LoyaltyAccountTransaction,
+        redemption: &Redemption,
+        event: &RewardRedeemed,
+    ) -> Result<(), LoyaltyErrors> {
+        let payload = serde_json::to_string(event)
+            .map_err(|e| LoyaltyErrors::InvalidValues(format!("{:?}", e)))?;
+
+        let mut connection = self.connection().await?;
+        let mut db_transaction = connection.begin().await.map_err(map_err)?;
+
+        // The redemption goes first so a reused redemption id is turned away before anything else
+        // is written
+        let redemption_inserted = sqlx::query!(
+            r#"
+    INSERT INTO loyalty_redemption ( programme_id, redemption_id, customer_id, reward_id, points, status, created_epoch )
+    VALUES ( $1, $2, $3, $4, $5, $6, $7 )
+    ON CONFLICT (redemption_id) DO NOTHING
+            "#,
+            redemption.programme_id(),
+            redemption.redemption_id(),
+            redemption.customer_id(),
+            redemption.reward_id(),
+            redemption.points(),
+            redemption.status().to_string(),
+            redemption.created_at().timestamp_millis()
+        )
+        .execute(&mut *db_transaction)
+        .await
+        .map_err(map_err)?;
+
+        if redemption_inserted.rows_affected() != 1 {
+            let _ = db_transaction.rollback().await;
+
+            return Err(LoyaltyErrors::RedemptionExists(format!(
+                "Redemption {} already exists",
+                redemption.redemption_id()
+            )));
+        }
+
+        let stock_updated = sqlx::query!(
+            r#"
+    UPDATE loyalty_reward
+    SET stock = stock - 1
+    WHERE programme_id = $1 AND reward_id = $2 AND stock > 0
+            "#,
+            redemption.programme_id(),
+            redemption.reward_id()
+        )
+        .execute(&mut *db_transaction)
+        .await
+        .map_err(map_err)?;
+
+        if stock_updated.rows_affected() != 1 {
+            let _ = db_transaction.rollback().await;
+
+            return Err(LoyaltyErrors::RewardUnavailable(format!(
+                "Reward {} is out of stock",
+                redemption.reward_id()
+            )));
+        }
+
+        sqlx::query!(
+            r#"
+    INSERT INTO loyalty_transaction ( programme_id, customer_id, date_epoch, order_number, change, recorded_epoch )
+    VALUES ( $1, $2, $3, $4, $5, $6 )
+            "#,
+            account.programme_id(),
+            account.customer_id(),
+            transaction.date().timestamp_millis(),
+            transaction.order_number(),
+            transaction.change(),
+            transaction.recorded_at().timestamp_millis()
+        )
+        .execute(&mut *db_transaction)
+        .await
+        .map_err(map_err)?;
+
+        let points_updated = sqlx::query!(
+            r#"
+    UPDATE loyalty
+    SET current_points = current_points + $1
+    WHERE programme_id = $2 AND customer_id = $3 AND current_points + $1 >= 0
+            "#,
+            transaction.change(),
+            account.programme_id(),
+            account.customer_id()
+        )
+        .execute(&mut *db_transaction)
+        .await
+        .map_err(map_err)?;
+
+        if points_updated.rows_affected() != 1 {
+            let _ = db_transaction.rollback().await;
+
+            return Err(LoyaltyErrors::PointsNotAvailable(
+                "Current points not enough to cover this redemption".to_string(),
+            ));
+        }
+
+        sqlx::query!(
+            r#"
+    INSERT INTO loyalty_reward_outbox ( programme_id, redemption_id, payload, created_epoch )
+    VALUES ( $1, $2, $3, $4 )
+            "#,
+            event.programme_id,
+            event.redemption_id,
+            payload,
+            event.redeemed_at.timestamp_millis()
+        )
+        .execute(&mut *db_transaction)
+        .await
+        .map_err(map_err)?;
+
+        db_transaction.commit().await.map_err(map_err)?;
+
+        info!("Committed redemption");
+
+        let _ = &self
+            .cache_delete(account.programme_id(), account.customer_id())
+            .await;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "db_retrieve_redemption", skip(self))]
+    async fn retrieve_redemption(
+        &self,
+        programme_id: &str,
+        redemption_id: &str,
+    ) -> Result<Redemption, LoyaltyErrors> {
+        let row = sqlx::query_as!(
+            RedemptionRow,
+            r#"
+            SELECT programme_id, redemption_id, customer_id, reward_id, points, status, created_epoch
+            FROM loyalty_redemption
+            WHERE programme_id = $1 AND redemption_id = $2
+            "#,
+            programme_id,
+            redemption_id,
+        )
+        .fetch_optional(&mut *self.connection().await?)
+        .await
+        .map_err(map_err)?;
+
+        match row {
+            Some(row) => row.try_into(),
+            None => Err(LoyaltyErrors::RedemptionNotFound(format!(
+                "Redemption {} not found",
+                redemption_id
+            ))),
+        }
+    }
+
+    #[tracing::instrument(name = "db_update_redemption_status", skip(self, redemption), fields(redemption_id=redemption.redemption_id()))]
+    async fn update_redemption_status(&self, redemption: &Redemption) -> Result<(), LoyaltyErrors> {
+        sqlx::query!(
+            r#"
+    UPDATE loyalty_redemption
+    SET status = $1
+    WHERE programme_id = $2 AND redemption_id = $3
+            "#,
+            redemption.status().to_string(),
+            redemption.programme_id(),
+            redemption.redemption_id()
+        )
+        .execute(&mut *self.connection().await?)
+        .await
+        .map_err(map_err)?;
+
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl RewardOutbox for PostgresLoyaltyPoints {
+    #[tracing::instrument(name = "db_pending_reward_redeemed", skip(self))]
+    async fn pending_reward_redeemed(
+        &self,
+        limit: i64,
+    ) -> Result<Vec<RewardRedeemed>, LoyaltyErrors> {
+        let rows = sqlx::query!(
+            r#"
+            SELECT payload
+            FROM loyalty_reward_outbox
+            ORDER BY created_epoch
+            LIMIT $1
+            "#,
+            limit,
+        )
+        .fetch_all(&mut *self.connection().await?)
+        .await
+        .map_err(map_err)?;
+
+        rows.into_iter()
+            .map(|row| {
+                serde_json::from_str(&row.payload)
+                    .map_err(|e| LoyaltyErrors::InvalidValues(format!("{:?}", e)))
+            })
+            .collect()
+    }
+
+    #[tracing::instrument(name = "db_remove_reward_redeemed", skip(self, evt), fields(redemption_id=evt.redemption_id))]
+    async fn remove_reward_redeemed(&self, evt: &RewardRedeemed) -> Result<(), LoyaltyErrors> {
+        sqlx::query!(
+            r#"
+    DELETE FROM loyalty_reward_outbox
+    WHERE programme_id = $1 AND redemption_id = $2
+            "#,
+            evt.programme_id,
+            evt.redemption_id
+        )
+        .execute(&mut *self.connection().await?)
+        .await
+        .map_err(map_err)?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,144 @@
+use async_trait::async_trait;
+use chrono::DateTime;
+use loyalty_core::{
+    FlaggedSpend, FlaggedSpends, LoyaltyAccount, LoyaltyAccountTransaction, LoyaltyErrors,
+};
+use sqlx::Connection;
+
+use crate::PostgresLoyaltyPoints;
+
+struct FlaggedSpendRow {
+    programme_id: String,
+    customer_id: String,
+    order_number: String,
+    spend: f32,
+    reasons: Vec<String>,
+    flagged_epoch: i64,
+}
+
+impl From<FlaggedSpendRow> for FlaggedSpend {
+    fn from(row: FlaggedSpendRow) -> Self {
+        FlaggedSpend::from(
+            row.programme_id,
+            row.customer_id,
+            row.order_number,
+            row.spend,
+            row.reasons,
+            DateTime::from_timestamp_millis(row.flagged_epoch).unwrap(),
+        )
+    }
+}
+
+fn map_err(e: sqlx::Error) -> LoyaltyErrors {
+    LoyaltyErrors::DatabaseError(format!("Database Error: {:?}", e))
+}
+
+#[async_trait]
+impl FlaggedSpends for PostgresLoyaltyPoints {
+    #[tracing::instrument(name = "db_spend_flagged", skip(self, account, transaction, flagged_spend), fields(programme_id=flagged_spend.programme_id(), customer_id=flagged_spend.customer_id(), order_number=flagged_spend.order_number()))]
+    async fn spend_flagged(
+        &self,
+        account: &LoyaltyAccount,
+        transaction: LoyaltyAccountTransaction,
+        flagged_spend: &FlaggedSpend,
+    ) -> Result<(), LoyaltyErrors> {
+        let metadata = match transaction.metadata().is_empty() {
+            true => None,
+            false => serde_json::to_string(transaction.metadata()).ok(),
+        };
+
+        let mut connection = self.connection().await?;
+        let mut db_transaction = connection.begin().await.map_err(map_err)?;
+
+        sqlx::query!(
+            r#"
+    INSERT INTO loyalty_transaction ( programme_id, customer_id, date_epoch, order_number, change, metadata, vests_epoch, recorded_epoch )
+    VALUES ( $1, $2, $3, $4, $5, $6, $7, $8 )
+            "#,
+            account.programme_id(),
+            account.customer_id(),
+            transaction.date().timestamp_millis(),
+            transaction.order_number(),
+            transaction.change(),
+            metadata,
+            transaction.vests_at().map(|date| date.timestamp_millis()),
+            transaction.recorded_at().timestamp_millis()
+        )
+        .execute(&mut *db_transaction)
+        .await
+        .map_err(map_err)?;
+
+        // Applied relative to the stored balance so a concurrent spend can't overdraw the account
+        let updated = sqlx::query!(
+            r#"
+    UPDATE loyalty
+    SET current_points = current_points + $1
+    WHERE programme_id = $2 AND customer_id = $3 AND current_points + $1 >= 0
+            "#,
+            transaction.change(),
+            account.programme_id(),
+            account.customer_id()
+        )
+        .execute(&mut *db_transaction)
+        .await
+        .map_err(map_err)?;
+
+        if updated.rows_affected() != 1 {
+            let _ = db_transaction.rollback().await;
+
+            return Err(LoyaltyErrors::PointsNotAvailable(format!(
+                "Current points not enough to cover spend for {}",
+                account.customer_id()
+            )));
+        }
+
+        sqlx::query!(
+            r#"
+    INSERT INTO loyalty_flagged_spend ( programme_id, customer_id, order_number, spend, reasons, flagged_epoch )
+    VALUES ( $1, $2, $3, $4, $5, $6 )
+    ON CONFLICT (programme_id, customer_id, order_number) DO NOTHING
+            "#,
+            flagged_spend.programme_id(),
+            flagged_spend.customer_id(),
+            flagged_spend.order_number(),
+            flagged_spend.spend(),
+            flagged_spend.reasons(),
+            flagged_spend.flagged_at().timestamp_millis()
+        )
+        .execute(&mut *db_transaction)
+        .await
+        .map_err(map_err)?;
+
+        db_transaction.commit().await.map_err(map_err)?;
+
+        drop(connection);
+
+        let _ = &self
+            .cache_delete(account.programme_id(), account.customer_id())
+            .await;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "db_list_flagged_spends", skip(self))]
+    async fn list_flagged_spends(
+        &self,
+        programme_id: &str,
+    ) -> Result<Vec<FlaggedSpend>, LoyaltyErrors> {
+        let rows = sqlx::query_as!(
+            FlaggedSpendRow,
+            r#"
+            SELECT programme_id, customer_id, order_number, spend, reasons, flagged_epoch
+            FROM loyalty_flagged_spend
+            WHERE programme_id = $1
+            ORDER BY flagged_epoch
+            "#,
+            programme_id,
+        )
+        .fetch_all(&mut *self.connection().await?)
+        .await
+        .map_err(map_err)?;
+
+        Ok(rows.into_iter().map(|row| row.into()).collect())
+    }
+}
@@ -0,0 +1,68 @@
+use std::collections::HashMap;
+
+use opentelemetry::propagation::{TextMapCompositePropagator, TextMapPropagator};
+use opentelemetry::trace::TraceContextExt;
+use opentelemetry::{global, Context};
+use opentelemetry_datadog::DatadogPropagator;
+use opentelemetry_sdk::propagation::TraceContextPropagator;
+use tracing::Span;
+use tracing_opentelemetry::OpenTelemetrySpanExt;
+
+use crate::observability::use_datadog;
+
+/// Reads the W3C `traceparent` and `tracestate` headers, and Datadog's `x-datadog-*` headers too
+/// when traces are sent to Datadog, so a producer that only sets those is still followed.
+pub fn configure_propagation() {
+    let mut propagators: Vec<Box<dyn TextMapPropagator + Send + Sync>> =
+        vec![Box::new(TraceContextPropagator::new())];
+
+    if use_datadog() {
+        propagators.push(Box::new(DatadogPropagator::default()));
+    }
+
+    global::set_text_map_propagator(TextMapCompositePropagator::new(propagators));
+}
+
+/// The trace context the producer sent with a message, empty if it sent none. Header names are
+/// expected in lower case, as `message_headers` returns them.
+pub fn trace_context(headers: &HashMap<String, String>) -> Context {
+    global::get_text_map_propagator(|propagator| propagator.extract(headers))
+}
+
+/// The span a consumed message is processed in, a child of the producer's span with the
+/// OpenTelemetry messaging attributes. The consumer group and message id are left for the caller
+/// to record once known.
+pub fn message_span(
+    topic: &str,
+    partition: i32,
+    offset: i64,
+    headers: &HashMap<String, String>,
+) -> Span {
+    let span = tracing::info_span!(
+        "process_message",
+        otel.name = %format!("{} process", topic),
+        otel.kind = "consumer",
+        messaging.system = "kafka",
+        messaging.operation = "process",
+        messaging.destination.name = %topic,
+        messaging.destination.partition.id = %partition,
+        messaging.kafka.message.offset = offset,
+        messaging.kafka.consumer.group = tracing::field::Empty,
+        messaging.message.id = tracing::field::Empty,
+    );
+
+    span.set_parent(trace_context(headers));
+
+    span
+}
+
+/// Links a span handling several messages together to the trace each message was produced in,
+/// as it can only have one parent.
+pub fn link_message(span: &Span, headers: &HashMap<String, String>) {
+    let context = trace_context(headers);
+    let span_context = context.span().span_context().clone();
+
+    if span_context.is_valid() {
+        span.add_link(span_context);
+    }
+}
@@ -1,8 +1,17 @@
+use std::collections::HashMap;
+
 use loyalty_adapters::{
-    configure_instrumentation, ApplicationAdapters, PostgresLoyaltyPoints,
+    check_topic_programme, configure_exchange_rates, configure_instrumentation,
+    configure_payload_decoders, decode_event, link_message, message_span, split_programme_topic,
+    ApplicationAdapters, DeadLetter, HandleError, IncomingEvent, KafkaEventPublisher,
+    PayloadDecoders, PostgresLoyaltyPoints,
+};
+use loyalty_core::{
+    BonusPolicy, Campaigns, CustomerProfiles, CustomerRegistered, CustomerRegisteredEventHandler,
+    ExchangeRates, Households, LoyaltyErrors, LoyaltyPoints, OrderConfirmed,
+    OrderConfirmedEventHandler, Programmes, Referrals, SystemClock, VestingPolicy,
 };
-use loyalty_core::{LoyaltyPoints, OrderConfirmedEventHandler};
-use tracing::info;
+use tracing::{info, Instrument, Span};
 
 use aws_lambda_events::kafka::{KafkaEvent, KafkaRecord};
 use base64::prelude::*;
@@ -16,66 +25,290 @@ async fn main() -> Result<(), Error> {
 
     let adapters = ApplicationAdapters::new(postgres_loyalty).await;
 
-    run(service_fn(|evt| function_handler(evt, &adapters))).await
+    let vesting = vesting_policy();
+    let bonuses = bonus_policy();
+    let exchange_rates = configure_exchange_rates();
+    let dead_letters = KafkaEventPublisher::new()?;
+    let decoders = configure_payload_decoders()?;
+
+    run(service_fn(|evt| {
+        function_handler(
+            evt,
+            &adapters,
+            &vesting,
+            &bonuses,
+            exchange_rates.as_ref(),
+            &dead_letters,
+            &decoders,
+        )
+    }))
+    .await
 }
 
-async fn function_handler<T: LoyaltyPoints + Send + Sync>(
+async fn function_handler<
+    T: LoyaltyPoints
+        + Programmes
+        + Campaigns
+        + CustomerProfiles
+        + Referrals
+        + Households
+        + Send
+        + Sync,
+>(
     event: LambdaEvent<KafkaEvent>,
     adapters: &ApplicationAdapters<T>,
+    vesting: &VestingPolicy,
+    bonuses: &BonusPolicy,
+    exchange_rates: &(dyn ExchangeRates + Send + Sync),
+    dead_letters: &KafkaEventPublisher,
+    decoders: &PayloadDecoders,
 ) -> Result<(), Error> {
-    for (_, val) in event.payload.records {
-        for record in val {
-            let _ = process_message(adapters, record).await;
-
-            // TODO: Implement dead letter handling
-            // Current implementation will move forward IF a message can't be processed. There is no way
-            // currently with the Kafka<>Lambda integration to batch 'success' messages. If there is an error
-            // the problematic message should be moved to a seperate persistence store. SQS?
+    let mut orders = vec![];
+    let mut retryable = None;
+
+    for record in event.payload.records.into_values().flatten() {
+        let headers = record_headers(&record);
+
+        // Each record is processed in the trace it was produced in
+        let span = message_span(
+            record.topic.as_deref().unwrap_or(""),
+            record.partition as i32,
+            record.offset,
+            &headers,
+        );
+
+        let Some((payload, event)) = decode_record(&record, &headers, decoders)
+            .instrument(span.clone())
+            .await
+        else {
+            continue;
+        };
+
+        // Orders are held back and earned together once every record has been read
+        let result = match event {
+            Ok(IncomingEvent::OrderConfirmed(evt)) => {
+                orders.push((record, headers, payload, evt));
+                continue;
+            }
+            Ok(IncomingEvent::CustomerRegistered(evt)) => {
+                handle_customer_registered(adapters, &evt)
+                    .instrument(span.clone())
+                    .await
+            }
+            Err(e) => Err(e),
+        };
+
+        if let Err(error) = result {
+            if error.is_retryable() {
+                tracing::warn!("{}", error.message());
+                retryable.get_or_insert(error);
+            } else {
+                dead_letter(dead_letters, &record, &headers, &payload, &error)
+                    .instrument(span)
+                    .await?;
+            }
         }
     }
 
-    Ok(())
+    // The orders come from many traces, so the batch links to each of them
+    let batch = tracing::info_span!("process_batch", messages = orders.len());
+
+    for (_, headers, ..) in &orders {
+        link_message(&batch, headers);
+    }
+
+    async {
+        let events: Vec<&OrderConfirmed> = orders.iter().map(|(.., evt)| evt).collect();
+
+        let results = OrderConfirmedEventHandler::handle_batch(
+            &adapters.loyalty_points,
+            &adapters.loyalty_points,
+            &adapters.loyalty_points,
+            &adapters.loyalty_points,
+            &adapters.loyalty_points,
+            exchange_rates,
+            vesting,
+            bonuses,
+            &SystemClock,
+            &events,
+        )
+        .await;
+
+        for ((record, headers, payload, _), result) in orders.iter().zip(results) {
+            if let Err(e) = result {
+                let error =
+                    HandleError::processing("Failure processing 'OrderConfirmed' event", &e);
+
+                if error.is_retryable() {
+                    tracing::warn!("{}", error.message());
+                    retryable.get_or_insert(error);
+                } else {
+                    dead_letter(dead_letters, record, headers, payload, &error).await?;
+                }
+            }
+        }
+
+        Ok::<(), Error>(())
+    }
+    .instrument(batch)
+    .await?;
+
+    // Records that can never succeed are dead-lettered. A retryable failure, or a record that
+    // can't be dead-lettered, fails the invocation so Lambda redelivers the batch, there is no way
+    // with the Kafka<>Lambda integration to report individual records as successful. Records that
+    // were handled are skipped when redelivered, as orders are only earned on once.
+    match retryable {
+        Some(error) => Err(error.message().into()),
+        None => Ok(()),
+    }
 }
 
-#[tracing::instrument(name = "process_message", skip(application, record))]
-async fn process_message<T: LoyaltyPoints + Send + Sync>(
-    application: &ApplicationAdapters<T>,
-    record: KafkaRecord,
-) -> Result<(), ()> {
-    let message_value = match record.value {
-        Some(val) => val,
-        None => {
-            tracing::warn!("Empty message received, skipping");
-            return Ok(())
-        },
+/// Decodes a record into its event, returning the payload alongside it for dead-lettering. Empty
+/// records and records that aren't base64 are skipped. Runs in the record's `message_span`.
+async fn decode_record(
+    record: &KafkaRecord,
+    headers: &HashMap<String, String>,
+    decoders: &PayloadDecoders,
+) -> Option<(Vec<u8>, Result<IncomingEvent, HandleError>)> {
+    let Some(message_value) = &record.value else {
+        tracing::warn!("Empty message received, skipping");
+        return None;
     };
 
-    let decoded = BASE64_STANDARD.decode(message_value).map_err(|e| {
-        tracing::error!("Failure decoding message: {}", e);
-    })?;
+    let decoded = BASE64_STANDARD
+        .decode(message_value)
+        .map_err(|e| {
+            tracing::error!("Failure decoding message: {}", e);
+        })
+        .ok()?;
 
-    let evt_payload = serde_json::from_slice(&decoded);
+    let (topic_programme, topic) = split_programme_topic(record.topic.as_deref().unwrap_or(""));
 
-    match evt_payload {
-        Ok(evt) => {
-            let handle_result =
-                OrderConfirmedEventHandler::handle(&application.loyalty_points, &evt).await;
+    // A schema registry that can't be reached is a retryable failure, failing the invocation
+    let event = match decoders.decode(topic, headers, &decoded).await {
+        Ok(body) => match decode_event(topic, headers, body) {
+            Ok((metadata, event)) => {
+                if let Some(id) = &metadata.id {
+                    Span::current().record("messaging.message.id", id.as_str());
+                }
 
-            match handle_result {
-                Ok(_) => {
-                    info!("Processed successfully");
+                info!(
+                    "Received '{}' v{} event {:?}",
+                    metadata.event_type, metadata.version, metadata.id
+                );
 
-                    Ok(())
-                }
-                Err(_) => {
-                    tracing::error!("Failure processing 'OrderConfirmed' event");
-                    Err(())
-                }
+                check_topic_programme(topic_programme, event.programme_id())
+                    .map_err(|e| HandleError::Invalid(format!("{:?}", e)))
+                    .and_then(|_| validate_event(event))
             }
-        }
-        Err(_) => {
-            tracing::error!("Failure parsing payload to 'OrderConfirmed' event");
-            Err(())
-        }
+            Err(e) => Err(HandleError::Invalid(format!("{:?}", e))),
+        },
+        Err(e) => Err(e),
+    };
+
+    Some((decoded, event))
+}
+
+fn validate_event(event: IncomingEvent) -> Result<IncomingEvent, HandleError> {
+    if let IncomingEvent::OrderConfirmed(evt) = &event {
+        evt.validate()
+            .map_err(|e| HandleError::Invalid(format!("Invalid 'OrderConfirmed' event: {}", e)))?;
     }
+
+    Ok(event)
+}
+
+async fn dead_letter(
+    dead_letters: &KafkaEventPublisher,
+    record: &KafkaRecord,
+    headers: &HashMap<String, String>,
+    payload: &[u8],
+    error: &HandleError,
+) -> Result<(), LoyaltyErrors> {
+    tracing::error!("{}", error.message());
+
+    let key = record
+        .key
+        .as_ref()
+        .and_then(|key| BASE64_STANDARD.decode(key).ok());
+
+    let dead_letter = DeadLetter {
+        topic: record.topic.as_deref().unwrap_or(""),
+        partition: record.partition as i32,
+        offset: record.offset,
+        key: key.as_deref(),
+        payload,
+        headers,
+        error,
+        attempts: 1,
+    };
+
+    dead_letters
+        .publish_dead_letter(&dead_letter)
+        .await
+        .inspect_err(|e| tracing::error!("Failure dead-lettering message: {:?}", e))
+}
+
+/// Collects the UTF-8 headers of a record, header names are compared case insensitively.
+fn record_headers(record: &KafkaRecord) -> HashMap<String, String> {
+    record
+        .headers
+        .iter()
+        .flatten()
+        .filter_map(|(key, value)| {
+            let value = value.iter().map(|byte| *byte as u8).collect();
+            Some((key.to_lowercase(), String::from_utf8(value).ok()?))
+        })
+        .collect()
+}
+
+async fn handle_customer_registered<
+    T: LoyaltyPoints
+        + Programmes
+        + Campaigns
+        + CustomerProfiles
+        + Referrals
+        + Households
+        + Send
+        + Sync,
+>(
+    application: &ApplicationAdapters<T>,
+    evt: &CustomerRegistered,
+) -> Result<(), HandleError> {
+    CustomerRegisteredEventHandler::handle(
+        &application.loyalty_points,
+        &application.loyalty_points,
+        &SystemClock,
+        evt,
+    )
+    .await
+    .map_err(|e| HandleError::processing("Failure processing 'CustomerRegistered' event", &e))?;
+
+    info!("Processed successfully");
+
+    Ok(())
+}
+
+fn vesting_policy() -> VestingPolicy {
+    std::env::var("POINTS_VESTING_DAYS")
+        .ok()
+        .and_then(|days| days.parse().ok())
+        .map(VestingPolicy::new)
+        .unwrap_or_default()
+}
+
+fn bonus_policy() -> BonusPolicy {
+    let defaults = BonusPolicy::default();
+    let env_f32 = |name: &str| {
+        std::env::var(name)
+            .ok()
+            .and_then(|value| value.parse().ok())
+    };
+
+    BonusPolicy::new(
+        env_f32("SIGNUP_BONUS_POINTS").unwrap_or(defaults.signup_bonus()),
+        env_f32("BIRTHDAY_BONUS_POINTS").unwrap_or(defaults.birthday_bonus()),
+        env_f32("REFERRER_BONUS_POINTS").unwrap_or(defaults.referrer_bonus()),
+        env_f32("REFEREE_BONUS_POINTS").unwrap_or(defaults.referee_bonus()),
+    )
 }
@@ -0,0 +1,366 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Write;
+use std::sync::Mutex;
+
+use chrono::{DateTime, TimeDelta, Utc};
+use rdkafka::statistics::Statistics;
+use rdkafka::TopicPartitionList;
+
+/// How often librdkafka reports its statistics, in milliseconds.
+pub const STATISTICS_INTERVAL_MS: i64 = 5_000;
+
+/// What the consumer last reported about itself, used for the readiness check and exported as
+/// metrics.
+#[derive(Default)]
+pub struct ConsumerHealth {
+    state: Mutex<ConsumerState>,
+}
+
+#[derive(Default)]
+struct ConsumerState {
+    assigned: BTreeSet<(String, i32)>,
+    stalled: BTreeSet<(String, i32)>,
+    lag: BTreeMap<(String, i32), i64>,
+    brokers_up: usize,
+    rebalances: u64,
+    statistics_at: Option<DateTime<Utc>>,
+    last_message_at: Option<DateTime<Utc>>,
+}
+
+impl ConsumerHealth {
+    /// Records the lag of each partition and how many brokers are reachable.
+    pub fn record_statistics(&self, statistics: &Statistics, now: DateTime<Utc>) {
+        let lag = statistics
+            .topics
+            .values()
+            .flat_map(|topic| {
+                topic
+                    .partitions
+                    .values()
+                    // librdkafka reports -1 for its internal partition and for lag it doesn't
+                    // know yet, such as on partitions nothing has been committed to
+                    .filter(|p| p.partition >= 0 && p.consumer_lag >= 0)
+                    .map(|p| ((topic.topic.clone(), p.partition), p.consumer_lag))
+            })
+            .collect();
+
+        let brokers_up = statistics
+            .brokers
+            .values()
+            .filter(|broker| broker.state == "UP")
+            .count();
+
+        let mut state = self.state.lock().unwrap();
+
+        state.lag = lag;
+        state.brokers_up = brokers_up;
+        state.statistics_at = Some(now);
+    }
+
+    pub fn assigned(&self, partitions: &TopicPartitionList) {
+        let mut state = self.state.lock().unwrap();
+
+        state.rebalances += 1;
+
+        for p in partitions.elements() {
+            state
+                .assigned
+                .insert((p.topic().to_string(), p.partition()));
+        }
+    }
+
+    pub fn revoked(&self, partitions: &TopicPartitionList) {
+        let mut state = self.state.lock().unwrap();
+
+        state.rebalances += 1;
+
+        for p in partitions.elements() {
+            let key = (p.topic().to_string(), p.partition());
+
+            state.assigned.remove(&key);
+            state.stalled.remove(&key);
+        }
+    }
+
+    /// Records that the partition can't move past a message that could neither be handled nor
+    /// moved to a retry or dead-letter topic.
+    pub fn stalled(&self, topic: &str, partition: i32) {
+        self.state
+            .lock()
+            .unwrap()
+            .stalled
+            .insert((topic.to_string(), partition));
+    }
+
+    pub fn unstalled(&self, topic: &str, partition: i32) {
+        self.state
+            .lock()
+            .unwrap()
+            .stalled
+            .remove(&(topic.to_string(), partition));
+    }
+
+    pub fn message_received(&self, now: DateTime<Utc>) {
+        self.state.lock().unwrap().last_message_at = Some(now);
+    }
+
+    /// Ready means a broker is reachable, partitions are assigned and none of them is stalled or
+    /// more than `max_lag` messages behind. Statistics stop arriving if the consumer stops polling, so if
+    /// they are stale the consumer is treated as stuck.
+    pub fn readiness(&self, max_lag: i64, now: DateTime<Utc>) -> Result<(), String> {
+        let state = self.state.lock().unwrap();
+
+        let stale_after = TimeDelta::milliseconds(STATISTICS_INTERVAL_MS * 3);
+
+        match state.statistics_at {
+            None => return Err("No consumer statistics reported yet".to_string()),
+            Some(at) if now - at > stale_after => {
+                return Err(format!("Consumer statistics last reported at {}", at))
+            }
+            Some(_) => {}
+        }
+
+        if state.brokers_up == 0 {
+            return Err("No Kafka brokers are reachable".to_string());
+        }
+
+        if state.assigned.is_empty() {
+            return Err("No partitions are assigned".to_string());
+        }
+
+        let stalled: Vec<String> = state
+            .stalled
+            .iter()
+            .map(|(topic, partition)| format!("{}[{}] is stalled", topic, partition))
+            .collect();
+
+        if !stalled.is_empty() {
+            return Err(stalled.join(", "));
+        }
+
+        let lagging: Vec<String> = state
+            .assigned_lag()
+            .filter(|(_, lag)| *lag > max_lag)
+            .map(|((topic, partition), lag)| format!("{}[{}] is {} behind", topic, partition, lag))
+            .collect();
+
+        if !lagging.is_empty() {
+            return Err(lagging.join(", "));
+        }
+
+        Ok(())
+    }
+
+    /// The consumer's metrics in the Prometheus text format.
+    pub fn metrics(&self) -> String {
+        let state = self.state.lock().unwrap();
+        let mut metrics = String::new();
+
+        let _ = writeln!(
+            metrics,
+            "# HELP loyalty_consumer_lag Messages on the partition not yet committed.\n\
+             # TYPE loyalty_consumer_lag gauge"
+        );
+
+        for ((topic, partition), lag) in state.assigned_lag() {
+            let _ = writeln!(
+                metrics,
+                "loyalty_consumer_lag{{topic=\"{}\",partition=\"{}\"}} {}",
+                topic, partition, lag
+            );
+        }
+
+        let _ = writeln!(
+            metrics,
+            "# HELP loyalty_consumer_assigned_partitions Partitions assigned to this consumer.\n\
+             # TYPE loyalty_consumer_assigned_partitions gauge\n\
+             loyalty_consumer_assigned_partitions {}",
+            state.assigned.len()
+        );
+
+        let _ = writeln!(
+            metrics,
+            "# HELP loyalty_consumer_stalled_partitions Partitions stuck on a message that can't \
+             be moved.\n\
+             # TYPE loyalty_consumer_stalled_partitions gauge\n\
+             loyalty_consumer_stalled_partitions {}",
+            state.stalled.len()
+        );
+
+        let _ = writeln!(
+            metrics,
+            "# HELP loyalty_consumer_rebalances_total Partition assignments and revocations.\n\
+             # TYPE loyalty_consumer_rebalances_total counter\n\
+             loyalty_consumer_rebalances_total {}",
+            state.rebalances
+        );
+
+        let _ = writeln!(
+            metrics,
+            "# HELP loyalty_consumer_brokers_up Kafka brokers the consumer is connected to.\n\
+             # TYPE loyalty_consumer_brokers_up gauge\n\
+             loyalty_consumer_brokers_up {}",
+            state.brokers_up
+        );
+
+        if let Some(at) = state.last_message_at {
+            let _ = writeln!(
+                metrics,
+                "# HELP loyalty_consumer_last_message_timestamp_seconds Last message received.\n\
+                 # TYPE loyalty_consumer_last_message_timestamp_seconds gauge\n\
+                 loyalty_consumer_last_message_timestamp_seconds {}",
+                at.timestamp()
+            );
+        }
+
+        metrics
+    }
+}
+
+impl ConsumerState {
+    /// The lag of the partitions currently assigned, statistics also cover the rest of the topic.
+    fn assigned_lag(&self) -> impl Iterator<Item = (&(String, i32), i64)> {
+        self.assigned
+            .iter()
+            .filter_map(|key| Some((key, *self.lag.get(key)?)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use rdkafka::statistics::{Broker, Partition, Topic};
+
+    use super::*;
+
+    fn statistics(lag: &[(i32, i64)]) -> Statistics {
+        let partitions = lag
+            .iter()
+            .map(|(partition, consumer_lag)| {
+                let partition = Partition {
+                    partition: *partition,
+                    consumer_lag: *consumer_lag,
+                    ..Partition::default()
+                };
+
+                (partition.partition, partition)
+            })
+            .collect();
+
+        let topic = Topic {
+            topic: "order-completed".to_string(),
+            partitions,
+            ..Topic::default()
+        };
+
+        let broker = Broker {
+            state: "UP".to_string(),
+            ..Broker::default()
+        };
+
+        Statistics {
+            brokers: HashMap::from([("kafka:29092/1".to_string(), broker)]),
+            topics: HashMap::from([("order-completed".to_string(), topic)]),
+            ..Statistics::default()
+        }
+    }
+
+    fn assigned(partitions: &[i32]) -> TopicPartitionList {
+        let mut list = TopicPartitionList::new();
+
+        for partition in partitions {
+            list.add_partition("order-completed", *partition);
+        }
+
+        list
+    }
+
+    #[test]
+    fn consumer_within_the_lag_limit_is_ready() {
+        let health = ConsumerHealth::default();
+        let now = Utc::now();
+
+        health.assigned(&assigned(&[0, 1]));
+        health.record_statistics(&statistics(&[(0, 10), (1, 100)]), now);
+
+        assert!(health.readiness(100, now).is_ok());
+    }
+
+    #[test]
+    fn consumer_lagging_on_an_assigned_partition_is_not_ready() {
+        let health = ConsumerHealth::default();
+        let now = Utc::now();
+
+        health.assigned(&assigned(&[0, 1]));
+        health.record_statistics(&statistics(&[(0, 10), (1, 101)]), now);
+
+        let reason = health.readiness(100, now).unwrap_err();
+
+        assert_eq!(reason, "order-completed[1] is 101 behind");
+    }
+
+    #[test]
+    fn consumer_with_a_stalled_partition_is_not_ready_until_it_moves_on() {
+        let health = ConsumerHealth::default();
+        let now = Utc::now();
+
+        health.assigned(&assigned(&[0, 1]));
+        health.record_statistics(&statistics(&[(0, 10), (1, 10)]), now);
+        health.stalled("order-completed", 1);
+
+        assert_eq!(
+            health.readiness(100, now).unwrap_err(),
+            "order-completed[1] is stalled"
+        );
+
+        health.unstalled("order-completed", 1);
+
+        assert!(health.readiness(100, now).is_ok());
+    }
+
+    #[test]
+    fn stalled_partition_revoked_from_the_consumer_is_ignored() {
+        let health = ConsumerHealth::default();
+        let now = Utc::now();
+
+        health.assigned(&assigned(&[0, 1]));
+        health.record_statistics(&statistics(&[(0, 10), (1, 10)]), now);
+        health.stalled("order-completed", 1);
+        health.revoked(&assigned(&[1]));
+
+        assert!(health.readiness(100, now).is_ok());
+    }
+
+    #[test]
+    fn lag_on_partitions_assigned_elsewhere_is_ignored() {
+        let health = ConsumerHealth::default();
+        let now = Utc::now();
+
+        health.assigned(&assigned(&[0, 1]));
+        health.revoked(&assigned(&[1]));
+        health.record_statistics(&statistics(&[(-1, 5000), (0, 10), (1, 5000)]), now);
+
+        assert!(health.readiness(100, now).is_ok());
+    }
+
+    #[test]
+    fn consumer_without_partitions_or_fresh_statistics_is_not_ready() {
+        let health = ConsumerHealth::default();
+        let now = Utc::now();
+
+        assert!(health.readiness(100, now).is_err());
+
+        health.record_statistics(&statistics(&[(0, 0)]), now);
+
+        assert_eq!(
+            health.readiness(100, now).unwrap_err(),
+            "No partitions are assigned"
+        );
+
+        health.assigned(&assigned(&[0]));
+
+        let stale = now + TimeDelta::milliseconds(STATISTICS_INTERVAL_MS * 3 + 1);
+        assert!(health.readiness(100, stale).is_err());
+    }
+}
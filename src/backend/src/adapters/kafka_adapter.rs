@@ -1,117 +1,962 @@
-use loyalty_adapters::ApplicationAdapters;
-use loyalty_core::{LoyaltyPoints, OrderConfirmedEventHandler};
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::{Arc, Mutex, OnceLock, Weak};
+use std::time::Duration;
+
+use chrono::Utc;
+use futures::future::join_all;
+use loyalty_adapters::{
+    check_topic_programme, decode_event, link_message, message_headers, message_span,
+    split_programme_topic, ApplicationAdapters, DeadLetter, HandleError, IncomingEvent,
+    KafkaEventPublisher, MessageTransactions, PayloadDecoders, PostgresConsumerOffsets, Topics,
+};
+use loyalty_core::{
+    BonusPolicy, Campaigns, CustomerProfiles, CustomerRegistered, CustomerRegisteredEventHandler,
+    ExchangeRates, Households, LoyaltyErrors, LoyaltyPoints, OrderConfirmed,
+    OrderConfirmedEventHandler, Programmes, Referrals, SystemClock, VestingPolicy,
+};
 use rdkafka::client::ClientContext;
-use rdkafka::config::{ClientConfig, RDKafkaLogLevel};
+use rdkafka::config::ClientConfig;
 use rdkafka::consumer::stream_consumer::StreamConsumer;
-use rdkafka::consumer::{CommitMode, Consumer, ConsumerContext};
-use rdkafka::message::BorrowedMessage;
-use rdkafka::Message;
-use tracing::{error, info};
+use rdkafka::consumer::{CommitMode, Consumer, ConsumerContext, Rebalance};
+use rdkafka::message::{BorrowedMessage, OwnedMessage};
+use rdkafka::statistics::Statistics;
+use rdkafka::{Message, Offset, TopicPartitionList};
+use tokio::sync::{mpsc, watch, Notify, Semaphore, SemaphorePermit};
+use tokio::time::Instant;
+use tracing::{error, info, Instrument, Span};
+
+use super::health::{ConsumerHealth, STATISTICS_INTERVAL_MS};
+use super::pipeline::{BatchLimits, OffsetTracker, ProcessingLimits};
+use super::retry::{MessageOrigin, PausedPartitions, RetryPolicy};
+
+/// Commits the offsets handled so far before partitions are revoked, so whichever consumer is
+/// assigned them next doesn't read those messages again, and holds assigned partitions until they
+/// are moved to their stored offsets when those are kept in the database. Also keeps track of the
+/// consumer's health from its assignments and the statistics librdkafka reports.
+pub struct CustomContext {
+    consumer: OnceLock<Weak<LoggingConsumer>>,
+    offsets: Arc<Mutex<OffsetTracker>>,
+    offset_store: Option<Arc<PostgresConsumerOffsets>>,
+    unpositioned: Mutex<Vec<(String, i32)>>,
+    positioning: Notify,
+    health: Arc<ConsumerHealth>,
+}
+
+impl ClientContext for CustomContext {
+    fn stats(&self, statistics: Statistics) {
+        self.health.record_statistics(&statistics, Utc::now());
+    }
+}
+
+impl ConsumerContext for CustomContext {
+    fn pre_rebalance(&self, rebalance: &Rebalance<'_>) {
+        match rebalance {
+            Rebalance::Revoke(partitions) => {
+                info!("Partitions revoked: {}", describe_partitions(partitions));
+
+                self.health.revoked(partitions);
+
+                self.unpositioned
+                    .lock()
+                    .unwrap()
+                    .retain(|(topic, partition)| {
+                        !partitions
+                            .elements()
+                            .iter()
+                            .any(|p| p.topic() == topic && p.partition() == *partition)
+                    });
+
+                let revoked: Vec<_> = {
+                    let mut offsets = self.offsets.lock().unwrap();
+
+                    partitions
+                        .elements()
+                        .iter()
+                        .filter_map(|p| {
+                            let offset = offsets.revoke(p.topic(), p.partition())?;
+
+                            Some((p.topic().to_string(), p.partition(), offset))
+                        })
+                        .collect()
+                };
+
+                // The consumer is gone once it is being dropped, it has already committed by then
+                if let Some(consumer) = self.consumer.get().and_then(Weak::upgrade) {
+                    commit_sync(&consumer, revoked);
+                }
+            }
+            Rebalance::Error(e) => error!("Rebalance failed: {:?}", e),
+            Rebalance::Assign(_) => {}
+        }
+    }
+
+    fn post_rebalance(&self, rebalance: &Rebalance<'_>) {
+        if let Rebalance::Assign(partitions) = rebalance {
+            info!("Partitions assigned: {}", describe_partitions(partitions));
+
+            self.health.assigned(partitions);
+
+            if self.offset_store.is_some() {
+                self.hold_until_positioned(partitions);
+            }
+        }
+    }
+}
+
+impl CustomContext {
+    /// Pauses assigned partitions until the consumer has moved them to their stored offsets,
+    /// which can't be read here without blocking the consumer.
+    fn hold_until_positioned(&self, partitions: &TopicPartitionList) {
+        let Some(consumer) = self.consumer.get().and_then(Weak::upgrade) else {
+            return;
+        };
+
+        if let Err(e) = consumer.pause(partitions) {
+            error!(
+                "Failure pausing assigned partitions, starting from committed offsets: {:?}",
+                e
+            );
+            return;
+        }
+
+        self.unpositioned.lock().unwrap().extend(
+            partitions
+                .elements()
+                .iter()
+                .map(|p| (p.topic().to_string(), p.partition())),
+        );
+
+        self.positioning.notify_one();
+    }
+}
+
+fn describe_partitions(partitions: &TopicPartitionList) -> String {
+    partitions
+        .elements()
+        .iter()
+        .map(|p| format!("{}[{}]", p.topic(), p.partition()))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Commits the given offsets, waiting for the broker to acknowledge them.
+fn commit_sync(consumer: &LoggingConsumer, offsets: Vec<(String, i32, i64)>) {
+    let mut partitions = TopicPartitionList::new();
+
+    for (topic, partition, offset) in offsets {
+        if let Err(e) = partitions.add_partition_offset(&topic, partition, Offset::Offset(offset)) {
+            error!("Failure committing offset: {:?}", e);
+        }
+    }
 
-pub struct CustomContext;
+    if partitions.count() == 0 {
+        return;
+    }
 
-impl ClientContext for CustomContext {}
+    match consumer.commit(&partitions, CommitMode::Sync) {
+        Ok(()) => info!("Committed offsets: {}", describe_partitions(&partitions)),
+        Err(e) => error!("Failure committing offsets: {:?}", e),
+    }
+}
 
-impl ConsumerContext for CustomContext {}
+/// Runs `future` unless shutdown is signalled first.
+async fn until_shutdown<F: Future>(
+    shutdown: &mut watch::Receiver<bool>,
+    future: F,
+) -> Option<F::Output> {
+    tokio::select! {
+        biased;
+        _ = shutdown.wait_for(|stopping| *stopping) => None,
+        output = future => Some(output),
+    }
+}
 
 type LoggingConsumer = StreamConsumer<CustomContext>;
 
-pub struct KafkaConnection<T: LoyaltyPoints + Send + Sync> {
-    pub consumer: LoggingConsumer,
-    adapters: ApplicationAdapters<T>,
+/// How long to wait before handling a message again when it could neither be handled nor moved
+/// to a retry or dead-letter topic.
+const STALLED_RETRY_DELAY: Duration = Duration::from_secs(5);
+
+/// Subscription pattern matching both the shared topic and the `<programme>.<topic>` topics
+/// dedicated to a single programme.
+pub fn programme_topic_pattern(topic: &str) -> String {
+    format!("^([a-z0-9-]+\\.)?{}$", topic)
 }
 
-pub struct KafkaCredentials {
-    pub username: String,
-    pub password: String,
+pub struct KafkaConnection<
+    T: LoyaltyPoints
+        + Programmes
+        + Campaigns
+        + CustomerProfiles
+        + Referrals
+        + Households
+        + MessageTransactions
+        + Send
+        + Sync,
+> {
+    pub consumer: Arc<LoggingConsumer>,
+    group_id: String,
+    topics: Topics,
+    decoders: PayloadDecoders,
+    adapters: ApplicationAdapters<T>,
+    vesting: VestingPolicy,
+    bonuses: BonusPolicy,
+    exchange_rates: Box<dyn ExchangeRates + Send + Sync>,
+    publisher: KafkaEventPublisher,
+    retries: RetryPolicy,
+    paused: Mutex<PausedPartitions>,
+    limits: ProcessingLimits,
+    batch: Option<BatchLimits>,
+    in_flight: Semaphore,
+    offsets: Arc<Mutex<OffsetTracker>>,
+    offset_store: Option<Arc<PostgresConsumerOffsets>>,
 }
 
-impl<T: LoyaltyPoints + Send + Sync> KafkaConnection<T> {
-    #[tracing::instrument(name = "new_kafka_connection", skip(broker, credentials, adapters))]
+/// A message handed to a lane, holding its place in the in-flight limit until it is handled.
+type InFlight<'a> = (OwnedMessage, SemaphorePermit<'a>);
+
+impl<
+        T: LoyaltyPoints
+            + Programmes
+            + Campaigns
+            + CustomerProfiles
+            + Referrals
+            + Households
+            + MessageTransactions
+            + Send
+            + Sync,
+    > KafkaConnection<T>
+{
+    #[tracing::instrument(
+        name = "new_kafka_connection",
+        skip(
+            config,
+            decoders,
+            adapters,
+            vesting,
+            bonuses,
+            exchange_rates,
+            publisher,
+            retries,
+            limits,
+            batch,
+            offset_store
+        )
+    )]
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
-        broker: String,
-        group_id: String,
-        credentials: Option<KafkaCredentials>,
+        mut config: ClientConfig,
+        topics: Topics,
+        decoders: PayloadDecoders,
         adapters: ApplicationAdapters<T>,
+        vesting: VestingPolicy,
+        bonuses: BonusPolicy,
+        exchange_rates: Box<dyn ExchangeRates + Send + Sync>,
+        publisher: KafkaEventPublisher,
+        retries: RetryPolicy,
+        limits: ProcessingLimits,
+        batch: Option<BatchLimits>,
+        offset_store: Option<PostgresConsumerOffsets>,
     ) -> KafkaConnection<T> {
-        let context = CustomContext;
-
-        let consumer: LoggingConsumer = match credentials {
-            Some(creds) => ClientConfig::new()
-                .set("group.id", group_id)
-                .set("bootstrap.servers", broker)
-                .set("security.protocol", "SASL_SSL")
-                .set("sasl.mechanisms", "PLAIN")
-                .set("sasl.username", creds.username)
-                .set("sasl.password", creds.password)
-                .set_log_level(RDKafkaLogLevel::Debug)
-                .create_with_context(context)
-                .expect("Consumer creation failed"),
-            None => ClientConfig::new()
-                .set("group.id", group_id)
-                .set("bootstrap.servers", broker)
-                .set_log_level(RDKafkaLogLevel::Debug)
-                .create_with_context(context)
-                .expect("Consumer creation failed"),
+        let offsets = Arc::new(Mutex::new(OffsetTracker::default()));
+        let offset_store = offset_store.map(Arc::new);
+
+        let context = CustomContext {
+            consumer: OnceLock::new(),
+            offsets: offsets.clone(),
+            offset_store: offset_store.clone(),
+            unpositioned: Mutex::new(vec![]),
+            positioning: Notify::new(),
+            health: Arc::new(ConsumerHealth::default()),
         };
 
+        // Readiness expects statistics at this interval, so it isn't left to configuration
+        config.set("statistics.interval.ms", STATISTICS_INTERVAL_MS.to_string());
+
+        let group_id = config.get("group.id").unwrap_or_default().to_string();
+
+        let consumer: LoggingConsumer = config
+            .create_with_context(context)
+            .expect("Consumer creation failed");
+
+        let consumer = Arc::new(consumer);
+
+        let _ = consumer.context().consumer.set(Arc::downgrade(&consumer));
+
         Self {
             consumer,
+            group_id,
+            topics,
+            decoders,
             adapters,
+            vesting,
+            bonuses,
+            exchange_rates,
+            publisher,
+            retries,
+            paused: Mutex::new(PausedPartitions::default()),
+            limits,
+            batch,
+            in_flight: Semaphore::new(limits.max_in_flight()),
+            offsets,
+            offset_store,
+        }
+    }
+
+    /// The consumer's health, as reported by librdkafka.
+    pub fn health(&self) -> &Arc<ConsumerHealth> {
+        &self.consumer.context().health
+    }
+
+    /// Receives messages until shutdown is signalled, either handing each to the lane for its
+    /// key or, in batch mode, handling them a batch at a time. Returns once every message already
+    /// received has been handled.
+    pub async fn process(&self, shutdown: watch::Receiver<bool>) {
+        if let Some(batch) = self.batch {
+            return self.process_batches(batch, shutdown).await;
+        }
+
+        let (lanes, receivers): (Vec<_>, Vec<_>) = (0..self.limits.lanes())
+            .map(|_| mpsc::channel(self.limits.max_in_flight()))
+            .unzip();
+
+        let lanes_done = join_all(
+            receivers
+                .into_iter()
+                .map(|r| self.process_lane(r, shutdown.clone())),
+        );
+
+        tokio::join!(self.receive(lanes, shutdown), lanes_done);
+    }
+
+    /// Lanes run concurrently with each other, but each handles its own messages in order. The
+    /// lanes are dropped when receiving stops, so each finishes once it has drained its queue.
+    async fn receive<'a>(
+        &'a self,
+        lanes: Vec<mpsc::Sender<InFlight<'a>>>,
+        mut shutdown: watch::Receiver<bool>,
+    ) {
+        loop {
+            // Stop receiving once enough messages are in flight, the permit travels with the
+            // message and is given back once it has been handled
+            let Some(Ok(permit)) = until_shutdown(&mut shutdown, self.in_flight.acquire()).await
+            else {
+                break;
+            };
+
+            let Some(received) = until_shutdown(&mut shutdown, self.next_message(None)).await
+            else {
+                break;
+            };
+
+            let Some(m) = received else {
+                continue;
+            };
+
+            let lane = &lanes[self.limits.lane_for(&m)];
+
+            if lane.send((m.detach(), permit)).await.is_err() {
+                break;
+            }
+        }
+
+        info!("Stopped receiving, draining in-flight messages");
+    }
+
+    async fn process_lane(
+        &self,
+        mut messages: mpsc::Receiver<InFlight<'_>>,
+        mut shutdown: watch::Receiver<bool>,
+    ) {
+        while let Some((m, permit)) = messages.recv().await {
+            let handled = match self.process_message(&m).await {
+                Ok(()) => true,
+                Err(e) => {
+                    error!("Failure moving failed message: {:?}", e);
+                    self.retry_stalled(&m, &mut shutdown).await
+                }
+            };
+
+            drop(permit);
+
+            if handled {
+                self.commit_handled(&m).await;
+            }
+        }
+    }
+
+    /// Handles a message that could neither be handled nor moved again until it is, reporting its
+    /// partition as stalled meanwhile since nothing after it can be committed. Gives up once
+    /// shutdown is signalled, the message is read again on restart. Returns whether it was
+    /// handled.
+    async fn retry_stalled(&self, m: &OwnedMessage, shutdown: &mut watch::Receiver<bool>) -> bool {
+        self.health().stalled(m.topic(), m.partition());
+
+        let mut handled = false;
+
+        while until_shutdown(shutdown, tokio::time::sleep(STALLED_RETRY_DELAY))
+            .await
+            .is_some()
+        {
+            match self.process_message(m).await {
+                Ok(()) => {
+                    handled = true;
+                    break;
+                }
+                Err(e) => error!("Failure moving failed message: {:?}", e),
+            }
+        }
+
+        self.health().unstalled(m.topic(), m.partition());
+
+        handled
+    }
+
+    async fn process_batches(&self, batch: BatchLimits, mut shutdown: watch::Receiver<bool>) {
+        loop {
+            let deadline = Instant::now() + batch.max_wait();
+            let mut messages = vec![];
+
+            while messages.len() < batch.max_messages() {
+                let remaining = deadline.saturating_duration_since(Instant::now());
+
+                if remaining.is_zero() {
+                    break;
+                }
+
+                let Some(received) =
+                    until_shutdown(&mut shutdown, self.next_message(Some(remaining))).await
+                else {
+                    break;
+                };
+
+                if let Some(m) = received {
+                    messages.push(m.detach());
+                }
+            }
+
+            if !messages.is_empty() {
+                self.process_batch(&messages, &mut shutdown).await;
+            }
+
+            if *shutdown.borrow() {
+                info!("Stopped receiving, last batch handled");
+                return;
+            }
         }
     }
 
-    pub async fn process(&self) {
+    /// Waits for the next message that is due to be handled, pausing the partition of any retry
+    /// that isn't yet. Gives up after `max_wait`, or in time to resume a paused partition.
+    async fn next_message(&self, max_wait: Option<Duration>) -> Option<BorrowedMessage<'_>> {
+        self.start_from_stored_offsets().await;
+        self.resume_due_partitions();
+
         info!("Wait for receive");
-        match &self.consumer.recv().await {
-            Err(e) => tracing::warn!("Kafka error: {}", e),
-            Ok(m) => {
-                info!("Received message");
-                let _ = &self.process_message(m).await;
+
+        let receive = async {
+            match max_wait.into_iter().chain(self.next_resume()).min() {
+                Some(wait) => tokio::time::timeout(wait, self.consumer.recv()).await.ok(),
+                None => Some(self.consumer.recv().await),
+            }
+        };
+
+        // Newly assigned partitions stay paused until they are positioned, which can't wait for
+        // a message from another partition
+        let received = tokio::select! {
+            received = receive => received?,
+            _ = self.consumer.context().positioning.notified() => return None,
+        };
+
+        let m = match received {
+            Err(e) => {
+                tracing::warn!("Kafka error: {}", e);
+                return None;
+            }
+            Ok(m) => m,
+        };
+
+        info!("Received message");
+
+        self.health().message_received(Utc::now());
+
+        if let Some(wait) = MessageOrigin::of(&m, &message_headers(&m)).wait(Utc::now()) {
+            info!("Retry is not due for {:?}, pausing partition", wait);
+            self.pause_partition(&m, wait);
+            return None;
+        }
+
+        self.offsets
+            .lock()
+            .unwrap()
+            .started(m.topic(), m.partition(), m.offset());
+
+        Some(m)
+    }
+
+    /// Processes the message in a span continuing the trace it was produced in.
+    pub async fn process_message(&self, m: &OwnedMessage) -> Result<(), LoyaltyErrors> {
+        let headers = message_headers(m);
+        let origin = MessageOrigin::of(m, &headers);
+
+        async {
+            let handle_result = match self.decode(m, &headers, &origin).await {
+                Ok(event) => self.handle_message(m, event).await,
+                Err(e) => Err(e),
+            };
+
+            self.settle(m, &headers, &origin, handle_result).await
+        }
+        .instrument(self.message_span(m, &headers))
+        .await
+    }
+
+    fn message_span(&self, m: &OwnedMessage, headers: &HashMap<String, String>) -> Span {
+        let span = message_span(m.topic(), m.partition(), m.offset(), headers);
+
+        span.record("messaging.kafka.consumer.group", self.group_id.as_str());
+
+        span
+    }
+
+    /// Handles registrations one at a time, then every order in the batch together so each
+    /// customer's account is loaded and written once. Registrations go first so a referred
+    /// customer's first order still rewards the referral. Offsets are committed once at the end.
+    /// Each message is decoded in its own producer's trace, the batch span links to them all.
+    #[tracing::instrument(name = "process_batch", skip(self, messages, shutdown), fields(messages = messages.len()))]
+    async fn process_batch(&self, messages: &[OwnedMessage], shutdown: &mut watch::Receiver<bool>) {
+        let mut orders = vec![];
+        let mut settled = vec![];
+
+        for m in messages {
+            let headers = message_headers(m);
+            let origin = MessageOrigin::of(m, &headers);
+
+            link_message(&Span::current(), &headers);
+
+            let span = self.message_span(m, &headers);
+
+            let handle_result = match self
+                .decode(m, &headers, &origin)
+                .instrument(span.clone())
+                .await
+            {
+                Ok(IncomingEvent::OrderConfirmed(evt)) => {
+                    orders.push((m, headers, origin, evt));
+                    continue;
+                }
+                Ok(event) => {
+                    self.handle_event(&self.adapters.loyalty_points, event)
+                        .instrument(span.clone())
+                        .await
+                }
+                Err(e) => Err(e),
+            };
+
+            let result = self
+                .settle(m, &headers, &origin, handle_result)
+                .instrument(span)
+                .await;
+
+            settled.push((m, result));
+        }
+
+        let events: Vec<&OrderConfirmed> = orders.iter().map(|(.., evt)| evt).collect();
+
+        let results = OrderConfirmedEventHandler::handle_batch(
+            &self.adapters.loyalty_points,
+            &self.adapters.loyalty_points,
+            &self.adapters.loyalty_points,
+            &self.adapters.loyalty_points,
+            &self.adapters.loyalty_points,
+            self.exchange_rates.as_ref(),
+            &self.vesting,
+            &self.bonuses,
+            &SystemClock,
+            &events,
+        )
+        .await;
+
+        for ((m, headers, origin, _), result) in orders.iter().zip(results) {
+            let handle_result = result.map_err(|e| {
+                HandleError::processing("Failure processing 'OrderConfirmed' event", &e)
+            });
+
+            settled.push((m, self.settle(m, headers, origin, handle_result).await));
+        }
+
+        let mut commits = HashMap::new();
+
+        for (m, result) in settled {
+            if let Err(e) = result {
+                error!("Failure moving failed message: {:?}", e);
+
+                if !self.retry_stalled(m, shutdown).await {
+                    continue;
+                }
+            }
+
+            let handled =
+                self.offsets
+                    .lock()
+                    .unwrap()
+                    .handled(m.topic(), m.partition(), m.offset());
+
+            if let Some(offset) = handled {
+                commits.insert((m.topic(), m.partition()), offset);
+            }
+        }
+
+        let mut partitions = TopicPartitionList::new();
+
+        for ((topic, partition), offset) in commits {
+            if let Err(e) =
+                partitions.add_partition_offset(topic, partition, Offset::Offset(offset))
+            {
+                error!("Failure committing offset: {:?}", e);
+            }
+        }
+
+        if partitions.count() > 0 {
+            if let Err(e) = self.consumer.commit(&partitions, CommitMode::Async) {
+                error!("Failure committing offsets: {:?}", e);
             }
         }
     }
 
-    #[tracing::instrument(name = "process_message", skip(self))]
-    pub async fn process_message(&self, m: &BorrowedMessage<'_>) {
-        let payload = match m.payload_view::<str>() {
-            None => "",
-            Some(Ok(s)) => s,
-            Some(Err(e)) => {
-                tracing::warn!("Error while deserializing message payload: {:?}", e);
-                ""
+    /// Decodes the message, checking it belongs to the programme its topic is dedicated to.
+    async fn decode(
+        &self,
+        m: &OwnedMessage,
+        headers: &HashMap<String, String>,
+        origin: &MessageOrigin,
+    ) -> Result<IncomingEvent, HandleError> {
+        let (topic_programme, topic) = split_programme_topic(&origin.topic);
+
+        let topic = self.topics.canonical(topic);
+
+        let body = self
+            .decoders
+            .decode(topic, headers, m.payload().unwrap_or_default())
+            .await?;
+
+        let (metadata, event) = decode_event(topic, headers, body)
+            .map_err(|e| HandleError::Invalid(format!("{:?}", e)))?;
+
+        if let Some(id) = &metadata.id {
+            Span::current().record("messaging.message.id", id.as_str());
+        }
+
+        info!(
+            "Received '{}' v{} event {:?}, attempt {}",
+            metadata.event_type,
+            metadata.version,
+            metadata.id,
+            origin.attempts + 1
+        );
+
+        check_topic_programme(topic_programme, event.programme_id())
+            .map_err(|e| HandleError::Invalid(format!("{:?}", e)))?;
+
+        if let IncomingEvent::OrderConfirmed(evt) = &event {
+            evt.validate().map_err(|e| {
+                HandleError::Invalid(format!("Invalid 'OrderConfirmed' event: {}", e))
+            })?;
+        }
+
+        Ok(event)
+    }
+
+    /// A failed message is moved to the next retry topic if it may succeed later, or the
+    /// dead-letter topic if not, so it doesn't block the partition. It is only committed once it
+    /// is safely there.
+    async fn settle(
+        &self,
+        m: &OwnedMessage,
+        headers: &HashMap<String, String>,
+        origin: &MessageOrigin,
+        handle_result: Result<(), HandleError>,
+    ) -> Result<(), LoyaltyErrors> {
+        let Err(e) = handle_result else {
+            return Ok(());
+        };
+
+        error!("{}", e.message());
+
+        let attempts = origin.attempts + 1;
+        let payload = m.payload().unwrap_or_default();
+
+        let moved = match self
+            .retries
+            .next_tier(attempts)
+            .filter(|_| e.is_retryable())
+        {
+            Some(tier) => {
+                info!("Retrying on '{}' in {:?}", tier.topic(), tier.delay());
+
+                let retry_headers =
+                    origin.retry_headers(headers, attempts, Utc::now() + tier.delay());
+
+                self.publisher
+                    .republish(tier.topic(), m.key(), payload, retry_headers)
+                    .await
+            }
+            None => {
+                let dead_letter = DeadLetter {
+                    topic: &origin.topic,
+                    partition: origin.partition,
+                    offset: origin.offset,
+                    key: m.key(),
+                    payload,
+                    headers,
+                    error: &e,
+                    attempts,
+                };
+
+                self.publisher.publish_dead_letter(&dead_letter).await
+            }
+        };
+
+        moved?;
+
+        // Its changes were rolled back, but it has been dealt with
+        match &self.offset_store {
+            Some(store) => store.processed(m.topic(), m.partition(), m.offset()).await,
+            None => Ok(()),
+        }
+    }
+
+    /// Commits the partition up to the first message still in flight, messages after it may
+    /// already be handled on other lanes but are read again if the consumer restarts. When offsets
+    /// are stored in the database those are recognised and skipped.
+    async fn commit_handled(&self, m: &OwnedMessage) {
+        let handled = self
+            .offsets
+            .lock()
+            .unwrap()
+            .handled(m.topic(), m.partition(), m.offset());
+
+        let Some(offset) = handled else {
+            return;
+        };
+
+        let mut partitions = TopicPartitionList::new();
+
+        let commit = partitions
+            .add_partition_offset(m.topic(), m.partition(), Offset::Offset(offset))
+            .and_then(|_| self.consumer.commit(&partitions, CommitMode::Async));
+
+        if let Err(e) = commit {
+            error!("Failure committing offset: {:?}", e);
+        }
+
+        if let Some(store) = &self.offset_store {
+            if let Err(e) = store.advance(m.topic(), m.partition(), offset).await {
+                error!("Failure storing offset: {:?}", e);
             }
+        }
+    }
+
+    /// Stops fetching from the message's partition and rewinds to the message, so it is read
+    /// again once the partition is resumed.
+    fn pause_partition(&self, m: &BorrowedMessage<'_>, wait: Duration) {
+        let mut partitions = TopicPartitionList::new();
+        partitions.add_partition(m.topic(), m.partition());
+
+        if let Err(e) = self.consumer.pause(&partitions) {
+            error!("Failure pausing partition: {:?}", e);
+            return;
+        }
+
+        self.paused
+            .lock()
+            .unwrap()
+            .pause(m.topic(), m.partition(), Instant::now() + wait);
+
+        let seek = self.consumer.seek(
+            m.topic(),
+            m.partition(),
+            Offset::Offset(m.offset()),
+            Duration::from_secs(5),
+        );
+
+        if let Err(e) = seek {
+            error!("Failure rewinding paused partition: {:?}", e);
+        }
+    }
+
+    /// Moves partitions assigned since the last message to their stored offsets and resumes them,
+    /// partitions without one start from Kafka's committed offset.
+    async fn start_from_stored_offsets(&self) {
+        let Some(store) = &self.offset_store else {
+            return;
         };
 
-        let evt_payload = serde_json::from_str(payload);
+        let assigned = std::mem::take(&mut *self.consumer.context().unpositioned.lock().unwrap());
 
-        match evt_payload {
-            Ok(evt) => {
-                let handle_result = OrderConfirmedEventHandler::handle(&self.adapters.loyalty_points, &evt).await;
+        if assigned.is_empty() {
+            return;
+        }
 
-                match handle_result {
-                    Ok(_) => {
-                        let _ = self.consumer.commit_message(m, CommitMode::Async);
+        // Messages already processed are still skipped, they are just read again first
+        match store.positions(&assigned).await {
+            Ok(positions) => {
+                for (topic, partition, offset) in positions {
+                    let seek = self.consumer.seek(
+                        &topic,
+                        partition,
+                        Offset::Offset(offset),
+                        Duration::from_secs(5),
+                    );
+
+                    match seek {
+                        Ok(()) => info!(
+                            "Starting {}[{}] from stored offset {}",
+                            topic, partition, offset
+                        ),
+                        Err(e) => error!("Failure seeking to stored offset: {:?}", e),
                     }
-                    Err(_) => error!("Failure processing 'OrderConfirmed' event"),
                 }
             }
-            Err(_) => {
-                error!("Failure parsing payload to 'OrderConfirmed' event")
+            Err(e) => error!(
+                "Failure reading stored offsets, starting from committed offsets: {:?}",
+                e
+            ),
+        }
+
+        let mut partitions = TopicPartitionList::new();
+
+        for (topic, partition) in &assigned {
+            partitions.add_partition(topic, *partition);
+        }
+
+        if let Err(e) = self.consumer.resume(&partitions) {
+            error!("Failure resuming assigned partitions: {:?}", e);
+        }
+    }
+
+    fn resume_due_partitions(&self) {
+        let due = self.paused.lock().unwrap().take_due(Instant::now());
+
+        if due.is_empty() {
+            return;
+        }
+
+        let mut partitions = TopicPartitionList::new();
+
+        for (topic, partition) in &due {
+            partitions.add_partition(topic, *partition);
+        }
+
+        if let Err(e) = self.consumer.resume(&partitions) {
+            error!("Failure resuming partitions: {:?}", e);
+        }
+    }
+
+    fn next_resume(&self) -> Option<Duration> {
+        self.paused.lock().unwrap().next_resume(Instant::now())
+    }
+
+    /// Handles the message's event, making its changes in one transaction with its offset when
+    /// offsets are stored in the database. A message already processed is skipped.
+    async fn handle_message(
+        &self,
+        m: &OwnedMessage,
+        event: IncomingEvent,
+    ) -> Result<(), HandleError> {
+        let Some(store) = &self.offset_store else {
+            return self
+                .handle_event(&self.adapters.loyalty_points, event)
+                .await;
+        };
+
+        let transaction = self
+            .adapters
+            .loyalty_points
+            .begin_message(store.group_id(), m.topic(), m.partition(), m.offset())
+            .await
+            .map_err(|e| HandleError::processing("Failure starting message transaction", &e))?;
+
+        let Some(transaction) = transaction else {
+            info!("Message was already processed, skipping");
+            return Ok(());
+        };
+
+        // An error drops the transaction, rolling back every change the message made
+        self.handle_event(&transaction, event).await?;
+
+        transaction
+            .commit_message()
+            .await
+            .map_err(|e| HandleError::processing("Failure committing message transaction", &e))
+    }
+
+    async fn handle_event(&self, adapters: &T, event: IncomingEvent) -> Result<(), HandleError> {
+        match event {
+            IncomingEvent::OrderConfirmed(evt) => self.handle_order_confirmed(adapters, &evt).await,
+            IncomingEvent::CustomerRegistered(evt) => {
+                self.handle_customer_registered(adapters, &evt).await
             }
         }
     }
 
-    pub async fn subscribe(&self, message_channel_name: &str) {
-        let channels = vec![message_channel_name];
-        self.consumer
-            .subscribe(&channels)
-            .expect("Can't subscribe to specified topics");
+    async fn handle_order_confirmed(
+        &self,
+        adapters: &T,
+        evt: &OrderConfirmed,
+    ) -> Result<(), HandleError> {
+        OrderConfirmedEventHandler::handle(
+            adapters,
+            adapters,
+            adapters,
+            adapters,
+            adapters,
+            self.exchange_rates.as_ref(),
+            &self.vesting,
+            &self.bonuses,
+            &SystemClock,
+            evt,
+        )
+        .await
+        .map_err(|e| HandleError::processing("Failure processing 'OrderConfirmed' event", &e))
     }
-}
 
-impl<T: LoyaltyPoints + Send + Sync> Drop for KafkaConnection<T> {
-    fn drop(&mut self) {
-        let _ = self.consumer.unassign();
+    async fn handle_customer_registered(
+        &self,
+        adapters: &T,
+        evt: &CustomerRegistered,
+    ) -> Result<(), HandleError> {
+        CustomerRegisteredEventHandler::handle(adapters, adapters, &SystemClock, evt)
+            .await
+            .map_err(|e| {
+                HandleError::processing("Failure processing 'CustomerRegistered' event", &e)
+            })
+    }
+
+    /// Commits every handled offset synchronously and leaves the consumer group, so the
+    /// partitions are handed to another consumer straight away rather than once the session
+    /// times out. Call once `process` has returned.
+    pub fn close(&self) {
+        let committed = self.offsets.lock().unwrap().committed();
+
+        commit_sync(&self.consumer, committed);
+
+        self.consumer.unsubscribe();
+
+        info!("Left consumer group");
+    }
+
+    /// Subscribes to the given topics, and the retry topics failed messages are moved to.
+    pub async fn subscribe(&self, message_channel_names: &[&str]) {
+        let topics: Vec<&str> = message_channel_names
+            .iter()
+            .copied()
+            .chain(self.retries.topics())
+            .collect();
+
+        self.consumer
+            .subscribe(&topics)
+            .expect("Can't subscribe to specified topics");
     }
 }
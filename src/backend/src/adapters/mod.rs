@@ -1,2 +1,8 @@
+mod health;
 mod kafka_adapter;
-pub use kafka_adapter::{KafkaConnection, KafkaCredentials};
\ No newline at end of file
+mod pipeline;
+mod retry;
+pub use health::ConsumerHealth;
+pub use kafka_adapter::{programme_topic_pattern, KafkaConnection};
+pub use pipeline::{BatchLimits, ProcessingLimits};
+pub use retry::{RetryPolicy, RetryTier};
\ No newline at end of file
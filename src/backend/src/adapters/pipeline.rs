@@ -0,0 +1,220 @@
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{BTreeSet, HashMap};
+use std::hash::{Hash, Hasher};
+use std::time::Duration;
+
+use rdkafka::Message;
+
+/// How many messages are processed at once. Messages are spread across `lanes` by key, each lane
+/// handles its messages one at a time so messages for the same customer stay in order.
+#[derive(Clone, Copy, Debug)]
+pub struct ProcessingLimits {
+    lanes: usize,
+    max_in_flight: usize,
+}
+
+impl ProcessingLimits {
+    pub fn new(lanes: usize, max_in_flight: usize) -> Self {
+        Self {
+            lanes: lanes.max(1),
+            max_in_flight: max_in_flight.max(1),
+        }
+    }
+
+    pub fn lanes(&self) -> usize {
+        self.lanes
+    }
+
+    /// The most messages received but not yet handled, across every lane.
+    pub fn max_in_flight(&self) -> usize {
+        self.max_in_flight
+    }
+
+    /// The lane a message is handled on. Messages without a key are kept in order per partition.
+    pub fn lane_for<M: Message>(&self, message: &M) -> usize {
+        let mut hasher = DefaultHasher::new();
+
+        match message.key() {
+            Some(key) => key.hash(&mut hasher),
+            None => (message.topic(), message.partition()).hash(&mut hasher),
+        }
+
+        (hasher.finish() % self.lanes as u64) as usize
+    }
+}
+
+impl Default for ProcessingLimits {
+    fn default() -> Self {
+        Self::new(8, 64)
+    }
+}
+
+/// Batch mode pulls up to `max_messages` at a time, waiting at most `max_wait` for them to
+/// arrive, and handles them together.
+#[derive(Clone, Copy, Debug)]
+pub struct BatchLimits {
+    max_messages: usize,
+    max_wait: Duration,
+}
+
+impl BatchLimits {
+    pub fn new(max_messages: usize, max_wait: Duration) -> Self {
+        Self {
+            max_messages: max_messages.max(1),
+            max_wait,
+        }
+    }
+
+    pub fn max_messages(&self) -> usize {
+        self.max_messages
+    }
+
+    pub fn max_wait(&self) -> Duration {
+        self.max_wait
+    }
+}
+
+/// Tracks the messages in flight on each partition, so an offset is only committed once every
+/// message before it on the partition has been handled.
+#[derive(Default)]
+pub struct OffsetTracker {
+    partitions: HashMap<(String, i32), PartitionOffsets>,
+}
+
+#[derive(Default)]
+struct PartitionOffsets {
+    in_flight: BTreeSet<i64>,
+    handled_up_to: Option<i64>,
+    committed: Option<i64>,
+}
+
+impl OffsetTracker {
+    pub fn started(&mut self, topic: &str, partition: i32, offset: i64) {
+        self.partitions
+            .entry((topic.to_string(), partition))
+            .or_default()
+            .in_flight
+            .insert(offset);
+    }
+
+    /// Marks a message as handled, returning the offset to commit for its partition if that has
+    /// moved on. A message that is never handled holds back commits for the rest of its partition.
+    pub fn handled(&mut self, topic: &str, partition: i32, offset: i64) -> Option<i64> {
+        let offsets = self.partitions.get_mut(&(topic.to_string(), partition))?;
+
+        // A message from before the partition was revoked and assigned again is handled by
+        // whichever consumer reads it next, so it mustn't move this partition on
+        if !offsets.in_flight.remove(&offset) {
+            return None;
+        }
+
+        offsets.handled_up_to = offsets.handled_up_to.max(Some(offset + 1));
+
+        let commit = match offsets.in_flight.first() {
+            Some(lowest) => *lowest,
+            None => offsets.handled_up_to?,
+        };
+
+        if offsets.committed >= Some(commit) {
+            return None;
+        }
+
+        offsets.committed = Some(commit);
+
+        Some(commit)
+    }
+
+    /// Stops tracking a revoked partition, returning the offset last committed for it.
+    pub fn revoke(&mut self, topic: &str, partition: i32) -> Option<i64> {
+        self.partitions
+            .remove(&(topic.to_string(), partition))?
+            .committed
+    }
+
+    /// The offset last committed on every partition, to commit again synchronously on shutdown.
+    pub fn committed(&self) -> Vec<(String, i32, i64)> {
+        self.partitions
+            .iter()
+            .filter_map(|((topic, partition), offsets)| {
+                Some((topic.clone(), *partition, offsets.committed?))
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rdkafka::message::OwnedMessage;
+    use rdkafka::Timestamp;
+
+    use super::*;
+
+    #[test]
+    fn offset_is_committed_once_every_earlier_message_is_handled() {
+        let mut tracker = OffsetTracker::default();
+
+        for offset in 10..13 {
+            tracker.started("order-completed", 0, offset);
+        }
+
+        assert_eq!(tracker.handled("order-completed", 0, 11), Some(10));
+        assert_eq!(tracker.handled("order-completed", 0, 12), None);
+        assert_eq!(tracker.handled("order-completed", 0, 10), Some(13));
+        assert_eq!(
+            tracker.committed(),
+            vec![("order-completed".to_string(), 0, 13)]
+        );
+    }
+
+    #[test]
+    fn partitions_are_committed_independently() {
+        let mut tracker = OffsetTracker::default();
+
+        tracker.started("order-completed", 0, 5);
+        tracker.started("order-completed", 1, 7);
+        tracker.started("order-completed", 1, 8);
+
+        assert_eq!(tracker.handled("order-completed", 1, 7), Some(8));
+        assert_eq!(tracker.handled("order-completed", 0, 5), Some(6));
+        assert_eq!(tracker.handled("order-completed", 1, 8), Some(9));
+    }
+
+    #[test]
+    fn message_from_before_a_revocation_does_not_move_the_partition_on() {
+        let mut tracker = OffsetTracker::default();
+
+        tracker.started("order-completed", 0, 3);
+        assert_eq!(tracker.handled("order-completed", 0, 3), Some(4));
+        tracker.started("order-completed", 0, 4);
+
+        assert_eq!(tracker.revoke("order-completed", 0), Some(4));
+        assert_eq!(tracker.handled("order-completed", 0, 4), None);
+
+        tracker.started("order-completed", 0, 6);
+
+        assert_eq!(tracker.handled("order-completed", 0, 4), None);
+        assert_eq!(tracker.handled("order-completed", 0, 6), Some(7));
+    }
+
+    #[test]
+    fn messages_with_the_same_key_share_a_lane() {
+        let limits = ProcessingLimits::new(8, 64);
+        let message = |key: &str, partition: i32| {
+            OwnedMessage::new(
+                None,
+                Some(key.as_bytes().to_vec()),
+                "order-completed".to_string(),
+                Timestamp::NotAvailable,
+                partition,
+                0,
+                None,
+            )
+        };
+
+        assert_eq!(
+            limits.lane_for(&message("james", 0)),
+            limits.lane_for(&message("james", 1))
+        );
+        assert!(limits.lane_for(&message("sarah", 0)) < limits.lanes());
+    }
+}
@@ -0,0 +1,297 @@
+use std::{collections::HashMap, str::FromStr, time::Duration};
+
+use chrono::{DateTime, TimeZone, Utc};
+use rdkafka::message::{Header, OwnedHeaders};
+use rdkafka::Message;
+use tokio::time::Instant;
+
+const ORIGINAL_TOPIC_HEADER: &str = "retry.original.topic";
+const ORIGINAL_PARTITION_HEADER: &str = "retry.original.partition";
+const ORIGINAL_OFFSET_HEADER: &str = "retry.original.offset";
+const ATTEMPTS_HEADER: &str = "retry.attempts";
+const DUE_AT_HEADER: &str = "retry.due-at";
+
+/// A retry topic, messages published to it are not tried again until `delay` has passed.
+#[derive(Clone, Debug)]
+pub struct RetryTier {
+    topic: String,
+    delay: Duration,
+}
+
+impl RetryTier {
+    pub fn new(topic: String, delay: Duration) -> Self {
+        Self { topic, delay }
+    }
+
+    pub fn topic(&self) -> &str {
+        &self.topic
+    }
+
+    pub fn delay(&self) -> Duration {
+        self.delay
+    }
+}
+
+/// Retryable failures move through each tier in turn, and are dead-lettered once every tier has
+/// been tried.
+#[derive(Clone, Debug)]
+pub struct RetryPolicy {
+    tiers: Vec<RetryTier>,
+}
+
+impl RetryPolicy {
+    pub fn new(tiers: Vec<RetryTier>) -> Self {
+        Self { tiers }
+    }
+
+    pub fn topics(&self) -> impl Iterator<Item = &str> {
+        self.tiers.iter().map(RetryTier::topic)
+    }
+
+    /// The tier a message goes to after `attempts` failed attempts, if it has any left.
+    pub fn next_tier(&self, attempts: u32) -> Option<&RetryTier> {
+        let index = usize::try_from(attempts).ok()?.checked_sub(1)?;
+
+        self.tiers.get(index)
+    }
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self::new(vec![
+            RetryTier::new("loyalty-retry-1m".to_string(), Duration::from_secs(60)),
+            RetryTier::new(
+                "loyalty-retry-10m".to_string(),
+                Duration::from_secs(10 * 60),
+            ),
+        ])
+    }
+}
+
+fn header<T: FromStr>(headers: &HashMap<String, String>, name: &str) -> Option<T> {
+    headers.get(name).and_then(|value| value.parse().ok())
+}
+
+/// Where a message was first consumed from and how many times it has been tried. Messages on a
+/// retry topic carry this in `retry.*` headers, anything else is on its first attempt.
+#[derive(Clone, Debug)]
+pub struct MessageOrigin {
+    pub topic: String,
+    pub partition: i32,
+    pub offset: i64,
+    pub attempts: u32,
+    pub due_at: Option<DateTime<Utc>>,
+}
+
+impl MessageOrigin {
+    pub fn of<M: Message>(message: &M, headers: &HashMap<String, String>) -> Self {
+        match headers.get(ORIGINAL_TOPIC_HEADER) {
+            Some(topic) => Self {
+                topic: topic.clone(),
+                partition: header(headers, ORIGINAL_PARTITION_HEADER)
+                    .unwrap_or(message.partition()),
+                offset: header(headers, ORIGINAL_OFFSET_HEADER).unwrap_or(message.offset()),
+                attempts: header(headers, ATTEMPTS_HEADER).unwrap_or_default(),
+                due_at: header(headers, DUE_AT_HEADER)
+                    .and_then(|millis| Utc.timestamp_millis_opt(millis).single()),
+            },
+            None => Self {
+                topic: message.topic().to_string(),
+                partition: message.partition(),
+                offset: message.offset(),
+                attempts: 0,
+                due_at: None,
+            },
+        }
+    }
+
+    /// How long until the message is due to be tried again, if it isn't due yet.
+    pub fn wait(&self, now: DateTime<Utc>) -> Option<Duration> {
+        (self.due_at? - now)
+            .to_std()
+            .ok()
+            .filter(|wait| !wait.is_zero())
+    }
+
+    /// The headers for publishing the message to a retry topic. The original headers are kept so
+    /// binary mode CloudEvents still decode, the `retry.*` headers are replaced.
+    pub fn retry_headers(
+        &self,
+        headers: &HashMap<String, String>,
+        attempts: u32,
+        due_at: DateTime<Utc>,
+    ) -> OwnedHeaders {
+        let partition = self.partition.to_string();
+        let offset = self.offset.to_string();
+        let attempts = attempts.to_string();
+        let due_at = due_at.timestamp_millis().to_string();
+
+        let retry_headers = [
+            (ORIGINAL_TOPIC_HEADER, self.topic.as_str()),
+            (ORIGINAL_PARTITION_HEADER, &partition),
+            (ORIGINAL_OFFSET_HEADER, &offset),
+            (ATTEMPTS_HEADER, &attempts),
+            (DUE_AT_HEADER, &due_at),
+        ];
+
+        headers
+            .iter()
+            .filter(|(key, _)| !key.starts_with("retry."))
+            .map(|(key, value)| (key.as_str(), value.as_str()))
+            .chain(retry_headers)
+            .fold(OwnedHeaders::new(), |owned, (key, value)| {
+                owned.insert(Header {
+                    key,
+                    value: Some(value),
+                })
+            })
+    }
+}
+
+/// Partitions paused because the retry at their head isn't due yet, and when each is due.
+#[derive(Default)]
+pub struct PausedPartitions {
+    paused: Vec<(String, i32, Instant)>,
+}
+
+impl PausedPartitions {
+    pub fn pause(&mut self, topic: &str, partition: i32, until: Instant) {
+        self.paused.push((topic.to_string(), partition, until));
+    }
+
+    /// Stops tracking the partitions that are due by `now`, returning them to be resumed.
+    pub fn take_due(&mut self, now: Instant) -> Vec<(String, i32)> {
+        let (due, waiting): (Vec<_>, Vec<_>) = self
+            .paused
+            .drain(..)
+            .partition(|(_, _, until)| *until <= now);
+
+        self.paused = waiting;
+
+        due.into_iter()
+            .map(|(topic, partition, _)| (topic, partition))
+            .collect()
+    }
+
+    /// How long until the next paused partition is due.
+    pub fn next_resume(&self, now: Instant) -> Option<Duration> {
+        self.paused
+            .iter()
+            .map(|(_, _, until)| until.saturating_duration_since(now))
+            .min()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rdkafka::message::{Headers, OwnedMessage};
+    use rdkafka::Timestamp;
+
+    use super::*;
+
+    fn message(topic: &str, partition: i32, offset: i64) -> OwnedMessage {
+        OwnedMessage::new(
+            None,
+            None,
+            topic.to_string(),
+            Timestamp::NotAvailable,
+            partition,
+            offset,
+            None,
+        )
+    }
+
+    #[test]
+    fn each_failed_attempt_moves_to_the_next_tier() {
+        let policy = RetryPolicy::default();
+
+        assert!(policy.next_tier(0).is_none());
+        assert_eq!(policy.next_tier(1).unwrap().topic(), "loyalty-retry-1m");
+        assert_eq!(policy.next_tier(2).unwrap().topic(), "loyalty-retry-10m");
+        assert!(policy.next_tier(3).is_none());
+    }
+
+    #[test]
+    fn message_without_retry_headers_is_on_its_first_attempt() {
+        let origin = MessageOrigin::of(&message("order-completed", 1, 42), &HashMap::new());
+
+        assert_eq!(origin.topic, "order-completed");
+        assert_eq!(origin.partition, 1);
+        assert_eq!(origin.offset, 42);
+        assert_eq!(origin.attempts, 0);
+        assert!(origin.wait(Utc::now()).is_none());
+    }
+
+    #[test]
+    fn retry_waits_until_it_is_due() {
+        let now = Utc::now();
+        let due_at = now + chrono::Duration::seconds(30);
+        let headers = HashMap::from([
+            (
+                ORIGINAL_TOPIC_HEADER.to_string(),
+                "order-completed".to_string(),
+            ),
+            (ORIGINAL_PARTITION_HEADER.to_string(), "1".to_string()),
+            (ORIGINAL_OFFSET_HEADER.to_string(), "42".to_string()),
+            (ATTEMPTS_HEADER.to_string(), "1".to_string()),
+            (
+                DUE_AT_HEADER.to_string(),
+                due_at.timestamp_millis().to_string(),
+            ),
+        ]);
+
+        let origin = MessageOrigin::of(&message("loyalty-retry-1m", 0, 7), &headers);
+
+        assert_eq!(origin.topic, "order-completed");
+        assert_eq!(origin.partition, 1);
+        assert_eq!(origin.offset, 42);
+        assert_eq!(origin.attempts, 1);
+
+        let wait = origin.wait(now).unwrap();
+        assert!(wait > Duration::from_secs(29) && wait <= Duration::from_secs(30));
+        assert!(origin.wait(due_at).is_none());
+        assert!(origin.wait(due_at + chrono::Duration::seconds(1)).is_none());
+    }
+
+    #[test]
+    fn retry_headers_keep_the_original_headers() {
+        let origin = MessageOrigin::of(&message("order-completed", 1, 42), &HashMap::new());
+        let headers = HashMap::from([
+            ("ce_type".to_string(), "order.confirmed".to_string()),
+            (ATTEMPTS_HEADER.to_string(), "1".to_string()),
+        ]);
+
+        let retry_headers = origin.retry_headers(&headers, 2, Utc::now());
+        let values: HashMap<&str, &[u8]> = retry_headers
+            .iter()
+            .map(|header| (header.key, header.value.unwrap_or_default()))
+            .collect();
+
+        assert_eq!(values.len(), 6);
+        assert_eq!(values["ce_type"], b"order.confirmed");
+        assert_eq!(values[ORIGINAL_TOPIC_HEADER], b"order-completed");
+        assert_eq!(values[ATTEMPTS_HEADER], b"2");
+    }
+
+    #[test]
+    fn paused_partitions_resume_once_due() {
+        let now = Instant::now();
+        let mut paused = PausedPartitions::default();
+
+        paused.pause("loyalty-retry-1m", 0, now + Duration::from_secs(10));
+        paused.pause("loyalty-retry-1m", 1, now + Duration::from_secs(5));
+
+        assert_eq!(paused.next_resume(now), Some(Duration::from_secs(5)));
+        assert!(paused.take_due(now).is_empty());
+
+        let due = paused.take_due(now + Duration::from_secs(5));
+        assert_eq!(due, vec![("loyalty-retry-1m".to_string(), 1)]);
+        assert_eq!(
+            paused.next_resume(now + Duration::from_secs(5)),
+            Some(Duration::from_secs(5))
+        );
+
+        assert_eq!(paused.take_due(now + Duration::from_secs(10)).len(), 1);
+        assert!(paused.next_resume(now).is_none());
+    }
+}
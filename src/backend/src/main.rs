@@ -1,62 +1,120 @@
+use axum::extract::State;
 use axum::http::StatusCode;
 use axum::Router;
 use axum::routing::get;
-use loyalty_adapters::{configure_instrumentation, ApplicationAdapters, PostgresLoyaltyPoints};
-use loyalty_core::LoyaltyPoints;
-use tracing::info;
+use loyalty_adapters::{
+    configure_exchange_rates, configure_instrumentation, configure_kafka_consumer,
+    configure_payload_decoders, shutdown_instrumentation, ApplicationAdapters, KafkaEventPublisher,
+    MessageTransactions, PostgresConsumerOffsets, PostgresLoyaltyPoints, Topics,
+};
+use chrono::Utc;
+use loyalty_core::{
+    BonusPolicy, Campaigns, CustomerProfiles, GrantBirthdayBonusesCommandHandler, Households,
+    LoyaltyPoints, Programmes, Referrals, SystemClock, VestingPolicy,
+};
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{info, warn};
 
-use adapters::{KafkaConnection, KafkaCredentials};
+use adapters::{
+    programme_topic_pattern, BatchLimits, ConsumerHealth, KafkaConnection, ProcessingLimits,
+    RetryPolicy, RetryTier,
+};
 use tokio::signal;
+use tokio::sync::watch;
 
 mod adapters;
 
-async fn process<T: LoyaltyPoints + Send + Sync>(receiver: &KafkaConnection<T>, topic: &str) {
+async fn process<
+    T: LoyaltyPoints
+        + Programmes
+        + Campaigns
+        + CustomerProfiles
+        + Referrals
+        + Households
+        + MessageTransactions
+        + Send
+        + Sync,
+>(
+    receiver: &KafkaConnection<T>,
+    topics: &[&str],
+    shutdown: watch::Receiver<bool>,
+) {
     info!("Subscribing");
 
-    receiver.subscribe(topic).await;
+    receiver.subscribe(topics).await;
 
-    loop {
-        info!("Receiving");
+    info!("Receiving");
 
-        receiver.process().await
-    }
+    receiver.process(shutdown).await;
+
+    receiver.close();
 }
 
 #[tokio::main]
 async fn main() -> Result<(), anyhow::Error> {
     let _ = configure_instrumentation();
 
-    let username = std::env::var("KAFKA_USERNAME");
-    let password = std::env::var("KAFKA_PASSWORD");
     let broker = std::env::var("BROKER").expect("'BROKER' environment variable is not set");
     let group_id = std::env::var("GROUP_ID").expect("'GROUP_ID' environment variable is not set");
 
-    let credentials = match username {
-        Ok(user) => Some(KafkaCredentials {
-            username: user,
-            password: password.expect("Password to be set if user is"),
-        }),
-        Err(_) => None,
-    };
+    let kafka_config = configure_kafka_consumer(&broker, &group_id)?;
+    let topics = Topics::from_env();
 
     let database = PostgresLoyaltyPoints::new().await?;
+    let offset_store = consumer_offsets(&database, &group_id)?;
+    let health_database = database.shared();
+    let birthday_job_database = database.shared();
 
     let application_adapters = ApplicationAdapters::new(database).await;
 
+    let bonuses = bonus_policy();
+
     let connection = KafkaConnection::new(
-        broker,
-        group_id,
-        credentials,
+        kafka_config,
+        topics.clone(),
+        configure_payload_decoders()?,
         application_adapters,
+        vesting_policy(),
+        bonuses.clone(),
+        configure_exchange_rates(),
+        KafkaEventPublisher::new()?,
+        retry_policy(),
+        processing_limits(),
+        batch_limits(),
+        offset_store,
     );
 
+    let health = Arc::new(HealthState {
+        consumer: connection.health().clone(),
+        database: health_database,
+        max_lag: env_usize("READY_MAX_CONSUMER_LAG").unwrap_or(1000) as i64,
+    });
+
+    let (stop_consumer, shutdown) = watch::channel(false);
+
+    let consumer = tokio::spawn(async move {
+        let order_completed = programme_topic_pattern(&topics.order_completed);
+        let customer_registered = programme_topic_pattern(&topics.customer_registered);
+
+        process(
+            &connection,
+            &[&order_completed, &customer_registered],
+            shutdown,
+        )
+        .await;
+    });
+
     tokio::spawn(async move {
-        process(&connection, "order-completed").await;
+        grant_birthday_bonuses(birthday_job_database, bonuses).await;
     });
 
     tokio::spawn(async move {
         let app = Router::new()
-        .route("/health", get(health));
+            .route("/health/live", get(live))
+            .route("/health/ready", get(ready))
+            .route("/metrics", get(metrics))
+            .with_state(health);
 
         let port = std::env::var("PORT").unwrap_or("8080".to_string());
 
@@ -68,20 +126,198 @@ async fn main() -> Result<(), anyhow::Error> {
             .unwrap();
     });
 
-    match signal::ctrl_c().await {
-        Ok(()) => {
-            info!("Shutting down");
-        }
-        Err(err) => {
+    shutdown_signal().await;
+
+    info!("Shutting down");
+
+    // Stop fetching and wait for the messages already received to be handled and committed
+    let _ = stop_consumer.send(true);
+
+    if tokio::time::timeout(shutdown_timeout(), consumer).await.is_err() {
+        warn!("Timed out draining in-flight messages");
+    }
+
+    shutdown_instrumentation(Duration::from_secs(2));
+
+    Ok(())
+}
+
+/// Waits for ctrl-c, or the SIGTERM container platforms send before stopping a task.
+async fn shutdown_signal() {
+    let ctrl_c = async {
+        if let Err(err) = signal::ctrl_c().await {
             eprintln!("Unable to listen for shutdown signal: {}", err);
+            std::future::pending::<()>().await;
         }
     };
 
-    info!("Shutting down");
+    #[cfg(unix)]
+    let terminate = async {
+        signal::unix::signal(signal::unix::SignalKind::terminate())
+            .expect("failed to install signal handler")
+            .recv()
+            .await;
+    };
 
-    Ok(())
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => {},
+        _ = terminate => {},
+    }
+}
+
+/// How long to wait for in-flight messages to drain on shutdown from `SHUTDOWN_TIMEOUT_SECONDS`,
+/// 25 seconds by default to fit inside the usual 30 second grace period before a SIGKILL.
+fn shutdown_timeout() -> Duration {
+    Duration::from_secs(env_usize("SHUTDOWN_TIMEOUT_SECONDS").unwrap_or(25) as u64)
 }
 
-async fn health() -> StatusCode {
+struct HealthState {
+    consumer: Arc<ConsumerHealth>,
+    database: PostgresLoyaltyPoints,
+    max_lag: i64,
+}
+
+async fn live() -> StatusCode {
     StatusCode::OK
-}
\ No newline at end of file
+}
+
+/// Ready once the database is reachable, partitions are assigned and none of them is more than
+/// `READY_MAX_CONSUMER_LAG` messages behind.
+async fn ready(State(state): State<Arc<HealthState>>) -> (StatusCode, String) {
+    if let Err(e) = state.database.ping().await {
+        return (
+            StatusCode::SERVICE_UNAVAILABLE,
+            format!("Database is unreachable: {:?}", e),
+        );
+    }
+
+    match state.consumer.readiness(state.max_lag, Utc::now()) {
+        Ok(()) => (StatusCode::OK, "Ready".to_string()),
+        Err(reason) => (StatusCode::SERVICE_UNAVAILABLE, reason),
+    }
+}
+
+async fn metrics(State(state): State<Arc<HealthState>>) -> String {
+    state.consumer.metrics()
+}
+
+fn vesting_policy() -> VestingPolicy {
+    std::env::var("POINTS_VESTING_DAYS")
+        .ok()
+        .and_then(|days| days.parse().ok())
+        .map(VestingPolicy::new)
+        .unwrap_or_default()
+}
+
+/// Retry tiers from `RETRY_TOPICS`, a comma separated list of `<topic>:<delay seconds>` such as
+/// `loyalty-retry-1m:60,loyalty-retry-10m:600`. Setting it empty disables retries.
+fn retry_policy() -> RetryPolicy {
+    let Ok(topics) = std::env::var("RETRY_TOPICS") else {
+        return RetryPolicy::default();
+    };
+
+    let tiers = topics
+        .split(',')
+        .filter_map(|tier| {
+            let (topic, seconds) = tier.trim().split_once(':')?;
+
+            Some(RetryTier::new(
+                topic.to_string(),
+                Duration::from_secs(seconds.parse().ok()?),
+            ))
+        })
+        .collect();
+
+    RetryPolicy::new(tiers)
+}
+
+/// How many customers' messages are processed at once from `CONSUMER_CONCURRENCY`, and how many
+/// messages can be in flight from `CONSUMER_MAX_IN_FLIGHT`.
+fn processing_limits() -> ProcessingLimits {
+    let defaults = ProcessingLimits::default();
+
+    ProcessingLimits::new(
+        env_usize("CONSUMER_CONCURRENCY").unwrap_or(defaults.lanes()),
+        env_usize("CONSUMER_MAX_IN_FLIGHT").unwrap_or(defaults.max_in_flight()),
+    )
+}
+
+/// Batch mode is switched on by setting `CONSUMER_BATCH_SIZE`, the most messages handled in one
+/// batch. `CONSUMER_BATCH_WAIT_MS` is how long to wait for a batch to fill, 100ms by default.
+fn batch_limits() -> Option<BatchLimits> {
+    let max_messages = env_usize("CONSUMER_BATCH_SIZE")?;
+    let max_wait = env_usize("CONSUMER_BATCH_WAIT_MS").unwrap_or(100);
+
+    Some(BatchLimits::new(
+        max_messages,
+        Duration::from_millis(max_wait as u64),
+    ))
+}
+
+/// Offsets are kept in Postgres, committed with each message's changes so none are made twice,
+/// when `CONSUMER_OFFSETS_IN_DATABASE` is `true`. Batches commit many messages' changes together,
+/// so this can't be combined with batch mode.
+fn consumer_offsets(
+    database: &PostgresLoyaltyPoints,
+    group_id: &str,
+) -> Result<Option<PostgresConsumerOffsets>, anyhow::Error> {
+    let enabled = std::env::var("CONSUMER_OFFSETS_IN_DATABASE")
+        .map(|value| value == "true")
+        .unwrap_or(false);
+
+    if !enabled {
+        return Ok(None);
+    }
+
+    if batch_limits().is_some() {
+        anyhow::bail!("'CONSUMER_OFFSETS_IN_DATABASE' can't be used with 'CONSUMER_BATCH_SIZE'");
+    }
+
+    Ok(Some(PostgresConsumerOffsets::new(database, group_id)))
+}
+
+/// Runs the birthday bonus job once a day, starting immediately. Bonuses are keyed by year so a
+/// restart that runs the job twice on the same day does not grant a second bonus.
+async fn grant_birthday_bonuses(database: PostgresLoyaltyPoints, bonuses: BonusPolicy) {
+    let mut interval = tokio::time::interval(Duration::from_secs(24 * 60 * 60));
+
+    loop {
+        interval.tick().await;
+
+        let result = GrantBirthdayBonusesCommandHandler::handle(
+            &database,
+            &database,
+            &bonuses,
+            &SystemClock,
+            Utc::now().date_naive(),
+        )
+        .await;
+
+        match result {
+            Ok(granted) => info!("Granted {} birthday bonuses", granted),
+            Err(e) => tracing::error!("Failure granting birthday bonuses: {:?}", e),
+        }
+    }
+}
+
+fn bonus_policy() -> BonusPolicy {
+    let defaults = BonusPolicy::default();
+
+    BonusPolicy::new(
+        env_f32("SIGNUP_BONUS_POINTS").unwrap_or(defaults.signup_bonus()),
+        env_f32("BIRTHDAY_BONUS_POINTS").unwrap_or(defaults.birthday_bonus()),
+        env_f32("REFERRER_BONUS_POINTS").unwrap_or(defaults.referrer_bonus()),
+        env_f32("REFEREE_BONUS_POINTS").unwrap_or(defaults.referee_bonus()),
+    )
+}
+
+fn env_f32(name: &str) -> Option<f32> {
+    std::env::var(name).ok().and_then(|value| value.parse().ok())
+}
+
+fn env_usize(name: &str) -> Option<usize> {
+    std::env::var(name).ok().and_then(|value| value.parse().ok())
+}
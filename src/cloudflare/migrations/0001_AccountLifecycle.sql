@@ -0,0 +1,2 @@
+ALTER TABLE loyalty ADD COLUMN status TEXT NOT NULL DEFAULT 'Active';
+ALTER TABLE loyalty ADD COLUMN merged_into TEXT;
@@ -0,0 +1,2 @@
+ALTER TABLE loyalty_transaction ADD COLUMN transfer_id TEXT;
+CREATE UNIQUE INDEX IF NOT EXISTS loyalty_transaction_transfer_idx ON loyalty_transaction (customer_id, transfer_id) WHERE transfer_id IS NOT NULL;
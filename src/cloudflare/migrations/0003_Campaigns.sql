@@ -0,0 +1,2 @@
+ALTER TABLE loyalty_transaction ADD COLUMN metadata TEXT;
+CREATE TABLE IF NOT EXISTS loyalty_campaign (campaign_id TEXT PRIMARY KEY, name TEXT NOT NULL, starts_epoch INTEGER NOT NULL, ends_epoch INTEGER NOT NULL, min_order_value REAL, customer_ids TEXT NOT NULL DEFAULT '[]', reward_type TEXT NOT NULL, reward_value REAL NOT NULL, budget_cap REAL, points_awarded REAL NOT NULL DEFAULT 0);
@@ -0,0 +1,3 @@
+CREATE TABLE IF NOT EXISTS loyalty_referral_code (customer_id TEXT PRIMARY KEY, referral_code TEXT NOT NULL UNIQUE);
+CREATE TABLE IF NOT EXISTS loyalty_referral (referee_id TEXT PRIMARY KEY, referrer_id TEXT NOT NULL, referral_code TEXT NOT NULL, status TEXT NOT NULL, created_epoch INTEGER NOT NULL, rewarded_epoch INTEGER);
+CREATE INDEX IF NOT EXISTS loyalty_referral_referrer_idx ON loyalty_referral (referrer_id);
@@ -0,0 +1,4 @@
+CREATE TABLE IF NOT EXISTS loyalty_household (household_id TEXT PRIMARY KEY, name TEXT NOT NULL, points REAL NOT NULL DEFAULT 0, created_epoch INTEGER NOT NULL);
+CREATE TABLE IF NOT EXISTS loyalty_household_member (customer_id TEXT PRIMARY KEY, household_id TEXT NOT NULL, role TEXT NOT NULL, spend_limit REAL, contributed_points REAL NOT NULL DEFAULT 0);
+CREATE INDEX IF NOT EXISTS loyalty_household_member_household_idx ON loyalty_household_member (household_id);
+CREATE TABLE IF NOT EXISTS loyalty_household_transaction (household_id TEXT NOT NULL, customer_id TEXT NOT NULL, date_epoch REAL NOT NULL, order_number TEXT NOT NULL, change REAL NOT NULL, PRIMARY KEY (household_id, order_number));
@@ -0,0 +1,36 @@
+-- Everything that predates programmes belongs to the default programme, which keeps the
+-- original earn rate of half a point per unit spent
+CREATE TABLE IF NOT EXISTS loyalty_programme (programme_id TEXT PRIMARY KEY, name TEXT NOT NULL, earn_rate REAL NOT NULL, created_epoch INTEGER NOT NULL);
+INSERT OR IGNORE INTO loyalty_programme (programme_id, name, earn_rate, created_epoch) VALUES ('default', 'Default', 0.5, 0);
+
+-- SQLite can't change a primary key, so tables keyed by customer are rebuilt keyed by programme
+-- and customer
+CREATE TABLE loyalty_new (programme_id TEXT NOT NULL DEFAULT 'default', customer_id TEXT NOT NULL, current_points REAL, status TEXT NOT NULL DEFAULT 'Active', merged_into TEXT, PRIMARY KEY (programme_id, customer_id));
+INSERT INTO loyalty_new (customer_id, current_points, status, merged_into) SELECT customer_id, current_points, status, merged_into FROM loyalty;
+DROP TABLE loyalty;
+ALTER TABLE loyalty_new RENAME TO loyalty;
+
+ALTER TABLE loyalty_transaction ADD COLUMN programme_id TEXT NOT NULL DEFAULT 'default';
+DROP INDEX IF EXISTS loyalty_transaction_transfer_idx;
+CREATE UNIQUE INDEX IF NOT EXISTS loyalty_transaction_transfer_idx ON loyalty_transaction (programme_id, customer_id, transfer_id) WHERE transfer_id IS NOT NULL;
+
+ALTER TABLE loyalty_campaign ADD COLUMN programme_id TEXT NOT NULL DEFAULT 'default';
+
+CREATE TABLE loyalty_referral_code_new (programme_id TEXT NOT NULL DEFAULT 'default', customer_id TEXT NOT NULL, referral_code TEXT NOT NULL, PRIMARY KEY (programme_id, customer_id), UNIQUE (programme_id, referral_code));
+INSERT INTO loyalty_referral_code_new (customer_id, referral_code) SELECT customer_id, referral_code FROM loyalty_referral_code;
+DROP TABLE loyalty_referral_code;
+ALTER TABLE loyalty_referral_code_new RENAME TO loyalty_referral_code;
+
+CREATE TABLE loyalty_referral_new (programme_id TEXT NOT NULL DEFAULT 'default', referee_id TEXT NOT NULL, referrer_id TEXT NOT NULL, referral_code TEXT NOT NULL, status TEXT NOT NULL, created_epoch INTEGER NOT NULL, rewarded_epoch INTEGER, PRIMARY KEY (programme_id, referee_id));
+INSERT INTO loyalty_referral_new (referee_id, referrer_id, referral_code, status, created_epoch, rewarded_epoch) SELECT referee_id, referrer_id, referral_code, status, created_epoch, rewarded_epoch FROM loyalty_referral;
+DROP TABLE loyalty_referral;
+ALTER TABLE loyalty_referral_new RENAME TO loyalty_referral;
+CREATE INDEX IF NOT EXISTS loyalty_referral_referrer_idx ON loyalty_referral (programme_id, referrer_id);
+
+ALTER TABLE loyalty_household ADD COLUMN programme_id TEXT NOT NULL DEFAULT 'default';
+
+CREATE TABLE loyalty_household_member_new (programme_id TEXT NOT NULL DEFAULT 'default', customer_id TEXT NOT NULL, household_id TEXT NOT NULL, role TEXT NOT NULL, spend_limit REAL, contributed_points REAL NOT NULL DEFAULT 0, PRIMARY KEY (programme_id, customer_id));
+INSERT INTO loyalty_household_member_new (customer_id, household_id, role, spend_limit, contributed_points) SELECT customer_id, household_id, role, spend_limit, contributed_points FROM loyalty_household_member;
+DROP TABLE loyalty_household_member;
+ALTER TABLE loyalty_household_member_new RENAME TO loyalty_household_member;
+CREATE INDEX IF NOT EXISTS loyalty_household_member_household_idx ON loyalty_household_member (household_id);
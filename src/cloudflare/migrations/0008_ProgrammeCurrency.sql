@@ -0,0 +1 @@
+ALTER TABLE loyalty_programme ADD COLUMN base_currency TEXT NOT NULL DEFAULT 'USD';
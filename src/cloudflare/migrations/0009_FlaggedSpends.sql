@@ -0,0 +1 @@
+CREATE TABLE IF NOT EXISTS loyalty_flagged_spend (programme_id TEXT NOT NULL, customer_id TEXT NOT NULL, order_number TEXT NOT NULL, spend REAL NOT NULL, reasons TEXT NOT NULL DEFAULT '[]', flagged_epoch INTEGER NOT NULL, PRIMARY KEY (programme_id, customer_id, order_number));
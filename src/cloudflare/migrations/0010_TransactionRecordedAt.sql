@@ -0,0 +1 @@
+ALTER TABLE loyalty_transaction ADD COLUMN recorded_epoch REAL;
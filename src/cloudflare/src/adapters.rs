@@ -1,6 +1,11 @@
 use async_trait::async_trait;
-use chrono::DateTime;
-use loyalty_core::{LoyaltyAccount, LoyaltyAccountTransaction, LoyaltyErrors, LoyaltyPoints};
+use chrono::{DateTime, Utc};
+use loyalty_core::{
+    AccountStatus, Campaign, CampaignEligibility, CampaignReward, Campaigns, FlaggedSpend,
+    FlaggedSpends, Household, HouseholdMember, HouseholdRole, Households, LoyaltyAccount,
+    LoyaltyAccountTransaction, LoyaltyErrors, LoyaltyPoints, Programme, Programmes, Referral,
+    ReferralBonuses, ReferralStatus, Referrals,
+};
 use serde::Deserialize;
 use wasm_bindgen_futures::wasm_bindgen::JsValue;
 use worker::D1Database;
@@ -17,8 +22,11 @@ impl D1DataAccessLayer {
 
 #[derive(Deserialize)]
 struct LoyaltyAccountRow {
+    programme_id: String,
     customer_id: String,
     current_points: f32,
+    status: String,
+    merged_into: Option<String>,
 }
 
 #[derive(Deserialize)]
@@ -26,31 +34,201 @@ struct LoyaltyTransactionRow {
     date_epoch: i32,
     order_number: String,
     change: f32,
+    transfer_id: Option<String>,
+    metadata: Option<String>,
+    vests_epoch: Option<f64>,
+    recorded_epoch: Option<f64>,
+}
+
+#[derive(Deserialize)]
+struct ProgrammeRow {
+    programme_id: String,
+    name: String,
+    earn_rate: f32,
+    base_currency: String,
+    created_epoch: i64,
+}
+
+impl From<ProgrammeRow> for Programme {
+    fn from(row: ProgrammeRow) -> Self {
+        Programme::from(
+            row.programme_id,
+            row.name,
+            row.earn_rate,
+            row.base_currency,
+            DateTime::from_timestamp_millis(row.created_epoch).unwrap(),
+        )
+    }
+}
+
+#[derive(Deserialize)]
+struct FlaggedSpendRow {
+    programme_id: String,
+    customer_id: String,
+    order_number: String,
+    spend: f32,
+    reasons: String,
+    flagged_epoch: i64,
+}
+
+impl From<FlaggedSpendRow> for FlaggedSpend {
+    fn from(row: FlaggedSpendRow) -> Self {
+        FlaggedSpend::from(
+            row.programme_id,
+            row.customer_id,
+            row.order_number,
+            row.spend,
+            serde_json::from_str(&row.reasons).unwrap_or_default(),
+            DateTime::from_timestamp_millis(row.flagged_epoch).unwrap(),
+        )
+    }
+}
+
+#[derive(Deserialize)]
+struct CampaignRow {
+    programme_id: String,
+    campaign_id: String,
+    name: String,
+    starts_epoch: i64,
+    ends_epoch: i64,
+    min_order_value: Option<f32>,
+    customer_ids: String,
+    reward_type: String,
+    reward_value: f32,
+    budget_cap: Option<f32>,
+    points_awarded: f32,
+}
+
+#[derive(Deserialize)]
+struct ReferralCodeRow {
+    customer_id: String,
+    referral_code: String,
+}
+
+#[derive(Deserialize)]
+struct ReferralRow {
+    programme_id: String,
+    referrer_id: String,
+    referee_id: String,
+    referral_code: String,
+    status: String,
+    created_epoch: i64,
+    rewarded_epoch: Option<i64>,
+}
+
+impl TryFrom<ReferralRow> for Referral {
+    type Error = LoyaltyErrors;
+
+    fn try_from(row: ReferralRow) -> Result<Self, Self::Error> {
+        Ok(Referral::from(
+            row.programme_id,
+            row.referrer_id,
+            row.referee_id,
+            row.referral_code,
+            row.status.parse::<ReferralStatus>()?,
+            DateTime::from_timestamp_millis(row.created_epoch).unwrap(),
+            row.rewarded_epoch.and_then(DateTime::from_timestamp_millis),
+        ))
+    }
+}
+
+#[derive(Deserialize)]
+struct HouseholdRow {
+    programme_id: String,
+    household_id: String,
+    name: String,
+    points: f32,
+    created_epoch: i64,
+}
+
+#[derive(Deserialize)]
+struct HouseholdMemberRow {
+    customer_id: String,
+    household_id: String,
+    role: String,
+    spend_limit: Option<f32>,
+    contributed_points: f32,
+}
+
+impl TryFrom<HouseholdMemberRow> for HouseholdMember {
+    type Error = LoyaltyErrors;
+
+    fn try_from(row: HouseholdMemberRow) -> Result<Self, Self::Error> {
+        Ok(HouseholdMember::from(
+            row.customer_id,
+            row.role.parse::<HouseholdRole>()?,
+            row.spend_limit,
+            row.contributed_points,
+        ))
+    }
+}
+
+impl TryFrom<CampaignRow> for Campaign {
+    type Error = LoyaltyErrors;
+
+    fn try_from(row: CampaignRow) -> Result<Self, Self::Error> {
+        let reward = match row.reward_type.as_str() {
+            "Multiplier" => CampaignReward::Multiplier(row.reward_value),
+            "FlatBonus" => CampaignReward::FlatBonus(row.reward_value),
+            other => {
+                return Err(LoyaltyErrors::InvalidValues(format!(
+                    "Unknown campaign reward type {}",
+                    other
+                )))
+            }
+        };
+
+        Ok(Campaign::from(
+            row.programme_id,
+            row.campaign_id,
+            row.name,
+            DateTime::from_timestamp_millis(row.starts_epoch).unwrap(),
+            DateTime::from_timestamp_millis(row.ends_epoch).unwrap(),
+            CampaignEligibility {
+                min_order_value: row.min_order_value,
+                customer_ids: serde_json::from_str(&row.customer_ids).unwrap_or_default(),
+            },
+            reward,
+            row.budget_cap,
+            row.points_awarded,
+        ))
+    }
 }
 
 #[worker::send]
-async fn insert_new_account_to_db(value: &D1DataAccessLayer, account: &LoyaltyAccount) {
-    let _ = value
+async fn insert_new_account_to_db(
+    value: &D1DataAccessLayer,
+    account: &LoyaltyAccount,
+    transactions: &[LoyaltyAccountTransaction],
+) -> Result<(), worker::Error> {
+    let mut statements = vec![value
         .db
-        .prepare("INSERT INTO loyalty ( customer_id, current_points ) VALUES ( ?1, ?2 )")
+        .prepare("INSERT INTO loyalty ( programme_id, customer_id, current_points ) VALUES ( ?1, ?2, ?3 )")
         .bind(&[
+            JsValue::from(account.programme_id()),
             JsValue::from(account.customer_id()),
             JsValue::from(*account.current_points()),
-        ])
-        .unwrap()
-        .run()
-        .await;
+        ])?];
+
+    for transaction in transactions {
+        statements.push(insert_transaction_statement(value, account, transaction)?);
+    }
+
+    value.db.batch(statements).await?;
+
+    Ok(())
 }
 
 #[worker::send]
 async fn retrieve_from_db(
     value: &D1DataAccessLayer,
+    programme_id: &str,
     customer_id: &str,
 ) -> Option<LoyaltyAccountRow> {
     let res = value
         .db
-        .prepare("SELECT customer_id, current_points FROM loyalty WHERE customer_id = ?1")
-        .bind(&[JsValue::from(customer_id)])
+        .prepare("SELECT programme_id, customer_id, current_points, status, merged_into FROM loyalty WHERE programme_id = ?1 AND customer_id = ?2")
+        .bind(&[JsValue::from(programme_id), JsValue::from(customer_id)])
         .unwrap()
         .first::<LoyaltyAccountRow>(None)
         .await;
@@ -68,12 +246,13 @@ async fn retrieve_from_db(
 #[worker::send]
 async fn retrieve_transactions_from_db(
     value: &D1DataAccessLayer,
+    programme_id: &str,
     customer_id: &str,
 ) -> Vec<LoyaltyAccountTransaction> {
     let res = value
         .db
-        .prepare("SELECT date_epoch, order_number, change FROM loyalty_transaction WHERE customer_id = ?1")
-        .bind(&[JsValue::from(customer_id)])
+        .prepare("SELECT date_epoch, order_number, change, transfer_id, metadata, vests_epoch, recorded_epoch FROM loyalty_transaction WHERE programme_id = ?1 AND customer_id = ?2")
+        .bind(&[JsValue::from(programme_id), JsValue::from(customer_id)])
         .unwrap()
         .all()
         .await;
@@ -91,6 +270,24 @@ async fn retrieve_transactions_from_db(
                             transaction.order_number.clone(),
                             transaction.change,
                         )
+                        .with_transfer_id(transaction.transfer_id.clone())
+                        .with_metadata(
+                            transaction
+                                .metadata
+                                .as_deref()
+                                .and_then(|metadata| serde_json::from_str(metadata).ok())
+                                .unwrap_or_default(),
+                        )
+                        .with_vests_at(
+                            transaction
+                                .vests_epoch
+                                .and_then(|epoch| DateTime::from_timestamp_millis(epoch as i64)),
+                        )
+                        .with_recorded_at(
+                            transaction
+                                .recorded_epoch
+                                .and_then(|epoch| DateTime::from_timestamp_millis(epoch as i64)),
+                        )
                     })
                     .collect(),
                 Err(e) => {
@@ -118,15 +315,28 @@ async fn add_transaction_to_db(
     transaction: &LoyaltyAccountTransaction,
 ) {
     let timestamp_millis = transaction.date().timestamp_millis() as i32;
+    let metadata = match transaction.metadata().is_empty() {
+        true => JsValue::NULL,
+        false => serde_json::to_string(transaction.metadata())
+            .map(JsValue::from)
+            .unwrap_or(JsValue::NULL),
+    };
 
     let _ = value
         .db
-        .prepare("INSERT INTO loyalty_transaction (customer_id, date_epoch, order_number, change) VALUES (?1, ?2, ?3, ?4)")
+        .prepare("INSERT INTO loyalty_transaction (programme_id, customer_id, date_epoch, order_number, change, metadata, vests_epoch, recorded_epoch) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)")
         .bind(&[
+            JsValue::from(account.programme_id()),
             JsValue::from(account.customer_id()),
             JsValue::from(timestamp_millis),
             JsValue::from(transaction.order_number()),
             JsValue::from(transaction.change()),
+            metadata,
+            transaction
+                .vests_at()
+                .map(|date| JsValue::from(date.timestamp_millis() as f64))
+                .unwrap_or(JsValue::NULL),
+            JsValue::from(transaction.recorded_at().timestamp_millis() as f64),
         ])
         .unwrap()
         .run()
@@ -137,9 +347,12 @@ async fn add_transaction_to_db(
 async fn update_total_points_in_db(value: &D1DataAccessLayer, account: &LoyaltyAccount) {
     let _ = value
         .db
-        .prepare("UPDATE loyalty SET current_points = $1 WHERE customer_id = $2")
+        .prepare(
+            "UPDATE loyalty SET current_points = $1 WHERE programme_id = $2 AND customer_id = $3",
+        )
         .bind(&[
             JsValue::from(*account.current_points()),
+            JsValue::from(account.programme_id()),
             JsValue::from(account.customer_id()),
         ])
         .unwrap()
@@ -147,30 +360,961 @@ async fn update_total_points_in_db(value: &D1DataAccessLayer, account: &LoyaltyA
         .await;
 }
 
+fn insert_transaction_statement(
+    value: &D1DataAccessLayer,
+    account: &LoyaltyAccount,
+    transaction: &LoyaltyAccountTransaction,
+) -> Result<worker::D1PreparedStatement, worker::Error> {
+    let metadata = match transaction.metadata().is_empty() {
+        true => JsValue::NULL,
+        false => serde_json::to_string(transaction.metadata())
+            .map(JsValue::from)
+            .unwrap_or(JsValue::NULL),
+    };
+
+    value
+        .db
+        .prepare("INSERT INTO loyalty_transaction (programme_id, customer_id, date_epoch, order_number, change, metadata, vests_epoch, recorded_epoch) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)")
+        .bind(&[
+            JsValue::from(account.programme_id()),
+            JsValue::from(account.customer_id()),
+            JsValue::from(transaction.date().timestamp_millis() as i32),
+            JsValue::from(transaction.order_number()),
+            JsValue::from(transaction.change()),
+            metadata,
+            transaction
+                .vests_at()
+                .map(|date| JsValue::from(date.timestamp_millis() as f64))
+                .unwrap_or(JsValue::NULL),
+            JsValue::from(transaction.recorded_at().timestamp_millis() as f64),
+        ])
+}
+
+#[worker::send]
+async fn add_transactions_in_db(
+    value: &D1DataAccessLayer,
+    account: &LoyaltyAccount,
+    transactions: &[LoyaltyAccountTransaction],
+) -> Result<(), worker::Error> {
+    let mut statements = vec![];
+
+    for transaction in transactions {
+        statements.push(insert_transaction_statement(value, account, transaction)?);
+    }
+
+    statements.push(
+        value
+            .db
+            .prepare("UPDATE loyalty SET current_points = ?1 WHERE programme_id = ?2 AND customer_id = ?3")
+            .bind(&[
+                JsValue::from(*account.current_points()),
+                JsValue::from(account.programme_id()),
+                JsValue::from(account.customer_id()),
+            ])?,
+    );
+
+    value.db.batch(statements).await?;
+
+    Ok(())
+}
+
+#[worker::send]
+async fn transfer_in_db(
+    value: &D1DataAccessLayer,
+    source: &LoyaltyAccount,
+    target: &LoyaltyAccount,
+    debit: &LoyaltyAccountTransaction,
+    credit: &LoyaltyAccountTransaction,
+) -> Result<(), worker::Error> {
+    let mut statements = vec![];
+
+    for (account, transaction) in [(source, debit), (target, credit)] {
+        let transfer_id = transaction.transfer_id().map(JsValue::from).unwrap_or(JsValue::NULL);
+
+        statements.push(
+            value
+                .db
+                .prepare("INSERT INTO loyalty_transaction (programme_id, customer_id, date_epoch, order_number, change, transfer_id, recorded_epoch) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)")
+                .bind(&[
+                    JsValue::from(account.programme_id()),
+                    JsValue::from(account.customer_id()),
+                    JsValue::from(transaction.date().timestamp_millis() as i32),
+                    JsValue::from(transaction.order_number()),
+                    JsValue::from(transaction.change()),
+                    transfer_id,
+                    JsValue::from(transaction.recorded_at().timestamp_millis() as f64),
+                ])?,
+        );
+        statements.push(
+            value
+                .db
+                .prepare("UPDATE loyalty SET current_points = current_points + ?1 WHERE programme_id = ?2 AND customer_id = ?3")
+                .bind(&[
+                    JsValue::from(transaction.change()),
+                    JsValue::from(account.programme_id()),
+                    JsValue::from(account.customer_id()),
+                ])?,
+        );
+    }
+
+    value.db.batch(statements).await?;
+
+    Ok(())
+}
+
+#[worker::send]
+async fn update_status_in_db(
+    value: &D1DataAccessLayer,
+    account: &LoyaltyAccount,
+) -> Result<(), worker::Error> {
+    let merged_into = account.merged_into().map(JsValue::from).unwrap_or(JsValue::NULL);
+
+    value
+        .db
+        .prepare("UPDATE loyalty SET status = ?1, merged_into = ?2 WHERE programme_id = ?3 AND customer_id = ?4")
+        .bind(&[
+            JsValue::from(account.status().to_string()),
+            merged_into,
+            JsValue::from(account.programme_id()),
+            JsValue::from(account.customer_id()),
+        ])?
+        .run()
+        .await?;
+
+    Ok(())
+}
+
+#[worker::send]
+async fn merge_accounts_in_db(
+    value: &D1DataAccessLayer,
+    source: &LoyaltyAccount,
+    target: &LoyaltyAccount,
+) -> Result<(), worker::Error> {
+    let programme_id = JsValue::from(source.programme_id());
+
+    let statements = vec![
+        value
+            .db
+            .prepare("UPDATE loyalty_transaction SET customer_id = ?1 WHERE programme_id = ?2 AND customer_id = ?3")
+            .bind(&[
+                JsValue::from(target.customer_id()),
+                programme_id.clone(),
+                JsValue::from(source.customer_id()),
+            ])?,
+        value
+            .db
+            .prepare("UPDATE loyalty SET current_points = ?1, status = ?2, merged_into = ?3 WHERE programme_id = ?4 AND customer_id = ?5")
+            .bind(&[
+                JsValue::from(*source.current_points()),
+                JsValue::from(source.status().to_string()),
+                JsValue::from(target.customer_id()),
+                programme_id.clone(),
+                JsValue::from(source.customer_id()),
+            ])?,
+        value
+            .db
+            .prepare("UPDATE loyalty SET current_points = ?1 WHERE programme_id = ?2 AND customer_id = ?3")
+            .bind(&[
+                JsValue::from(*target.current_points()),
+                programme_id.clone(),
+                JsValue::from(target.customer_id()),
+            ])?,
+        value
+            .db
+            .prepare("UPDATE loyalty SET merged_into = ?1 WHERE programme_id = ?2 AND merged_into = ?3")
+            .bind(&[
+                JsValue::from(target.customer_id()),
+                programme_id,
+                JsValue::from(source.customer_id()),
+            ])?,
+    ];
+
+    value.db.batch(statements).await?;
+
+    Ok(())
+}
+
+#[worker::send]
+async fn erase_in_db(
+    value: &D1DataAccessLayer,
+    customer_id: &str,
+    account: &LoyaltyAccount,
+) -> Result<(), worker::Error> {
+    let programme_id = JsValue::from(account.programme_id());
+
+    let statements = vec![
+        value
+            .db
+            .prepare("UPDATE loyalty_transaction SET customer_id = ?1 WHERE programme_id = ?2 AND customer_id = ?3")
+            .bind(&[
+                JsValue::from(account.customer_id()),
+                programme_id.clone(),
+                JsValue::from(customer_id),
+            ])?,
+        value
+            .db
+            .prepare("UPDATE loyalty SET customer_id = ?1, status = ?2 WHERE programme_id = ?3 AND customer_id = ?4")
+            .bind(&[
+                JsValue::from(account.customer_id()),
+                JsValue::from(account.status().to_string()),
+                programme_id.clone(),
+                JsValue::from(customer_id),
+            ])?,
+        value
+            .db
+            .prepare("UPDATE loyalty SET merged_into = ?1 WHERE programme_id = ?2 AND merged_into = ?3")
+            .bind(&[
+                JsValue::from(account.customer_id()),
+                programme_id.clone(),
+                JsValue::from(customer_id),
+            ])?,
+        value
+            .db
+            .prepare("UPDATE loyalty_campaign SET customer_ids = (SELECT json_group_array(CASE WHEN value = ?3 THEN ?1 ELSE value END) FROM json_each(customer_ids)) WHERE programme_id = ?2 AND EXISTS (SELECT 1 FROM json_each(customer_ids) WHERE value = ?3)")
+            .bind(&[
+                JsValue::from(account.customer_id()),
+                programme_id.clone(),
+                JsValue::from(customer_id),
+            ])?,
+        value
+            .db
+            .prepare("UPDATE loyalty_referral_code SET customer_id = ?1 WHERE programme_id = ?2 AND customer_id = ?3")
+            .bind(&[
+                JsValue::from(account.customer_id()),
+                programme_id.clone(),
+                JsValue::from(customer_id),
+            ])?,
+        value
+            .db
+            .prepare("UPDATE loyalty_referral SET referee_id = CASE WHEN referee_id = ?3 THEN ?1 ELSE referee_id END, referrer_id = CASE WHEN referrer_id = ?3 THEN ?1 ELSE referrer_id END WHERE programme_id = ?2 AND (referee_id = ?3 OR referrer_id = ?3)")
+            .bind(&[
+                JsValue::from(account.customer_id()),
+                programme_id.clone(),
+                JsValue::from(customer_id),
+            ])?,
+        value
+            .db
+            .prepare("UPDATE loyalty_household_transaction SET customer_id = ?1 WHERE customer_id = ?3 AND household_id IN (SELECT household_id FROM loyalty_household WHERE programme_id = ?2)")
+            .bind(&[
+                JsValue::from(account.customer_id()),
+                programme_id.clone(),
+                JsValue::from(customer_id),
+            ])?,
+        value
+            .db
+            .prepare("UPDATE loyalty_household_member SET customer_id = ?1 WHERE programme_id = ?2 AND customer_id = ?3")
+            .bind(&[
+                JsValue::from(account.customer_id()),
+                programme_id.clone(),
+                JsValue::from(customer_id),
+            ])?,
+        value
+            .db
+            .prepare("UPDATE loyalty_flagged_spend SET customer_id = ?1 WHERE programme_id = ?2 AND customer_id = ?3")
+            .bind(&[
+                JsValue::from(account.customer_id()),
+                programme_id.clone(),
+                JsValue::from(customer_id),
+            ])?,
+    ];
+
+    value.db.batch(statements).await?;
+
+    Ok(())
+}
+
+const CAMPAIGN_COLUMNS: &str = "programme_id, campaign_id, name, starts_epoch, ends_epoch, min_order_value, customer_ids, reward_type, reward_value, budget_cap, points_awarded";
+
+#[worker::send]
+async fn save_campaign_to_db(
+    value: &D1DataAccessLayer,
+    campaign: &Campaign,
+) -> Result<(), worker::Error> {
+    let (reward_type, reward_value) = match campaign.reward() {
+        CampaignReward::Multiplier(value) => ("Multiplier", value),
+        CampaignReward::FlatBonus(value) => ("FlatBonus", value),
+    };
+    let customer_ids = serde_json::to_string(&campaign.eligibility().customer_ids)
+        .unwrap_or("[]".to_string());
+
+    value
+        .db
+        .prepare("INSERT INTO loyalty_campaign (programme_id, campaign_id, name, starts_epoch, ends_epoch, min_order_value, customer_ids, reward_type, reward_value, budget_cap) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10) ON CONFLICT (campaign_id) DO UPDATE SET name = excluded.name, starts_epoch = excluded.starts_epoch, ends_epoch = excluded.ends_epoch, min_order_value = excluded.min_order_value, customer_ids = excluded.customer_ids, reward_type = excluded.reward_type, reward_value = excluded.reward_value, budget_cap = excluded.budget_cap")
+        .bind(&[
+            JsValue::from(campaign.programme_id()),
+            JsValue::from(campaign.campaign_id()),
+            JsValue::from(campaign.name()),
+            JsValue::from(campaign.starts_at().timestamp_millis() as f64),
+            JsValue::from(campaign.ends_at().timestamp_millis() as f64),
+            campaign.eligibility().min_order_value.map(JsValue::from).unwrap_or(JsValue::NULL),
+            JsValue::from(customer_ids),
+            JsValue::from(reward_type),
+            JsValue::from(reward_value),
+            campaign.budget_cap().map(JsValue::from).unwrap_or(JsValue::NULL),
+        ])?
+        .run()
+        .await?;
+
+    Ok(())
+}
+
+#[worker::send]
+async fn retrieve_campaign_from_db(
+    value: &D1DataAccessLayer,
+    programme_id: &str,
+    campaign_id: &str,
+) -> Result<Option<CampaignRow>, worker::Error> {
+    value
+        .db
+        .prepare(format!(
+            "SELECT {} FROM loyalty_campaign WHERE programme_id = ?1 AND campaign_id = ?2",
+            CAMPAIGN_COLUMNS
+        ))
+        .bind(&[JsValue::from(programme_id), JsValue::from(campaign_id)])?
+        .first::<CampaignRow>(None)
+        .await
+}
+
+#[worker::send]
+async fn list_campaigns_from_db(
+    value: &D1DataAccessLayer,
+    programme_id: &str,
+    active_at: Option<DateTime<Utc>>,
+) -> Result<Vec<CampaignRow>, worker::Error> {
+    let statement = match active_at {
+        Some(at) => value
+            .db
+            .prepare(format!("SELECT {} FROM loyalty_campaign WHERE programme_id = ?1 AND starts_epoch <= ?2 AND ends_epoch > ?2", CAMPAIGN_COLUMNS))
+            .bind(&[
+                JsValue::from(programme_id),
+                JsValue::from(at.timestamp_millis() as f64),
+            ])?,
+        None => value
+            .db
+            .prepare(format!("SELECT {} FROM loyalty_campaign WHERE programme_id = ?1 ORDER BY starts_epoch", CAMPAIGN_COLUMNS))
+            .bind(&[JsValue::from(programme_id)])?,
+    };
+
+    statement.all().await?.results::<CampaignRow>()
+}
+
+#[worker::send]
+async fn delete_campaign_from_db(
+    value: &D1DataAccessLayer,
+    programme_id: &str,
+    campaign_id: &str,
+) -> Result<(), worker::Error> {
+    value
+        .db
+        .prepare("DELETE FROM loyalty_campaign WHERE programme_id = ?1 AND campaign_id = ?2")
+        .bind(&[JsValue::from(programme_id), JsValue::from(campaign_id)])?
+        .run()
+        .await?;
+
+    Ok(())
+}
+
+#[worker::send]
+async fn reserve_campaign_budget_in_db(
+    value: &D1DataAccessLayer,
+    campaign_id: &str,
+    points: f32,
+) -> Result<bool, worker::Error> {
+    let result = value
+        .db
+        .prepare("UPDATE loyalty_campaign SET points_awarded = points_awarded + ?1 WHERE campaign_id = ?2 AND (budget_cap IS NULL OR points_awarded + ?1 <= budget_cap)")
+        .bind(&[JsValue::from(points), JsValue::from(campaign_id)])?
+        .run()
+        .await?;
+
+    Ok(result
+        .meta()?
+        .and_then(|meta| meta.changes)
+        .is_some_and(|changes| changes == 1))
+}
+
+#[worker::send]
+async fn assign_referral_code_in_db(
+    value: &D1DataAccessLayer,
+    programme_id: &str,
+    customer_id: &str,
+    referral_code: &str,
+) -> Result<Option<ReferralCodeRow>, worker::Error> {
+    value
+        .db
+        .prepare("INSERT INTO loyalty_referral_code (programme_id, customer_id, referral_code) VALUES (?1, ?2, ?3) ON CONFLICT (programme_id, customer_id) DO NOTHING")
+        .bind(&[
+            JsValue::from(programme_id),
+            JsValue::from(customer_id),
+            JsValue::from(referral_code),
+        ])?
+        .run()
+        .await?;
+
+    value
+        .db
+        .prepare("SELECT customer_id, referral_code FROM loyalty_referral_code WHERE programme_id = ?1 AND customer_id = ?2")
+        .bind(&[JsValue::from(programme_id), JsValue::from(customer_id)])?
+        .first::<ReferralCodeRow>(None)
+        .await
+}
+
+#[worker::send]
+async fn retrieve_referral_code_from_db(
+    value: &D1DataAccessLayer,
+    programme_id: &str,
+    referral_code: &str,
+) -> Result<Option<ReferralCodeRow>, worker::Error> {
+    value
+        .db
+        .prepare("SELECT customer_id, referral_code FROM loyalty_referral_code WHERE programme_id = ?1 AND referral_code = ?2")
+        .bind(&[JsValue::from(programme_id), JsValue::from(referral_code)])?
+        .first::<ReferralCodeRow>(None)
+        .await
+}
+
+const REFERRAL_COLUMNS: &str =
+    "programme_id, referrer_id, referee_id, referral_code, status, created_epoch, rewarded_epoch";
+
+#[worker::send]
+async fn save_referral_to_db(
+    value: &D1DataAccessLayer,
+    referral: &Referral,
+) -> Result<(), worker::Error> {
+    value
+        .db
+        .prepare(format!(
+            "INSERT INTO loyalty_referral ({}) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            REFERRAL_COLUMNS
+        ))
+        .bind(&[
+            JsValue::from(referral.programme_id()),
+            JsValue::from(referral.referrer_id()),
+            JsValue::from(referral.referee_id()),
+            JsValue::from(referral.referral_code()),
+            JsValue::from(referral.status().to_string()),
+            JsValue::from(referral.created_at().timestamp_millis() as f64),
+            referral
+                .rewarded_at()
+                .map(|date| JsValue::from(date.timestamp_millis() as f64))
+                .unwrap_or(JsValue::NULL),
+        ])?
+        .run()
+        .await?;
+
+    Ok(())
+}
+
+#[worker::send]
+async fn retrieve_referral_from_db(
+    value: &D1DataAccessLayer,
+    programme_id: &str,
+    referee_id: &str,
+) -> Result<Option<ReferralRow>, worker::Error> {
+    value
+        .db
+        .prepare(format!(
+            "SELECT {} FROM loyalty_referral WHERE programme_id = ?1 AND referee_id = ?2",
+            REFERRAL_COLUMNS
+        ))
+        .bind(&[JsValue::from(programme_id), JsValue::from(referee_id)])?
+        .first::<ReferralRow>(None)
+        .await
+}
+
+#[worker::send]
+async fn list_referrals_from_db(
+    value: &D1DataAccessLayer,
+    programme_id: &str,
+    referrer_id: &str,
+) -> Result<Vec<ReferralRow>, worker::Error> {
+    value
+        .db
+        .prepare(format!("SELECT {} FROM loyalty_referral WHERE programme_id = ?1 AND referrer_id = ?2 ORDER BY created_epoch", REFERRAL_COLUMNS))
+        .bind(&[JsValue::from(programme_id), JsValue::from(referrer_id)])?
+        .all()
+        .await?
+        .results::<ReferralRow>()
+}
+
+#[worker::send]
+async fn reward_referral_in_db(
+    value: &D1DataAccessLayer,
+    referral: &Referral,
+    credits: Vec<(&LoyaltyAccount, LoyaltyAccountTransaction)>,
+) -> Result<bool, worker::Error> {
+    let programme_id = JsValue::from(referral.programme_id());
+    let referee_id = JsValue::from(referral.referee_id());
+    let pending = JsValue::from(ReferralStatus::Pending.to_string());
+    let mut statements = vec![];
+
+    // A batch runs as a single transaction, every credit is guarded on the referral still being
+    // pending and the status only moves on in the final statement
+    for (account, transaction) in credits {
+        statements.push(
+            value
+                .db
+                .prepare("INSERT INTO loyalty_transaction (programme_id, customer_id, date_epoch, order_number, change, recorded_epoch) SELECT ?1, ?2, ?3, ?4, ?5, ?8 WHERE EXISTS (SELECT 1 FROM loyalty_referral WHERE programme_id = ?1 AND referee_id = ?6 AND status = ?7)")
+                .bind(&[
+                    programme_id.clone(),
+                    JsValue::from(account.customer_id()),
+                    JsValue::from(transaction.date().timestamp_millis() as i32),
+                    JsValue::from(transaction.order_number()),
+                    JsValue::from(transaction.change()),
+                    referee_id.clone(),
+                    pending.clone(),
+                    JsValue::from(transaction.recorded_at().timestamp_millis() as f64),
+                ])?,
+        );
+        statements.push(
+            value
+                .db
+                .prepare("UPDATE loyalty SET current_points = current_points + ?1 WHERE programme_id = ?2 AND customer_id = ?3 AND EXISTS (SELECT 1 FROM loyalty_referral WHERE programme_id = ?2 AND referee_id = ?4 AND status = ?5)")
+                .bind(&[
+                    JsValue::from(transaction.change()),
+                    programme_id.clone(),
+                    JsValue::from(account.customer_id()),
+                    referee_id.clone(),
+                    pending.clone(),
+                ])?,
+        );
+    }
+
+    statements.push(
+        value
+            .db
+            .prepare("UPDATE loyalty_referral SET status = ?1, rewarded_epoch = ?2 WHERE programme_id = ?3 AND referee_id = ?4 AND status = ?5")
+            .bind(&[
+                JsValue::from(referral.status().to_string()),
+                JsValue::from(referral.rewarded_at().unwrap_or(Utc::now()).timestamp_millis() as f64),
+                programme_id,
+                referee_id,
+                pending,
+            ])?,
+    );
+
+    let results = value.db.batch(statements).await?;
+
+    Ok(match results.last() {
+        Some(result) => result
+            .meta()?
+            .and_then(|meta| meta.changes)
+            .is_some_and(|changes| changes == 1),
+        None => false,
+    })
+}
+
+const HOUSEHOLD_MEMBER_COLUMNS: &str =
+    "customer_id, household_id, role, spend_limit, contributed_points";
+
+fn insert_household_member_statement(
+    value: &D1DataAccessLayer,
+    household_id: &str,
+    member: &HouseholdMember,
+) -> Result<worker::D1PreparedStatement, worker::Error> {
+    // Members join the programme of their household
+    value
+        .db
+        .prepare(format!("INSERT INTO loyalty_household_member (programme_id, {}) SELECT programme_id, ?1, household_id, ?3, ?4, ?5 FROM loyalty_household WHERE household_id = ?2", HOUSEHOLD_MEMBER_COLUMNS))
+        .bind(&[
+            JsValue::from(member.customer_id()),
+            JsValue::from(household_id),
+            JsValue::from(member.role().to_string()),
+            member.spend_limit().map(JsValue::from).unwrap_or(JsValue::NULL),
+            JsValue::from(member.contributed_points()),
+        ])
+}
+
+#[worker::send]
+async fn create_household_in_db(
+    value: &D1DataAccessLayer,
+    household: &Household,
+) -> Result<(), worker::Error> {
+    let mut statements = vec![value
+        .db
+        .prepare("INSERT INTO loyalty_household (programme_id, household_id, name, points, created_epoch) VALUES (?1, ?2, ?3, ?4, ?5)")
+        .bind(&[
+            JsValue::from(household.programme_id()),
+            JsValue::from(household.household_id()),
+            JsValue::from(household.name()),
+            JsValue::from(household.points()),
+            JsValue::from(household.created_at().timestamp_millis() as f64),
+        ])?];
+
+    for member in household.members() {
+        statements.push(insert_household_member_statement(value, household.household_id(), member)?);
+    }
+
+    value.db.batch(statements).await?;
+
+    Ok(())
+}
+
+#[worker::send]
+async fn retrieve_household_from_db(
+    value: &D1DataAccessLayer,
+    programme_id: &str,
+    household_id: &str,
+) -> Result<Option<(HouseholdRow, Vec<HouseholdMemberRow>)>, worker::Error> {
+    let household = value
+        .db
+        .prepare("SELECT programme_id, household_id, name, points, created_epoch FROM loyalty_household WHERE programme_id = ?1 AND household_id = ?2")
+        .bind(&[JsValue::from(programme_id), JsValue::from(household_id)])?
+        .first::<HouseholdRow>(None)
+        .await?;
+
+    let Some(household) = household else {
+        return Ok(None);
+    };
+
+    let members = value
+        .db
+        .prepare(format!("SELECT {} FROM loyalty_household_member WHERE household_id = ?1 ORDER BY customer_id", HOUSEHOLD_MEMBER_COLUMNS))
+        .bind(&[JsValue::from(household_id)])?
+        .all()
+        .await?
+        .results::<HouseholdMemberRow>()?;
+
+    Ok(Some((household, members)))
+}
+
+#[worker::send]
+async fn retrieve_household_member_from_db(
+    value: &D1DataAccessLayer,
+    programme_id: &str,
+    customer_id: &str,
+) -> Result<Option<HouseholdMemberRow>, worker::Error> {
+    value
+        .db
+        .prepare(format!(
+            "SELECT {} FROM loyalty_household_member WHERE programme_id = ?1 AND customer_id = ?2",
+            HOUSEHOLD_MEMBER_COLUMNS
+        ))
+        .bind(&[JsValue::from(programme_id), JsValue::from(customer_id)])?
+        .first::<HouseholdMemberRow>(None)
+        .await
+}
+
+#[worker::send]
+async fn add_household_member_to_db(
+    value: &D1DataAccessLayer,
+    household_id: &str,
+    member: &HouseholdMember,
+) -> Result<(), worker::Error> {
+    insert_household_member_statement(value, household_id, member)?
+        .run()
+        .await?;
+
+    Ok(())
+}
+
+#[worker::send]
+async fn remove_household_member_from_db(
+    value: &D1DataAccessLayer,
+    household_id: &str,
+    customer_id: &str,
+) -> Result<(), worker::Error> {
+    value
+        .db
+        .prepare("DELETE FROM loyalty_household_member WHERE household_id = ?1 AND customer_id = ?2")
+        .bind(&[JsValue::from(household_id), JsValue::from(customer_id)])?
+        .run()
+        .await?;
+
+    Ok(())
+}
+
+#[worker::send]
+async fn contribute_to_household_in_db(
+    value: &D1DataAccessLayer,
+    household_id: &str,
+    customer_id: &str,
+    transaction: &LoyaltyAccountTransaction,
+) -> Result<bool, worker::Error> {
+    let not_credited = "NOT EXISTS (SELECT 1 FROM loyalty_household_transaction WHERE household_id = ?2 AND order_number = ?3)";
+
+    // The batch runs as a single transaction, both balances only move if the order has not been
+    // credited before and the order is recorded last
+    let statements = vec![
+        value
+            .db
+            .prepare(format!("UPDATE loyalty_household SET points = points + ?1 WHERE household_id = ?2 AND {}", not_credited))
+            .bind(&[
+                JsValue::from(transaction.change()),
+                JsValue::from(household_id),
+                JsValue::from(transaction.order_number()),
+            ])?,
+        value
+            .db
+            .prepare(format!("UPDATE loyalty_household_member SET contributed_points = contributed_points + ?1 WHERE household_id = ?2 AND customer_id = ?4 AND {}", not_credited))
+            .bind(&[
+                JsValue::from(transaction.change()),
+                JsValue::from(household_id),
+                JsValue::from(transaction.order_number()),
+                JsValue::from(customer_id),
+            ])?,
+        value
+            .db
+            .prepare("INSERT OR IGNORE INTO loyalty_household_transaction (household_id, customer_id, date_epoch, order_number, change) VALUES (?1, ?2, ?3, ?4, ?5)")
+            .bind(&[
+                JsValue::from(household_id),
+                JsValue::from(customer_id),
+                JsValue::from(transaction.date().timestamp_millis() as f64),
+                JsValue::from(transaction.order_number()),
+                JsValue::from(transaction.change()),
+            ])?,
+    ];
+
+    let results = value.db.batch(statements).await?;
+
+    Ok(match results.last() {
+        Some(result) => result
+            .meta()?
+            .and_then(|meta| meta.changes)
+            .is_some_and(|changes| changes == 1),
+        None => false,
+    })
+}
+
+#[worker::send]
+async fn spend_from_household_in_db(
+    value: &D1DataAccessLayer,
+    household_id: &str,
+    customer_id: &str,
+    transaction: &LoyaltyAccountTransaction,
+) -> Result<bool, worker::Error> {
+    // The spend is only recorded while the pool can cover it, and the pool is only debited once
+    // the spend has been recorded
+    let statements = vec![
+        value
+            .db
+            .prepare("INSERT INTO loyalty_household_transaction (household_id, customer_id, date_epoch, order_number, change) SELECT ?1, ?2, ?3, ?4, ?5 WHERE EXISTS (SELECT 1 FROM loyalty_household WHERE household_id = ?1 AND points + ?5 >= 0)")
+            .bind(&[
+                JsValue::from(household_id),
+                JsValue::from(customer_id),
+                JsValue::from(transaction.date().timestamp_millis() as f64),
+                JsValue::from(transaction.order_number()),
+                JsValue::from(transaction.change()),
+            ])?,
+        value
+            .db
+            .prepare("UPDATE loyalty_household SET points = points + ?1 WHERE household_id = ?2 AND points + ?1 >= 0 AND EXISTS (SELECT 1 FROM loyalty_household_transaction WHERE household_id = ?2 AND order_number = ?3)")
+            .bind(&[
+                JsValue::from(transaction.change()),
+                JsValue::from(household_id),
+                JsValue::from(transaction.order_number()),
+            ])?,
+    ];
+
+    let results = value.db.batch(statements).await?;
+
+    Ok(match results.last() {
+        Some(result) => result
+            .meta()?
+            .and_then(|meta| meta.changes)
+            .is_some_and(|changes| changes == 1),
+        None => false,
+    })
+}
+
+const PROGRAMME_COLUMNS: &str = "programme_id, name, earn_rate, base_currency, created_epoch";
+
+#[worker::send]
+async fn save_programme_to_db(
+    value: &D1DataAccessLayer,
+    programme: &Programme,
+) -> Result<(), worker::Error> {
+    value
+        .db
+        .prepare(format!("INSERT INTO loyalty_programme ({}) VALUES (?1, ?2, ?3, ?4, ?5) ON CONFLICT (programme_id) DO UPDATE SET name = excluded.name, earn_rate = excluded.earn_rate", PROGRAMME_COLUMNS))
+        .bind(&[
+            JsValue::from(programme.programme_id()),
+            JsValue::from(programme.name()),
+            JsValue::from(programme.earn_rate()),
+            JsValue::from(programme.base_currency()),
+            JsValue::from(programme.created_at().timestamp_millis() as f64),
+        ])?
+        .run()
+        .await?;
+
+    Ok(())
+}
+
+#[worker::send]
+async fn retrieve_programme_from_db(
+    value: &D1DataAccessLayer,
+    programme_id: &str,
+) -> Result<Option<ProgrammeRow>, worker::Error> {
+    value
+        .db
+        .prepare(format!(
+            "SELECT {} FROM loyalty_programme WHERE programme_id = ?1",
+            PROGRAMME_COLUMNS
+        ))
+        .bind(&[JsValue::from(programme_id)])?
+        .first::<ProgrammeRow>(None)
+        .await
+}
+
+#[worker::send]
+async fn list_programmes_from_db(
+    value: &D1DataAccessLayer,
+) -> Result<Vec<ProgrammeRow>, worker::Error> {
+    value
+        .db
+        .prepare(format!(
+            "SELECT {} FROM loyalty_programme ORDER BY programme_id",
+            PROGRAMME_COLUMNS
+        ))
+        .all()
+        .await?
+        .results::<ProgrammeRow>()
+}
+
+const FLAGGED_SPEND_COLUMNS: &str =
+    "programme_id, customer_id, order_number, spend, reasons, flagged_epoch";
+
+#[worker::send]
+async fn spend_flagged_in_db(
+    value: &D1DataAccessLayer,
+    account: &LoyaltyAccount,
+    transaction: &LoyaltyAccountTransaction,
+    flagged_spend: &FlaggedSpend,
+) -> Result<(), worker::Error> {
+    let metadata = match transaction.metadata().is_empty() {
+        true => JsValue::NULL,
+        false => serde_json::to_string(transaction.metadata())
+            .map(JsValue::from)
+            .unwrap_or(JsValue::NULL),
+    };
+    let reasons = serde_json::to_string(flagged_spend.reasons()).unwrap_or("[]".to_string());
+
+    let statements = vec![
+        value
+            .db
+            .prepare("INSERT INTO loyalty_transaction (programme_id, customer_id, date_epoch, order_number, change, metadata, vests_epoch, recorded_epoch) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)")
+            .bind(&[
+                JsValue::from(account.programme_id()),
+                JsValue::from(account.customer_id()),
+                JsValue::from(transaction.date().timestamp_millis() as i32),
+                JsValue::from(transaction.order_number()),
+                JsValue::from(transaction.change()),
+                metadata,
+                transaction
+                    .vests_at()
+                    .map(|date| JsValue::from(date.timestamp_millis() as f64))
+                    .unwrap_or(JsValue::NULL),
+                JsValue::from(transaction.recorded_at().timestamp_millis() as f64),
+            ])?,
+        value
+            .db
+            .prepare("UPDATE loyalty SET current_points = ?1 WHERE programme_id = ?2 AND customer_id = ?3")
+            .bind(&[
+                JsValue::from(*account.current_points()),
+                JsValue::from(account.programme_id()),
+                JsValue::from(account.customer_id()),
+            ])?,
+        value
+            .db
+            .prepare(format!("INSERT INTO loyalty_flagged_spend ({}) VALUES (?1, ?2, ?3, ?4, ?5, ?6) ON CONFLICT (programme_id, customer_id, order_number) DO NOTHING", FLAGGED_SPEND_COLUMNS))
+            .bind(&[
+                JsValue::from(flagged_spend.programme_id()),
+                JsValue::from(flagged_spend.customer_id()),
+                JsValue::from(flagged_spend.order_number()),
+                JsValue::from(flagged_spend.spend()),
+                JsValue::from(reasons),
+                JsValue::from(flagged_spend.flagged_at().timestamp_millis() as f64),
+            ])?,
+    ];
+
+    value.db.batch(statements).await?;
+
+    Ok(())
+}
+
+#[worker::send]
+async fn list_flagged_spends_from_db(
+    value: &D1DataAccessLayer,
+    programme_id: &str,
+) -> Result<Vec<FlaggedSpendRow>, worker::Error> {
+    value
+        .db
+        .prepare(format!(
+            "SELECT {} FROM loyalty_flagged_spend WHERE programme_id = ?1 ORDER BY flagged_epoch",
+            FLAGGED_SPEND_COLUMNS
+        ))
+        .bind(&[JsValue::from(programme_id)])?
+        .all()
+        .await?
+        .results::<FlaggedSpendRow>()
+}
+
+#[async_trait]
+impl Programmes for D1DataAccessLayer {
+    async fn save_programme(&self, programme: &Programme) -> Result<(), LoyaltyErrors> {
+        save_programme_to_db(self, programme)
+            .await
+            .map_err(|e| LoyaltyErrors::DatabaseError(format!("{:?}", e)))
+    }
+
+    async fn retrieve_programme(&self, programme_id: &str) -> Result<Programme, LoyaltyErrors> {
+        let row = retrieve_programme_from_db(self, programme_id)
+            .await
+            .map_err(|e| LoyaltyErrors::DatabaseError(format!("{:?}", e)))?;
+
+        match row {
+            Some(row) => Ok(row.into()),
+            None => Err(LoyaltyErrors::ProgrammeNotFound(format!(
+                "Programme {} not found",
+                programme_id
+            ))),
+        }
+    }
+
+    async fn list_programmes(&self) -> Result<Vec<Programme>, LoyaltyErrors> {
+        Ok(list_programmes_from_db(self)
+            .await
+            .map_err(|e| LoyaltyErrors::DatabaseError(format!("{:?}", e)))?
+            .into_iter()
+            .map(|row| row.into())
+            .collect())
+    }
+}
+
 #[async_trait]
 impl LoyaltyPoints for D1DataAccessLayer {
     async fn new_account(
         &self,
-        customer_id: String,
-    ) -> anyhow::Result<LoyaltyAccount, LoyaltyErrors> {
-        let account = LoyaltyAccount::new(customer_id)?;
-
-        insert_new_account_to_db(self, &account).await;
-
-        Ok(account)
+        account: &LoyaltyAccount,
+        transactions: Vec<LoyaltyAccountTransaction>,
+    ) -> anyhow::Result<(), LoyaltyErrors> {
+        insert_new_account_to_db(self, account, &transactions)
+            .await
+            .map_err(|e| LoyaltyErrors::DatabaseError(format!("{:?}", e)))
     }
 
-    async fn retrieve(&self, customer_id: &str) -> anyhow::Result<LoyaltyAccount, LoyaltyErrors> {
-        let account = retrieve_from_db(self, customer_id).await;
+    async fn retrieve(
+        &self,
+        programme_id: &str,
+        customer_id: &str,
+    ) -> anyhow::Result<LoyaltyAccount, LoyaltyErrors> {
+        let account = retrieve_from_db(self, programme_id, customer_id).await;
 
         match account {
             Some(account) => {
-                let transactions = retrieve_transactions_from_db(self, customer_id).await;
+                let transactions =
+                    retrieve_transactions_from_db(self, programme_id, customer_id).await;
+
+                let status = account.status.parse::<AccountStatus>()?;
 
-                Ok(
-                    LoyaltyAccount::from(account.customer_id, account.current_points, transactions)
-                        .unwrap(),
+                Ok(LoyaltyAccount::from(
+                    account.programme_id,
+                    account.customer_id,
+                    account.current_points,
+                    transactions,
                 )
+                .unwrap()
+                .with_status(status, account.merged_into))
             }
             None => Err(LoyaltyErrors::AccountNotFound()),
         }
@@ -187,4 +1331,346 @@ impl LoyaltyPoints for D1DataAccessLayer {
 
         Ok(())
     }
+
+    async fn add_transactions(
+        &self,
+        account: &LoyaltyAccount,
+        transactions: Vec<LoyaltyAccountTransaction>,
+    ) -> anyhow::Result<(), LoyaltyErrors> {
+        add_transactions_in_db(self, account, &transactions)
+            .await
+            .map_err(|e| LoyaltyErrors::DatabaseError(format!("{:?}", e)))
+    }
+
+    async fn transfer(
+        &self,
+        source: &LoyaltyAccount,
+        target: &LoyaltyAccount,
+        debit: LoyaltyAccountTransaction,
+        credit: LoyaltyAccountTransaction,
+    ) -> anyhow::Result<(), LoyaltyErrors> {
+        transfer_in_db(self, source, target, &debit, &credit)
+            .await
+            .map_err(|e| LoyaltyErrors::DatabaseError(format!("{:?}", e)))
+    }
+
+    async fn update_status(&self, account: &LoyaltyAccount) -> anyhow::Result<(), LoyaltyErrors> {
+        update_status_in_db(self, account)
+            .await
+            .map_err(|e| LoyaltyErrors::DatabaseError(format!("{:?}", e)))
+    }
+
+    async fn merge_accounts(
+        &self,
+        source: &LoyaltyAccount,
+        target: &LoyaltyAccount,
+    ) -> anyhow::Result<(), LoyaltyErrors> {
+        merge_accounts_in_db(self, source, target)
+            .await
+            .map_err(|e| LoyaltyErrors::DatabaseError(format!("{:?}", e)))
+    }
+
+    async fn erase(
+        &self,
+        customer_id: &str,
+        account: &LoyaltyAccount,
+    ) -> anyhow::Result<(), LoyaltyErrors> {
+        erase_in_db(self, customer_id, account)
+            .await
+            .map_err(|e| LoyaltyErrors::DatabaseError(format!("{:?}", e)))
+    }
+}
+
+#[async_trait]
+impl Campaigns for D1DataAccessLayer {
+    async fn save_campaign(&self, campaign: &Campaign) -> Result<(), LoyaltyErrors> {
+        save_campaign_to_db(self, campaign)
+            .await
+            .map_err(|e| LoyaltyErrors::DatabaseError(format!("{:?}", e)))
+    }
+
+    async fn retrieve_campaign(
+        &self,
+        programme_id: &str,
+        campaign_id: &str,
+    ) -> Result<Campaign, LoyaltyErrors> {
+        let row = retrieve_campaign_from_db(self, programme_id, campaign_id)
+            .await
+            .map_err(|e| LoyaltyErrors::DatabaseError(format!("{:?}", e)))?;
+
+        match row {
+            Some(row) => row.try_into(),
+            None => Err(LoyaltyErrors::CampaignNotFound(format!(
+                "Campaign {} not found",
+                campaign_id
+            ))),
+        }
+    }
+
+    async fn list_campaigns(&self, programme_id: &str) -> Result<Vec<Campaign>, LoyaltyErrors> {
+        list_campaigns_from_db(self, programme_id, None)
+            .await
+            .map_err(|e| LoyaltyErrors::DatabaseError(format!("{:?}", e)))?
+            .into_iter()
+            .map(|row| row.try_into())
+            .collect()
+    }
+
+    async fn delete_campaign(
+        &self,
+        programme_id: &str,
+        campaign_id: &str,
+    ) -> Result<(), LoyaltyErrors> {
+        delete_campaign_from_db(self, programme_id, campaign_id)
+            .await
+            .map_err(|e| LoyaltyErrors::DatabaseError(format!("{:?}", e)))
+    }
+
+    async fn active_campaigns(
+        &self,
+        programme_id: &str,
+        at: DateTime<Utc>,
+    ) -> Result<Vec<Campaign>, LoyaltyErrors> {
+        list_campaigns_from_db(self, programme_id, Some(at))
+            .await
+            .map_err(|e| LoyaltyErrors::DatabaseError(format!("{:?}", e)))?
+            .into_iter()
+            .map(|row| row.try_into())
+            .collect()
+    }
+
+    async fn reserve_budget(&self, campaign_id: &str, points: f32) -> Result<bool, LoyaltyErrors> {
+        reserve_campaign_budget_in_db(self, campaign_id, points)
+            .await
+            .map_err(|e| LoyaltyErrors::DatabaseError(format!("{:?}", e)))
+    }
+}
+
+#[async_trait]
+impl Referrals for D1DataAccessLayer {
+    async fn assign_referral_code(
+        &self,
+        programme_id: &str,
+        customer_id: &str,
+        referral_code: &str,
+    ) -> Result<String, LoyaltyErrors> {
+        let row = assign_referral_code_in_db(self, programme_id, customer_id, referral_code)
+            .await
+            .map_err(|e| LoyaltyErrors::DatabaseError(format!("{:?}", e)))?;
+
+        match row {
+            Some(row) => Ok(row.referral_code),
+            None => Err(LoyaltyErrors::DatabaseError(format!(
+                "Referral code for {} was not stored",
+                customer_id
+            ))),
+        }
+    }
+
+    async fn customer_for_referral_code(
+        &self,
+        programme_id: &str,
+        referral_code: &str,
+    ) -> Result<String, LoyaltyErrors> {
+        let row = retrieve_referral_code_from_db(self, programme_id, referral_code)
+            .await
+            .map_err(|e| LoyaltyErrors::DatabaseError(format!("{:?}", e)))?;
+
+        match row {
+            Some(row) => Ok(row.customer_id),
+            None => Err(LoyaltyErrors::ReferralNotFound(format!(
+                "Referral code {} not found",
+                referral_code
+            ))),
+        }
+    }
+
+    async fn save_referral(&self, referral: &Referral) -> Result<(), LoyaltyErrors> {
+        save_referral_to_db(self, referral)
+            .await
+            .map_err(|e| LoyaltyErrors::DatabaseError(format!("{:?}", e)))
+    }
+
+    async fn retrieve_referral(
+        &self,
+        programme_id: &str,
+        referee_id: &str,
+    ) -> Result<Referral, LoyaltyErrors> {
+        let row = retrieve_referral_from_db(self, programme_id, referee_id)
+            .await
+            .map_err(|e| LoyaltyErrors::DatabaseError(format!("{:?}", e)))?;
+
+        match row {
+            Some(row) => row.try_into(),
+            None => Err(LoyaltyErrors::ReferralNotFound(format!(
+                "Referral for {} not found",
+                referee_id
+            ))),
+        }
+    }
+
+    async fn list_referrals(
+        &self,
+        programme_id: &str,
+        referrer_id: &str,
+    ) -> Result<Vec<Referral>, LoyaltyErrors> {
+        list_referrals_from_db(self, programme_id, referrer_id)
+            .await
+            .map_err(|e| LoyaltyErrors::DatabaseError(format!("{:?}", e)))?
+            .into_iter()
+            .map(|row| row.try_into())
+            .collect()
+    }
+
+    async fn reward_referral<'a>(
+        &self,
+        referral: &Referral,
+        referee: &LoyaltyAccount,
+        referrer: Option<&'a LoyaltyAccount>,
+        bonuses: ReferralBonuses,
+    ) -> Result<bool, LoyaltyErrors> {
+        let mut credits = vec![];
+
+        if let Some(transaction) = bonuses.referee_bonus {
+            credits.push((referee, transaction));
+        }
+
+        if let (Some(referrer), Some(transaction)) = (referrer, bonuses.referrer_bonus) {
+            credits.push((referrer, transaction));
+        }
+
+        reward_referral_in_db(self, referral, credits)
+            .await
+            .map_err(|e| LoyaltyErrors::DatabaseError(format!("{:?}", e)))
+    }
+}
+
+#[async_trait]
+impl Households for D1DataAccessLayer {
+    async fn create_household(&self, household: &Household) -> Result<(), LoyaltyErrors> {
+        create_household_in_db(self, household)
+            .await
+            .map_err(|e| LoyaltyErrors::DatabaseError(format!("{:?}", e)))
+    }
+
+    async fn retrieve_household(
+        &self,
+        programme_id: &str,
+        household_id: &str,
+    ) -> Result<Household, LoyaltyErrors> {
+        let row = retrieve_household_from_db(self, programme_id, household_id)
+            .await
+            .map_err(|e| LoyaltyErrors::DatabaseError(format!("{:?}", e)))?;
+
+        let Some((household, members)) = row else {
+            return Err(LoyaltyErrors::HouseholdNotFound(format!(
+                "Household {} not found",
+                household_id
+            )));
+        };
+
+        Ok(Household::from(
+            household.programme_id,
+            household.household_id,
+            household.name,
+            household.points,
+            members
+                .into_iter()
+                .map(|row| row.try_into())
+                .collect::<Result<Vec<HouseholdMember>, LoyaltyErrors>>()?,
+            DateTime::from_timestamp_millis(household.created_epoch).unwrap(),
+        ))
+    }
+
+    async fn household_for_member(
+        &self,
+        programme_id: &str,
+        customer_id: &str,
+    ) -> Result<Household, LoyaltyErrors> {
+        let member = retrieve_household_member_from_db(self, programme_id, customer_id)
+            .await
+            .map_err(|e| LoyaltyErrors::DatabaseError(format!("{:?}", e)))?;
+
+        match member {
+            Some(member) => {
+                self.retrieve_household(programme_id, &member.household_id)
+                    .await
+            }
+            None => Err(LoyaltyErrors::HouseholdNotFound(format!(
+                "{} does not belong to a household",
+                customer_id
+            ))),
+        }
+    }
+
+    async fn add_member(
+        &self,
+        household_id: &str,
+        member: &HouseholdMember,
+    ) -> Result<(), LoyaltyErrors> {
+        add_household_member_to_db(self, household_id, member)
+            .await
+            .map_err(|e| LoyaltyErrors::DatabaseError(format!("{:?}", e)))
+    }
+
+    async fn remove_member(&self, household_id: &str, customer_id: &str) -> Result<(), LoyaltyErrors> {
+        remove_household_member_from_db(self, household_id, customer_id)
+            .await
+            .map_err(|e| LoyaltyErrors::DatabaseError(format!("{:?}", e)))
+    }
+
+    async fn contribute(
+        &self,
+        household_id: &str,
+        customer_id: &str,
+        transaction: LoyaltyAccountTransaction,
+    ) -> Result<bool, LoyaltyErrors> {
+        contribute_to_household_in_db(self, household_id, customer_id, &transaction)
+            .await
+            .map_err(|e| LoyaltyErrors::DatabaseError(format!("{:?}", e)))
+    }
+
+    async fn spend(
+        &self,
+        household_id: &str,
+        customer_id: &str,
+        transaction: LoyaltyAccountTransaction,
+    ) -> Result<(), LoyaltyErrors> {
+        let spent = spend_from_household_in_db(self, household_id, customer_id, &transaction)
+            .await
+            .map_err(|e| LoyaltyErrors::DatabaseError(format!("{:?}", e)))?;
+
+        match spent {
+            true => Ok(()),
+            false => Err(LoyaltyErrors::PointsNotAvailable(
+                "Household points not enough to cover this transaction".to_string(),
+            )),
+        }
+    }
+}
+
+#[async_trait]
+impl FlaggedSpends for D1DataAccessLayer {
+    async fn spend_flagged(
+        &self,
+        account: &LoyaltyAccount,
+        transaction: LoyaltyAccountTransaction,
+        flagged_spend: &FlaggedSpend,
+    ) -> Result<(), LoyaltyErrors> {
+        spend_flagged_in_db(self, account, &transaction, flagged_spend)
+            .await
+            .map_err(|e| LoyaltyErrors::DatabaseError(format!("{:?}", e)))
+    }
+
+    async fn list_flagged_spends(
+        &self,
+        programme_id: &str,
+    ) -> Result<Vec<FlaggedSpend>, LoyaltyErrors> {
+        Ok(list_flagged_spends_from_db(self, programme_id)
+            .await
+            .map_err(|e| LoyaltyErrors::DatabaseError(format!("{:?}", e)))?
+            .into_iter()
+            .map(|row| row.into())
+            .collect())
+    }
 }
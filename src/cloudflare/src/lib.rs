@@ -8,10 +8,13 @@ use axum::{
     Json, Router,
 };
 use loyalty_core::{
-    LoyaltyDto, LoyaltyErrors, LoyaltyPoints, OrderConfirmed, OrderConfirmedEventHandler,
-    RetrieveLoyaltyAccountQueryHandler, SpendLoyaltyPointsCommand,
-    SpendLoyaltyPointsCommandHandler,
+    BonusPolicy, FlaggedSpends, Households, ListReferralsQueryHandler, LoyaltyDto, LoyaltyErrors,
+    LoyaltyPoints, OrderConfirmed, OrderConfirmedEventHandler, ReferralCodeDto, ReferralDto,
+    Referrals, RetrieveLoyaltyAccountQueryHandler, RetrieveReferralCodeQueryHandler,
+    SpendLoyaltyPointsCommand, SpendLoyaltyPointsCommandHandler, SpendRiskPolicy,
+    StaticExchangeRates, SystemClock, ValidationError, VestingPolicy, DEFAULT_PROGRAMME_ID,
 };
+use serde::Serialize;
 use tower_service::Service;
 use tracing_subscriber::{fmt::format::Pretty, layer::SubscriberExt, util::SubscriberInitExt};
 use tracing_web::{performance_layer, MakeConsoleWriter};
@@ -21,6 +24,7 @@ mod adapters;
 
 pub struct AppState<T: LoyaltyPoints + Send + Sync> {
     pub loyalty_points: T,
+    pub spend_risk_policy: SpendRiskPolicy,
 }
 
 #[event(start)]
@@ -51,24 +55,46 @@ async fn fetch(
 
     let postgres_db = D1DataAccessLayer::new(db).await;
 
+    let spend_risk_policy = env
+        .var("SPEND_RISK_RULES")
+        .ok()
+        .and_then(|rules| serde_json::from_str(&rules.to_string()).ok())
+        .map(SpendRiskPolicy::new)
+        .unwrap_or_default();
+
     let shared_state = Arc::new(AppState {
         loyalty_points: postgres_db,
+        spend_risk_policy,
     });
 
     let mut app: Router = Router::new()
-        .route("/loyalty/:customer_id", get(get_loyalty_points))
-        .route("/loyalty/:customer_id/spend", post(spend_loyalty_points))
+        .route("/loyalty/:customer_id", get(get_default_loyalty_points))
+        .route("/loyalty/:customer_id/spend", post(spend_default_loyalty_points))
+        .route("/programmes/:programme_id/loyalty/:customer_id", get(get_loyalty_points))
+        .route("/programmes/:programme_id/loyalty/:customer_id/spend", post(spend_loyalty_points))
+        .route(
+            "/programmes/:programme_id/loyalty/:customer_id/referral-code",
+            get(get_referral_code),
+        )
+        .route("/programmes/:programme_id/loyalty/:customer_id/referrals", get(list_referrals))
         .with_state(shared_state);
 
     Ok(app.call(req).await?)
 }
 
-async fn get_loyalty_points<T: LoyaltyPoints + Send + Sync>(
+async fn get_loyalty_points<T: LoyaltyPoints + Households + Send + Sync>(
     State(state): State<Arc<AppState<T>>>,
-    path: Path<String>,
+    path: Path<(String, String)>,
 ) -> (StatusCode, Json<Option<LoyaltyDto>>) {
-    let loyalty_points =
-        RetrieveLoyaltyAccountQueryHandler::handle(&state.loyalty_points, path.0).await;
+    let (programme_id, customer_id) = path.0;
+
+    let loyalty_points = RetrieveLoyaltyAccountQueryHandler::handle(
+        &state.loyalty_points,
+        &state.loyalty_points,
+        programme_id,
+        customer_id,
+    )
+    .await;
 
     match loyalty_points {
         Ok(loyalty) => (StatusCode::OK, (Json(Some(loyalty)))),
@@ -76,23 +102,100 @@ async fn get_loyalty_points<T: LoyaltyPoints + Send + Sync>(
     }
 }
 
-async fn spend_loyalty_points<T: LoyaltyPoints + Send + Sync>(
+async fn spend_loyalty_points<T: LoyaltyPoints + FlaggedSpends + Send + Sync>(
     State(state): State<Arc<AppState<T>>>,
-    Json(payload): Json<SpendLoyaltyPointsCommand>,
+    path: Path<(String, String)>,
+    Json(mut payload): Json<SpendLoyaltyPointsCommand>,
 ) -> (StatusCode, Json<Option<LoyaltyDto>>) {
-    let loyalty_points =
-        SpendLoyaltyPointsCommandHandler::handle(&state.loyalty_points, payload).await;
+    let (programme_id, customer_id) = path.0;
+
+    if payload.bind_path(programme_id, customer_id).is_err() {
+        return (StatusCode::BAD_REQUEST, Json(None));
+    }
+
+    let loyalty_points = SpendLoyaltyPointsCommandHandler::handle(
+        &state.loyalty_points,
+        &state.loyalty_points,
+        &state.spend_risk_policy,
+        &SystemClock,
+        payload,
+    )
+    .await;
 
     match loyalty_points {
         Ok(account) => (StatusCode::OK, (Json(Some(account)))),
         Err(e) => match e {
             LoyaltyErrors::PointsNotAvailable(_) => (StatusCode::BAD_REQUEST, (Json(None))),
+            LoyaltyErrors::SpendBlocked(_) => (StatusCode::FORBIDDEN, (Json(None))),
+            LoyaltyErrors::AccountNotFound() => (StatusCode::NOT_FOUND, (Json(None))),
+            _ => (StatusCode::INTERNAL_SERVER_ERROR, (Json(None))),
+        },
+    }
+}
+
+/// Kept for clients from before programmes were introduced, acts on the default programme.
+async fn get_default_loyalty_points<T: LoyaltyPoints + Households + Send + Sync>(
+    state: State<Arc<AppState<T>>>,
+    Path(customer_id): Path<String>,
+) -> (StatusCode, Json<Option<LoyaltyDto>>) {
+    get_loyalty_points(state, Path((DEFAULT_PROGRAMME_ID.to_string(), customer_id))).await
+}
+
+/// Kept for clients from before programmes were introduced, acts on the default programme.
+async fn spend_default_loyalty_points<T: LoyaltyPoints + FlaggedSpends + Send + Sync>(
+    state: State<Arc<AppState<T>>>,
+    Path(customer_id): Path<String>,
+    payload: Json<SpendLoyaltyPointsCommand>,
+) -> (StatusCode, Json<Option<LoyaltyDto>>) {
+    spend_loyalty_points(state, Path((DEFAULT_PROGRAMME_ID.to_string(), customer_id)), payload)
+        .await
+}
+
+async fn get_referral_code<T: LoyaltyPoints + Referrals + Send + Sync>(
+    State(state): State<Arc<AppState<T>>>,
+    path: Path<(String, String)>,
+) -> (StatusCode, Json<Option<ReferralCodeDto>>) {
+    let (programme_id, customer_id) = path.0;
+
+    let referral_code = RetrieveReferralCodeQueryHandler::handle(
+        &state.loyalty_points,
+        &state.loyalty_points,
+        programme_id,
+        customer_id,
+    )
+    .await;
+
+    match referral_code {
+        Ok(referral_code) => (StatusCode::OK, Json(Some(referral_code))),
+        Err(e) => match e {
             LoyaltyErrors::AccountNotFound() => (StatusCode::NOT_FOUND, (Json(None))),
             _ => (StatusCode::INTERNAL_SERVER_ERROR, (Json(None))),
         },
     }
 }
 
+async fn list_referrals<T: LoyaltyPoints + Referrals + Send + Sync>(
+    State(state): State<Arc<AppState<T>>>,
+    path: Path<(String, String)>,
+) -> (StatusCode, Json<Vec<ReferralDto>>) {
+    let (programme_id, customer_id) = path.0;
+
+    let referrals =
+        ListReferralsQueryHandler::handle(&state.loyalty_points, programme_id, customer_id).await;
+
+    match referrals {
+        Ok(referrals) => (StatusCode::OK, Json(referrals)),
+        Err(_) => (StatusCode::INTERNAL_SERVER_ERROR, Json(vec![])),
+    }
+}
+
+/// An invalid event, and every field that failed validation, sent to the dead letter queue.
+#[derive(Serialize)]
+struct DeadLetter<'a> {
+    error: ValidationError,
+    event: &'a OrderConfirmed,
+}
+
 #[event(queue)]
 pub async fn main(message_batch: MessageBatch<OrderConfirmed>, env: Env, _: Context) -> Result<()> {
     console_error_panic_hook::set_once();
@@ -101,10 +204,66 @@ pub async fn main(message_batch: MessageBatch<OrderConfirmed>, env: Env, _: Cont
 
     let postgres_db = D1DataAccessLayer::new(db).await;
 
-    for message in message_batch.messages()? {
-        let res = OrderConfirmedEventHandler::handle(&postgres_db, message.body()).await;
+    let vesting = env
+        .var("POINTS_VESTING_DAYS")
+        .ok()
+        .and_then(|days| days.to_string().parse().ok())
+        .map(VestingPolicy::new)
+        .unwrap_or_default();
+
+    let bonuses = BonusPolicy::default();
+
+    let exchange_rates = env
+        .var("EXCHANGE_RATES")
+        .ok()
+        .and_then(|rates| serde_json::from_str(&rates.to_string()).ok())
+        .and_then(|rates| StaticExchangeRates::new(rates).ok())
+        .unwrap_or_default();
+
+    let dead_letters = env.queue("DEAD_LETTER_QUEUE")?;
+
+    let messages = message_batch.messages()?;
+    let mut valid_messages = vec![];
+
+    for message in &messages {
+        // Invalid events can never succeed, so skip the retries and dead letter them straight away
+        if let Err(error) = message.body().validate() {
+            tracing::error!("Invalid 'OrderConfirmed' event: {}", error);
+
+            let dead_letter = DeadLetter {
+                error,
+                event: message.body(),
+            };
+
+            if dead_letters.send(&dead_letter).await.is_ok() {
+                message.ack();
+            }
+
+            continue;
+        }
+
+        valid_messages.push(message);
+    }
+
+    let events: Vec<&OrderConfirmed> = valid_messages.iter().map(|m| m.body()).collect();
+
+    // Each customer's orders are earned in one write, messages that fail are left to be retried
+    let results = OrderConfirmedEventHandler::handle_batch(
+        &postgres_db,
+        &postgres_db,
+        &postgres_db,
+        &postgres_db,
+        &postgres_db,
+        &exchange_rates,
+        &vesting,
+        &bonuses,
+        &SystemClock,
+        &events,
+    )
+    .await;
 
-        if res.is_ok() {
+    for (message, result) in valid_messages.into_iter().zip(results) {
+        if result.is_ok() {
             message.ack();
         }
     }
@@ -0,0 +1,3 @@
+-- Add migration script here
+ALTER TABLE loyalty ADD COLUMN status VARCHAR(32) NOT NULL DEFAULT 'Active';
+ALTER TABLE loyalty ADD COLUMN merged_into VARCHAR(255);
@@ -0,0 +1,5 @@
+-- Add migration script here
+ALTER TABLE loyalty_transaction ADD COLUMN transfer_id VARCHAR(255);
+CREATE UNIQUE INDEX loyalty_transaction_transfer_idx
+  ON loyalty_transaction (customer_id, transfer_id)
+  WHERE transfer_id IS NOT NULL;
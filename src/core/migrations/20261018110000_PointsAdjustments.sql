@@ -0,0 +1,15 @@
+-- Add migration script here
+CREATE TABLE loyalty_adjustment (
+  adjustment_id VARCHAR(255) PRIMARY KEY,
+  customer_id VARCHAR(255) NOT NULL,
+  points REAL NOT NULL,
+  reason_code VARCHAR(32) NOT NULL,
+  note TEXT NOT NULL,
+  requested_by VARCHAR(255) NOT NULL,
+  status VARCHAR(32) NOT NULL,
+  reviewed_by VARCHAR(255),
+  created_epoch bigint NOT NULL,
+  reviewed_epoch bigint
+);
+
+CREATE INDEX loyalty_adjustment_status_idx ON loyalty_adjustment (status);
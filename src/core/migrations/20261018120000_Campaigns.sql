@@ -0,0 +1,17 @@
+-- Add migration script here
+CREATE TABLE loyalty_campaign (
+  campaign_id VARCHAR(255) PRIMARY KEY,
+  name VARCHAR(255) NOT NULL,
+  starts_epoch bigint NOT NULL,
+  ends_epoch bigint NOT NULL,
+  min_order_value REAL,
+  customer_ids TEXT[] NOT NULL DEFAULT '{}',
+  reward_type VARCHAR(32) NOT NULL,
+  reward_value REAL NOT NULL,
+  budget_cap REAL,
+  points_awarded REAL NOT NULL DEFAULT 0
+);
+
+CREATE INDEX loyalty_campaign_window_idx ON loyalty_campaign (starts_epoch, ends_epoch);
+
+ALTER TABLE loyalty_transaction ADD COLUMN metadata TEXT;
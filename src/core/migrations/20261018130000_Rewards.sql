@@ -0,0 +1,21 @@
+-- Add migration script here
+CREATE TABLE loyalty_reward (
+  reward_id VARCHAR(255) PRIMARY KEY,
+  name VARCHAR(255) NOT NULL,
+  description TEXT NOT NULL,
+  points_cost REAL NOT NULL,
+  stock INTEGER NOT NULL CHECK (stock >= 0),
+  available_from_epoch bigint,
+  available_until_epoch bigint
+);
+
+CREATE TABLE loyalty_redemption (
+  redemption_id VARCHAR(255) PRIMARY KEY,
+  customer_id VARCHAR(255) NOT NULL,
+  reward_id VARCHAR(255) NOT NULL REFERENCES loyalty_reward (reward_id),
+  points REAL NOT NULL,
+  status VARCHAR(32) NOT NULL,
+  created_epoch bigint NOT NULL
+);
+
+CREATE INDEX loyalty_redemption_customer_idx ON loyalty_redemption (customer_id);
@@ -0,0 +1,8 @@
+-- Add migration script here
+CREATE TABLE loyalty_reward_outbox (
+  redemption_id VARCHAR(255) PRIMARY KEY,
+  payload TEXT NOT NULL,
+  created_epoch bigint NOT NULL
+);
+
+CREATE INDEX loyalty_reward_outbox_created_idx ON loyalty_reward_outbox (created_epoch);
@@ -0,0 +1,12 @@
+-- Add migration script here
+CREATE TABLE loyalty_customer_profile (
+  customer_id VARCHAR(255) PRIMARY KEY,
+  display_name VARCHAR(255) NOT NULL,
+  email VARCHAR(255) NOT NULL,
+  birthday DATE,
+  marketing_opt_in BOOLEAN NOT NULL DEFAULT FALSE,
+  joined_epoch bigint NOT NULL
+);
+
+CREATE INDEX loyalty_customer_profile_birthday_idx
+  ON loyalty_customer_profile ((EXTRACT(MONTH FROM birthday)::int), (EXTRACT(DAY FROM birthday)::int));
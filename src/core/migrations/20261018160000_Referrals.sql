@@ -0,0 +1,16 @@
+-- Add migration script here
+CREATE TABLE loyalty_referral_code (
+  customer_id VARCHAR(255) PRIMARY KEY,
+  referral_code VARCHAR(32) NOT NULL UNIQUE
+);
+
+CREATE TABLE loyalty_referral (
+  referee_id VARCHAR(255) PRIMARY KEY,
+  referrer_id VARCHAR(255) NOT NULL,
+  referral_code VARCHAR(32) NOT NULL,
+  status VARCHAR(32) NOT NULL,
+  created_epoch bigint NOT NULL,
+  rewarded_epoch bigint
+);
+
+CREATE INDEX loyalty_referral_referrer_idx ON loyalty_referral (referrer_id);
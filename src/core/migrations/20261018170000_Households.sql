@@ -0,0 +1,26 @@
+-- Add migration script here
+CREATE TABLE loyalty_household (
+  household_id VARCHAR(255) PRIMARY KEY,
+  name VARCHAR(255) NOT NULL,
+  points REAL NOT NULL DEFAULT 0 CHECK (points >= 0),
+  created_epoch bigint NOT NULL
+);
+
+CREATE TABLE loyalty_household_member (
+  customer_id VARCHAR(255) PRIMARY KEY,
+  household_id VARCHAR(255) NOT NULL REFERENCES loyalty_household (household_id),
+  role VARCHAR(32) NOT NULL,
+  spend_limit REAL,
+  contributed_points REAL NOT NULL DEFAULT 0
+);
+
+CREATE INDEX loyalty_household_member_household_idx ON loyalty_household_member (household_id);
+
+CREATE TABLE loyalty_household_transaction (
+  household_id VARCHAR(255) NOT NULL REFERENCES loyalty_household (household_id),
+  customer_id VARCHAR(255) NOT NULL,
+  date_epoch bigint NOT NULL,
+  order_number VARCHAR(255) NOT NULL,
+  change REAL NOT NULL,
+  PRIMARY KEY (household_id, order_number)
+);
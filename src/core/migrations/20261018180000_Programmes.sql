@@ -0,0 +1,62 @@
+-- Add migration script here
+CREATE TABLE loyalty_programme (
+  programme_id VARCHAR(255) PRIMARY KEY,
+  name VARCHAR(255) NOT NULL,
+  earn_rate REAL NOT NULL CHECK (earn_rate >= 0),
+  created_epoch bigint NOT NULL
+);
+
+-- Everything that predates programmes belongs to the default programme, which keeps the
+-- original earn rate of half a point per unit spent
+INSERT INTO loyalty_programme ( programme_id, name, earn_rate, created_epoch )
+VALUES ( 'default', 'Default', 0.5, 0 );
+
+ALTER TABLE loyalty ADD COLUMN programme_id VARCHAR(255) NOT NULL DEFAULT 'default';
+CREATE UNIQUE INDEX loyalty_programme_customer_idx ON loyalty (programme_id, customer_id);
+
+ALTER TABLE loyalty_transaction ADD COLUMN programme_id VARCHAR(255) NOT NULL DEFAULT 'default';
+CREATE INDEX loyalty_transaction_programme_customer_idx
+  ON loyalty_transaction (programme_id, customer_id);
+DROP INDEX loyalty_transaction_transfer_idx;
+CREATE UNIQUE INDEX loyalty_transaction_transfer_idx
+  ON loyalty_transaction (programme_id, customer_id, transfer_id)
+  WHERE transfer_id IS NOT NULL;
+
+ALTER TABLE loyalty_adjustment ADD COLUMN programme_id VARCHAR(255) NOT NULL DEFAULT 'default';
+DROP INDEX loyalty_adjustment_status_idx;
+CREATE INDEX loyalty_adjustment_status_idx ON loyalty_adjustment (programme_id, status);
+
+ALTER TABLE loyalty_campaign ADD COLUMN programme_id VARCHAR(255) NOT NULL DEFAULT 'default';
+DROP INDEX loyalty_campaign_window_idx;
+CREATE INDEX loyalty_campaign_window_idx ON loyalty_campaign (programme_id, starts_epoch, ends_epoch);
+
+ALTER TABLE loyalty_reward ADD COLUMN programme_id VARCHAR(255) NOT NULL DEFAULT 'default';
+CREATE INDEX loyalty_reward_programme_idx ON loyalty_reward (programme_id);
+
+ALTER TABLE loyalty_redemption ADD COLUMN programme_id VARCHAR(255) NOT NULL DEFAULT 'default';
+
+ALTER TABLE loyalty_reward_outbox ADD COLUMN programme_id VARCHAR(255) NOT NULL DEFAULT 'default';
+ALTER TABLE loyalty_reward_outbox DROP CONSTRAINT loyalty_reward_outbox_pkey;
+ALTER TABLE loyalty_reward_outbox ADD PRIMARY KEY (programme_id, redemption_id);
+
+ALTER TABLE loyalty_customer_profile ADD COLUMN programme_id VARCHAR(255) NOT NULL DEFAULT 'default';
+ALTER TABLE loyalty_customer_profile DROP CONSTRAINT loyalty_customer_profile_pkey;
+ALTER TABLE loyalty_customer_profile ADD PRIMARY KEY (programme_id, customer_id);
+
+ALTER TABLE loyalty_referral_code ADD COLUMN programme_id VARCHAR(255) NOT NULL DEFAULT 'default';
+ALTER TABLE loyalty_referral_code DROP CONSTRAINT loyalty_referral_code_pkey;
+ALTER TABLE loyalty_referral_code DROP CONSTRAINT loyalty_referral_code_referral_code_key;
+ALTER TABLE loyalty_referral_code ADD PRIMARY KEY (programme_id, customer_id);
+ALTER TABLE loyalty_referral_code ADD UNIQUE (programme_id, referral_code);
+
+ALTER TABLE loyalty_referral ADD COLUMN programme_id VARCHAR(255) NOT NULL DEFAULT 'default';
+ALTER TABLE loyalty_referral DROP CONSTRAINT loyalty_referral_pkey;
+ALTER TABLE loyalty_referral ADD PRIMARY KEY (programme_id, referee_id);
+DROP INDEX loyalty_referral_referrer_idx;
+CREATE INDEX loyalty_referral_referrer_idx ON loyalty_referral (programme_id, referrer_id);
+
+ALTER TABLE loyalty_household ADD COLUMN programme_id VARCHAR(255) NOT NULL DEFAULT 'default';
+
+ALTER TABLE loyalty_household_member ADD COLUMN programme_id VARCHAR(255) NOT NULL DEFAULT 'default';
+ALTER TABLE loyalty_household_member DROP CONSTRAINT loyalty_household_member_pkey;
+ALTER TABLE loyalty_household_member ADD PRIMARY KEY (programme_id, customer_id);
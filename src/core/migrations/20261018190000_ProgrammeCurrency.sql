@@ -0,0 +1,3 @@
+-- Add migration script here
+-- Order values are converted into each programme's base currency before points are earned
+ALTER TABLE loyalty_programme ADD COLUMN base_currency VARCHAR(3) NOT NULL DEFAULT 'USD';
@@ -0,0 +1,12 @@
+-- Add migration script here
+CREATE TABLE loyalty_flagged_spend (
+  programme_id VARCHAR(255) NOT NULL,
+  customer_id VARCHAR(255) NOT NULL,
+  order_number VARCHAR(255) NOT NULL,
+  spend REAL NOT NULL,
+  reasons TEXT[] NOT NULL DEFAULT '{}',
+  flagged_epoch bigint NOT NULL,
+  PRIMARY KEY (programme_id, customer_id, order_number)
+);
+
+CREATE INDEX loyalty_flagged_spend_programme_idx ON loyalty_flagged_spend (programme_id, flagged_epoch);
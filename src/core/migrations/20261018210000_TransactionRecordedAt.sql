@@ -0,0 +1,2 @@
+-- Add migration script here
+ALTER TABLE loyalty_transaction ADD COLUMN recorded_epoch bigint;
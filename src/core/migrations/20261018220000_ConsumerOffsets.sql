@@ -0,0 +1,16 @@
+-- Add migration script here
+CREATE TABLE consumer_offset (
+  group_id VARCHAR(255) NOT NULL,
+  topic VARCHAR(255) NOT NULL,
+  partition INTEGER NOT NULL,
+  next_offset BIGINT NOT NULL,
+  PRIMARY KEY (group_id, topic, partition)
+);
+
+CREATE TABLE consumed_message (
+  group_id VARCHAR(255) NOT NULL,
+  topic VARCHAR(255) NOT NULL,
+  partition INTEGER NOT NULL,
+  message_offset BIGINT NOT NULL,
+  PRIMARY KEY (group_id, topic, partition, message_offset)
+);
@@ -0,0 +1,177 @@
+use serde::Deserialize;
+use tracing::info;
+
+use crate::{
+    adjustments::{
+        AdjustmentPolicy, PointsAdjustment, PointsAdjustmentDto, PointsAdjustments, ReasonCode,
+    },
+    clock::Clock,
+    loyalty::{retrieve_active_account, LoyaltyErrors, LoyaltyPoints},
+    validation::bind_path_id,
+};
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AdjustPointsCommand {
+    #[serde(default)]
+    programme_id: String,
+    customer_id: String,
+    points: f32,
+    reason_code: ReasonCode,
+    note: String,
+    /// The authenticated operator making the request, never taken from the body.
+    #[serde(skip)]
+    operator_id: String,
+}
+
+impl AdjustPointsCommand {
+    pub fn bind_path(&mut self, programme_id: String) -> Result<(), LoyaltyErrors> {
+        bind_path_id("programmeId", &mut self.programme_id, programme_id)?;
+
+        Ok(())
+    }
+
+    pub fn bind_operator(&mut self, operator_id: String) {
+        self.operator_id = operator_id;
+    }
+}
+
+pub struct AdjustPointsCommandHandler;
+
+impl AdjustPointsCommandHandler {
+    #[tracing::instrument(name = "handle_adjust_points", skip(loyalty_points, adjustments, policy, clock, command), fields(programme_id=command.programme_id, customer_id=command.customer_id, points=command.points, reason_code=command.reason_code.to_string(), operator_id=command.operator_id))]
+    pub async fn handle<T: LoyaltyPoints, A: PointsAdjustments, K: Clock>(
+        loyalty_points: &T,
+        adjustments: &A,
+        policy: &AdjustmentPolicy,
+        clock: &K,
+        command: AdjustPointsCommand,
+    ) -> anyhow::Result<PointsAdjustmentDto, LoyaltyErrors> {
+        let mut account =
+            retrieve_active_account(loyalty_points, &command.programme_id, &command.customer_id)
+                .await?;
+
+        let now = clock.now();
+
+        let mut adjustment = PointsAdjustment::new(
+            account.programme_id().to_string(),
+            account.customer_id().to_string(),
+            command.points,
+            command.reason_code,
+            command.note,
+            command.operator_id,
+            now,
+        )?;
+
+        if policy.requires_approval(command.points) {
+            info!(
+                "Adjustment {} requires approval",
+                adjustment.adjustment_id()
+            );
+
+            adjustments.save_adjustment(&adjustment).await?;
+
+            return Ok(adjustment.into());
+        }
+
+        let transaction = adjustment.apply(&mut account, None, now)?;
+
+        adjustments
+            .apply_adjustment(&account, transaction, &adjustment)
+            .await?;
+
+        Ok(adjustment.into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        adjustments::{AdjustmentStatus, MockPointsAdjustments},
+        clock::SystemClock,
+        loyalty::{LoyaltyAccount, MockLoyaltyPoints},
+        programmes::DEFAULT_PROGRAMME_ID,
+    };
+
+    use super::*;
+
+    fn command(points: f32) -> AdjustPointsCommand {
+        AdjustPointsCommand {
+            programme_id: DEFAULT_PROGRAMME_ID.to_string(),
+            customer_id: "james".to_string(),
+            points,
+            reason_code: ReasonCode::Goodwill,
+            note: "Late delivery".to_string(),
+            operator_id: "agent-1".to_string(),
+        }
+    }
+
+    #[tokio::test]
+    async fn on_adjustment_below_threshold_points_should_be_applied() {
+        let mut loyalty_points = MockLoyaltyPoints::new();
+        loyalty_points
+            .expect_retrieve()
+            .times(1)
+            .returning(|programme_id, customer_id| {
+                LoyaltyAccount::from(
+                    programme_id.to_string(),
+                    customer_id.to_string(),
+                    10.0,
+                    vec![],
+                )
+            });
+        let mut adjustments = MockPointsAdjustments::new();
+        adjustments
+            .expect_apply_adjustment()
+            .withf(|account, transaction, _| {
+                *account.current_points() == 60.0 && transaction.change() == 50.0
+            })
+            .times(1)
+            .returning(|_, _, _| Ok(()));
+        adjustments.expect_save_adjustment().times(0);
+
+        let result = AdjustPointsCommandHandler::handle(
+            &loyalty_points,
+            &adjustments,
+            &AdjustmentPolicy::new(100.0),
+            &SystemClock,
+            command(50.0),
+        )
+        .await;
+
+        assert_eq!(result.unwrap().status, AdjustmentStatus::Applied);
+    }
+
+    #[tokio::test]
+    async fn on_adjustment_above_threshold_should_be_pending() {
+        let mut loyalty_points = MockLoyaltyPoints::new();
+        loyalty_points
+            .expect_retrieve()
+            .times(1)
+            .returning(|programme_id, customer_id| {
+                LoyaltyAccount::from(
+                    programme_id.to_string(),
+                    customer_id.to_string(),
+                    10.0,
+                    vec![],
+                )
+            });
+        let mut adjustments = MockPointsAdjustments::new();
+        adjustments
+            .expect_save_adjustment()
+            .times(1)
+            .returning(|_| Ok(()));
+        adjustments.expect_apply_adjustment().times(0);
+
+        let result = AdjustPointsCommandHandler::handle(
+            &loyalty_points,
+            &adjustments,
+            &AdjustmentPolicy::new(100.0),
+            &SystemClock,
+            command(-150.0),
+        )
+        .await;
+
+        assert_eq!(result.unwrap().status, AdjustmentStatus::Pending);
+    }
+}
@@ -0,0 +1,425 @@
+use std::{fmt::Display, str::FromStr};
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::loyalty::{LoyaltyAccount, LoyaltyAccountTransaction, LoyaltyErrors};
+
+#[cfg(any(test, feature = "mocks"))]
+use mockall::automock;
+
+#[derive(Deserialize, Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ReasonCode {
+    Goodwill,
+    Correction,
+    Compensation,
+    Fraud,
+}
+
+impl Display for ReasonCode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let reason = match self {
+            ReasonCode::Goodwill => "Goodwill",
+            ReasonCode::Correction => "Correction",
+            ReasonCode::Compensation => "Compensation",
+            ReasonCode::Fraud => "Fraud",
+        };
+
+        write!(f, "{}", reason)
+    }
+}
+
+impl FromStr for ReasonCode {
+    type Err = LoyaltyErrors;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "Goodwill" => Ok(ReasonCode::Goodwill),
+            "Correction" => Ok(ReasonCode::Correction),
+            "Compensation" => Ok(ReasonCode::Compensation),
+            "Fraud" => Ok(ReasonCode::Fraud),
+            _ => Err(LoyaltyErrors::InvalidValues(format!(
+                "'{}' is not a valid reason code",
+                s
+            ))),
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AdjustmentStatus {
+    /// Waiting for a second operator to approve or reject.
+    Pending,
+    /// The points have been applied to the account.
+    Applied,
+    /// The adjustment was rejected and no points were applied.
+    Rejected,
+}
+
+impl Display for AdjustmentStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let status = match self {
+            AdjustmentStatus::Pending => "Pending",
+            AdjustmentStatus::Applied => "Applied",
+            AdjustmentStatus::Rejected => "Rejected",
+        };
+
+        write!(f, "{}", status)
+    }
+}
+
+impl FromStr for AdjustmentStatus {
+    type Err = LoyaltyErrors;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "Pending" => Ok(AdjustmentStatus::Pending),
+            "Applied" => Ok(AdjustmentStatus::Applied),
+            "Rejected" => Ok(AdjustmentStatus::Rejected),
+            _ => Err(LoyaltyErrors::InvalidValues(format!(
+                "'{}' is not a valid adjustment status",
+                s
+            ))),
+        }
+    }
+}
+
+/// Adjustments whose size is above `approval_threshold` need a second operator to approve them.
+#[derive(Clone, Debug)]
+pub struct AdjustmentPolicy {
+    approval_threshold: f32,
+}
+
+impl AdjustmentPolicy {
+    pub fn new(approval_threshold: f32) -> Self {
+        Self { approval_threshold }
+    }
+
+    pub(crate) fn requires_approval(&self, points: f32) -> bool {
+        points.abs() > self.approval_threshold
+    }
+}
+
+impl Default for AdjustmentPolicy {
+    fn default() -> Self {
+        Self::new(500.0)
+    }
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct PointsAdjustmentDto {
+    pub programme_id: String,
+    pub adjustment_id: String,
+    pub customer_id: String,
+    pub points: f32,
+    pub reason_code: ReasonCode,
+    pub note: String,
+    pub requested_by: String,
+    pub status: AdjustmentStatus,
+    pub reviewed_by: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub reviewed_at: Option<DateTime<Utc>>,
+}
+
+impl From<PointsAdjustment> for PointsAdjustmentDto {
+    fn from(value: PointsAdjustment) -> Self {
+        PointsAdjustmentDto {
+            programme_id: value.programme_id,
+            adjustment_id: value.adjustment_id,
+            customer_id: value.customer_id,
+            points: value.points,
+            reason_code: value.reason_code,
+            note: value.note,
+            requested_by: value.requested_by,
+            status: value.status,
+            reviewed_by: value.reviewed_by,
+            created_at: value.created_at,
+            reviewed_at: value.reviewed_at,
+        }
+    }
+}
+
+pub struct PointsAdjustment {
+    programme_id: String,
+    adjustment_id: String,
+    customer_id: String,
+    points: f32,
+    reason_code: ReasonCode,
+    note: String,
+    requested_by: String,
+    status: AdjustmentStatus,
+    reviewed_by: Option<String>,
+    created_at: DateTime<Utc>,
+    reviewed_at: Option<DateTime<Utc>>,
+}
+
+impl PointsAdjustment {
+    pub(crate) fn new(
+        programme_id: String,
+        customer_id: String,
+        points: f32,
+        reason_code: ReasonCode,
+        note: String,
+        requested_by: String,
+        created_at: DateTime<Utc>,
+    ) -> Result<Self, LoyaltyErrors> {
+        if customer_id.is_empty() || note.trim().is_empty() || requested_by.is_empty() {
+            return Err(LoyaltyErrors::InvalidValues(
+                "CustomerID, note and operator cannot be empty".to_string(),
+            ));
+        }
+
+        if !points.is_finite() || points == 0.0 {
+            return Err(LoyaltyErrors::InvalidValues(
+                "Adjustment points must be a non-zero number".to_string(),
+            ));
+        }
+
+        Ok(Self {
+            programme_id,
+            adjustment_id: format!("ADJ-{}", Uuid::new_v4()),
+            customer_id,
+            points,
+            reason_code,
+            note,
+            requested_by,
+            status: AdjustmentStatus::Pending,
+            reviewed_by: None,
+            created_at,
+            reviewed_at: None,
+        })
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub fn from(
+        programme_id: String,
+        adjustment_id: String,
+        customer_id: String,
+        points: f32,
+        reason_code: ReasonCode,
+        note: String,
+        requested_by: String,
+        status: AdjustmentStatus,
+        reviewed_by: Option<String>,
+        created_at: DateTime<Utc>,
+        reviewed_at: Option<DateTime<Utc>>,
+    ) -> Self {
+        Self {
+            programme_id,
+            adjustment_id,
+            customer_id,
+            points,
+            reason_code,
+            note,
+            requested_by,
+            status,
+            reviewed_by,
+            created_at,
+            reviewed_at,
+        }
+    }
+
+    pub fn programme_id(&self) -> &str {
+        &self.programme_id
+    }
+    pub fn adjustment_id(&self) -> &str {
+        &self.adjustment_id
+    }
+    pub fn customer_id(&self) -> &str {
+        &self.customer_id
+    }
+    pub fn points(&self) -> f32 {
+        self.points
+    }
+    pub fn reason_code(&self) -> ReasonCode {
+        self.reason_code
+    }
+    pub fn note(&self) -> &str {
+        &self.note
+    }
+    pub fn requested_by(&self) -> &str {
+        &self.requested_by
+    }
+    pub fn status(&self) -> AdjustmentStatus {
+        self.status
+    }
+    pub fn reviewed_by(&self) -> Option<&str> {
+        self.reviewed_by.as_deref()
+    }
+    pub fn created_at(&self) -> DateTime<Utc> {
+        self.created_at
+    }
+    pub fn reviewed_at(&self) -> Option<DateTime<Utc>> {
+        self.reviewed_at
+    }
+
+    /// Applies the adjustment to the account, recording `reviewed_by` when a second operator
+    /// approved it.
+    pub(crate) fn apply(
+        &mut self,
+        account: &mut LoyaltyAccount,
+        reviewed_by: Option<String>,
+        at: DateTime<Utc>,
+    ) -> Result<LoyaltyAccountTransaction, LoyaltyErrors> {
+        self.ensure_pending()?;
+
+        let transaction = account.adjust_points(&self.adjustment_id, self.points, at)?;
+
+        if reviewed_by.is_some() {
+            self.reviewed_at = Some(at);
+        }
+        self.reviewed_by = reviewed_by;
+        self.status = AdjustmentStatus::Applied;
+
+        Ok(transaction)
+    }
+
+    pub(crate) fn approve(
+        &mut self,
+        account: &mut LoyaltyAccount,
+        operator_id: String,
+        at: DateTime<Utc>,
+    ) -> Result<LoyaltyAccountTransaction, LoyaltyErrors> {
+        self.ensure_second_operator(&operator_id)?;
+
+        self.apply(account, Some(operator_id), at)
+    }
+
+    pub(crate) fn reject(
+        &mut self,
+        operator_id: String,
+        at: DateTime<Utc>,
+    ) -> Result<(), LoyaltyErrors> {
+        self.ensure_pending()?;
+        self.ensure_second_operator(&operator_id)?;
+
+        self.status = AdjustmentStatus::Rejected;
+        self.reviewed_by = Some(operator_id);
+        self.reviewed_at = Some(at);
+
+        Ok(())
+    }
+
+    fn ensure_pending(&self) -> Result<(), LoyaltyErrors> {
+        if self.status != AdjustmentStatus::Pending {
+            return Err(LoyaltyErrors::InvalidValues(format!(
+                "Adjustment {} is already {}",
+                self.adjustment_id, self.status
+            )));
+        }
+
+        Ok(())
+    }
+
+    fn ensure_second_operator(&self, operator_id: &str) -> Result<(), LoyaltyErrors> {
+        if operator_id.is_empty() || operator_id == self.requested_by {
+            return Err(LoyaltyErrors::InvalidValues(
+                "Adjustments must be reviewed by a different operator".to_string(),
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg_attr(any(test, feature = "mocks"), automock)]
+#[async_trait]
+pub trait PointsAdjustments {
+    async fn save_adjustment(&self, adjustment: &PointsAdjustment) -> Result<(), LoyaltyErrors>;
+    async fn retrieve_adjustment(
+        &self,
+        programme_id: &str,
+        adjustment_id: &str,
+    ) -> Result<PointsAdjustment, LoyaltyErrors>;
+    async fn list_pending_adjustments(
+        &self,
+        programme_id: &str,
+    ) -> Result<Vec<PointsAdjustment>, LoyaltyErrors>;
+    /// Records the adjustment, its transaction and the new balance in a single atomic write.
+    async fn apply_adjustment(
+        &self,
+        account: &LoyaltyAccount,
+        transaction: LoyaltyAccountTransaction,
+        adjustment: &PointsAdjustment,
+    ) -> Result<(), LoyaltyErrors>;
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::programmes::DEFAULT_PROGRAMME_ID;
+
+    use super::*;
+
+    fn adjustment(points: f32) -> PointsAdjustment {
+        PointsAdjustment::new(
+            DEFAULT_PROGRAMME_ID.to_string(),
+            "james".to_string(),
+            points,
+            ReasonCode::Goodwill,
+            "Late delivery".to_string(),
+            "agent-1".to_string(),
+            Utc::now(),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn adjustment_requires_note() {
+        let result = PointsAdjustment::new(
+            DEFAULT_PROGRAMME_ID.to_string(),
+            "james".to_string(),
+            10.0,
+            ReasonCode::Goodwill,
+            " ".to_string(),
+            "agent-1".to_string(),
+            Utc::now(),
+        );
+
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn adjustment_cannot_be_approved_by_requesting_operator() {
+        let mut adjustment = adjustment(1000.0);
+        let mut account =
+            LoyaltyAccount::new(DEFAULT_PROGRAMME_ID.to_string(), "james".to_string()).unwrap();
+
+        let result = adjustment.approve(&mut account, "agent-1".to_string(), Utc::now());
+
+        assert!(result.is_err());
+        assert_eq!(adjustment.status, AdjustmentStatus::Pending);
+        assert_eq!(*account.current_points(), 0.0);
+    }
+
+    #[test]
+    fn approved_adjustment_applies_points() {
+        let mut adjustment = adjustment(1000.0);
+        let mut account =
+            LoyaltyAccount::new(DEFAULT_PROGRAMME_ID.to_string(), "james".to_string()).unwrap();
+
+        adjustment
+            .approve(&mut account, "agent-2".to_string(), Utc::now())
+            .unwrap();
+
+        assert_eq!(adjustment.status, AdjustmentStatus::Applied);
+        assert_eq!(adjustment.reviewed_by(), Some("agent-2"));
+        assert_eq!(*account.current_points(), 1000.0);
+    }
+
+    #[test]
+    fn rejected_adjustment_cannot_be_approved() {
+        let mut adjustment = adjustment(1000.0);
+        let mut account =
+            LoyaltyAccount::new(DEFAULT_PROGRAMME_ID.to_string(), "james".to_string()).unwrap();
+
+        adjustment
+            .reject("agent-2".to_string(), Utc::now())
+            .unwrap();
+        let result = adjustment.approve(&mut account, "agent-3".to_string(), Utc::now());
+
+        assert!(result.is_err());
+        assert_eq!(adjustment.status, AdjustmentStatus::Rejected);
+    }
+}
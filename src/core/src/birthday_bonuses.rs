@@ -0,0 +1,154 @@
+use chrono::NaiveDate;
+use tracing::info;
+
+use crate::{
+    clock::Clock,
+    loyalty::{retrieve_active_account, LoyaltyErrors, LoyaltyPoints},
+    profiles::{birthday_dates, BonusPolicy, CustomerProfiles},
+};
+
+pub struct GrantBirthdayBonusesCommandHandler;
+
+impl GrantBirthdayBonusesCommandHandler {
+    /// Grants the birthday bonus to every customer celebrating on `today`, returning how many
+    /// bonuses were granted. Bonuses are keyed by year so the job is safe to run more than once a
+    /// day, and a failure for one customer does not stop the others.
+    #[tracing::instrument(
+        name = "handle_grant_birthday_bonuses",
+        skip(loyalty_points, profiles, policy, clock)
+    )]
+    pub async fn handle<T: LoyaltyPoints, P: CustomerProfiles, K: Clock>(
+        loyalty_points: &T,
+        profiles: &P,
+        policy: &BonusPolicy,
+        clock: &K,
+        today: NaiveDate,
+    ) -> anyhow::Result<usize, LoyaltyErrors> {
+        let mut granted = 0;
+
+        for (month, day) in birthday_dates(today) {
+            for profile in profiles.profiles_with_birthday(month, day).await? {
+                if !profile.celebrates_birthday_on(today) {
+                    continue;
+                }
+
+                let mut account = match retrieve_active_account(
+                    loyalty_points,
+                    profile.programme_id(),
+                    profile.customer_id(),
+                )
+                .await
+                {
+                    Ok(account) => account,
+                    Err(LoyaltyErrors::AccountNotFound()) => continue,
+                    Err(e) => {
+                        tracing::error!(
+                            "Failure retrieving account {}: {:?}",
+                            profile.customer_id(),
+                            e
+                        );
+                        continue;
+                    }
+                };
+
+                let transaction =
+                    match policy.apply_birthday_bonus(&mut account, today, clock.now()) {
+                        Ok(Some(transaction)) => transaction,
+                        Ok(None) => return Ok(granted),
+                        Err(e) => {
+                            info!(
+                                "Skipping birthday bonus for {}: {:?}",
+                                profile.customer_id(),
+                                e
+                            );
+                            continue;
+                        }
+                    };
+
+                match loyalty_points.add_transaction(&account, transaction).await {
+                    Ok(_) => granted += 1,
+                    Err(e) => tracing::error!(
+                        "Failure granting birthday bonus to {}: {:?}",
+                        profile.customer_id(),
+                        e
+                    ),
+                }
+            }
+        }
+
+        Ok(granted)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Utc;
+
+    use crate::{
+        clock::SystemClock,
+        loyalty::{LoyaltyAccount, LoyaltyAccountTransaction, MockLoyaltyPoints},
+        profiles::{CustomerProfile, MockCustomerProfiles},
+        programmes::DEFAULT_PROGRAMME_ID,
+    };
+
+    use super::*;
+
+    fn profile(customer_id: &str) -> CustomerProfile {
+        CustomerProfile::from(
+            DEFAULT_PROGRAMME_ID.to_string(),
+            customer_id.to_string(),
+            customer_id.to_string(),
+            "".to_string(),
+            NaiveDate::from_ymd_opt(1990, 10, 18),
+            false,
+            Utc::now(),
+        )
+    }
+
+    #[tokio::test]
+    async fn birthday_bonus_should_be_granted_once_per_year() {
+        let mut profiles = MockCustomerProfiles::new();
+        profiles
+            .expect_profiles_with_birthday()
+            .times(1)
+            .returning(|_, _| Ok(vec![profile("james"), profile("jane")]));
+
+        let mut loyalty_points = MockLoyaltyPoints::new();
+        loyalty_points
+            .expect_retrieve()
+            .returning(|programme_id, customer_id| match customer_id {
+                "jane" => LoyaltyAccount::from(
+                    programme_id.to_string(),
+                    customer_id.to_string(),
+                    50.0,
+                    vec![LoyaltyAccountTransaction::new(
+                        Utc::now(),
+                        "BIRTHDAY-2026".to_string(),
+                        50.0,
+                    )],
+                ),
+                _ => LoyaltyAccount::from(
+                    programme_id.to_string(),
+                    customer_id.to_string(),
+                    0.0,
+                    vec![],
+                ),
+            });
+        loyalty_points
+            .expect_add_transaction()
+            .withf(|account, _| account.customer_id() == "james")
+            .times(1)
+            .returning(|_, _| Ok(()));
+
+        let result = GrantBirthdayBonusesCommandHandler::handle(
+            &loyalty_points,
+            &profiles,
+            &BonusPolicy::default(),
+            &SystemClock,
+            NaiveDate::from_ymd_opt(2026, 10, 18).unwrap(),
+        )
+        .await;
+
+        assert_eq!(result.unwrap(), 1);
+    }
+}
@@ -0,0 +1,402 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use tracing::info;
+use uuid::Uuid;
+
+use crate::loyalty::LoyaltyErrors;
+
+#[cfg(any(test, feature = "mocks"))]
+use mockall::automock;
+
+#[derive(Deserialize, Serialize, Clone, Copy, Debug, PartialEq)]
+#[serde(tag = "type", content = "value")]
+pub enum CampaignReward {
+    /// Multiplies the base points earned for an order, e.g. 2.0 for double points.
+    Multiplier(f32),
+    /// Adds a fixed number of points to every eligible order.
+    FlatBonus(f32),
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug, Default, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct CampaignEligibility {
+    #[serde(default)]
+    pub min_order_value: Option<f32>,
+    /// Customers the campaign is limited to, an empty list means every customer is eligible.
+    #[serde(default)]
+    pub customer_ids: Vec<String>,
+}
+
+/// The bonus points a single campaign contributed to an order.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CampaignBonus {
+    pub campaign_id: String,
+    pub points: f32,
+}
+
+#[derive(Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CampaignDto {
+    pub programme_id: String,
+    pub campaign_id: String,
+    pub name: String,
+    pub starts_at: DateTime<Utc>,
+    pub ends_at: DateTime<Utc>,
+    pub eligibility: CampaignEligibility,
+    pub reward: CampaignReward,
+    pub budget_cap: Option<f32>,
+    pub points_awarded: f32,
+}
+
+impl From<Campaign> for CampaignDto {
+    fn from(value: Campaign) -> Self {
+        CampaignDto {
+            programme_id: value.programme_id,
+            campaign_id: value.campaign_id,
+            name: value.name,
+            starts_at: value.starts_at,
+            ends_at: value.ends_at,
+            eligibility: value.eligibility,
+            reward: value.reward,
+            budget_cap: value.budget_cap,
+            points_awarded: value.points_awarded,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Campaign {
+    programme_id: String,
+    campaign_id: String,
+    name: String,
+    starts_at: DateTime<Utc>,
+    ends_at: DateTime<Utc>,
+    eligibility: CampaignEligibility,
+    reward: CampaignReward,
+    budget_cap: Option<f32>,
+    points_awarded: f32,
+}
+
+impl Campaign {
+    pub(crate) fn new(
+        programme_id: String,
+        name: String,
+        starts_at: DateTime<Utc>,
+        ends_at: DateTime<Utc>,
+        eligibility: CampaignEligibility,
+        reward: CampaignReward,
+        budget_cap: Option<f32>,
+    ) -> Result<Self, LoyaltyErrors> {
+        let campaign = Self {
+            programme_id,
+            campaign_id: format!("CMP-{}", Uuid::new_v4()),
+            name,
+            starts_at,
+            ends_at,
+            eligibility,
+            reward,
+            budget_cap,
+            points_awarded: 0.0,
+        };
+
+        campaign.validate()?;
+
+        Ok(campaign)
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub fn from(
+        programme_id: String,
+        campaign_id: String,
+        name: String,
+        starts_at: DateTime<Utc>,
+        ends_at: DateTime<Utc>,
+        eligibility: CampaignEligibility,
+        reward: CampaignReward,
+        budget_cap: Option<f32>,
+        points_awarded: f32,
+    ) -> Self {
+        Self {
+            programme_id,
+            campaign_id,
+            name,
+            starts_at,
+            ends_at,
+            eligibility,
+            reward,
+            budget_cap,
+            points_awarded,
+        }
+    }
+
+    pub fn programme_id(&self) -> &str {
+        &self.programme_id
+    }
+    pub fn campaign_id(&self) -> &str {
+        &self.campaign_id
+    }
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+    pub fn starts_at(&self) -> DateTime<Utc> {
+        self.starts_at
+    }
+    pub fn ends_at(&self) -> DateTime<Utc> {
+        self.ends_at
+    }
+    pub fn eligibility(&self) -> &CampaignEligibility {
+        &self.eligibility
+    }
+    pub fn reward(&self) -> CampaignReward {
+        self.reward
+    }
+    pub fn budget_cap(&self) -> Option<f32> {
+        self.budget_cap
+    }
+    pub fn points_awarded(&self) -> f32 {
+        self.points_awarded
+    }
+
+    pub(crate) fn update(
+        &mut self,
+        name: String,
+        starts_at: DateTime<Utc>,
+        ends_at: DateTime<Utc>,
+        eligibility: CampaignEligibility,
+        reward: CampaignReward,
+        budget_cap: Option<f32>,
+    ) -> Result<(), LoyaltyErrors> {
+        let mut updated = self.clone();
+        updated.name = name;
+        updated.starts_at = starts_at;
+        updated.ends_at = ends_at;
+        updated.eligibility = eligibility;
+        updated.reward = reward;
+        updated.budget_cap = budget_cap;
+
+        updated.validate()?;
+
+        *self = updated;
+
+        Ok(())
+    }
+
+    fn validate(&self) -> Result<(), LoyaltyErrors> {
+        if self.name.trim().is_empty() {
+            return Err(LoyaltyErrors::InvalidValues(
+                "Campaign name cannot be empty".to_string(),
+            ));
+        }
+
+        if self.ends_at <= self.starts_at {
+            return Err(LoyaltyErrors::InvalidValues(
+                "Campaign must end after it starts".to_string(),
+            ));
+        }
+
+        let valid_reward = match self.reward {
+            CampaignReward::Multiplier(multiplier) => multiplier.is_finite() && multiplier > 1.0,
+            CampaignReward::FlatBonus(bonus) => bonus.is_finite() && bonus > 0.0,
+        };
+
+        if !valid_reward {
+            return Err(LoyaltyErrors::InvalidValues(
+                "Campaign multiplier must be above 1 and flat bonuses above 0".to_string(),
+            ));
+        }
+
+        if self.budget_cap.is_some_and(|cap| !cap.is_finite() || cap <= 0.0) {
+            return Err(LoyaltyErrors::InvalidValues(
+                "Campaign budget cap must be greater than 0".to_string(),
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// The bonus points this campaign adds to an order, if the order is eligible.
+    pub(crate) fn bonus_for(
+        &self,
+        customer_id: &str,
+        order_value: f32,
+        base_points: f32,
+        at: DateTime<Utc>,
+    ) -> Option<f32> {
+        if at < self.starts_at || at >= self.ends_at {
+            return None;
+        }
+
+        if self
+            .eligibility
+            .min_order_value
+            .is_some_and(|min_order_value| order_value < min_order_value)
+        {
+            return None;
+        }
+
+        if !self.eligibility.customer_ids.is_empty()
+            && !self.eligibility.customer_ids.iter().any(|id| id == customer_id)
+        {
+            return None;
+        }
+
+        let bonus = match self.reward {
+            CampaignReward::Multiplier(multiplier) => base_points * (multiplier - 1.0),
+            CampaignReward::FlatBonus(bonus) => bonus,
+        };
+
+        if bonus <= 0.0 {
+            return None;
+        }
+
+        Some(bonus)
+    }
+}
+
+#[cfg_attr(any(test, feature = "mocks"), automock)]
+#[async_trait]
+pub trait Campaigns {
+    async fn save_campaign(&self, campaign: &Campaign) -> Result<(), LoyaltyErrors>;
+    async fn retrieve_campaign(
+        &self,
+        programme_id: &str,
+        campaign_id: &str,
+    ) -> Result<Campaign, LoyaltyErrors>;
+    async fn list_campaigns(&self, programme_id: &str) -> Result<Vec<Campaign>, LoyaltyErrors>;
+    async fn delete_campaign(
+        &self,
+        programme_id: &str,
+        campaign_id: &str,
+    ) -> Result<(), LoyaltyErrors>;
+    async fn active_campaigns(
+        &self,
+        programme_id: &str,
+        at: DateTime<Utc>,
+    ) -> Result<Vec<Campaign>, LoyaltyErrors>;
+    /// Atomically adds `points` to the campaign's awarded total, returning false if doing so
+    /// would exceed the budget cap.
+    async fn reserve_budget(&self, campaign_id: &str, points: f32) -> Result<bool, LoyaltyErrors>;
+}
+
+/// Evaluates every active campaign for an order, reserving budget for each bonus awarded.
+pub(crate) async fn campaign_bonuses<C: Campaigns>(
+    campaigns: &C,
+    programme_id: &str,
+    customer_id: &str,
+    order_value: f32,
+    base_points: f32,
+    at: DateTime<Utc>,
+) -> Result<Vec<CampaignBonus>, LoyaltyErrors> {
+    let mut bonuses = vec![];
+
+    for campaign in campaigns.active_campaigns(programme_id, at).await? {
+        let Some(points) = campaign.bonus_for(customer_id, order_value, base_points, at) else {
+            continue;
+        };
+
+        if campaigns.reserve_budget(&campaign.campaign_id, points).await? {
+            bonuses.push(CampaignBonus {
+                campaign_id: campaign.campaign_id,
+                points,
+            });
+        } else {
+            info!("Campaign {} budget exhausted", campaign.campaign_id);
+        }
+    }
+
+    Ok(bonuses)
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Duration;
+
+    use crate::programmes::DEFAULT_PROGRAMME_ID;
+
+    use super::*;
+
+    fn campaign(reward: CampaignReward, min_order_value: Option<f32>) -> Campaign {
+        Campaign::new(
+            DEFAULT_PROGRAMME_ID.to_string(),
+            "Double points weekend".to_string(),
+            Utc::now() - Duration::days(1),
+            Utc::now() + Duration::days(1),
+            CampaignEligibility {
+                min_order_value,
+                customer_ids: vec![],
+            },
+            reward,
+            None,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn multiplier_campaign_adds_bonus_on_base_points() {
+        let campaign = campaign(CampaignReward::Multiplier(3.0), None);
+
+        let bonus = campaign.bonus_for("james", 100.0, 50.0, Utc::now());
+
+        assert_eq!(bonus, Some(100.0));
+    }
+
+    #[test]
+    fn campaign_does_not_apply_below_min_order_value() {
+        let campaign = campaign(CampaignReward::FlatBonus(10.0), Some(100.0));
+
+        let bonus = campaign.bonus_for("james", 99.0, 49.5, Utc::now());
+
+        assert_eq!(bonus, None);
+    }
+
+    #[test]
+    fn campaign_does_not_apply_outside_of_window() {
+        let campaign = campaign(CampaignReward::FlatBonus(10.0), None);
+
+        let bonus = campaign.bonus_for("james", 100.0, 50.0, Utc::now() + Duration::days(2));
+
+        assert_eq!(bonus, None);
+    }
+
+    #[test]
+    fn campaign_must_end_after_it_starts() {
+        let result = Campaign::new(
+            DEFAULT_PROGRAMME_ID.to_string(),
+            "Backwards".to_string(),
+            Utc::now(),
+            Utc::now() - Duration::days(1),
+            CampaignEligibility::default(),
+            CampaignReward::FlatBonus(10.0),
+            None,
+        );
+
+        assert!(result.is_err());
+    }
+
+    #[tokio::test]
+    async fn campaign_with_exhausted_budget_is_skipped() {
+        let mut campaigns = MockCampaigns::new();
+        campaigns
+            .expect_active_campaigns()
+            .times(1)
+            .returning(|_, _| Ok(vec![campaign(CampaignReward::FlatBonus(10.0), None)]));
+        campaigns
+            .expect_reserve_budget()
+            .times(1)
+            .returning(|_, _| Ok(false));
+
+        let bonuses = campaign_bonuses(
+            &campaigns,
+            DEFAULT_PROGRAMME_ID,
+            "james",
+            100.0,
+            50.0,
+            Utc::now(),
+        )
+        .await
+        .unwrap();
+
+        assert!(bonuses.is_empty());
+    }
+}
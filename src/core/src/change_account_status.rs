@@ -0,0 +1,130 @@
+use serde::Deserialize;
+
+use crate::{
+    loyalty::{AccountStatus, LoyaltyErrors, LoyaltyPoints},
+    validation::bind_path_id,
+    LoyaltyDto,
+};
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChangeAccountStatusCommand {
+    #[serde(default)]
+    programme_id: String,
+    #[serde(default)]
+    customer_id: String,
+    status: AccountStatus,
+}
+
+impl ChangeAccountStatusCommand {
+    pub fn bind_path(
+        &mut self,
+        programme_id: String,
+        customer_id: String,
+    ) -> Result<(), LoyaltyErrors> {
+        bind_path_id("programmeId", &mut self.programme_id, programme_id)?;
+        bind_path_id("customerId", &mut self.customer_id, customer_id)?;
+
+        Ok(())
+    }
+}
+
+pub struct ChangeAccountStatusCommandHandler;
+
+impl ChangeAccountStatusCommandHandler {
+    #[tracing::instrument(name = "handle_change_account_status", skip(loyalty_points, command), fields(programme_id=command.programme_id, customer_id=command.customer_id, status=command.status.to_string()))]
+    pub async fn handle<T: LoyaltyPoints>(
+        loyalty_points: &T,
+        command: ChangeAccountStatusCommand,
+    ) -> anyhow::Result<LoyaltyDto, LoyaltyErrors> {
+        let mut account = loyalty_points
+            .retrieve(&command.programme_id, &command.customer_id)
+            .await?;
+
+        account.change_status(command.status)?;
+
+        loyalty_points.update_status(&account).await?;
+
+        Ok(account.into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        loyalty::{LoyaltyAccount, MockLoyaltyPoints},
+        programmes::DEFAULT_PROGRAMME_ID,
+    };
+
+    use super::*;
+    use mockall::predicate;
+
+    #[tokio::test]
+    async fn on_valid_command_account_should_be_frozen() {
+        let test_customer_id = "james";
+
+        let mut loyalty_points = MockLoyaltyPoints::new();
+        loyalty_points
+            .expect_retrieve()
+            .with(
+                predicate::eq(DEFAULT_PROGRAMME_ID),
+                predicate::eq(test_customer_id),
+            )
+            .times(1)
+            .returning(|programme_id, customer_id| {
+                LoyaltyAccount::from(
+                    programme_id.to_string(),
+                    customer_id.to_string(),
+                    10.0,
+                    vec![],
+                )
+            });
+        loyalty_points
+            .expect_update_status()
+            .times(1)
+            .returning(|_| Ok(()));
+
+        let command = ChangeAccountStatusCommand {
+            programme_id: DEFAULT_PROGRAMME_ID.to_string(),
+            customer_id: test_customer_id.to_string(),
+            status: AccountStatus::Frozen,
+        };
+
+        let result = ChangeAccountStatusCommandHandler::handle(&loyalty_points, command).await;
+
+        assert_eq!(result.unwrap().status, AccountStatus::Frozen);
+    }
+
+    #[tokio::test]
+    async fn on_command_to_mark_account_merged_should_error() {
+        let test_customer_id = "james";
+
+        let mut loyalty_points = MockLoyaltyPoints::new();
+        loyalty_points
+            .expect_retrieve()
+            .with(
+                predicate::eq(DEFAULT_PROGRAMME_ID),
+                predicate::eq(test_customer_id),
+            )
+            .times(1)
+            .returning(|programme_id, customer_id| {
+                LoyaltyAccount::from(
+                    programme_id.to_string(),
+                    customer_id.to_string(),
+                    10.0,
+                    vec![],
+                )
+            });
+        loyalty_points.expect_update_status().times(0);
+
+        let command = ChangeAccountStatusCommand {
+            programme_id: DEFAULT_PROGRAMME_ID.to_string(),
+            customer_id: test_customer_id.to_string(),
+            status: AccountStatus::Merged,
+        };
+
+        let result = ChangeAccountStatusCommandHandler::handle(&loyalty_points, command).await;
+
+        assert!(matches!(result, Err(LoyaltyErrors::AccountNotActive(_))));
+    }
+}
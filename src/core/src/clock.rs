@@ -0,0 +1,20 @@
+use chrono::{DateTime, Utc};
+
+#[cfg(any(test, feature = "mocks"))]
+use mockall::automock;
+
+/// Where handlers get the current time from, so that tests and replays can control it.
+#[cfg_attr(any(test, feature = "mocks"), automock)]
+pub trait Clock {
+    fn now(&self) -> DateTime<Utc>;
+}
+
+/// Reads the time from the system clock.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> DateTime<Utc> {
+        Utc::now()
+    }
+}
@@ -0,0 +1,217 @@
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::Deserialize;
+use tracing::info;
+
+use crate::{
+    clock::Clock,
+    loyalty::LoyaltyErrors,
+    profiles::{CustomerProfile, CustomerProfiles},
+    programmes::DEFAULT_PROGRAMME_ID,
+    referrals::{register_referral, Referrals},
+};
+
+fn default_programme_id() -> String {
+    DEFAULT_PROGRAMME_ID.to_string()
+}
+
+#[derive(Deserialize)]
+pub struct CustomerRegistered {
+    #[serde(default = "default_programme_id")]
+    programme_id: String,
+    customer_id: String,
+    #[serde(default)]
+    display_name: String,
+    #[serde(default)]
+    email: String,
+    #[serde(default)]
+    birthday: Option<NaiveDate>,
+    #[serde(default)]
+    marketing_opt_in: bool,
+    #[serde(default)]
+    registered_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    referral_code: Option<String>,
+}
+
+impl CustomerRegistered {
+    pub fn programme_id(&self) -> &str {
+        &self.programme_id
+    }
+}
+
+pub struct CustomerRegisteredEventHandler {}
+
+impl CustomerRegisteredEventHandler {
+    #[tracing::instrument(name = "handle_customer_registered", skip(profiles, referrals, clock, evt), fields(programme_id=evt.programme_id, customer_id=evt.customer_id))]
+    pub async fn handle<P: CustomerProfiles, R: Referrals, K: Clock>(
+        profiles: &P,
+        referrals: &R,
+        clock: &K,
+        evt: &CustomerRegistered,
+    ) -> Result<(), LoyaltyErrors> {
+        match profiles
+            .retrieve_profile(&evt.programme_id, &evt.customer_id)
+            .await
+        {
+            Ok(_) => {
+                info!("Profile already exists, skipping duplicate registration");
+
+                return Ok(());
+            }
+            Err(LoyaltyErrors::ProfileNotFound(_)) => {}
+            Err(e) => {
+                tracing::error!("Failure retrieving profile from database: {:?}", e);
+
+                return Err(e);
+            }
+        }
+
+        let registered_at = evt.registered_at.unwrap_or_else(|| clock.now());
+
+        // The referral is recorded before the profile so a retried event still picks it up. An
+        // invalid referral is dropped without blocking the registration itself.
+        if let Some(referral_code) = &evt.referral_code {
+            match register_referral(
+                referrals,
+                &evt.programme_id,
+                &evt.customer_id,
+                referral_code,
+                registered_at,
+            )
+            .await
+            {
+                Ok(referral) => info!("Referred by {}", referral.referrer_id()),
+                Err(e @ LoyaltyErrors::DatabaseError(_)) => {
+                    tracing::error!("Failure recording referral: {:?}", e);
+
+                    return Err(e);
+                }
+                Err(e) => tracing::warn!("Referral rejected: {:?}", e),
+            }
+        }
+
+        let profile = CustomerProfile::new(
+            evt.programme_id.clone(),
+            evt.customer_id.clone(),
+            evt.display_name.clone(),
+            evt.email.clone(),
+            evt.birthday,
+            evt.marketing_opt_in,
+            registered_at,
+        )
+        .map_err(|e| {
+            tracing::error!("Invalid 'CustomerRegistered' event: {:?}", e);
+            e
+        })?;
+
+        profiles.save_profile(&profile).await.map_err(|e| {
+            tracing::error!("Failure saving profile: {:?}", e);
+            e
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{clock::SystemClock, profiles::MockCustomerProfiles, referrals::MockReferrals};
+
+    use super::*;
+
+    fn evt() -> CustomerRegistered {
+        CustomerRegistered {
+            programme_id: DEFAULT_PROGRAMME_ID.to_string(),
+            customer_id: "james".to_string(),
+            display_name: "James".to_string(),
+            email: "james@example.com".to_string(),
+            birthday: NaiveDate::from_ymd_opt(1990, 5, 1),
+            marketing_opt_in: true,
+            registered_at: None,
+            referral_code: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn on_new_customer_profile_should_be_saved() {
+        let mut profiles = MockCustomerProfiles::new();
+        profiles
+            .expect_retrieve_profile()
+            .times(1)
+            .returning(|_, id| Err(LoyaltyErrors::ProfileNotFound(id.to_string())));
+        profiles
+            .expect_save_profile()
+            .times(1)
+            .returning(|_| Ok(()));
+
+        let result = CustomerRegisteredEventHandler::handle(
+            &profiles,
+            &MockReferrals::new(),
+            &SystemClock,
+            &evt(),
+        )
+        .await;
+
+        assert!(result.is_ok());
+    }
+
+    #[tokio::test]
+    async fn on_duplicate_registration_profile_should_not_be_overwritten() {
+        let mut profiles = MockCustomerProfiles::new();
+        profiles
+            .expect_retrieve_profile()
+            .times(1)
+            .returning(|programme_id, id| {
+                Ok(CustomerProfile::from(
+                    programme_id.to_string(),
+                    id.to_string(),
+                    "James".to_string(),
+                    "".to_string(),
+                    None,
+                    false,
+                    Utc::now(),
+                ))
+            });
+        profiles.expect_save_profile().times(0);
+
+        let result = CustomerRegisteredEventHandler::handle(
+            &profiles,
+            &MockReferrals::new(),
+            &SystemClock,
+            &evt(),
+        )
+        .await;
+
+        assert!(result.is_ok());
+    }
+
+    #[tokio::test]
+    async fn on_self_referral_profile_should_still_be_saved() {
+        let mut profiles = MockCustomerProfiles::new();
+        profiles
+            .expect_retrieve_profile()
+            .times(1)
+            .returning(|_, id| Err(LoyaltyErrors::ProfileNotFound(id.to_string())));
+        profiles
+            .expect_save_profile()
+            .times(1)
+            .returning(|_| Ok(()));
+
+        let mut referrals = MockReferrals::new();
+        referrals
+            .expect_customer_for_referral_code()
+            .returning(|_, _| Ok("james".to_string()));
+        referrals
+            .expect_retrieve_referral()
+            .returning(|_, id| Err(LoyaltyErrors::ReferralNotFound(id.to_string())));
+        referrals.expect_save_referral().times(0);
+
+        let evt = CustomerRegistered {
+            referral_code: Some("REF-JAMES".to_string()),
+            ..evt()
+        };
+
+        let result =
+            CustomerRegisteredEventHandler::handle(&profiles, &referrals, &SystemClock, &evt).await;
+
+        assert!(result.is_ok());
+    }
+}
@@ -0,0 +1,101 @@
+use serde::Deserialize;
+use uuid::Uuid;
+
+use crate::{
+    loyalty::{LoyaltyErrors, LoyaltyPoints},
+    validation::bind_path_id,
+    LoyaltyDto,
+};
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EraseCustomerDataCommand {
+    #[serde(default)]
+    programme_id: String,
+    #[serde(default)]
+    customer_id: String,
+}
+
+impl EraseCustomerDataCommand {
+    pub fn bind_path(
+        &mut self,
+        programme_id: String,
+        customer_id: String,
+    ) -> Result<(), LoyaltyErrors> {
+        bind_path_id("programmeId", &mut self.programme_id, programme_id)?;
+        bind_path_id("customerId", &mut self.customer_id, customer_id)?;
+
+        Ok(())
+    }
+}
+
+pub struct EraseCustomerDataCommandHandler;
+
+impl EraseCustomerDataCommandHandler {
+    #[tracing::instrument(name = "handle_erase_customer_data", skip(loyalty_points, command))]
+    pub async fn handle<T: LoyaltyPoints>(
+        loyalty_points: &T,
+        command: EraseCustomerDataCommand,
+    ) -> anyhow::Result<LoyaltyDto, LoyaltyErrors> {
+        let mut account = loyalty_points
+            .retrieve(&command.programme_id, &command.customer_id)
+            .await?;
+
+        account.erase(format!("erased-{}", Uuid::new_v4()));
+
+        loyalty_points.erase(&command.customer_id, &account).await?;
+
+        Ok(account.into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        loyalty::{AccountStatus, LoyaltyAccount, MockLoyaltyPoints},
+        programmes::DEFAULT_PROGRAMME_ID,
+    };
+
+    use super::*;
+    use mockall::predicate;
+
+    #[tokio::test]
+    async fn on_valid_command_customer_id_should_be_pseudonymised() {
+        let test_customer_id = "james";
+
+        let mut loyalty_points = MockLoyaltyPoints::new();
+        loyalty_points
+            .expect_retrieve()
+            .with(
+                predicate::eq(DEFAULT_PROGRAMME_ID),
+                predicate::eq(test_customer_id),
+            )
+            .times(1)
+            .returning(|programme_id, customer_id| {
+                LoyaltyAccount::from(
+                    programme_id.to_string(),
+                    customer_id.to_string(),
+                    10.0,
+                    vec![],
+                )
+            });
+        loyalty_points
+            .expect_erase()
+            .with(predicate::eq(test_customer_id), predicate::always())
+            .times(1)
+            .returning(|_, _| Ok(()));
+
+        let command = EraseCustomerDataCommand {
+            programme_id: DEFAULT_PROGRAMME_ID.to_string(),
+            customer_id: test_customer_id.to_string(),
+        };
+
+        let result = EraseCustomerDataCommandHandler::handle(&loyalty_points, command).await;
+
+        let account = result.unwrap();
+
+        assert_ne!(account.customer_id, test_customer_id);
+        assert!(account.customer_id.starts_with("erased-"));
+        assert_eq!(account.status, AccountStatus::Closed);
+    }
+}
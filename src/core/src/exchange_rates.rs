@@ -0,0 +1,116 @@
+use std::collections::HashMap;
+
+use crate::loyalty::LoyaltyErrors;
+
+#[cfg(any(test, feature = "mocks"))]
+use mockall::automock;
+
+/// Provides the rates used to normalise order values into a programme's base currency.
+#[cfg_attr(any(test, feature = "mocks"), automock)]
+pub trait ExchangeRates {
+    /// The number of units of `to` that one unit of `from` buys.
+    fn rate(&self, from: &str, to: &str) -> Result<f32, LoyaltyErrors>;
+}
+
+/// A fixed table of rates, each quoted as the value of one unit of the currency in a common
+/// reference currency. A currency always converts to itself, even if it is missing from the table.
+#[derive(Clone, Debug, Default)]
+pub struct StaticExchangeRates {
+    rates: HashMap<String, f32>,
+}
+
+impl StaticExchangeRates {
+    pub fn new(rates: HashMap<String, f32>) -> Result<Self, LoyaltyErrors> {
+        if let Some((currency, _)) = rates
+            .iter()
+            .find(|(_, rate)| !rate.is_finite() || **rate <= 0.0)
+        {
+            return Err(LoyaltyErrors::InvalidValues(format!(
+                "Exchange rate for {} must be positive",
+                currency
+            )));
+        }
+
+        Ok(Self { rates })
+    }
+
+    fn reference_rate(&self, currency: &str) -> Result<f32, LoyaltyErrors> {
+        self.rates.get(currency).copied().ok_or_else(|| {
+            LoyaltyErrors::InvalidValues(format!("No exchange rate for {}", currency))
+        })
+    }
+}
+
+impl ExchangeRates for StaticExchangeRates {
+    fn rate(&self, from: &str, to: &str) -> Result<f32, LoyaltyErrors> {
+        if from == to {
+            return Ok(1.0);
+        }
+
+        Ok(self.reference_rate(from)? / self.reference_rate(to)?)
+    }
+}
+
+/// An order value as it was charged to the customer.
+#[derive(Clone, Debug)]
+pub(crate) struct OrderValue {
+    pub(crate) amount: f32,
+    pub(crate) currency: String,
+}
+
+impl OrderValue {
+    pub(crate) fn normalise<X: ExchangeRates + ?Sized>(
+        &self,
+        exchange_rates: &X,
+        base_currency: &str,
+    ) -> Result<f32, LoyaltyErrors> {
+        Ok(self.amount * exchange_rates.rate(&self.currency, base_currency)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rates() -> StaticExchangeRates {
+        StaticExchangeRates::new(HashMap::from([
+            ("USD".to_string(), 1.0),
+            ("EUR".to_string(), 1.1),
+            ("GBP".to_string(), 1.25),
+        ]))
+        .unwrap()
+    }
+
+    #[test]
+    fn order_value_is_normalised_through_the_reference_currency() {
+        let order_value = OrderValue {
+            amount: 100.0,
+            currency: "GBP".to_string(),
+        };
+
+        let normalised = order_value.normalise(&rates(), "EUR").unwrap();
+
+        assert!((normalised - 113.636_36).abs() < 0.001);
+    }
+
+    #[test]
+    fn currency_converts_to_itself_without_a_rate() {
+        let rate = StaticExchangeRates::default().rate("JPY", "JPY").unwrap();
+
+        assert_eq!(rate, 1.0);
+    }
+
+    #[test]
+    fn unknown_currency_should_fail() {
+        let result = rates().rate("JPY", "USD");
+
+        assert!(matches!(result, Err(LoyaltyErrors::InvalidValues(_))));
+    }
+
+    #[test]
+    fn rates_must_be_positive() {
+        let result = StaticExchangeRates::new(HashMap::from([("EUR".to_string(), 0.0)]));
+
+        assert!(result.is_err());
+    }
+}
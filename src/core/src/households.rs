@@ -0,0 +1,445 @@
+use std::{fmt::Display, str::FromStr};
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::loyalty::{LoyaltyAccountTransaction, LoyaltyErrors};
+
+#[cfg(any(test, feature = "mocks"))]
+use mockall::automock;
+
+#[derive(Deserialize, Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum HouseholdRole {
+    /// Can spend any amount from the pool and approve spends for other members.
+    Owner,
+    /// Can spend up to their own limit, anything above it needs an owner's approval.
+    Member,
+}
+
+impl Display for HouseholdRole {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let role = match self {
+            HouseholdRole::Owner => "Owner",
+            HouseholdRole::Member => "Member",
+        };
+
+        write!(f, "{}", role)
+    }
+}
+
+impl FromStr for HouseholdRole {
+    type Err = LoyaltyErrors;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "Owner" => Ok(HouseholdRole::Owner),
+            "Member" => Ok(HouseholdRole::Member),
+            _ => Err(LoyaltyErrors::InvalidValues(format!(
+                "'{}' is not a valid household role",
+                s
+            ))),
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct HouseholdMemberDto {
+    pub customer_id: String,
+    pub role: HouseholdRole,
+    pub spend_limit: Option<f32>,
+    pub contributed_points: f32,
+}
+
+impl From<HouseholdMember> for HouseholdMemberDto {
+    fn from(value: HouseholdMember) -> Self {
+        HouseholdMemberDto {
+            customer_id: value.customer_id,
+            role: value.role,
+            spend_limit: value.spend_limit,
+            contributed_points: value.contributed_points,
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct HouseholdDto {
+    pub programme_id: String,
+    pub household_id: String,
+    pub name: String,
+    pub points: f32,
+    pub members: Vec<HouseholdMemberDto>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl From<Household> for HouseholdDto {
+    fn from(value: Household) -> Self {
+        HouseholdDto {
+            programme_id: value.programme_id,
+            household_id: value.household_id,
+            name: value.name,
+            points: value.points,
+            members: value.members.into_iter().map(|member| member.into()).collect(),
+            created_at: value.created_at,
+        }
+    }
+}
+
+/// A single member's view of their household, returned alongside their own account.
+#[derive(Deserialize, Serialize)]
+pub struct HouseholdMembershipDto {
+    pub household_id: String,
+    pub role: HouseholdRole,
+    pub contributed_points: f32,
+    pub pool_points: f32,
+}
+
+#[derive(Clone, Debug)]
+pub struct HouseholdMember {
+    customer_id: String,
+    role: HouseholdRole,
+    spend_limit: Option<f32>,
+    contributed_points: f32,
+}
+
+impl HouseholdMember {
+    pub fn from(
+        customer_id: String,
+        role: HouseholdRole,
+        spend_limit: Option<f32>,
+        contributed_points: f32,
+    ) -> Self {
+        Self {
+            customer_id,
+            role,
+            spend_limit,
+            contributed_points,
+        }
+    }
+
+    pub fn customer_id(&self) -> &str {
+        &self.customer_id
+    }
+    pub fn role(&self) -> HouseholdRole {
+        self.role
+    }
+    pub fn spend_limit(&self) -> Option<f32> {
+        self.spend_limit
+    }
+    pub fn contributed_points(&self) -> f32 {
+        self.contributed_points
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Household {
+    programme_id: String,
+    household_id: String,
+    name: String,
+    points: f32,
+    members: Vec<HouseholdMember>,
+    created_at: DateTime<Utc>,
+}
+
+impl Household {
+    pub(crate) fn new(
+        programme_id: String,
+        name: String,
+        owner_id: String,
+        created_at: DateTime<Utc>,
+    ) -> Result<Self, LoyaltyErrors> {
+        if name.trim().is_empty() {
+            return Err(LoyaltyErrors::InvalidValues(
+                "Household name cannot be empty".to_string(),
+            ));
+        }
+
+        Ok(Self {
+            programme_id,
+            household_id: format!("HH-{}", Uuid::new_v4()),
+            name,
+            points: 0.0,
+            members: vec![HouseholdMember::from(
+                owner_id,
+                HouseholdRole::Owner,
+                None,
+                0.0,
+            )],
+            created_at,
+        })
+    }
+
+    pub fn from(
+        programme_id: String,
+        household_id: String,
+        name: String,
+        points: f32,
+        members: Vec<HouseholdMember>,
+        created_at: DateTime<Utc>,
+    ) -> Self {
+        Self {
+            programme_id,
+            household_id,
+            name,
+            points,
+            members,
+            created_at,
+        }
+    }
+
+    pub fn programme_id(&self) -> &str {
+        &self.programme_id
+    }
+    pub fn household_id(&self) -> &str {
+        &self.household_id
+    }
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+    pub fn points(&self) -> f32 {
+        self.points
+    }
+    pub fn members(&self) -> &[HouseholdMember] {
+        &self.members
+    }
+    pub fn created_at(&self) -> DateTime<Utc> {
+        self.created_at
+    }
+
+    pub fn member(&self, customer_id: &str) -> Option<&HouseholdMember> {
+        self.members.iter().find(|m| m.customer_id == customer_id)
+    }
+
+    pub(crate) fn membership(&self, customer_id: &str) -> Option<HouseholdMembershipDto> {
+        self.member(customer_id)
+            .map(|member| HouseholdMembershipDto {
+                household_id: self.household_id.clone(),
+                role: member.role,
+                contributed_points: member.contributed_points,
+                pool_points: self.points,
+            })
+    }
+
+    pub(crate) fn add_member(
+        &mut self,
+        customer_id: String,
+        role: HouseholdRole,
+        spend_limit: Option<f32>,
+    ) -> Result<HouseholdMember, LoyaltyErrors> {
+        if self.member(&customer_id).is_some() {
+            return Err(LoyaltyErrors::InvalidValues(format!(
+                "{} is already a member of household {}",
+                customer_id, self.household_id
+            )));
+        }
+
+        if spend_limit.is_some_and(|limit| !limit.is_finite() || limit < 0.0) {
+            return Err(LoyaltyErrors::InvalidValues(
+                "Spend limit cannot be negative".to_string(),
+            ));
+        }
+
+        let member = HouseholdMember::from(customer_id, role, spend_limit, 0.0);
+
+        self.members.push(member.clone());
+
+        Ok(member)
+    }
+
+    /// Contributions stay in the pool when a member leaves.
+    pub(crate) fn remove_member(&mut self, customer_id: &str) -> Result<(), LoyaltyErrors> {
+        let member = self.member(customer_id).ok_or_else(|| {
+            LoyaltyErrors::HouseholdNotFound(format!(
+                "{} is not a member of household {}",
+                customer_id, self.household_id
+            ))
+        })?;
+
+        let owners = self
+            .members
+            .iter()
+            .filter(|m| m.role == HouseholdRole::Owner)
+            .count();
+
+        if member.role == HouseholdRole::Owner && owners == 1 {
+            return Err(LoyaltyErrors::InvalidValues(
+                "The last owner cannot leave the household".to_string(),
+            ));
+        }
+
+        self.members.retain(|m| m.customer_id != customer_id);
+
+        Ok(())
+    }
+
+    /// Owners can spend freely. Members can spend up to their own limit, or any amount with the
+    /// approval of an owner.
+    fn authorise_spend(
+        &self,
+        customer_id: &str,
+        points: f32,
+        approved_by: Option<&str>,
+    ) -> Result<(), LoyaltyErrors> {
+        let member = self.member(customer_id).ok_or_else(|| {
+            LoyaltyErrors::HouseholdNotFound(format!(
+                "{} is not a member of household {}",
+                customer_id, self.household_id
+            ))
+        })?;
+
+        if member.role == HouseholdRole::Owner
+            || member.spend_limit.is_some_and(|limit| points <= limit)
+        {
+            return Ok(());
+        }
+
+        match approved_by.and_then(|approver| self.member(approver)) {
+            Some(approver) if approver.role == HouseholdRole::Owner => Ok(()),
+            _ => Err(LoyaltyErrors::SpendNotPermitted(format!(
+                "Spending {} points needs the approval of an owner of household {}",
+                points, self.household_id
+            ))),
+        }
+    }
+
+    pub(crate) fn spend_points(
+        &mut self,
+        customer_id: &str,
+        order_number: &str,
+        points: f32,
+        approved_by: Option<&str>,
+        at: DateTime<Utc>,
+    ) -> Result<LoyaltyAccountTransaction, LoyaltyErrors> {
+        if !points.is_finite() || points <= 0.0 {
+            return Err(LoyaltyErrors::InvalidValues(
+                "Spend must be greater than zero".to_string(),
+            ));
+        }
+
+        self.authorise_spend(customer_id, points, approved_by)?;
+
+        if self.points < points {
+            return Err(LoyaltyErrors::PointsNotAvailable(
+                "Household points not enough to cover this transaction".to_string(),
+            ));
+        }
+
+        self.points -= points;
+
+        Ok(LoyaltyAccountTransaction::new(
+            at,
+            order_number.to_string(),
+            -points,
+        ))
+    }
+}
+
+#[cfg_attr(any(test, feature = "mocks"), automock)]
+#[async_trait]
+pub trait Households {
+    /// Stores a new household together with its founding members.
+    async fn create_household(&self, household: &Household) -> Result<(), LoyaltyErrors>;
+    async fn retrieve_household(
+        &self,
+        programme_id: &str,
+        household_id: &str,
+    ) -> Result<Household, LoyaltyErrors>;
+    async fn household_for_member(
+        &self,
+        programme_id: &str,
+        customer_id: &str,
+    ) -> Result<Household, LoyaltyErrors>;
+    /// Fails with `InvalidValues` if the customer already belongs to a household.
+    async fn add_member(
+        &self,
+        household_id: &str,
+        member: &HouseholdMember,
+    ) -> Result<(), LoyaltyErrors>;
+    async fn remove_member(&self, household_id: &str, customer_id: &str) -> Result<(), LoyaltyErrors>;
+    /// Credits the pool and the member's contribution relative to the stored values so earns from
+    /// different members never overwrite each other. Returns false if the order was already
+    /// credited.
+    async fn contribute(
+        &self,
+        household_id: &str,
+        customer_id: &str,
+        transaction: LoyaltyAccountTransaction,
+    ) -> Result<bool, LoyaltyErrors>;
+    /// Debits the pool, failing with `PointsNotAvailable` if a concurrent spend got there first.
+    async fn spend(
+        &self,
+        household_id: &str,
+        customer_id: &str,
+        transaction: LoyaltyAccountTransaction,
+    ) -> Result<(), LoyaltyErrors>;
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::programmes::DEFAULT_PROGRAMME_ID;
+
+    use super::*;
+
+    fn household(points: f32) -> Household {
+        Household::from(
+            DEFAULT_PROGRAMME_ID.to_string(),
+            "HH-1".to_string(),
+            "Smiths".to_string(),
+            points,
+            vec![
+                HouseholdMember::from("alice".to_string(), HouseholdRole::Owner, None, 0.0),
+                HouseholdMember::from("james".to_string(), HouseholdRole::Member, Some(50.0), 0.0),
+            ],
+            Utc::now(),
+        )
+    }
+
+    #[test]
+    fn member_can_spend_within_their_limit() {
+        let mut household = household(100.0);
+
+        let transaction = household
+            .spend_points("james", "ORD1", 50.0, None, Utc::now())
+            .unwrap();
+
+        assert_eq!(transaction.change(), -50.0);
+        assert_eq!(household.points(), 50.0);
+    }
+
+    #[test]
+    fn member_spend_above_limit_needs_owner_approval() {
+        let mut household = household(100.0);
+
+        let denied = household.spend_points("james", "ORD1", 80.0, None, Utc::now());
+        let self_approved =
+            household.spend_points("james", "ORD1", 80.0, Some("james"), Utc::now());
+
+        assert!(matches!(denied, Err(LoyaltyErrors::SpendNotPermitted(_))));
+        assert!(matches!(
+            self_approved,
+            Err(LoyaltyErrors::SpendNotPermitted(_))
+        ));
+        assert!(household
+            .spend_points("james", "ORD1", 80.0, Some("alice"), Utc::now())
+            .is_ok());
+    }
+
+    #[test]
+    fn spend_cannot_exceed_pool() {
+        let mut household = household(100.0);
+
+        let result = household.spend_points("alice", "ORD1", 150.0, None, Utc::now());
+
+        assert!(matches!(result, Err(LoyaltyErrors::PointsNotAvailable(_))));
+        assert_eq!(household.points(), 100.0);
+    }
+
+    #[test]
+    fn last_owner_cannot_leave() {
+        let mut household = household(0.0);
+
+        assert!(household.remove_member("alice").is_err());
+        assert!(household.remove_member("james").is_ok());
+    }
+}
@@ -1,10 +1,105 @@
 #![allow(private_bounds)]
+mod adjust_points;
+mod adjustments;
+mod birthday_bonuses;
+mod campaigns;
+mod change_account_status;
+mod clock;
+mod customer_registered;
+mod erase_customer_data;
+mod exchange_rates;
+mod households;
+mod list_flagged_spends;
+mod list_pending_adjustments;
+mod manage_campaigns;
+mod manage_households;
+mod manage_profile;
+mod manage_programmes;
+mod manage_referrals;
 mod loyalty;
+mod merge_loyalty_accounts;
+mod manage_rewards;
 mod order_confirmed;
+mod profiles;
+mod programmes;
+mod redeem_reward;
+mod referrals;
+mod refund_order;
+mod retrieve_campaigns;
 mod retrieve_loyalty_account;
+mod review_adjustment;
+mod rewards;
+mod spend_household_points;
 mod spend_loyalty_points;
+mod spend_risk;
+mod transfer_points;
+mod validation;
 
-pub use order_confirmed::{OrderConfirmed, OrderConfirmedEventHandler};
-pub use loyalty::{LoyaltyAccount, LoyaltyDto, LoyaltyAccountTransaction, LoyaltyErrors, LoyaltyPoints};
+pub use adjust_points::{AdjustPointsCommand, AdjustPointsCommandHandler};
+pub use adjustments::{
+    AdjustmentPolicy, AdjustmentStatus, PointsAdjustment, PointsAdjustmentDto, PointsAdjustments,
+    ReasonCode,
+};
+pub use birthday_bonuses::GrantBirthdayBonusesCommandHandler;
+pub use campaigns::{
+    Campaign, CampaignBonus, CampaignDto, CampaignEligibility, CampaignReward, Campaigns,
+};
+pub use change_account_status::{ChangeAccountStatusCommand, ChangeAccountStatusCommandHandler};
+pub use clock::{Clock, SystemClock};
+pub use customer_registered::{CustomerRegistered, CustomerRegisteredEventHandler};
+pub use erase_customer_data::{EraseCustomerDataCommand, EraseCustomerDataCommandHandler};
+pub use exchange_rates::{ExchangeRates, StaticExchangeRates};
+pub use households::{
+    Household, HouseholdDto, HouseholdMember, HouseholdMemberDto, HouseholdMembershipDto,
+    HouseholdRole, Households,
+};
+pub use order_confirmed::{OrderConfirmed, OrderConfirmedEventHandler, VestingPolicy};
+pub use list_flagged_spends::ListFlaggedSpendsQueryHandler;
+pub use list_pending_adjustments::ListPendingAdjustmentsQueryHandler;
+pub use loyalty::{AccountStatus, LoyaltyAccount, LoyaltyDto, LoyaltyAccountTransaction, LoyaltyErrors, LoyaltyPoints};
+pub use manage_campaigns::{
+    CreateCampaignCommand, CreateCampaignCommandHandler, DeleteCampaignCommandHandler,
+    UpdateCampaignCommand, UpdateCampaignCommandHandler,
+};
+pub use manage_households::{
+    AddHouseholdMemberCommand, AddHouseholdMemberCommandHandler, CreateHouseholdCommand,
+    CreateHouseholdCommandHandler, RemoveHouseholdMemberCommandHandler,
+    RetrieveHouseholdQueryHandler,
+};
+pub use manage_profile::{
+    RetrieveCustomerProfileQueryHandler, UpdateCustomerProfileCommand,
+    UpdateCustomerProfileCommandHandler,
+};
+pub use manage_programmes::{
+    CreateProgrammeCommand, CreateProgrammeCommandHandler, ListProgrammesQueryHandler,
+    RetrieveProgrammeQueryHandler, UpdateProgrammeCommand, UpdateProgrammeCommandHandler,
+};
+pub use manage_rewards::{CreateRewardCommand, CreateRewardCommandHandler, ListRewardsQueryHandler};
+pub use manage_referrals::{ListReferralsQueryHandler, RetrieveReferralCodeQueryHandler};
+pub use merge_loyalty_accounts::{MergeLoyaltyAccountsCommand, MergeLoyaltyAccountsCommandHandler};
+pub use profiles::{BonusPolicy, CustomerProfile, CustomerProfileDto, CustomerProfiles};
+pub use programmes::{Programme, ProgrammeDto, Programmes, DEFAULT_PROGRAMME_ID};
+pub use redeem_reward::{
+    PublishRewardOutboxHandler, RedeemRewardCommand, RedeemRewardCommandHandler,
+    UpdateRedemptionStatusCommand, UpdateRedemptionStatusCommandHandler,
+};
+pub use referrals::{
+    Referral, ReferralBonuses, ReferralCodeDto, ReferralDto, ReferralStatus, Referrals,
+};
+pub use refund_order::{RefundOrderCommand, RefundOrderCommandHandler};
+pub use retrieve_campaigns::{ListCampaignsQueryHandler, RetrieveCampaignQueryHandler};
 pub use retrieve_loyalty_account::RetrieveLoyaltyAccountQueryHandler;
-pub use spend_loyalty_points::{SpendLoyaltyPointsCommand, SpendLoyaltyPointsCommandHandler};
\ No newline at end of file
+pub use review_adjustment::{
+    ApproveAdjustmentCommandHandler, RejectAdjustmentCommandHandler, ReviewAdjustmentCommand,
+};
+pub use rewards::{
+    FulfilmentStatus, Redemption, RedemptionDto, Reward, RewardDto, RewardEvents, RewardOutbox,
+    RewardRedeemed, Rewards,
+};
+pub use spend_household_points::{SpendHouseholdPointsCommand, SpendHouseholdPointsCommandHandler};
+pub use spend_loyalty_points::{SpendLoyaltyPointsCommand, SpendLoyaltyPointsCommandHandler};
+pub use spend_risk::{
+    FlaggedSpend, FlaggedSpendDto, FlaggedSpends, RiskAction, SpendRiskPolicy, SpendRiskRule,
+};
+pub use transfer_points::{TransferLimits, TransferPointsCommand, TransferPointsCommandHandler};
+pub use validation::{FieldError, ValidationError};
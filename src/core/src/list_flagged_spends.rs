@@ -0,0 +1,18 @@
+use crate::{
+    loyalty::LoyaltyErrors,
+    spend_risk::{FlaggedSpendDto, FlaggedSpends},
+};
+
+pub struct ListFlaggedSpendsQueryHandler;
+
+impl ListFlaggedSpendsQueryHandler {
+    #[tracing::instrument(name = "handle_list_flagged_spends", skip(flagged_spends))]
+    pub async fn handle<F: FlaggedSpends>(
+        flagged_spends: &F,
+        programme_id: String,
+    ) -> anyhow::Result<Vec<FlaggedSpendDto>, LoyaltyErrors> {
+        let flagged = flagged_spends.list_flagged_spends(&programme_id).await?;
+
+        Ok(flagged.into_iter().map(|spend| spend.into()).collect())
+    }
+}
@@ -0,0 +1,18 @@
+use crate::{
+    adjustments::{PointsAdjustmentDto, PointsAdjustments},
+    loyalty::LoyaltyErrors,
+};
+
+pub struct ListPendingAdjustmentsQueryHandler;
+
+impl ListPendingAdjustmentsQueryHandler {
+    #[tracing::instrument(name = "handle_list_pending_adjustments", skip(adjustments))]
+    pub async fn handle<A: PointsAdjustments>(
+        adjustments: &A,
+        programme_id: String,
+    ) -> anyhow::Result<Vec<PointsAdjustmentDto>, LoyaltyErrors> {
+        let pending = adjustments.list_pending_adjustments(&programme_id).await?;
+
+        Ok(pending.into_iter().map(|adjustment| adjustment.into()).collect())
+    }
+}
@@ -1,13 +1,20 @@
+use std::{collections::BTreeMap, fmt::Display, str::FromStr};
+
 use async_trait::async_trait;
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
 use tracing::info;
 
+use crate::{
+    campaigns::CampaignBonus, exchange_rates::OrderValue, households::HouseholdMembershipDto,
+    programmes::DEFAULT_PROGRAMME_ID,
+};
+
 #[cfg(any(test, feature = "mocks"))]
 use mockall::{automock, predicate::*};
 
-#[derive(Error, Debug)]
+#[derive(Error, Debug, Clone)]
 pub enum LoyaltyErrors {
     #[error("Invalid Values")]
     InvalidValues(String),
@@ -19,34 +26,146 @@ pub enum LoyaltyErrors {
     PointsNotAvailable(String),
     #[error("Database Error")]
     DatabaseError(String),
+    #[error("Account Not Active")]
+    AccountNotActive(String),
+    #[error("Transfer Limit Exceeded")]
+    TransferLimitExceeded(String),
+    #[error("Adjustment Not Found")]
+    AdjustmentNotFound(String),
+    #[error("Campaign Not Found")]
+    CampaignNotFound(String),
+    #[error("Reward Not Found")]
+    RewardNotFound(String),
+    #[error("Reward Unavailable")]
+    RewardUnavailable(String),
+    #[error("Redemption Not Found")]
+    RedemptionNotFound(String),
+    #[error("Redemption Exists")]
+    RedemptionExists(String),
+    #[error("Profile Not Found")]
+    ProfileNotFound(String),
+    #[error("Referral Not Found")]
+    ReferralNotFound(String),
+    #[error("Household Not Found")]
+    HouseholdNotFound(String),
+    #[error("Spend Not Permitted")]
+    SpendNotPermitted(String),
+    #[error("Programme Not Found")]
+    ProgrammeNotFound(String),
+    #[error("Spend Blocked")]
+    SpendBlocked(String),
+    #[error("Publish Error")]
+    PublishError(String),
+}
+
+impl LoyaltyErrors {
+    /// Whether the same request could succeed if tried again later, such as when the database or
+    /// broker is unavailable. Every other error is down to the request itself and will fail the
+    /// same way.
+    pub fn is_retryable(&self) -> bool {
+        matches!(
+            self,
+            LoyaltyErrors::DatabaseError(_) | LoyaltyErrors::PublishError(_)
+        )
+    }
+}
+
+#[derive(Deserialize, Serialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum AccountStatus {
+    /// The account can earn and spend points.
+    #[default]
+    Active,
+    /// The account continues to earn points but cannot spend them.
+    Frozen,
+    /// The account cannot earn or spend points.
+    Closed,
+    /// The account has been merged into another account, see `merged_into`.
+    Merged,
+}
+
+impl Display for AccountStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let status = match self {
+            AccountStatus::Active => "Active",
+            AccountStatus::Frozen => "Frozen",
+            AccountStatus::Closed => "Closed",
+            AccountStatus::Merged => "Merged",
+        };
+
+        write!(f, "{}", status)
+    }
+}
+
+impl FromStr for AccountStatus {
+    type Err = LoyaltyErrors;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "Active" => Ok(AccountStatus::Active),
+            "Frozen" => Ok(AccountStatus::Frozen),
+            "Closed" => Ok(AccountStatus::Closed),
+            "Merged" => Ok(AccountStatus::Merged),
+            _ => Err(LoyaltyErrors::InvalidValues(format!(
+                "'{}' is not a valid account status",
+                s
+            ))),
+        }
+    }
 }
 
 #[derive(Deserialize, Serialize)]
 pub struct LoyaltyDto {
+    pub programme_id: String,
     pub customer_id: String,
     pub current_points: f32,
+    /// Points earned recently that cannot be spent until their return window has passed.
+    pub pending_points: f32,
+    pub available_points: f32,
+    pub status: AccountStatus,
     pub transactions: Vec<LoyaltyAccountTransaction>,
+    /// The member's contribution and the shared balance, if the customer belongs to a household.
+    pub household: Option<HouseholdMembershipDto>,
 }
 
 impl From<LoyaltyAccount> for LoyaltyDto {
     fn from(value: LoyaltyAccount) -> Self {
+        let now = Utc::now();
+
         LoyaltyDto {
             current_points: value.current_points,
+            pending_points: value.pending_points(now),
+            available_points: value.available_points(now),
+            programme_id: value.programme_id,
             customer_id: value.customer_id,
+            status: value.status,
             transactions: value.transactions,
+            household: None,
         }
     }
 }
 
+fn default_programme_id() -> String {
+    DEFAULT_PROGRAMME_ID.to_string()
+}
+
 #[derive(Deserialize, Serialize)]
 pub struct LoyaltyAccount {
+    /// Accounts cached before programmes were introduced all belong to the default programme.
+    #[serde(default = "default_programme_id")]
+    programme_id: String,
     customer_id: String,
     current_points: f32,
     transactions: Vec<LoyaltyAccountTransaction>,
+    #[serde(default)]
+    status: AccountStatus,
+    #[serde(default)]
+    merged_into: Option<String>,
 }
 
 impl LoyaltyAccount {
-    const LOYALTY_ACCOUNT_PERCENTAGE: f32 = 0.5;
+    pub fn programme_id(&self) -> &str {
+        &self.programme_id
+    }
 
     pub fn customer_id(&self) -> &str {
         &self.customer_id
@@ -56,44 +175,246 @@ impl LoyaltyAccount {
         &self.current_points
     }
 
+    pub fn transactions(&self) -> &[LoyaltyAccountTransaction] {
+        &self.transactions
+    }
+
+    /// Points that are part of `current_points` but have not vested yet.
+    pub fn pending_points(&self, at: DateTime<Utc>) -> f32 {
+        self.transactions
+            .iter()
+            .filter(|t| t.is_pending(at))
+            .map(|t| t.change)
+            .sum()
+    }
+
+    pub fn available_points(&self, at: DateTime<Utc>) -> f32 {
+        self.current_points - self.pending_points(at)
+    }
+
+    pub fn status(&self) -> AccountStatus {
+        self.status
+    }
+
+    pub fn merged_into(&self) -> Option<&str> {
+        self.merged_into.as_deref()
+    }
+
     #[tracing::instrument(name = "new_loyalty_account")]
-    pub fn new(customer_id: String) -> anyhow::Result<Self, LoyaltyErrors> {
-        if customer_id.is_empty() {
-            return Err(LoyaltyErrors::InvalidValues(
-                "CustomerID cannot be empty".to_string(),
-            ));
-        }
+    pub fn new(programme_id: String, customer_id: String) -> anyhow::Result<Self, LoyaltyErrors> {
+        Self::validate_ids(&programme_id, &customer_id)?;
 
         Ok(Self {
+            programme_id,
             customer_id,
             current_points: 0.00,
             transactions: vec![],
+            status: AccountStatus::Active,
+            merged_into: None,
         })
     }
 
     pub fn from(
+        programme_id: String,
         customer_id: String,
         current_points: f32,
         transactions: Vec<LoyaltyAccountTransaction>,
     ) -> anyhow::Result<Self, LoyaltyErrors> {
-        if customer_id.is_empty() {
-            return Err(LoyaltyErrors::InvalidValues(
-                "CustomerID cannot be empty".to_string(),
-            ));
-        }
+        Self::validate_ids(&programme_id, &customer_id)?;
+
         Ok(Self {
+            programme_id,
             customer_id,
             current_points,
             transactions,
+            status: AccountStatus::Active,
+            merged_into: None,
         })
     }
 
-    #[tracing::instrument(name = "handle_add_transaction", skip(self))]
-    pub(crate) fn add_transaction(
+    fn validate_ids(programme_id: &str, customer_id: &str) -> Result<(), LoyaltyErrors> {
+        if programme_id.is_empty() {
+            return Err(LoyaltyErrors::InvalidValues(
+                "ProgrammeID cannot be empty".to_string(),
+            ));
+        }
+
+        if customer_id.is_empty() {
+            return Err(LoyaltyErrors::InvalidValues(
+                "CustomerID cannot be empty".to_string(),
+            ));
+        }
+
+        Ok(())
+    }
+
+    pub fn with_status(mut self, status: AccountStatus, merged_into: Option<String>) -> Self {
+        self.status = status;
+        self.merged_into = merged_into;
+        self
+    }
+
+    #[tracing::instrument(name = "handle_change_status", skip(self))]
+    pub(crate) fn change_status(&mut self, status: AccountStatus) -> Result<(), LoyaltyErrors> {
+        let allowed = matches!(
+            (self.status, status),
+            (AccountStatus::Active, AccountStatus::Frozen)
+                | (AccountStatus::Frozen, AccountStatus::Active)
+                | (AccountStatus::Active, AccountStatus::Closed)
+                | (AccountStatus::Frozen, AccountStatus::Closed)
+        );
+
+        if !allowed {
+            return Err(LoyaltyErrors::AccountNotActive(format!(
+                "Account cannot move from {} to {}",
+                self.status, status
+            )));
+        }
+
+        self.status = status;
+
+        Ok(())
+    }
+
+    /// Moves the balance and history of this account into `target`, leaving this account as a
+    /// tombstone that redirects to the target.
+    #[tracing::instrument(name = "handle_merge_into", skip(self, target), fields(target=target.customer_id))]
+    pub(crate) fn merge_into(&mut self, target: &mut LoyaltyAccount) -> Result<(), LoyaltyErrors> {
+        if self.customer_id == target.customer_id {
+            return Err(LoyaltyErrors::InvalidValues(
+                "An account cannot be merged into itself".to_string(),
+            ));
+        }
+
+        if self.programme_id != target.programme_id {
+            return Err(LoyaltyErrors::InvalidValues(
+                "Accounts can only be merged within the same programme".to_string(),
+            ));
+        }
+
+        for account in [&*self, &*target] {
+            if matches!(account.status, AccountStatus::Closed | AccountStatus::Merged) {
+                return Err(LoyaltyErrors::AccountNotActive(format!(
+                    "Account {} is {} and cannot be merged",
+                    account.customer_id, account.status
+                )));
+            }
+        }
+
+        target.current_points += self.current_points;
+        target.transactions.append(&mut self.transactions);
+
+        self.current_points = 0.0;
+        self.status = AccountStatus::Merged;
+        self.merged_into = Some(target.customer_id.clone());
+
+        Ok(())
+    }
+
+    /// Replaces the customer identifier with a pseudonym and closes the account.
+    #[tracing::instrument(name = "handle_erase", skip(self, pseudonym))]
+    pub(crate) fn erase(&mut self, pseudonym: String) {
+        self.customer_id = pseudonym;
+
+        if self.status != AccountStatus::Merged {
+            self.status = AccountStatus::Closed;
+        }
+    }
+
+    pub(crate) fn has_transfer(&self, transfer_id: &str) -> bool {
+        self.transactions
+            .iter()
+            .any(|t| t.transfer_id.as_deref() == Some(transfer_id))
+    }
+
+    /// Moves `points` from this account to `target`, returning the matching debit and credit.
+    #[tracing::instrument(name = "handle_transfer_to", skip(self, target), fields(target=target.customer_id))]
+    pub(crate) fn transfer_to(
         &mut self,
-        order_number: String,
-        order_value: f32,
-    ) -> anyhow::Result<LoyaltyAccountTransaction, LoyaltyErrors> {
+        target: &mut LoyaltyAccount,
+        transfer_id: &str,
+        points: f32,
+        at: DateTime<Utc>,
+    ) -> Result<(LoyaltyAccountTransaction, LoyaltyAccountTransaction), LoyaltyErrors> {
+        if self.customer_id == target.customer_id {
+            return Err(LoyaltyErrors::InvalidValues(
+                "Points cannot be transferred to the same account".to_string(),
+            ));
+        }
+
+        if self.programme_id != target.programme_id {
+            return Err(LoyaltyErrors::InvalidValues(
+                "Points can only be transferred within the same programme".to_string(),
+            ));
+        }
+
+        if matches!(target.status, AccountStatus::Closed | AccountStatus::Merged) {
+            return Err(LoyaltyErrors::AccountNotActive(format!(
+                "Account {} is {} and cannot receive points",
+                target.customer_id, target.status
+            )));
+        }
+
+        let debit = self.debit(
+            LoyaltyAccountTransaction::new(at, transfer_id.to_string(), -points)
+                .with_transfer_id(Some(transfer_id.to_string())),
+        )?;
+
+        let credit = LoyaltyAccountTransaction::new(debit.date, transfer_id.to_string(), points)
+            .with_transfer_id(Some(transfer_id.to_string()));
+        target.current_points += points;
+        target.transactions.push(credit.clone());
+
+        Ok((debit, credit))
+    }
+
+    /// Applies an operator adjustment or bonus. Unlike spending, adjustments are allowed on frozen
+    /// accounts.
+    #[tracing::instrument(name = "handle_adjust_points", skip(self))]
+    pub(crate) fn adjust_points(
+        &mut self,
+        reference: &str,
+        points: f32,
+        at: DateTime<Utc>,
+    ) -> Result<LoyaltyAccountTransaction, LoyaltyErrors> {
+        if matches!(self.status, AccountStatus::Closed | AccountStatus::Merged) {
+            return Err(LoyaltyErrors::AccountNotActive(format!(
+                "Account is {} and cannot be adjusted",
+                self.status
+            )));
+        }
+
+        if self.current_points + points < 0.0 {
+            return Err(LoyaltyErrors::PointsNotAvailable(
+                "Adjustment would take the balance below zero".to_string(),
+            ));
+        }
+
+        if self.transactions.iter().any(|t| t.order_number == reference) {
+            return Err(LoyaltyErrors::TransactionExistsForOrder(format!(
+                "Transaction already exists for adjustment {}",
+                reference
+            )));
+        }
+
+        self.current_points += points;
+
+        let transaction = LoyaltyAccountTransaction::new(at, reference.to_string(), points);
+
+        self.transactions.push(transaction.clone());
+
+        Ok(transaction)
+    }
+
+    /// Checks whether the account can earn points for `order_number` without changing it.
+    pub(crate) fn can_earn(&self, order_number: &str) -> Result<(), LoyaltyErrors> {
+        if matches!(self.status, AccountStatus::Closed | AccountStatus::Merged) {
+            return Err(LoyaltyErrors::AccountNotActive(format!(
+                "Account is {} and cannot earn points",
+                self.status
+            )));
+        }
+
         let existing_transactions: Vec<&LoyaltyAccountTransaction> = self
             .transactions
             .iter()
@@ -108,15 +429,105 @@ impl LoyaltyAccount {
             )));
         }
 
-        let points = order_value * Self::LOYALTY_ACCOUNT_PERCENTAGE;
+        Ok(())
+    }
+
+    #[cfg(test)]
+    pub(crate) fn add_transaction(
+        &mut self,
+        order_number: String,
+        order_value: f32,
+    ) -> anyhow::Result<LoyaltyAccountTransaction, LoyaltyErrors> {
+        let points = crate::programmes::Programme::default().points_for_order(order_value);
+
+        let now = Utc::now();
+
+        self.add_transaction_with_bonuses(order_number, points, &[], None, now, now, None)
+    }
+
+    /// Earns `base_points`, as worked out by the programme's earn rate, plus any campaign bonuses.
+    /// The order value as charged is kept on the transaction, before any currency conversion. The
+    /// transaction is dated when the order was placed and also keeps when it was recorded.
+    #[allow(clippy::too_many_arguments)]
+    #[tracing::instrument(name = "handle_add_transaction_with_bonuses", skip(self, bonuses))]
+    pub(crate) fn add_transaction_with_bonuses(
+        &mut self,
+        order_number: String,
+        base_points: f32,
+        bonuses: &[CampaignBonus],
+        order_value: Option<&OrderValue>,
+        occurred_at: DateTime<Utc>,
+        recorded_at: DateTime<Utc>,
+        vests_at: Option<DateTime<Utc>>,
+    ) -> anyhow::Result<LoyaltyAccountTransaction, LoyaltyErrors> {
+        self.can_earn(&order_number)?;
+
+        let mut points = base_points;
+        let mut metadata = BTreeMap::new();
+
+        if let Some(order_value) = order_value {
+            metadata.insert("order.value".to_string(), order_value.amount.to_string());
+            metadata.insert("order.currency".to_string(), order_value.currency.clone());
+        }
+
+        for bonus in bonuses {
+            points += bonus.points;
+            metadata.insert(
+                format!("campaign.{}", bonus.campaign_id),
+                bonus.points.to_string(),
+            );
+        }
+
         self.current_points += points;
 
-        let transaction = LoyaltyAccountTransaction {
-            date: Utc::now(),
-            order_number,
-            change: points,
+        let transaction = LoyaltyAccountTransaction::new(occurred_at, order_number, points)
+            .with_metadata(metadata)
+            .with_vests_at(vests_at)
+            .with_recorded_at(Some(recorded_at));
+
+        self.transactions.push(transaction.clone());
+
+        Ok(transaction)
+    }
+
+    /// Cancels the points earned for a returned order. Pending points are cancelled with a
+    /// matching pending reversal so the available balance is untouched, points that have already
+    /// vested are taken back from the available balance. The points are clawed back whatever the
+    /// account's status, and if they have already been spent the balance goes negative until
+    /// later earnings pay it off.
+    #[tracing::instrument(name = "handle_refund_order", skip(self))]
+    pub(crate) fn refund_order(
+        &mut self,
+        order_number: &str,
+        at: DateTime<Utc>,
+    ) -> Result<LoyaltyAccountTransaction, LoyaltyErrors> {
+        let refund_reference = format!("REFUND-{}", order_number);
+
+        let Some(earned) = self
+            .transactions
+            .iter()
+            .find(|t| t.order_number == order_number && t.change > 0.0)
+            .cloned()
+        else {
+            return Err(LoyaltyErrors::InvalidValues(format!(
+                "No points were earned for order {}",
+                order_number
+            )));
         };
 
+        if self.transactions.iter().any(|t| t.order_number == refund_reference) {
+            return Err(LoyaltyErrors::TransactionExistsForOrder(format!(
+                "Order {} has already been refunded",
+                order_number
+            )));
+        }
+
+        let vests_at = earned.vests_at.filter(|_| earned.is_pending(at));
+
+        let transaction = LoyaltyAccountTransaction::new(at, refund_reference, -earned.change)
+            .with_vests_at(vests_at);
+
+        self.current_points += transaction.change;
         self.transactions.push(transaction.clone());
 
         Ok(transaction)
@@ -126,10 +537,25 @@ impl LoyaltyAccount {
         &mut self,
         order_number: &str,
         spend: &f32,
+        at: DateTime<Utc>,
     ) -> Result<LoyaltyAccountTransaction, LoyaltyErrors> {
-        let new_points_total = self.current_points - spend;
+        let transaction = LoyaltyAccountTransaction::new(at, order_number.to_string(), -spend);
 
-        if new_points_total < 0.0 {
+        self.debit(transaction)
+    }
+
+    fn debit(
+        &mut self,
+        transaction: LoyaltyAccountTransaction,
+    ) -> Result<LoyaltyAccountTransaction, LoyaltyErrors> {
+        if self.status != AccountStatus::Active {
+            return Err(LoyaltyErrors::AccountNotActive(format!(
+                "Account is {} and cannot spend points",
+                self.status
+            )));
+        }
+
+        if self.available_points(transaction.date) + transaction.change < 0.0 {
             return Err(LoyaltyErrors::PointsNotAvailable(
                 "Current points not enough to cover this transaction".to_string(),
             ));
@@ -138,23 +564,17 @@ impl LoyaltyAccount {
         let existing_transactions: Vec<&LoyaltyAccountTransaction> = self
             .transactions
             .iter()
-            .filter(|t| t.order_number == order_number)
+            .filter(|t| t.order_number == transaction.order_number)
             .collect();
 
         if !existing_transactions.is_empty() {
             return Err(LoyaltyErrors::TransactionExistsForOrder(format!(
                 "Transaction already exists for order {}",
-                order_number
+                transaction.order_number
             )));
         }
 
-        self.current_points = new_points_total;
-
-        let transaction = LoyaltyAccountTransaction {
-            date: Utc::now(),
-            order_number: order_number.to_string(),
-            change: -spend,
-        };
+        self.current_points += transaction.change;
 
         self.transactions.push(transaction.clone());
 
@@ -164,9 +584,20 @@ impl LoyaltyAccount {
 
 #[derive(Deserialize, Serialize, Clone)]
 pub struct LoyaltyAccountTransaction {
+    /// When the points were earned or spent. For orders this is when the order was placed, which
+    /// vesting and campaigns are worked out from.
     pub(crate) date: DateTime<Utc>,
     pub(crate) order_number: String,
     pub(crate) change: f32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) transfer_id: Option<String>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub(crate) metadata: BTreeMap<String, String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) vests_at: Option<DateTime<Utc>>,
+    /// When the transaction was written, later than `date` for delayed or replayed events.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) recorded_at: Option<DateTime<Utc>>,
 }
 
 impl LoyaltyAccountTransaction {
@@ -174,10 +605,34 @@ impl LoyaltyAccountTransaction {
         Self {
             date,
             order_number,
-            change
+            change,
+            transfer_id: None,
+            metadata: BTreeMap::new(),
+            vests_at: None,
+            recorded_at: Some(date),
         }
     }
 
+    pub fn with_transfer_id(mut self, transfer_id: Option<String>) -> Self {
+        self.transfer_id = transfer_id;
+        self
+    }
+
+    pub fn with_metadata(mut self, metadata: BTreeMap<String, String>) -> Self {
+        self.metadata = metadata;
+        self
+    }
+
+    pub fn with_vests_at(mut self, vests_at: Option<DateTime<Utc>>) -> Self {
+        self.vests_at = vests_at;
+        self
+    }
+
+    pub fn with_recorded_at(mut self, recorded_at: Option<DateTime<Utc>>) -> Self {
+        self.recorded_at = recorded_at;
+        self
+    }
+
     pub fn date(&self) -> DateTime<Utc> {
         self.date
     }
@@ -187,31 +642,121 @@ impl LoyaltyAccountTransaction {
     pub fn change(&self) -> f32 {
         self.change
     }
+    pub fn transfer_id(&self) -> Option<&str> {
+        self.transfer_id.as_deref()
+    }
+    pub fn metadata(&self) -> &BTreeMap<String, String> {
+        &self.metadata
+    }
+    pub fn vests_at(&self) -> Option<DateTime<Utc>> {
+        self.vests_at
+    }
+    /// Transactions stored before the recording time was kept were recorded as they happened.
+    pub fn recorded_at(&self) -> DateTime<Utc> {
+        self.recorded_at.unwrap_or(self.date)
+    }
+
+    pub fn is_pending(&self, at: DateTime<Utc>) -> bool {
+        self.vests_at.is_some_and(|vests_at| vests_at > at)
+    }
 }
 
 #[cfg_attr(any(test, feature = "mocks"), automock)]
 #[async_trait]
 pub trait LoyaltyPoints {
+    /// Saves a new account with the transactions that open it in a single atomic write. Fails if
+    /// the account already exists.
     async fn new_account(
         &self,
-        customer_id: String,
+        account: &LoyaltyAccount,
+        transactions: Vec<LoyaltyAccountTransaction>,
+    ) -> anyhow::Result<(), LoyaltyErrors>;
+    async fn retrieve(
+        &self,
+        programme_id: &str,
+        customer_id: &str,
     ) -> anyhow::Result<LoyaltyAccount, LoyaltyErrors>;
-    async fn retrieve(&self, customer_id: &str) -> anyhow::Result<LoyaltyAccount, LoyaltyErrors>;
     async fn add_transaction(
         &self,
         account: &LoyaltyAccount,
         transaction: LoyaltyAccountTransaction,
     ) -> anyhow::Result<(), LoyaltyErrors>;
+    /// Inserts every transaction and applies their changes to the stored balance in a single
+    /// atomic write. Fails with `PointsNotAvailable` if that would overdraw the balance.
+    async fn add_transactions(
+        &self,
+        account: &LoyaltyAccount,
+        transactions: Vec<LoyaltyAccountTransaction>,
+    ) -> anyhow::Result<(), LoyaltyErrors>;
+    /// Applies both sides of a transfer in a single atomic write.
+    async fn transfer(
+        &self,
+        source: &LoyaltyAccount,
+        target: &LoyaltyAccount,
+        debit: LoyaltyAccountTransaction,
+        credit: LoyaltyAccountTransaction,
+    ) -> anyhow::Result<(), LoyaltyErrors>;
+    async fn update_status(&self, account: &LoyaltyAccount) -> anyhow::Result<(), LoyaltyErrors>;
+    async fn merge_accounts(
+        &self,
+        source: &LoyaltyAccount,
+        target: &LoyaltyAccount,
+    ) -> anyhow::Result<(), LoyaltyErrors>;
+    /// Replaces `customer_id` with the pseudonym on `account`, within the account's programme.
+    async fn erase(
+        &self,
+        customer_id: &str,
+        account: &LoyaltyAccount,
+    ) -> anyhow::Result<(), LoyaltyErrors>;
+}
+
+/// Retrieves an account, following the redirect left behind when an account is merged.
+pub(crate) async fn retrieve_active_account<T: LoyaltyPoints>(
+    loyalty_points: &T,
+    programme_id: &str,
+    customer_id: &str,
+) -> anyhow::Result<LoyaltyAccount, LoyaltyErrors> {
+    const MAX_REDIRECTS: usize = 5;
+
+    let mut account = loyalty_points.retrieve(programme_id, customer_id).await?;
+
+    for _ in 0..MAX_REDIRECTS {
+        match (account.status, account.merged_into.clone()) {
+            (AccountStatus::Merged, Some(merged_into)) => {
+                info!("Account {} merged into {}", account.customer_id, merged_into);
+                account = loyalty_points.retrieve(programme_id, &merged_into).await?;
+            }
+            _ => return Ok(account),
+        }
+    }
+
+    Err(LoyaltyErrors::InvalidValues(format!(
+        "Too many merge redirects for customer {}",
+        customer_id
+    )))
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn only_database_and_publish_errors_should_be_retryable() {
+        assert!(LoyaltyErrors::DatabaseError("connection refused".to_string()).is_retryable());
+        assert!(LoyaltyErrors::PublishError("broker unavailable".to_string()).is_retryable());
+        assert!(!LoyaltyErrors::InvalidValues("bad payload".to_string()).is_retryable());
+        assert!(!LoyaltyErrors::AccountNotFound().is_retryable());
+        assert!(!LoyaltyErrors::TransactionExistsForOrder("ORD1".to_string()).is_retryable());
+    }
+
     #[test]
     fn can_create_loyalty_account() {
         let test_customer_id = "test-id";
-        let account = LoyaltyAccount::new(test_customer_id.to_string()).unwrap();
+        let account = LoyaltyAccount::new(
+            DEFAULT_PROGRAMME_ID.to_string(),
+            test_customer_id.to_string(),
+        )
+        .unwrap();
 
         assert_eq!(account.customer_id, test_customer_id);
         assert_eq!(account.current_points, 0.00);
@@ -221,7 +766,11 @@ mod tests {
     #[test]
     fn can_create_loyalty_account_and_add_transaction() {
         let test_customer_id = "test-id";
-        let mut account = LoyaltyAccount::new(test_customer_id.to_string()).unwrap();
+        let mut account = LoyaltyAccount::new(
+            DEFAULT_PROGRAMME_ID.to_string(),
+            test_customer_id.to_string(),
+        )
+        .unwrap();
         let _ = account.add_transaction("ORD567".to_string(), 100.00);
 
         assert_eq!(account.current_points, 50.00);
@@ -231,10 +780,14 @@ mod tests {
     #[test]
     fn can_create_loyalty_account_and_spend_points_when_points_are_available() {
         let test_customer_id = "test-id";
-        let mut account = LoyaltyAccount::new(test_customer_id.to_string()).unwrap();
+        let mut account = LoyaltyAccount::new(
+            DEFAULT_PROGRAMME_ID.to_string(),
+            test_customer_id.to_string(),
+        )
+        .unwrap();
         let _ = account.add_transaction("ORD567".to_string(), 100.00);
 
-        let _ = account.spend_points("ORD789", &10.0);
+        let _ = account.spend_points("ORD789", &10.0, Utc::now());
 
         assert_eq!(account.current_points, 40.00);
         assert_eq!(account.transactions.len(), 2);
@@ -243,7 +796,11 @@ mod tests {
     #[test]
     fn can_create_loyalty_account_and_add_same_transaction_should_not_add_points() {
         let test_customer_id = "test-id";
-        let mut account = LoyaltyAccount::new(test_customer_id.to_string()).unwrap();
+        let mut account = LoyaltyAccount::new(
+            DEFAULT_PROGRAMME_ID.to_string(),
+            test_customer_id.to_string(),
+        )
+        .unwrap();
         let _ = account.add_transaction("ORD567".to_string(), 100.00);
         let _ = account.add_transaction("ORD567".to_string(), 100.00);
 
@@ -258,6 +815,7 @@ mod tests {
         let transactions = vec![];
 
         let account = LoyaltyAccount::from(
+            DEFAULT_PROGRAMME_ID.to_string(),
             test_customer_id.to_string(),
             test_points_total,
             transactions.clone(),
@@ -276,6 +834,7 @@ mod tests {
         let transactions = vec![];
 
         let mut account = LoyaltyAccount::from(
+            DEFAULT_PROGRAMME_ID.to_string(),
             test_customer_id.to_string(),
             test_points_total,
             transactions.clone(),
@@ -287,4 +846,207 @@ mod tests {
         assert_eq!(account.current_points, 60.00);
         assert_eq!(account.transactions.len(), 1);
     }
+
+    #[test]
+    fn frozen_account_can_earn_but_not_spend_points() {
+        let mut account = LoyaltyAccount::from(
+            DEFAULT_PROGRAMME_ID.to_string(),
+            "test-id".to_string(),
+            10.0,
+            vec![],
+        )
+        .unwrap();
+        account.change_status(AccountStatus::Frozen).unwrap();
+
+        let earn = account.add_transaction("ORD567".to_string(), 100.00);
+        let spend = account.spend_points("ORD789", &10.0, Utc::now());
+
+        assert!(earn.is_ok());
+        assert!(matches!(spend, Err(LoyaltyErrors::AccountNotActive(_))));
+        assert_eq!(account.current_points, 60.00);
+    }
+
+    #[test]
+    fn closed_account_cannot_earn_points() {
+        let mut account =
+            LoyaltyAccount::new(DEFAULT_PROGRAMME_ID.to_string(), "test-id".to_string()).unwrap();
+        account.change_status(AccountStatus::Closed).unwrap();
+
+        let earn = account.add_transaction("ORD567".to_string(), 100.00);
+
+        assert!(matches!(earn, Err(LoyaltyErrors::AccountNotActive(_))));
+        assert_eq!(account.current_points, 0.00);
+    }
+
+    #[test]
+    fn closed_account_cannot_be_reopened() {
+        let mut account =
+            LoyaltyAccount::new(DEFAULT_PROGRAMME_ID.to_string(), "test-id".to_string()).unwrap();
+        account.change_status(AccountStatus::Closed).unwrap();
+
+        let result = account.change_status(AccountStatus::Active);
+
+        assert!(result.is_err());
+        assert_eq!(account.status, AccountStatus::Closed);
+    }
+
+    #[test]
+    fn merging_accounts_combines_balances_and_leaves_tombstone() {
+        let mut source =
+            LoyaltyAccount::new(DEFAULT_PROGRAMME_ID.to_string(), "source".to_string()).unwrap();
+        let _ = source.add_transaction("ORD1".to_string(), 100.00);
+        let mut target =
+            LoyaltyAccount::new(DEFAULT_PROGRAMME_ID.to_string(), "target".to_string()).unwrap();
+        let _ = target.add_transaction("ORD2".to_string(), 20.00);
+
+        source.merge_into(&mut target).unwrap();
+
+        assert_eq!(target.current_points, 60.00);
+        assert_eq!(target.transactions.len(), 2);
+        assert_eq!(source.current_points, 0.00);
+        assert_eq!(source.transactions.len(), 0);
+        assert_eq!(source.status, AccountStatus::Merged);
+        assert_eq!(source.merged_into(), Some("target"));
+    }
+
+    #[test]
+    fn merging_account_into_itself_should_error() {
+        let mut source =
+            LoyaltyAccount::new(DEFAULT_PROGRAMME_ID.to_string(), "source".to_string()).unwrap();
+        let mut target =
+            LoyaltyAccount::new(DEFAULT_PROGRAMME_ID.to_string(), "source".to_string()).unwrap();
+
+        let result = source.merge_into(&mut target);
+
+        assert!(matches!(result, Err(LoyaltyErrors::InvalidValues(_))));
+    }
+
+    #[test]
+    fn erasing_account_pseudonymises_and_closes() {
+        let mut account =
+            LoyaltyAccount::new(DEFAULT_PROGRAMME_ID.to_string(), "test-id".to_string()).unwrap();
+
+        account.erase("erased-123".to_string());
+
+        assert_eq!(account.customer_id, "erased-123");
+        assert_eq!(account.status, AccountStatus::Closed);
+    }
+
+    #[test]
+    fn transfer_to_closed_account_should_error_and_leave_balances_unchanged() {
+        let mut source = LoyaltyAccount::from(
+            DEFAULT_PROGRAMME_ID.to_string(),
+            "source".to_string(),
+            50.0,
+            vec![],
+        )
+        .unwrap();
+        let mut target =
+            LoyaltyAccount::new(DEFAULT_PROGRAMME_ID.to_string(), "target".to_string()).unwrap();
+        target.change_status(AccountStatus::Closed).unwrap();
+
+        let result = source.transfer_to(&mut target, "TRF1", 10.0, Utc::now());
+
+        assert!(matches!(result, Err(LoyaltyErrors::AccountNotActive(_))));
+        assert_eq!(source.current_points, 50.0);
+        assert_eq!(target.current_points, 0.0);
+    }
+
+    #[test]
+    fn pending_points_cannot_be_spent_until_vested() {
+        let mut account =
+            LoyaltyAccount::new(DEFAULT_PROGRAMME_ID.to_string(), "test-id".to_string()).unwrap();
+        let vests_at = Utc::now() + chrono::Duration::days(14);
+        let _ = account.add_transaction_with_bonuses(
+            "ORD1".to_string(),
+            50.00,
+            &[],
+            None,
+            Utc::now(),
+            Utc::now(),
+            Some(vests_at),
+        );
+
+        assert_eq!(account.current_points, 50.00);
+        assert_eq!(account.pending_points(Utc::now()), 50.00);
+        assert_eq!(account.available_points(Utc::now()), 0.00);
+        assert_eq!(account.available_points(vests_at), 50.00);
+
+        let result = account.spend_points("ORD2", &10.0, Utc::now());
+
+        assert!(matches!(result, Err(LoyaltyErrors::PointsNotAvailable(_))));
+    }
+
+    #[test]
+    fn refunding_pending_order_cancels_pending_points_only() {
+        let mut account = LoyaltyAccount::from(
+            DEFAULT_PROGRAMME_ID.to_string(),
+            "test-id".to_string(),
+            20.0,
+            vec![],
+        )
+        .unwrap();
+        let vests_at = Utc::now() + chrono::Duration::days(14);
+        let _ = account.add_transaction_with_bonuses(
+            "ORD1".to_string(),
+            50.00,
+            &[],
+            None,
+            Utc::now(),
+            Utc::now(),
+            Some(vests_at),
+        );
+
+        let refund = account.refund_order("ORD1", Utc::now()).unwrap();
+
+        assert_eq!(refund.change, -50.00);
+        assert_eq!(account.current_points, 20.00);
+        assert_eq!(account.pending_points(Utc::now()), 0.00);
+        assert_eq!(account.available_points(Utc::now()), 20.00);
+        assert!(account.refund_order("ORD1", Utc::now()).is_err());
+    }
+
+    #[test]
+    fn refunding_vested_order_takes_points_from_available_balance() {
+        let mut account =
+            LoyaltyAccount::new(DEFAULT_PROGRAMME_ID.to_string(), "test-id".to_string()).unwrap();
+        let _ = account.add_transaction("ORD1".to_string(), 100.00);
+
+        let refund = account.refund_order("ORD1", Utc::now()).unwrap();
+
+        assert_eq!(refund.change, -50.00);
+        assert_eq!(account.current_points, 0.00);
+        assert!(account.refund_order("ORD1", Utc::now()).is_err());
+    }
+
+    #[test]
+    fn refunding_spent_points_leaves_a_negative_balance() {
+        let mut account =
+            LoyaltyAccount::new(DEFAULT_PROGRAMME_ID.to_string(), "test-id".to_string()).unwrap();
+        let _ = account.add_transaction("ORD1".to_string(), 100.00);
+        let _ = account.spend_points("SPEND1", &40.0, Utc::now());
+
+        let refund = account.refund_order("ORD1", Utc::now()).unwrap();
+
+        assert_eq!(refund.change, -50.00);
+        assert_eq!(account.current_points, -40.00);
+        assert_eq!(account.available_points(Utc::now()), -40.00);
+
+        let result = account.spend_points("SPEND2", &1.0, Utc::now());
+
+        assert!(matches!(result, Err(LoyaltyErrors::PointsNotAvailable(_))));
+    }
+
+    #[test]
+    fn refund_is_clawed_back_from_accounts_that_cannot_spend() {
+        let mut account =
+            LoyaltyAccount::new(DEFAULT_PROGRAMME_ID.to_string(), "test-id".to_string()).unwrap();
+        let _ = account.add_transaction("ORD1".to_string(), 100.00);
+        account.change_status(AccountStatus::Closed).unwrap();
+
+        let refund = account.refund_order("ORD1", Utc::now()).unwrap();
+
+        assert_eq!(refund.change, -50.00);
+        assert_eq!(account.current_points, 0.00);
+    }
 }
@@ -0,0 +1,190 @@
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+
+use crate::{
+    campaigns::{Campaign, CampaignDto, CampaignEligibility, CampaignReward, Campaigns},
+    loyalty::LoyaltyErrors,
+    validation::bind_path_id,
+};
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateCampaignCommand {
+    #[serde(default)]
+    programme_id: String,
+    name: String,
+    starts_at: DateTime<Utc>,
+    ends_at: DateTime<Utc>,
+    #[serde(default)]
+    eligibility: CampaignEligibility,
+    reward: CampaignReward,
+    budget_cap: Option<f32>,
+}
+
+impl CreateCampaignCommand {
+    pub fn bind_path(&mut self, programme_id: String) -> Result<(), LoyaltyErrors> {
+        bind_path_id("programmeId", &mut self.programme_id, programme_id)?;
+
+        Ok(())
+    }
+}
+
+pub struct CreateCampaignCommandHandler;
+
+impl CreateCampaignCommandHandler {
+    #[tracing::instrument(name = "handle_create_campaign", skip(campaigns, command), fields(programme_id=command.programme_id, name=command.name))]
+    pub async fn handle<C: Campaigns>(
+        campaigns: &C,
+        command: CreateCampaignCommand,
+    ) -> anyhow::Result<CampaignDto, LoyaltyErrors> {
+        let campaign = Campaign::new(
+            command.programme_id,
+            command.name,
+            command.starts_at,
+            command.ends_at,
+            command.eligibility,
+            command.reward,
+            command.budget_cap,
+        )?;
+
+        campaigns.save_campaign(&campaign).await?;
+
+        Ok(campaign.into())
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UpdateCampaignCommand {
+    #[serde(default)]
+    programme_id: String,
+    #[serde(default)]
+    campaign_id: String,
+    name: String,
+    starts_at: DateTime<Utc>,
+    ends_at: DateTime<Utc>,
+    #[serde(default)]
+    eligibility: CampaignEligibility,
+    reward: CampaignReward,
+    budget_cap: Option<f32>,
+}
+
+impl UpdateCampaignCommand {
+    pub fn bind_path(
+        &mut self,
+        programme_id: String,
+        campaign_id: String,
+    ) -> Result<(), LoyaltyErrors> {
+        bind_path_id("programmeId", &mut self.programme_id, programme_id)?;
+        bind_path_id("campaignId", &mut self.campaign_id, campaign_id)?;
+
+        Ok(())
+    }
+}
+
+pub struct UpdateCampaignCommandHandler;
+
+impl UpdateCampaignCommandHandler {
+    #[tracing::instrument(name = "handle_update_campaign", skip(campaigns, command), fields(programme_id=command.programme_id, campaign_id=command.campaign_id))]
+    pub async fn handle<C: Campaigns>(
+        campaigns: &C,
+        command: UpdateCampaignCommand,
+    ) -> anyhow::Result<CampaignDto, LoyaltyErrors> {
+        let mut campaign = campaigns
+            .retrieve_campaign(&command.programme_id, &command.campaign_id)
+            .await?;
+
+        campaign.update(
+            command.name,
+            command.starts_at,
+            command.ends_at,
+            command.eligibility,
+            command.reward,
+            command.budget_cap,
+        )?;
+
+        campaigns.save_campaign(&campaign).await?;
+
+        Ok(campaign.into())
+    }
+}
+
+pub struct DeleteCampaignCommandHandler;
+
+impl DeleteCampaignCommandHandler {
+    #[tracing::instrument(name = "handle_delete_campaign", skip(campaigns))]
+    pub async fn handle<C: Campaigns>(
+        campaigns: &C,
+        programme_id: String,
+        campaign_id: String,
+    ) -> anyhow::Result<(), LoyaltyErrors> {
+        campaigns
+            .retrieve_campaign(&programme_id, &campaign_id)
+            .await?;
+
+        campaigns.delete_campaign(&programme_id, &campaign_id).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Duration;
+
+    use crate::campaigns::MockCampaigns;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn on_valid_command_campaign_should_be_created() {
+        let mut campaigns = MockCampaigns::new();
+        campaigns
+            .expect_save_campaign()
+            .times(1)
+            .returning(|_| Ok(()));
+
+        let command = CreateCampaignCommand {
+            programme_id: "default".to_string(),
+            name: "3x points on orders over £100".to_string(),
+            starts_at: Utc::now(),
+            ends_at: Utc::now() + Duration::days(2),
+            eligibility: CampaignEligibility {
+                min_order_value: Some(100.0),
+                customer_ids: vec![],
+            },
+            reward: CampaignReward::Multiplier(3.0),
+            budget_cap: Some(10000.0),
+        };
+
+        let result = CreateCampaignCommandHandler::handle(&campaigns, command).await;
+
+        let campaign = result.unwrap();
+
+        assert!(campaign.campaign_id.starts_with("CMP-"));
+        assert_eq!(campaign.points_awarded, 0.0);
+    }
+
+    #[tokio::test]
+    async fn on_update_for_unknown_campaign_should_error() {
+        let mut campaigns = MockCampaigns::new();
+        campaigns
+            .expect_retrieve_campaign()
+            .times(1)
+            .returning(|_, id| Err(LoyaltyErrors::CampaignNotFound(id.to_string())));
+        campaigns.expect_save_campaign().times(0);
+
+        let command = UpdateCampaignCommand {
+            programme_id: "default".to_string(),
+            campaign_id: "CMP-1".to_string(),
+            name: "Double points weekend".to_string(),
+            starts_at: Utc::now(),
+            ends_at: Utc::now() + Duration::days(2),
+            eligibility: CampaignEligibility::default(),
+            reward: CampaignReward::Multiplier(2.0),
+            budget_cap: None,
+        };
+
+        let result = UpdateCampaignCommandHandler::handle(&campaigns, command).await;
+
+        assert!(matches!(result, Err(LoyaltyErrors::CampaignNotFound(_))));
+    }
+}
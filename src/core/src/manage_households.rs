@@ -0,0 +1,252 @@
+use chrono::Utc;
+use serde::Deserialize;
+
+use crate::{
+    households::{Household, HouseholdDto, HouseholdRole, Households},
+    loyalty::{retrieve_active_account, LoyaltyErrors, LoyaltyPoints},
+    validation::bind_path_id,
+};
+
+/// Customers can only belong to one household at a time.
+async fn ensure_not_in_household<H: Households>(
+    households: &H,
+    programme_id: &str,
+    customer_id: &str,
+) -> Result<(), LoyaltyErrors> {
+    match households
+        .household_for_member(programme_id, customer_id)
+        .await
+    {
+        Ok(household) => Err(LoyaltyErrors::InvalidValues(format!(
+            "{} already belongs to household {}",
+            customer_id,
+            household.household_id()
+        ))),
+        Err(LoyaltyErrors::HouseholdNotFound(_)) => Ok(()),
+        Err(e) => Err(e),
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateHouseholdCommand {
+    #[serde(default)]
+    programme_id: String,
+    name: String,
+    owner_id: String,
+}
+
+impl CreateHouseholdCommand {
+    pub fn bind_path(&mut self, programme_id: String) -> Result<(), LoyaltyErrors> {
+        bind_path_id("programmeId", &mut self.programme_id, programme_id)?;
+
+        Ok(())
+    }
+}
+
+pub struct CreateHouseholdCommandHandler;
+
+impl CreateHouseholdCommandHandler {
+    #[tracing::instrument(name = "handle_create_household", skip(loyalty_points, households, command), fields(programme_id=command.programme_id, owner_id=command.owner_id))]
+    pub async fn handle<T: LoyaltyPoints, H: Households>(
+        loyalty_points: &T,
+        households: &H,
+        command: CreateHouseholdCommand,
+    ) -> anyhow::Result<HouseholdDto, LoyaltyErrors> {
+        let owner =
+            retrieve_active_account(loyalty_points, &command.programme_id, &command.owner_id)
+                .await?;
+
+        ensure_not_in_household(households, owner.programme_id(), owner.customer_id()).await?;
+
+        let household = Household::new(
+            owner.programme_id().to_string(),
+            command.name,
+            owner.customer_id().to_string(),
+            Utc::now(),
+        )?;
+
+        households.create_household(&household).await?;
+
+        Ok(household.into())
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AddHouseholdMemberCommand {
+    #[serde(default)]
+    programme_id: String,
+    #[serde(default)]
+    household_id: String,
+    customer_id: String,
+    #[serde(default)]
+    role: Option<HouseholdRole>,
+    spend_limit: Option<f32>,
+}
+
+impl AddHouseholdMemberCommand {
+    pub fn bind_path(
+        &mut self,
+        programme_id: String,
+        household_id: String,
+    ) -> Result<(), LoyaltyErrors> {
+        bind_path_id("programmeId", &mut self.programme_id, programme_id)?;
+        bind_path_id("householdId", &mut self.household_id, household_id)?;
+
+        Ok(())
+    }
+}
+
+pub struct AddHouseholdMemberCommandHandler;
+
+impl AddHouseholdMemberCommandHandler {
+    #[tracing::instrument(name = "handle_add_household_member", skip(loyalty_points, households, command), fields(programme_id=command.programme_id, household_id=command.household_id, customer_id=command.customer_id))]
+    pub async fn handle<T: LoyaltyPoints, H: Households>(
+        loyalty_points: &T,
+        households: &H,
+        command: AddHouseholdMemberCommand,
+    ) -> anyhow::Result<HouseholdDto, LoyaltyErrors> {
+        let mut household = households
+            .retrieve_household(&command.programme_id, &command.household_id)
+            .await?;
+
+        let account = retrieve_active_account(
+            loyalty_points,
+            household.programme_id(),
+            &command.customer_id,
+        )
+        .await?;
+
+        ensure_not_in_household(households, account.programme_id(), account.customer_id()).await?;
+
+        let member = household.add_member(
+            account.customer_id().to_string(),
+            command.role.unwrap_or(HouseholdRole::Member),
+            command.spend_limit,
+        )?;
+
+        households
+            .add_member(household.household_id(), &member)
+            .await?;
+
+        Ok(household.into())
+    }
+}
+
+pub struct RemoveHouseholdMemberCommandHandler;
+
+impl RemoveHouseholdMemberCommandHandler {
+    #[tracing::instrument(name = "handle_remove_household_member", skip(households))]
+    pub async fn handle<H: Households>(
+        households: &H,
+        programme_id: String,
+        household_id: String,
+        customer_id: String,
+    ) -> anyhow::Result<HouseholdDto, LoyaltyErrors> {
+        let mut household = households
+            .retrieve_household(&programme_id, &household_id)
+            .await?;
+
+        household.remove_member(&customer_id)?;
+
+        households
+            .remove_member(household.household_id(), &customer_id)
+            .await?;
+
+        Ok(household.into())
+    }
+}
+
+pub struct RetrieveHouseholdQueryHandler;
+
+impl RetrieveHouseholdQueryHandler {
+    #[tracing::instrument(name = "handle_retrieve_household", skip(households))]
+    pub async fn handle<H: Households>(
+        households: &H,
+        programme_id: String,
+        household_id: String,
+    ) -> anyhow::Result<HouseholdDto, LoyaltyErrors> {
+        let household = households
+            .retrieve_household(&programme_id, &household_id)
+            .await?;
+
+        Ok(household.into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        households::{HouseholdMember, MockHouseholds},
+        loyalty::{LoyaltyAccount, MockLoyaltyPoints},
+        programmes::DEFAULT_PROGRAMME_ID,
+    };
+
+    use super::*;
+
+    #[tokio::test]
+    async fn customer_already_in_a_household_cannot_join_another() {
+        let mut loyalty_points = MockLoyaltyPoints::new();
+        loyalty_points
+            .expect_retrieve()
+            .returning(|programme_id, customer_id| {
+                LoyaltyAccount::from(
+                    programme_id.to_string(),
+                    customer_id.to_string(),
+                    0.0,
+                    vec![],
+                )
+            });
+
+        let mut households = MockHouseholds::new();
+        households
+            .expect_retrieve_household()
+            .returning(|programme_id, household_id| {
+                Ok(Household::from(
+                    programme_id.to_string(),
+                    household_id.to_string(),
+                    "Smiths".to_string(),
+                    0.0,
+                    vec![HouseholdMember::from(
+                        "alice".to_string(),
+                        HouseholdRole::Owner,
+                        None,
+                        0.0,
+                    )],
+                    Utc::now(),
+                ))
+            });
+        households
+            .expect_household_for_member()
+            .returning(|programme_id, customer_id| {
+                Ok(Household::from(
+                    programme_id.to_string(),
+                    "HH-OTHER".to_string(),
+                    "Joneses".to_string(),
+                    0.0,
+                    vec![HouseholdMember::from(
+                        customer_id.to_string(),
+                        HouseholdRole::Owner,
+                        None,
+                        0.0,
+                    )],
+                    Utc::now(),
+                ))
+            });
+        households.expect_add_member().times(0);
+
+        let command = AddHouseholdMemberCommand {
+            programme_id: DEFAULT_PROGRAMME_ID.to_string(),
+            household_id: "HH-1".to_string(),
+            customer_id: "james".to_string(),
+            role: None,
+            spend_limit: None,
+        };
+
+        let result =
+            AddHouseholdMemberCommandHandler::handle(&loyalty_points, &households, command).await;
+
+        assert!(matches!(result, Err(LoyaltyErrors::InvalidValues(_))));
+    }
+}
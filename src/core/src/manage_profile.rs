@@ -0,0 +1,78 @@
+use chrono::NaiveDate;
+use serde::Deserialize;
+
+use crate::{
+    loyalty::LoyaltyErrors,
+    profiles::{CustomerProfileDto, CustomerProfiles},
+    validation::bind_path_id,
+};
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UpdateCustomerProfileCommand {
+    #[serde(default)]
+    programme_id: String,
+    #[serde(default)]
+    customer_id: String,
+    display_name: String,
+    #[serde(default)]
+    email: String,
+    birthday: Option<NaiveDate>,
+    #[serde(default)]
+    marketing_opt_in: bool,
+}
+
+impl UpdateCustomerProfileCommand {
+    pub fn bind_path(
+        &mut self,
+        programme_id: String,
+        customer_id: String,
+    ) -> Result<(), LoyaltyErrors> {
+        bind_path_id("programmeId", &mut self.programme_id, programme_id)?;
+        bind_path_id("customerId", &mut self.customer_id, customer_id)?;
+
+        Ok(())
+    }
+}
+
+pub struct UpdateCustomerProfileCommandHandler;
+
+impl UpdateCustomerProfileCommandHandler {
+    #[tracing::instrument(name = "handle_update_customer_profile", skip(profiles, command), fields(programme_id=command.programme_id, customer_id=command.customer_id))]
+    pub async fn handle<P: CustomerProfiles>(
+        profiles: &P,
+        command: UpdateCustomerProfileCommand,
+    ) -> anyhow::Result<CustomerProfileDto, LoyaltyErrors> {
+        let mut profile = profiles
+            .retrieve_profile(&command.programme_id, &command.customer_id)
+            .await?;
+
+        profile.update(
+            command.display_name,
+            command.email,
+            command.birthday,
+            command.marketing_opt_in,
+        )?;
+
+        profiles.save_profile(&profile).await?;
+
+        Ok(profile.into())
+    }
+}
+
+pub struct RetrieveCustomerProfileQueryHandler;
+
+impl RetrieveCustomerProfileQueryHandler {
+    #[tracing::instrument(name = "handle_retrieve_customer_profile", skip(profiles))]
+    pub async fn handle<P: CustomerProfiles>(
+        profiles: &P,
+        programme_id: String,
+        customer_id: String,
+    ) -> anyhow::Result<CustomerProfileDto, LoyaltyErrors> {
+        let profile = profiles
+            .retrieve_profile(&programme_id, &customer_id)
+            .await?;
+
+        Ok(profile.into())
+    }
+}
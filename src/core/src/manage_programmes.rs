@@ -0,0 +1,143 @@
+use chrono::Utc;
+use serde::Deserialize;
+
+use crate::{
+    loyalty::LoyaltyErrors,
+    programmes::{Programme, ProgrammeDto, Programmes},
+    validation::bind_path_id,
+};
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateProgrammeCommand {
+    programme_id: String,
+    name: String,
+    earn_rate: f32,
+    base_currency: String,
+}
+
+pub struct CreateProgrammeCommandHandler;
+
+impl CreateProgrammeCommandHandler {
+    #[tracing::instrument(name = "handle_create_programme", skip(programmes, command), fields(programme_id=command.programme_id))]
+    pub async fn handle<P: Programmes>(
+        programmes: &P,
+        command: CreateProgrammeCommand,
+    ) -> anyhow::Result<ProgrammeDto, LoyaltyErrors> {
+        match programmes.retrieve_programme(&command.programme_id).await {
+            Ok(_) => {
+                return Err(LoyaltyErrors::InvalidValues(format!(
+                    "Programme {} already exists",
+                    command.programme_id
+                )))
+            }
+            Err(LoyaltyErrors::ProgrammeNotFound(_)) => {}
+            Err(e) => return Err(e),
+        }
+
+        let programme = Programme::new(
+            command.programme_id,
+            command.name,
+            command.earn_rate,
+            command.base_currency,
+            Utc::now(),
+        )?;
+
+        programmes.save_programme(&programme).await?;
+
+        Ok(programme.into())
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UpdateProgrammeCommand {
+    #[serde(default)]
+    programme_id: String,
+    name: String,
+    earn_rate: f32,
+}
+
+impl UpdateProgrammeCommand {
+    pub fn bind_path(&mut self, programme_id: String) -> Result<(), LoyaltyErrors> {
+        bind_path_id("programmeId", &mut self.programme_id, programme_id)?;
+
+        Ok(())
+    }
+}
+
+pub struct UpdateProgrammeCommandHandler;
+
+impl UpdateProgrammeCommandHandler {
+    #[tracing::instrument(name = "handle_update_programme", skip(programmes, command), fields(programme_id=command.programme_id))]
+    pub async fn handle<P: Programmes>(
+        programmes: &P,
+        command: UpdateProgrammeCommand,
+    ) -> anyhow::Result<ProgrammeDto, LoyaltyErrors> {
+        let mut programme = programmes.retrieve_programme(&command.programme_id).await?;
+
+        programme.update(command.name, command.earn_rate)?;
+
+        programmes.save_programme(&programme).await?;
+
+        Ok(programme.into())
+    }
+}
+
+pub struct RetrieveProgrammeQueryHandler;
+
+impl RetrieveProgrammeQueryHandler {
+    #[tracing::instrument(name = "handle_retrieve_programme", skip(programmes))]
+    pub async fn handle<P: Programmes>(
+        programmes: &P,
+        programme_id: String,
+    ) -> anyhow::Result<ProgrammeDto, LoyaltyErrors> {
+        let programme = programmes.retrieve_programme(&programme_id).await?;
+
+        Ok(programme.into())
+    }
+}
+
+pub struct ListProgrammesQueryHandler;
+
+impl ListProgrammesQueryHandler {
+    #[tracing::instrument(name = "handle_list_programmes", skip(programmes))]
+    pub async fn handle<P: Programmes>(
+        programmes: &P,
+    ) -> anyhow::Result<Vec<ProgrammeDto>, LoyaltyErrors> {
+        let programmes = programmes.list_programmes().await?;
+
+        Ok(programmes
+            .into_iter()
+            .map(|programme| programme.into())
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::programmes::MockProgrammes;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn existing_programme_should_not_be_overwritten() {
+        let mut programmes = MockProgrammes::new();
+        programmes
+            .expect_retrieve_programme()
+            .times(1)
+            .returning(|_| Ok(Programme::default()));
+        programmes.expect_save_programme().times(0);
+
+        let command = CreateProgrammeCommand {
+            programme_id: "default".to_string(),
+            name: "Default".to_string(),
+            earn_rate: 1.0,
+            base_currency: "USD".to_string(),
+        };
+
+        let result = CreateProgrammeCommandHandler::handle(&programmes, command).await;
+
+        assert!(matches!(result, Err(LoyaltyErrors::InvalidValues(_))));
+    }
+}
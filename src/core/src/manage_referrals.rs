@@ -0,0 +1,92 @@
+use crate::{
+    loyalty::{retrieve_active_account, LoyaltyErrors, LoyaltyPoints},
+    referrals::{new_referral_code, ReferralCodeDto, ReferralDto, Referrals},
+};
+
+pub struct RetrieveReferralCodeQueryHandler;
+
+impl RetrieveReferralCodeQueryHandler {
+    /// Codes are assigned the first time a customer asks for one.
+    #[tracing::instrument(name = "handle_retrieve_referral_code", skip(loyalty_points, referrals))]
+    pub async fn handle<T: LoyaltyPoints, R: Referrals>(
+        loyalty_points: &T,
+        referrals: &R,
+        programme_id: String,
+        customer_id: String,
+    ) -> anyhow::Result<ReferralCodeDto, LoyaltyErrors> {
+        let account = retrieve_active_account(loyalty_points, &programme_id, &customer_id).await?;
+
+        let referral_code = referrals
+            .assign_referral_code(
+                account.programme_id(),
+                account.customer_id(),
+                &new_referral_code(),
+            )
+            .await?;
+
+        Ok(ReferralCodeDto {
+            programme_id: account.programme_id().to_string(),
+            customer_id: account.customer_id().to_string(),
+            referral_code,
+        })
+    }
+}
+
+pub struct ListReferralsQueryHandler;
+
+impl ListReferralsQueryHandler {
+    #[tracing::instrument(name = "handle_list_referrals", skip(referrals))]
+    pub async fn handle<R: Referrals>(
+        referrals: &R,
+        programme_id: String,
+        customer_id: String,
+    ) -> anyhow::Result<Vec<ReferralDto>, LoyaltyErrors> {
+        let referrals = referrals
+            .list_referrals(&programme_id, &customer_id)
+            .await?;
+
+        Ok(referrals.into_iter().map(|referral| referral.into()).collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        loyalty::MockLoyaltyPoints, programmes::DEFAULT_PROGRAMME_ID, referrals::MockReferrals,
+        LoyaltyAccount,
+    };
+
+    use super::*;
+
+    #[tokio::test]
+    async fn existing_code_should_be_returned() {
+        let mut loyalty_points = MockLoyaltyPoints::new();
+        loyalty_points
+            .expect_retrieve()
+            .returning(|programme_id, customer_id| {
+                LoyaltyAccount::from(
+                    programme_id.to_string(),
+                    customer_id.to_string(),
+                    0.0,
+                    vec![],
+                )
+            });
+
+        let mut referrals = MockReferrals::new();
+        referrals
+            .expect_assign_referral_code()
+            .times(1)
+            .returning(|_, _, _| Ok("REF-EXISTING".to_string()));
+
+        let result = RetrieveReferralCodeQueryHandler::handle(
+            &loyalty_points,
+            &referrals,
+            DEFAULT_PROGRAMME_ID.to_string(),
+            "james".to_string(),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(result.referral_code, "REF-EXISTING");
+    }
+}
@@ -0,0 +1,154 @@
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+
+use crate::{
+    loyalty::LoyaltyErrors,
+    rewards::{Reward, RewardDto, Rewards},
+    validation::bind_path_id,
+};
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateRewardCommand {
+    #[serde(default)]
+    programme_id: String,
+    name: String,
+    #[serde(default)]
+    description: String,
+    points_cost: f32,
+    stock: i32,
+    available_from: Option<DateTime<Utc>>,
+    available_until: Option<DateTime<Utc>>,
+}
+
+impl CreateRewardCommand {
+    pub fn bind_path(&mut self, programme_id: String) -> Result<(), LoyaltyErrors> {
+        bind_path_id("programmeId", &mut self.programme_id, programme_id)?;
+
+        Ok(())
+    }
+}
+
+pub struct CreateRewardCommandHandler;
+
+impl CreateRewardCommandHandler {
+    #[tracing::instrument(name = "handle_create_reward", skip(rewards, command), fields(programme_id=command.programme_id, name=command.name, points_cost=command.points_cost))]
+    pub async fn handle<R: Rewards>(
+        rewards: &R,
+        command: CreateRewardCommand,
+    ) -> anyhow::Result<RewardDto, LoyaltyErrors> {
+        let reward = Reward::new(
+            command.programme_id,
+            command.name,
+            command.description,
+            command.points_cost,
+            command.stock,
+            command.available_from,
+            command.available_until,
+        )?;
+
+        rewards.save_reward(&reward).await?;
+
+        Ok(reward.into())
+    }
+}
+
+pub struct ListRewardsQueryHandler;
+
+impl ListRewardsQueryHandler {
+    /// Lists the rewards a customer could redeem right now, hiding anything out of stock or
+    /// outside its availability window.
+    #[tracing::instrument(name = "handle_list_rewards", skip(rewards))]
+    pub async fn handle<R: Rewards>(
+        rewards: &R,
+        programme_id: String,
+    ) -> anyhow::Result<Vec<RewardDto>, LoyaltyErrors> {
+        let now = Utc::now();
+
+        let rewards = rewards.list_rewards(&programme_id).await?;
+
+        Ok(rewards
+            .into_iter()
+            .filter(|reward| reward.ensure_available(now).is_ok())
+            .map(|reward| reward.into())
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Duration;
+
+    use crate::{programmes::DEFAULT_PROGRAMME_ID, rewards::MockRewards};
+
+    use super::*;
+
+    #[tokio::test]
+    async fn on_invalid_cost_reward_should_not_be_created() {
+        let mut rewards = MockRewards::new();
+        rewards.expect_save_reward().times(0);
+
+        let command = CreateRewardCommand {
+            programme_id: DEFAULT_PROGRAMME_ID.to_string(),
+            name: "Coffee".to_string(),
+            description: "".to_string(),
+            points_cost: 0.0,
+            stock: 10,
+            available_from: None,
+            available_until: None,
+        };
+
+        let result = CreateRewardCommandHandler::handle(&rewards, command).await;
+
+        assert!(matches!(result, Err(LoyaltyErrors::InvalidValues(_))));
+    }
+
+    #[tokio::test]
+    async fn catalogue_should_only_list_available_rewards() {
+        let mut rewards = MockRewards::new();
+        rewards
+            .expect_list_rewards()
+            .times(1)
+            .returning(|programme_id| {
+                Ok(vec![
+                    Reward::from(
+                        programme_id.to_string(),
+                        "RWD-1".to_string(),
+                        "Coffee".to_string(),
+                        "".to_string(),
+                        50.0,
+                        10,
+                        None,
+                        None,
+                    ),
+                    Reward::from(
+                        programme_id.to_string(),
+                        "RWD-2".to_string(),
+                        "Mug".to_string(),
+                        "".to_string(),
+                        500.0,
+                        0,
+                        None,
+                        None,
+                    ),
+                    Reward::from(
+                        programme_id.to_string(),
+                        "RWD-3".to_string(),
+                        "Advent calendar".to_string(),
+                        "".to_string(),
+                        800.0,
+                        10,
+                        None,
+                        Some(Utc::now() - Duration::days(1)),
+                    ),
+                ])
+            });
+
+        let result = ListRewardsQueryHandler::handle(&rewards, DEFAULT_PROGRAMME_ID.to_string())
+            .await
+            .unwrap();
+
+        assert_eq!(result.len(), 1);
+        assert_eq!(result[0].reward_id, "RWD-1");
+    }
+}
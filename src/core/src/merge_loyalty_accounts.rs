@@ -0,0 +1,120 @@
+use serde::Deserialize;
+
+use crate::{
+    loyalty::{retrieve_active_account, LoyaltyErrors, LoyaltyPoints},
+    validation::bind_path_id,
+    LoyaltyDto,
+};
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MergeLoyaltyAccountsCommand {
+    #[serde(default)]
+    programme_id: String,
+    source_customer_id: String,
+    target_customer_id: String,
+}
+
+impl MergeLoyaltyAccountsCommand {
+    pub fn bind_path(&mut self, programme_id: String) -> Result<(), LoyaltyErrors> {
+        bind_path_id("programmeId", &mut self.programme_id, programme_id)?;
+
+        Ok(())
+    }
+}
+
+pub struct MergeLoyaltyAccountsCommandHandler;
+
+impl MergeLoyaltyAccountsCommandHandler {
+    #[tracing::instrument(name = "handle_merge_loyalty_accounts", skip(loyalty_points, command), fields(programme_id=command.programme_id, source_customer_id=command.source_customer_id, target_customer_id=command.target_customer_id))]
+    pub async fn handle<T: LoyaltyPoints>(
+        loyalty_points: &T,
+        command: MergeLoyaltyAccountsCommand,
+    ) -> anyhow::Result<LoyaltyDto, LoyaltyErrors> {
+        let mut source = loyalty_points
+            .retrieve(&command.programme_id, &command.source_customer_id)
+            .await?;
+        let mut target = retrieve_active_account(
+            loyalty_points,
+            &command.programme_id,
+            &command.target_customer_id,
+        )
+        .await?;
+
+        source.merge_into(&mut target)?;
+
+        loyalty_points.merge_accounts(&source, &target).await?;
+
+        Ok(target.into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        loyalty::{LoyaltyAccount, MockLoyaltyPoints},
+        programmes::DEFAULT_PROGRAMME_ID,
+    };
+
+    use super::*;
+
+    #[tokio::test]
+    async fn on_valid_command_accounts_should_be_merged() {
+        let mut loyalty_points = MockLoyaltyPoints::new();
+        loyalty_points
+            .expect_retrieve()
+            .times(2)
+            .returning(|programme_id, customer_id| {
+                LoyaltyAccount::from(
+                    programme_id.to_string(),
+                    customer_id.to_string(),
+                    10.0,
+                    vec![],
+                )
+            });
+        loyalty_points
+            .expect_merge_accounts()
+            .times(1)
+            .returning(|_, _| Ok(()));
+
+        let command = MergeLoyaltyAccountsCommand {
+            programme_id: DEFAULT_PROGRAMME_ID.to_string(),
+            source_customer_id: "james".to_string(),
+            target_customer_id: "jim".to_string(),
+        };
+
+        let result = MergeLoyaltyAccountsCommandHandler::handle(&loyalty_points, command).await;
+
+        let account = result.unwrap();
+
+        assert_eq!(account.customer_id, "jim");
+        assert_eq!(account.current_points, 20.0);
+    }
+
+    #[tokio::test]
+    async fn on_command_with_same_customer_should_error() {
+        let mut loyalty_points = MockLoyaltyPoints::new();
+        loyalty_points
+            .expect_retrieve()
+            .times(2)
+            .returning(|programme_id, customer_id| {
+                LoyaltyAccount::from(
+                    programme_id.to_string(),
+                    customer_id.to_string(),
+                    10.0,
+                    vec![],
+                )
+            });
+        loyalty_points.expect_merge_accounts().times(0);
+
+        let command = MergeLoyaltyAccountsCommand {
+            programme_id: DEFAULT_PROGRAMME_ID.to_string(),
+            source_customer_id: "james".to_string(),
+            target_customer_id: "james".to_string(),
+        };
+
+        let result = MergeLoyaltyAccountsCommandHandler::handle(&loyalty_points, command).await;
+
+        assert!(result.is_err());
+    }
+}
@@ -1,66 +1,701 @@
-use serde::Deserialize;
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
 use tracing::info;
 
-use crate::loyalty::LoyaltyPoints;
+use crate::{
+    campaigns::{campaign_bonuses, Campaigns},
+    clock::Clock,
+    exchange_rates::{ExchangeRates, OrderValue},
+    households::Households,
+    loyalty::{
+        retrieve_active_account, LoyaltyAccount, LoyaltyAccountTransaction, LoyaltyErrors,
+        LoyaltyPoints,
+    },
+    profiles::BonusPolicy,
+    programmes::{Programme, Programmes, DEFAULT_PROGRAMME_ID},
+    referrals::{ReferralStatus, Referrals},
+    validation::ValidationError,
+};
 
-#[derive(Deserialize)]
+fn default_programme_id() -> String {
+    DEFAULT_PROGRAMME_ID.to_string()
+}
+
+#[derive(Deserialize, Serialize)]
 pub struct OrderConfirmed {
+    /// Events published before programmes were introduced belong to the default programme.
+    #[serde(default = "default_programme_id")]
+    programme_id: String,
     customer_id: String,
     order_id: String,
     order_value: f32,
+    /// ISO 4217 code the order was charged in, orders without one are in the programme's base
+    /// currency.
+    #[serde(default)]
+    currency: Option<String>,
+    /// When the order was placed. Events without one are treated as happening when processed.
+    #[serde(default)]
+    occurred_at: Option<DateTime<Utc>>,
+}
+
+impl OrderConfirmed {
+    pub fn programme_id(&self) -> &str {
+        &self.programme_id
+    }
+
+    /// Checks every field, reporting all that are invalid rather than stopping at the first.
+    pub fn validate(&self) -> Result<(), ValidationError> {
+        let mut result = ValidationError::default();
+
+        result.check(
+            !self.programme_id.trim().is_empty(),
+            "programme_id",
+            "must not be empty",
+        );
+        result.check(
+            !self.customer_id.trim().is_empty(),
+            "customer_id",
+            "must not be empty",
+        );
+        result.check(
+            !self.order_id.trim().is_empty(),
+            "order_id",
+            "must not be empty",
+        );
+        result.check(
+            self.order_value.is_finite() && self.order_value > 0.0,
+            "order_value",
+            "must be a finite number greater than 0",
+        );
+
+        if let Some(currency) = &self.currency {
+            result.check(
+                currency.len() == 3 && currency.chars().all(|c| c.is_ascii_uppercase()),
+                "currency",
+                "must be an ISO 4217 currency code",
+            );
+        }
+
+        result.into_result()
+    }
+}
+
+/// Points earned from an order stay pending for `vesting_days` so that returns can cancel them
+/// before they are spent.
+#[derive(Clone, Debug)]
+pub struct VestingPolicy {
+    vesting_days: i64,
+}
+
+impl VestingPolicy {
+    pub fn new(vesting_days: i64) -> Self {
+        Self { vesting_days }
+    }
+
+    pub(crate) fn vests_at(&self, earned_at: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        match self.vesting_days {
+            days if days > 0 => Some(earned_at + Duration::days(days)),
+            _ => None,
+        }
+    }
+}
+
+impl Default for VestingPolicy {
+    fn default() -> Self {
+        Self::new(14)
+    }
 }
 
 pub struct OrderConfirmedEventHandler {}
 
 impl OrderConfirmedEventHandler {
-    #[tracing::instrument(name = "handle_order_confirmed",skip(loyalty_points, evt), fields(customer_id=evt.customer_id, order_id=evt.order_id, order_value=evt.order_value))]
-    pub async fn handle<T: LoyaltyPoints>(loyalty_points: &T, evt: &OrderConfirmed) -> Result<(), ()> {
+    #[allow(clippy::too_many_arguments)]
+    #[tracing::instrument(name = "handle_order_confirmed",skip(loyalty_points, programmes, campaigns, referrals, households, exchange_rates, vesting, bonuses, clock, evt), fields(programme_id=evt.programme_id, customer_id=evt.customer_id, order_id=evt.order_id, order_value=evt.order_value, currency=evt.currency, occurred_at=?evt.occurred_at))]
+    pub async fn handle<
+        T: LoyaltyPoints,
+        P: Programmes,
+        C: Campaigns,
+        R: Referrals,
+        H: Households,
+        X: ExchangeRates + ?Sized,
+        K: Clock,
+    >(
+        loyalty_points: &T,
+        programmes: &P,
+        campaigns: &C,
+        referrals: &R,
+        households: &H,
+        exchange_rates: &X,
+        vesting: &VestingPolicy,
+        bonuses: &BonusPolicy,
+        clock: &K,
+        evt: &OrderConfirmed,
+    ) -> Result<(), LoyaltyErrors> {
         info!(
             "Processing message for customer {} with id {} and value {}",
             evt.customer_id, evt.order_id, evt.order_value
         );
 
-        let existing_account = loyalty_points.retrieve(&evt.customer_id).await;
+        evt.validate().map_err(|e| {
+            tracing::error!("Invalid 'OrderConfirmed' event: {}", e);
+            LoyaltyErrors::InvalidValues(e.to_string())
+        })?;
 
-        let mut account = match existing_account {
-            Ok(account) => {
-                info!("Existing loyalty account found");
+        let recorded_at = clock.now();
 
-                account
-            }
-            Err(e) => match e {
-                crate::loyalty::LoyaltyErrors::AccountNotFound() => {
-                        loyalty_points
-                        .new_account(evt.customer_id.clone())
-                        .await
-                        .map_err(|e| {
-                            tracing::error!("Failure creating new account: {:?}", e);
+        let programme = programmes
+            .retrieve_programme(&evt.programme_id)
+            .await
+            .map_err(|e| {
+                tracing::error!("Failure retrieving programme: {:?}", e);
+                e
+            })?;
+
+        let (order_value, normalised_value) = Self::order_value(evt, &programme, exchange_rates)
+            .map_err(|e| {
+                tracing::error!("Failure converting order value: {:?}", e);
+                e
+            })?;
+
+        let (mut account, mut opening) =
+            Self::open_account(loyalty_points, bonuses, evt, recorded_at).await?;
 
-                            
-                        })?
+        if account.can_earn(&evt.order_id).is_ok() {
+            let household_id = Self::household_for(households, &account).await?;
+
+            let transaction = Self::earn(
+                campaigns,
+                vesting,
+                &programme,
+                &mut account,
+                evt,
+                &order_value,
+                normalised_value,
+                household_id.is_some(),
+                recorded_at,
+            )
+            .await?;
+
+            match (transaction, household_id) {
+                (Some(transaction), Some(household_id)) => {
+                    // The member's account has to exist before it contributes to the household
+                    if let Some(opening) = opening.take() {
+                        Self::save_account(loyalty_points, &account, opening).await?;
+                    }
+
+                    Self::contribute(households, &household_id, &account, transaction).await?
                 }
-                crate::loyalty::LoyaltyErrors::InvalidValues(e)
-                | crate::loyalty::LoyaltyErrors::PointsNotAvailable(e)
-                | crate::loyalty::LoyaltyErrors::TransactionExistsForOrder(e)
-                | crate::loyalty::LoyaltyErrors::DatabaseError(e) => {
-                    tracing::error!("Failure retrieving account from database: {:?}", e);
+                (Some(transaction), None) => match &mut opening {
+                    Some(opening) => opening.push(transaction),
+                    None => {
+                        loyalty_points
+                            .add_transaction(&account, transaction)
+                            .await?
+                    }
+                },
+                (None, _) => {}
+            }
+        }
+
+        // A new account is saved with its signup bonus and first order in a single write
+        if let Some(opening) = opening {
+            Self::save_account(loyalty_points, &account, opening).await?;
+        }
+
+        Self::reward_referral(
+            loyalty_points,
+            referrals,
+            bonuses,
+            &mut account,
+            recorded_at,
+        )
+        .await
+    }
+
+    /// Handles a batch of events, loading each customer's account once and writing all of the
+    /// points they earn in a single write. Results are in the same order as `events`, a failure
+    /// only fails the events for that customer.
+    #[allow(clippy::too_many_arguments)]
+    #[tracing::instrument(name = "handle_order_confirmed_batch",skip(loyalty_points, programmes, campaigns, referrals, households, exchange_rates, vesting, bonuses, clock, events), fields(events=events.len()))]
+    pub async fn handle_batch<
+        T: LoyaltyPoints,
+        P: Programmes,
+        C: Campaigns,
+        R: Referrals,
+        H: Households,
+        X: ExchangeRates + ?Sized,
+        K: Clock,
+    >(
+        loyalty_points: &T,
+        programmes: &P,
+        campaigns: &C,
+        referrals: &R,
+        households: &H,
+        exchange_rates: &X,
+        vesting: &VestingPolicy,
+        bonuses: &BonusPolicy,
+        clock: &K,
+        events: &[&OrderConfirmed],
+    ) -> Vec<Result<(), LoyaltyErrors>> {
+        let mut results: Vec<Result<(), LoyaltyErrors>> = events.iter().map(|_| Ok(())).collect();
+        let mut customers: Vec<(&str, &str, Vec<usize>)> = vec![];
+
+        for (index, evt) in events.iter().enumerate() {
+            if let Err(e) = evt.validate() {
+                tracing::error!("Invalid 'OrderConfirmed' event: {}", e);
+                results[index] = Err(LoyaltyErrors::InvalidValues(e.to_string()));
+                continue;
+            }
+
+            let customer = customers.iter_mut().find(|(programme_id, customer_id, _)| {
+                *programme_id == evt.programme_id && *customer_id == evt.customer_id
+            });
+
+            match customer {
+                Some((_, _, indexes)) => indexes.push(index),
+                None => customers.push((&evt.programme_id, &evt.customer_id, vec![index])),
+            }
+        }
+
+        info!(
+            "Processing {} events for {} customers",
+            events.len(),
+            customers.len()
+        );
+
+        for (_, _, indexes) in customers {
+            let customer_events: Vec<&OrderConfirmed> =
+                indexes.iter().map(|index| events[*index]).collect();
+
+            let customer_results = Self::handle_customer(
+                loyalty_points,
+                programmes,
+                campaigns,
+                referrals,
+                households,
+                exchange_rates,
+                vesting,
+                bonuses,
+                clock,
+                &customer_events,
+            )
+            .await;
+
+            for (index, result) in indexes.into_iter().zip(customer_results) {
+                results[index] = result;
+            }
+        }
+
+        results
+    }
+
+    /// Applies every event for one customer to their account in memory, then writes the points
+    /// earned in one go. Pooled points are still credited to the household one order at a time.
+    #[allow(clippy::too_many_arguments)]
+    async fn handle_customer<
+        T: LoyaltyPoints,
+        P: Programmes,
+        C: Campaigns,
+        R: Referrals,
+        H: Households,
+        X: ExchangeRates + ?Sized,
+        K: Clock,
+    >(
+        loyalty_points: &T,
+        programmes: &P,
+        campaigns: &C,
+        referrals: &R,
+        households: &H,
+        exchange_rates: &X,
+        vesting: &VestingPolicy,
+        bonuses: &BonusPolicy,
+        clock: &K,
+        events: &[&OrderConfirmed],
+    ) -> Vec<Result<(), LoyaltyErrors>> {
+        let fail_all = |e: LoyaltyErrors| events.iter().map(|_| Err(e.clone())).collect();
+
+        let recorded_at = clock.now();
+
+        let programme = match programmes.retrieve_programme(&events[0].programme_id).await {
+            Ok(programme) => programme,
+            Err(e) => {
+                tracing::error!("Failure retrieving programme: {:?}", e);
+
+                return fail_all(e);
+            }
+        };
+
+        let (mut account, opening) =
+            match Self::open_account(loyalty_points, bonuses, events[0], recorded_at).await {
+                Ok(opened) => opened,
+                Err(e) => return fail_all(e),
+            };
 
-                    return Err(());
+        let household_id = match Self::household_for(households, &account).await {
+            Ok(household_id) => household_id,
+            Err(e) => return fail_all(e),
+        };
+
+        // The member's account has to exist before it contributes to the household, otherwise a
+        // new account is saved along with the orders
+        let opening = match (opening, &household_id) {
+            (Some(opening), Some(_)) => {
+                match Self::save_account(loyalty_points, &account, opening).await {
+                    Ok(()) => None,
+                    Err(e) => return fail_all(e),
                 }
-            },
+            }
+            (opening, _) => opening,
         };
 
-        let transaction = account.add_transaction(evt.order_id.clone(), evt.order_value);
+        let is_new = opening.is_some();
 
-        if transaction.is_ok() {
-            let update_res = loyalty_points
-                .add_transaction(&account, transaction.unwrap())
-                .await;
+        let mut results = vec![];
+        let mut transactions = opening.unwrap_or_default();
+        let mut written_together = vec![];
 
-            return match update_res {
-                Ok(_) => Ok(()),
-                Err(_) => Err(()),
+        for evt in events {
+            let earned = Self::earn_order(
+                campaigns,
+                exchange_rates,
+                vesting,
+                &programme,
+                &mut account,
+                evt,
+                household_id.is_some(),
+                recorded_at,
+            )
+            .await;
+
+            let result = match (earned, &household_id) {
+                (Ok(Some(transaction)), Some(household_id)) => {
+                    Self::contribute(households, household_id, &account, transaction).await
+                }
+                (Ok(Some(transaction)), None) => {
+                    transactions.push(transaction);
+                    written_together.push(results.len());
+                    Ok(())
+                }
+                (Ok(None), _) => Ok(()),
+                (Err(e), _) => Err(e),
             };
+
+            results.push(result);
+        }
+
+        if is_new || !transactions.is_empty() {
+            let written = match is_new {
+                true => Self::save_account(loyalty_points, &account, transactions).await,
+                false => {
+                    loyalty_points
+                        .add_transactions(&account, transactions)
+                        .await
+                }
+            };
+
+            if let Err(e) = written {
+                tracing::error!("Failure adding transactions: {:?}", e);
+
+                for index in written_together {
+                    results[index] = Err(e.clone());
+                }
+
+                // The account in memory holds orders that were never saved, so the referral is
+                // left for the retry rather than rewarded from it
+                return results;
+            }
+        }
+
+        // A failed referral reward fails every order so it is picked up when they are retried,
+        // orders that were already earned on are skipped the second time round
+        let referral = Self::reward_referral(
+            loyalty_points,
+            referrals,
+            bonuses,
+            &mut account,
+            recorded_at,
+        )
+        .await;
+
+        if let Err(e) = referral {
+            for result in results.iter_mut().filter(|result| result.is_ok()) {
+                *result = Err(e.clone());
+            }
+        }
+
+        results
+    }
+
+    /// The order's value in the currency it was charged in, and converted to the programme's base
+    /// currency.
+    fn order_value<X: ExchangeRates + ?Sized>(
+        evt: &OrderConfirmed,
+        programme: &Programme,
+        exchange_rates: &X,
+    ) -> Result<(OrderValue, f32), LoyaltyErrors> {
+        let order_value = OrderValue {
+            amount: evt.order_value,
+            currency: evt
+                .currency
+                .clone()
+                .unwrap_or_else(|| programme.base_currency().to_string()),
+        };
+
+        let normalised_value = order_value.normalise(exchange_rates, programme.base_currency())?;
+
+        Ok((order_value, normalised_value))
+    }
+
+    /// Retrieves the customer's account, or opens one with a signup bonus if this is their first
+    /// order. A new account is only held in memory, it is returned with the transactions that
+    /// open it for the caller to save, so it is never saved without them.
+    async fn open_account<T: LoyaltyPoints>(
+        loyalty_points: &T,
+        bonuses: &BonusPolicy,
+        evt: &OrderConfirmed,
+        recorded_at: DateTime<Utc>,
+    ) -> Result<(LoyaltyAccount, Option<Vec<LoyaltyAccountTransaction>>), LoyaltyErrors> {
+        let existing_account =
+            retrieve_active_account(loyalty_points, &evt.programme_id, &evt.customer_id).await;
+
+        match existing_account {
+            Ok(account) => {
+                info!("Existing loyalty account found");
+
+                Ok((account, None))
+            }
+            Err(LoyaltyErrors::AccountNotFound()) => {
+                let mut account =
+                    LoyaltyAccount::new(evt.programme_id.clone(), evt.customer_id.clone())?;
+
+                let signup_bonus = bonuses
+                    .apply_signup_bonus(&mut account, recorded_at)
+                    .map_err(|e| {
+                        tracing::error!("Failure granting signup bonus: {:?}", e);
+                        e
+                    })?;
+
+                Ok((account, Some(signup_bonus.into_iter().collect())))
+            }
+            Err(e) => {
+                tracing::error!("Failure retrieving account from database: {:?}", e);
+
+                Err(e)
+            }
+        }
+    }
+
+    async fn save_account<T: LoyaltyPoints>(
+        loyalty_points: &T,
+        account: &LoyaltyAccount,
+        transactions: Vec<LoyaltyAccountTransaction>,
+    ) -> Result<(), LoyaltyErrors> {
+        loyalty_points
+            .new_account(account, transactions)
+            .await
+            .map_err(|e| {
+                tracing::error!("Failure creating new account: {:?}", e);
+                e
+            })
+    }
+
+    async fn household_for<H: Households>(
+        households: &H,
+        account: &LoyaltyAccount,
+    ) -> Result<Option<String>, LoyaltyErrors> {
+        match households
+            .household_for_member(account.programme_id(), account.customer_id())
+            .await
+        {
+            Ok(household) => Ok(Some(household.household_id().to_string())),
+            Err(LoyaltyErrors::HouseholdNotFound(_)) => Ok(None),
+            Err(e) => {
+                tracing::error!("Failure retrieving household: {:?}", e);
+
+                Err(e)
+            }
+        }
+    }
+
+    /// Converts the order's value and earns on it, unless the account has already earned on the
+    /// order.
+    #[allow(clippy::too_many_arguments)]
+    async fn earn_order<C: Campaigns, X: ExchangeRates + ?Sized>(
+        campaigns: &C,
+        exchange_rates: &X,
+        vesting: &VestingPolicy,
+        programme: &Programme,
+        account: &mut LoyaltyAccount,
+        evt: &OrderConfirmed,
+        pooled: bool,
+        recorded_at: DateTime<Utc>,
+    ) -> Result<Option<LoyaltyAccountTransaction>, LoyaltyErrors> {
+        let (order_value, normalised_value) = Self::order_value(evt, programme, exchange_rates)
+            .map_err(|e| {
+                tracing::error!("Failure converting order value: {:?}", e);
+                e
+            })?;
+
+        if account.can_earn(&evt.order_id).is_err() {
+            return Ok(None);
+        }
+
+        Self::earn(
+            campaigns,
+            vesting,
+            programme,
+            account,
+            evt,
+            &order_value,
+            normalised_value,
+            pooled,
+            recorded_at,
+        )
+        .await
+    }
+
+    /// Adds the points earned on an order, with any campaign bonuses, to the account in memory.
+    /// The transaction is returned for the caller to write.
+    #[allow(clippy::too_many_arguments)]
+    async fn earn<C: Campaigns>(
+        campaigns: &C,
+        vesting: &VestingPolicy,
+        programme: &Programme,
+        account: &mut LoyaltyAccount,
+        evt: &OrderConfirmed,
+        order_value: &OrderValue,
+        normalised_value: f32,
+        pooled: bool,
+        recorded_at: DateTime<Utc>,
+    ) -> Result<Option<LoyaltyAccountTransaction>, LoyaltyErrors> {
+        // Points are dated when the order was placed so that replayed or delayed events vest and
+        // match campaigns as they would have at the time
+        let occurred_at = evt.occurred_at.unwrap_or(recorded_at);
+
+        let base_points = programme.points_for_order(normalised_value);
+
+        let campaign_bonuses = campaign_bonuses(
+            campaigns,
+            account.programme_id(),
+            account.customer_id(),
+            normalised_value,
+            base_points,
+            occurred_at,
+        )
+        .await
+        .map_err(|e| {
+            tracing::error!("Failure applying campaigns: {:?}", e);
+            e
+        })?;
+
+        // Pooled points are credited straight to the shared balance, vesting only applies to
+        // points held on the member's own account
+        let vests_at = match pooled {
+            true => None,
+            false => vesting.vests_at(occurred_at),
+        };
+
+        let transaction = account.add_transaction_with_bonuses(
+            evt.order_id.clone(),
+            base_points,
+            &campaign_bonuses,
+            Some(order_value),
+            occurred_at,
+            recorded_at,
+            vests_at,
+        );
+
+        match transaction {
+            Ok(transaction) => Ok(Some(transaction)),
+            Err(LoyaltyErrors::TransactionExistsForOrder(_)) => {
+                info!("Order {} already earned on", evt.order_id);
+
+                Ok(None)
+            }
+            Err(e) => {
+                tracing::error!("Failure earning points: {:?}", e);
+
+                Err(e)
+            }
+        }
+    }
+
+    async fn contribute<H: Households>(
+        households: &H,
+        household_id: &str,
+        account: &LoyaltyAccount,
+        transaction: LoyaltyAccountTransaction,
+    ) -> Result<(), LoyaltyErrors> {
+        let credited = households
+            .contribute(household_id, account.customer_id(), transaction)
+            .await
+            .map_err(|e| {
+                tracing::error!("Failure crediting household: {:?}", e);
+                e
+            })?;
+
+        if !credited {
+            info!("Order already credited to household {}", household_id);
+        }
+
+        Ok(())
+    }
+
+    /// A referral stays pending until the referred customer's first order is processed, a
+    /// redelivered order picks up a referral whose reward failed the first time round.
+    async fn reward_referral<T: LoyaltyPoints, R: Referrals>(
+        loyalty_points: &T,
+        referrals: &R,
+        bonuses: &BonusPolicy,
+        referee: &mut LoyaltyAccount,
+        at: DateTime<Utc>,
+    ) -> Result<(), LoyaltyErrors> {
+        let mut referral = match referrals
+            .retrieve_referral(referee.programme_id(), referee.customer_id())
+            .await
+        {
+            Ok(referral) if referral.status() == ReferralStatus::Pending => referral,
+            Ok(_) | Err(LoyaltyErrors::ReferralNotFound(_)) => return Ok(()),
+            Err(e) => {
+                tracing::error!("Failure retrieving referral: {:?}", e);
+
+                return Err(e);
+            }
+        };
+
+        let mut referrer = match retrieve_active_account(
+            loyalty_points,
+            referee.programme_id(),
+            referral.referrer_id(),
+        )
+        .await
+        {
+            Ok(account) => Some(account),
+            Err(LoyaltyErrors::AccountNotFound()) => None,
+            Err(e) => {
+                tracing::error!("Failure retrieving referrer account: {:?}", e);
+
+                return Err(e);
+            }
+        };
+
+        let referral_bonuses = referral
+            .reward(bonuses, referee, referrer.as_mut(), at)
+            .map_err(|e| {
+                tracing::error!("Failure rewarding referral: {:?}", e);
+                e
+            })?;
+
+        let rewarded = referrals
+            .reward_referral(&referral, referee, referrer.as_ref(), referral_bonuses)
+            .await
+            .map_err(|e| {
+                tracing::error!("Failure storing referral reward: {:?}", e);
+                e
+            })?;
+
+        if rewarded {
+            info!("Rewarded referral from {}", referral.referrer_id());
         }
 
         Ok(())
@@ -70,8 +705,13 @@ impl OrderConfirmedEventHandler {
 #[cfg(test)]
 mod tests {
     use crate::{
+        campaigns::{Campaign, CampaignEligibility, CampaignReward, MockCampaigns},
+        clock::{MockClock, SystemClock},
+        exchange_rates::{MockExchangeRates, StaticExchangeRates},
+        households::{Household, HouseholdMember, HouseholdRole, MockHouseholds},
         loyalty::{LoyaltyAccount, MockLoyaltyPoints},
-        LoyaltyErrors,
+        programmes::{MockProgrammes, Programme},
+        referrals::{MockReferrals, Referral},
     };
 
     use super::*;
@@ -86,29 +726,90 @@ mod tests {
         let mut loyalty_points = MockLoyaltyPoints::new();
         loyalty_points
             .expect_retrieve()
-            .with(predicate::eq(test_customer_id))
+            .with(
+                predicate::eq(DEFAULT_PROGRAMME_ID),
+                predicate::eq(test_customer_id),
+            )
             .times(1)
-            .returning(move |_| Err(LoyaltyErrors::AccountNotFound()));
-        loyalty_points
-            .expect_add_transaction()
-            .times(1)
-            .returning(|_, _| Ok(()));
+            .returning(move |_, _| Err(LoyaltyErrors::AccountNotFound()));
         loyalty_points
             .expect_new_account()
+            .withf(|account, transactions| {
+                let order_numbers: Vec<String> =
+                    transactions.iter().map(|t| t.order_number()).collect();
+
+                order_numbers == ["SIGNUP-BONUS", "ORD987"] && *account.current_points() == 150.0
+            })
             .times(1)
-            .returning(LoyaltyAccount::new);
+            .returning(|_, _| Ok(()));
+        loyalty_points.expect_add_transaction().never();
 
         let evt = OrderConfirmed {
+            programme_id: DEFAULT_PROGRAMME_ID.to_string(),
             customer_id: test_customer_id.to_string(),
             order_id: test_order_id.to_string(),
             order_value: test_order_value,
+            currency: None,
+            occurred_at: None,
         };
 
-        let result = OrderConfirmedEventHandler::handle(&loyalty_points, &evt).await;
+        let result = OrderConfirmedEventHandler::handle(
+            &loyalty_points,
+            &default_programme(),
+            &no_campaigns(),
+            &no_referrals(),
+            &no_household(),
+            &StaticExchangeRates::default(),
+            &VestingPolicy::default(),
+            &BonusPolicy::default(),
+            &SystemClock,
+            &evt,
+        )
+        .await;
 
         assert!(result.is_ok());
     }
 
+    #[tokio::test]
+    async fn failed_first_order_should_not_open_account() {
+        let mut loyalty_points = MockLoyaltyPoints::new();
+        loyalty_points
+            .expect_retrieve()
+            .times(1)
+            .returning(move |_, _| Err(LoyaltyErrors::AccountNotFound()));
+        loyalty_points.expect_new_account().never();
+
+        let mut campaigns = MockCampaigns::new();
+        campaigns
+            .expect_active_campaigns()
+            .returning(|_, _| Err(LoyaltyErrors::DatabaseError("unavailable".to_string())));
+
+        let evt = OrderConfirmed {
+            programme_id: DEFAULT_PROGRAMME_ID.to_string(),
+            customer_id: "james".to_string(),
+            order_id: "ORD987".to_string(),
+            order_value: 100.0,
+            currency: None,
+            occurred_at: None,
+        };
+
+        let result = OrderConfirmedEventHandler::handle(
+            &loyalty_points,
+            &default_programme(),
+            &campaigns,
+            &no_referrals(),
+            &no_household(),
+            &StaticExchangeRates::default(),
+            &VestingPolicy::default(),
+            &BonusPolicy::default(),
+            &SystemClock,
+            &evt,
+        )
+        .await;
+
+        assert!(matches!(result, Err(LoyaltyErrors::DatabaseError(_))));
+    }
+
     #[tokio::test]
     async fn on_valid_event_for_existing_customer_should_create_account_and_add_points() {
         let test_customer_id = "james";
@@ -118,22 +819,820 @@ mod tests {
         let mut loyalty_points = MockLoyaltyPoints::new();
         loyalty_points
             .expect_retrieve()
-            .with(predicate::eq(test_customer_id))
+            .with(
+                predicate::eq(DEFAULT_PROGRAMME_ID),
+                predicate::eq(test_customer_id),
+            )
             .times(1)
-            .returning(|customer_id| LoyaltyAccount::from(customer_id.to_string(), 10.0, vec![]));
+            .returning(|programme_id, customer_id| {
+                LoyaltyAccount::from(
+                    programme_id.to_string(),
+                    customer_id.to_string(),
+                    10.0,
+                    vec![],
+                )
+            });
         loyalty_points
             .expect_add_transaction()
             .times(1)
             .returning(|_, _| Ok(()));
 
         let evt = OrderConfirmed {
+            programme_id: DEFAULT_PROGRAMME_ID.to_string(),
             customer_id: test_customer_id.to_string(),
             order_id: test_order_id.to_string(),
             order_value: test_order_value,
+            currency: None,
+            occurred_at: None,
+        };
+
+        let result = OrderConfirmedEventHandler::handle(
+            &loyalty_points,
+            &default_programme(),
+            &no_campaigns(),
+            &no_referrals(),
+            &no_household(),
+            &StaticExchangeRates::default(),
+            &VestingPolicy::default(),
+            &BonusPolicy::default(),
+            &SystemClock,
+            &evt,
+        )
+        .await;
+
+        assert!(result.is_ok());
+    }
+
+    #[tokio::test]
+    async fn on_valid_event_during_campaign_should_add_bonus_points() {
+        let test_customer_id = "james";
+
+        let mut loyalty_points = MockLoyaltyPoints::new();
+        loyalty_points
+            .expect_retrieve()
+            .with(
+                predicate::eq(DEFAULT_PROGRAMME_ID),
+                predicate::eq(test_customer_id),
+            )
+            .times(1)
+            .returning(|programme_id, customer_id| {
+                LoyaltyAccount::from(
+                    programme_id.to_string(),
+                    customer_id.to_string(),
+                    0.0,
+                    vec![],
+                )
+            });
+        loyalty_points
+            .expect_add_transaction()
+            .withf(|account, transaction| {
+                *account.current_points() == 100.0 && transaction.metadata().len() == 3
+            })
+            .times(1)
+            .returning(|_, _| Ok(()));
+
+        let mut campaigns = MockCampaigns::new();
+        campaigns
+            .expect_active_campaigns()
+            .times(1)
+            .returning(|programme_id, _| {
+                Ok(vec![Campaign::from(
+                    programme_id.to_string(),
+                    "CMP-1".to_string(),
+                    "Double points".to_string(),
+                    Utc::now() - chrono::Duration::days(1),
+                    Utc::now() + chrono::Duration::days(1),
+                    CampaignEligibility::default(),
+                    CampaignReward::Multiplier(2.0),
+                    None,
+                    0.0,
+                )])
+            });
+        campaigns
+            .expect_reserve_budget()
+            .times(1)
+            .returning(|_, _| Ok(true));
+
+        let evt = OrderConfirmed {
+            programme_id: DEFAULT_PROGRAMME_ID.to_string(),
+            customer_id: test_customer_id.to_string(),
+            order_id: "ORD987".to_string(),
+            order_value: 100.00,
+            currency: None,
+            occurred_at: None,
+        };
+
+        let result = OrderConfirmedEventHandler::handle(
+            &loyalty_points,
+            &default_programme(),
+            &campaigns,
+            &no_referrals(),
+            &no_household(),
+            &StaticExchangeRates::default(),
+            &VestingPolicy::new(0),
+            &BonusPolicy::default(),
+            &SystemClock,
+            &evt,
+        )
+        .await;
+
+        assert!(result.is_ok());
+    }
+
+    #[tokio::test]
+    async fn on_valid_event_earned_points_should_be_pending() {
+        let test_customer_id = "james";
+
+        let mut loyalty_points = MockLoyaltyPoints::new();
+        loyalty_points
+            .expect_retrieve()
+            .with(
+                predicate::eq(DEFAULT_PROGRAMME_ID),
+                predicate::eq(test_customer_id),
+            )
+            .times(1)
+            .returning(|programme_id, customer_id| {
+                LoyaltyAccount::from(
+                    programme_id.to_string(),
+                    customer_id.to_string(),
+                    0.0,
+                    vec![],
+                )
+            });
+        loyalty_points
+            .expect_add_transaction()
+            .withf(|account, transaction| {
+                *account.current_points() == 50.0
+                    && account.available_points(Utc::now()) == 0.0
+                    && transaction.vests_at().is_some()
+            })
+            .times(1)
+            .returning(|_, _| Ok(()));
+
+        let evt = OrderConfirmed {
+            programme_id: DEFAULT_PROGRAMME_ID.to_string(),
+            customer_id: test_customer_id.to_string(),
+            order_id: "ORD987".to_string(),
+            order_value: 100.00,
+            currency: None,
+            occurred_at: None,
+        };
+
+        let result = OrderConfirmedEventHandler::handle(
+            &loyalty_points,
+            &default_programme(),
+            &no_campaigns(),
+            &no_referrals(),
+            &no_household(),
+            &StaticExchangeRates::default(),
+            &VestingPolicy::new(14),
+            &BonusPolicy::default(),
+            &SystemClock,
+            &evt,
+        )
+        .await;
+
+        assert!(result.is_ok());
+    }
+
+    #[tokio::test]
+    async fn delayed_event_should_be_dated_and_vested_from_when_the_order_was_placed() {
+        let recorded_at = Utc::now();
+        let occurred_at = recorded_at - Duration::days(20);
+
+        let mut clock = MockClock::new();
+        clock.expect_now().return_const(recorded_at);
+
+        let mut loyalty_points = MockLoyaltyPoints::new();
+        loyalty_points
+            .expect_retrieve()
+            .times(1)
+            .returning(|programme_id, customer_id| {
+                LoyaltyAccount::from(
+                    programme_id.to_string(),
+                    customer_id.to_string(),
+                    0.0,
+                    vec![],
+                )
+            });
+        loyalty_points
+            .expect_add_transaction()
+            .withf(move |account, transaction| {
+                transaction.date() == occurred_at
+                    && transaction.recorded_at() == recorded_at
+                    && account.available_points(recorded_at) == 50.0
+            })
+            .times(1)
+            .returning(|_, _| Ok(()));
+
+        let evt = OrderConfirmed {
+            programme_id: DEFAULT_PROGRAMME_ID.to_string(),
+            customer_id: "james".to_string(),
+            order_id: "ORD987".to_string(),
+            order_value: 100.00,
+            currency: None,
+            occurred_at: Some(occurred_at),
+        };
+
+        let result = OrderConfirmedEventHandler::handle(
+            &loyalty_points,
+            &default_programme(),
+            &no_campaigns(),
+            &no_referrals(),
+            &no_household(),
+            &StaticExchangeRates::default(),
+            &VestingPolicy::new(14),
+            &BonusPolicy::default(),
+            &clock,
+            &evt,
+        )
+        .await;
+
+        assert!(result.is_ok());
+    }
+
+    #[tokio::test]
+    async fn first_order_of_referred_customer_should_reward_both_sides() {
+        let mut loyalty_points = MockLoyaltyPoints::new();
+        loyalty_points
+            .expect_retrieve()
+            .returning(|programme_id, customer_id| {
+                LoyaltyAccount::from(
+                    programme_id.to_string(),
+                    customer_id.to_string(),
+                    0.0,
+                    vec![],
+                )
+            });
+        loyalty_points
+            .expect_add_transaction()
+            .times(1)
+            .returning(|_, _| Ok(()));
+
+        let mut referrals = MockReferrals::new();
+        referrals
+            .expect_retrieve_referral()
+            .returning(|programme_id, referee_id| {
+                Ok(Referral::from(
+                    programme_id.to_string(),
+                    "alice".to_string(),
+                    referee_id.to_string(),
+                    "REF-ALICE".to_string(),
+                    ReferralStatus::Pending,
+                    Utc::now(),
+                    None,
+                ))
+            });
+        referrals
+            .expect_reward_referral()
+            .withf(|referral, referee, referrer, bonuses| {
+                referral.status() == ReferralStatus::Rewarded
+                    && *referee.current_points() == 150.0
+                    && referrer.is_some_and(|referrer| *referrer.current_points() == 200.0)
+                    && bonuses.referee_bonus.is_some()
+                    && bonuses.referrer_bonus.is_some()
+            })
+            .times(1)
+            .returning(|_, _, _, _| Ok(true));
+
+        let evt = OrderConfirmed {
+            programme_id: DEFAULT_PROGRAMME_ID.to_string(),
+            customer_id: "james".to_string(),
+            order_id: "ORD987".to_string(),
+            order_value: 100.00,
+            currency: None,
+            occurred_at: None,
+        };
+
+        let result = OrderConfirmedEventHandler::handle(
+            &loyalty_points,
+            &default_programme(),
+            &no_campaigns(),
+            &referrals,
+            &no_household(),
+            &StaticExchangeRates::default(),
+            &VestingPolicy::default(),
+            &BonusPolicy::default(),
+            &SystemClock,
+            &evt,
+        )
+        .await;
+
+        assert!(result.is_ok());
+    }
+
+    #[tokio::test]
+    async fn household_member_earnings_should_flow_into_the_pool() {
+        let mut loyalty_points = MockLoyaltyPoints::new();
+        loyalty_points
+            .expect_retrieve()
+            .returning(|programme_id, customer_id| {
+                LoyaltyAccount::from(
+                    programme_id.to_string(),
+                    customer_id.to_string(),
+                    0.0,
+                    vec![],
+                )
+            });
+        loyalty_points.expect_add_transaction().times(0);
+
+        let mut households = MockHouseholds::new();
+        households
+            .expect_household_for_member()
+            .returning(|programme_id, customer_id| {
+                Ok(Household::from(
+                    programme_id.to_string(),
+                    "HH-1".to_string(),
+                    "Smiths".to_string(),
+                    0.0,
+                    vec![HouseholdMember::from(
+                        customer_id.to_string(),
+                        HouseholdRole::Member,
+                        None,
+                        0.0,
+                    )],
+                    Utc::now(),
+                ))
+            });
+        households
+            .expect_contribute()
+            .withf(|household_id, customer_id, transaction| {
+                household_id == "HH-1"
+                    && customer_id == "james"
+                    && transaction.change() == 50.0
+                    && transaction.vests_at().is_none()
+            })
+            .times(1)
+            .returning(|_, _, _| Ok(true));
+
+        let evt = OrderConfirmed {
+            programme_id: DEFAULT_PROGRAMME_ID.to_string(),
+            customer_id: "james".to_string(),
+            order_id: "ORD987".to_string(),
+            order_value: 100.00,
+            currency: None,
+            occurred_at: None,
         };
 
-        let result = OrderConfirmedEventHandler::handle(&loyalty_points, &evt).await;
+        let result = OrderConfirmedEventHandler::handle(
+            &loyalty_points,
+            &default_programme(),
+            &no_campaigns(),
+            &no_referrals(),
+            &households,
+            &StaticExchangeRates::default(),
+            &VestingPolicy::default(),
+            &BonusPolicy::default(),
+            &SystemClock,
+            &evt,
+        )
+        .await;
 
         assert!(result.is_ok());
     }
+
+    #[tokio::test]
+    async fn on_valid_event_points_should_be_earned_at_the_programme_rate() {
+        let mut loyalty_points = MockLoyaltyPoints::new();
+        loyalty_points
+            .expect_retrieve()
+            .with(predicate::eq("acme"), predicate::eq("james"))
+            .times(1)
+            .returning(|programme_id, customer_id| {
+                LoyaltyAccount::from(
+                    programme_id.to_string(),
+                    customer_id.to_string(),
+                    0.0,
+                    vec![],
+                )
+            });
+        loyalty_points
+            .expect_add_transaction()
+            .withf(|account, transaction| {
+                account.programme_id() == "acme" && transaction.change() == 200.0
+            })
+            .times(1)
+            .returning(|_, _| Ok(()));
+
+        let mut programmes = MockProgrammes::new();
+        programmes
+            .expect_retrieve_programme()
+            .with(predicate::eq("acme"))
+            .returning(|programme_id| {
+                Ok(Programme::from(
+                    programme_id.to_string(),
+                    "Acme Rewards".to_string(),
+                    2.0,
+                    "USD".to_string(),
+                    Utc::now(),
+                ))
+            });
+
+        let evt = OrderConfirmed {
+            programme_id: "acme".to_string(),
+            customer_id: "james".to_string(),
+            order_id: "ORD987".to_string(),
+            order_value: 100.00,
+            currency: None,
+            occurred_at: None,
+        };
+
+        let result = OrderConfirmedEventHandler::handle(
+            &loyalty_points,
+            &programmes,
+            &no_campaigns(),
+            &no_referrals(),
+            &no_household(),
+            &StaticExchangeRates::default(),
+            &VestingPolicy::new(0),
+            &BonusPolicy::default(),
+            &SystemClock,
+            &evt,
+        )
+        .await;
+
+        assert!(result.is_ok());
+    }
+
+    #[tokio::test]
+    async fn order_in_another_currency_should_earn_on_the_converted_value() {
+        let mut loyalty_points = MockLoyaltyPoints::new();
+        loyalty_points
+            .expect_retrieve()
+            .times(1)
+            .returning(|programme_id, customer_id| {
+                LoyaltyAccount::from(
+                    programme_id.to_string(),
+                    customer_id.to_string(),
+                    0.0,
+                    vec![],
+                )
+            });
+        loyalty_points
+            .expect_add_transaction()
+            .withf(|account, transaction| {
+                *account.current_points() == 62.5
+                    && transaction.metadata().get("order.value") == Some(&"100".to_string())
+                    && transaction.metadata().get("order.currency") == Some(&"GBP".to_string())
+            })
+            .times(1)
+            .returning(|_, _| Ok(()));
+
+        let mut exchange_rates = MockExchangeRates::new();
+        exchange_rates
+            .expect_rate()
+            .with(predicate::eq("GBP"), predicate::eq("USD"))
+            .times(1)
+            .returning(|_, _| Ok(1.25));
+
+        let evt = OrderConfirmed {
+            programme_id: DEFAULT_PROGRAMME_ID.to_string(),
+            customer_id: "james".to_string(),
+            order_id: "ORD987".to_string(),
+            order_value: 100.00,
+            currency: Some("GBP".to_string()),
+            occurred_at: None,
+        };
+
+        let result = OrderConfirmedEventHandler::handle(
+            &loyalty_points,
+            &default_programme(),
+            &no_campaigns(),
+            &no_referrals(),
+            &no_household(),
+            &exchange_rates,
+            &VestingPolicy::new(0),
+            &BonusPolicy::default(),
+            &SystemClock,
+            &evt,
+        )
+        .await;
+
+        assert!(result.is_ok());
+    }
+
+    #[tokio::test]
+    async fn invalid_event_should_report_every_failing_field_and_not_earn() {
+        let mut loyalty_points = MockLoyaltyPoints::new();
+        loyalty_points.expect_retrieve().times(0);
+        loyalty_points.expect_add_transaction().times(0);
+
+        let evt = OrderConfirmed {
+            programme_id: DEFAULT_PROGRAMME_ID.to_string(),
+            customer_id: "james".to_string(),
+            order_id: "".to_string(),
+            order_value: -10.0,
+            currency: Some("usd".to_string()),
+            occurred_at: None,
+        };
+
+        let fields: Vec<String> = evt
+            .validate()
+            .unwrap_err()
+            .errors
+            .into_iter()
+            .map(|error| error.field)
+            .collect();
+
+        assert_eq!(fields, vec!["order_id", "order_value", "currency"]);
+
+        for order_value in [0.0, f32::NAN, f32::INFINITY] {
+            let evt = OrderConfirmed {
+                programme_id: DEFAULT_PROGRAMME_ID.to_string(),
+                customer_id: "james".to_string(),
+                order_id: "ORD987".to_string(),
+                order_value,
+                currency: None,
+                occurred_at: None,
+            };
+
+            let result = OrderConfirmedEventHandler::handle(
+                &loyalty_points,
+                &MockProgrammes::new(),
+                &no_campaigns(),
+                &no_referrals(),
+                &no_household(),
+                &StaticExchangeRates::default(),
+                &VestingPolicy::default(),
+                &BonusPolicy::default(),
+                &SystemClock,
+                &evt,
+            )
+            .await;
+
+            assert!(result.is_err());
+        }
+    }
+
+    #[tokio::test]
+    async fn order_in_currency_without_a_rate_should_not_earn() {
+        let mut loyalty_points = MockLoyaltyPoints::new();
+        loyalty_points.expect_retrieve().times(0);
+        loyalty_points.expect_add_transaction().times(0);
+
+        let evt = OrderConfirmed {
+            programme_id: DEFAULT_PROGRAMME_ID.to_string(),
+            customer_id: "james".to_string(),
+            order_id: "ORD987".to_string(),
+            order_value: 100.00,
+            currency: Some("JPY".to_string()),
+            occurred_at: None,
+        };
+
+        let result = OrderConfirmedEventHandler::handle(
+            &loyalty_points,
+            &default_programme(),
+            &no_campaigns(),
+            &no_referrals(),
+            &no_household(),
+            &StaticExchangeRates::default(),
+            &VestingPolicy::new(0),
+            &BonusPolicy::default(),
+            &SystemClock,
+            &evt,
+        )
+        .await;
+
+        assert!(result.is_err());
+    }
+
+    #[tokio::test]
+    async fn batch_should_load_each_customer_once_and_write_their_orders_together() {
+        let mut loyalty_points = MockLoyaltyPoints::new();
+        loyalty_points
+            .expect_retrieve()
+            .times(2)
+            .returning(|programme_id, customer_id| {
+                LoyaltyAccount::from(
+                    programme_id.to_string(),
+                    customer_id.to_string(),
+                    0.0,
+                    vec![],
+                )
+            });
+        loyalty_points
+            .expect_add_transactions()
+            .withf(|account, transactions| {
+                let orders: Vec<String> = transactions.iter().map(|t| t.order_number()).collect();
+
+                account.customer_id() == "james" && orders == ["ORD1", "ORD3"]
+            })
+            .times(1)
+            .returning(|_, _| Ok(()));
+        loyalty_points
+            .expect_add_transactions()
+            .withf(|account, transactions| {
+                account.customer_id() == "sarah" && transactions.len() == 1
+            })
+            .times(1)
+            .returning(|_, _| Ok(()));
+
+        let events = [
+            order("james", "ORD1", 100.0),
+            order("sarah", "ORD2", 20.0),
+            order("james", "ORD3", 50.0),
+        ];
+
+        let results = handle_batch(&loyalty_points, &events).await;
+
+        assert_eq!(results.len(), 3);
+        assert!(results.iter().all(|result| result.is_ok()));
+    }
+
+    #[tokio::test]
+    async fn batch_should_open_new_account_with_its_orders() {
+        let mut loyalty_points = MockLoyaltyPoints::new();
+        loyalty_points
+            .expect_retrieve()
+            .times(1)
+            .returning(|_, _| Err(LoyaltyErrors::AccountNotFound()));
+        loyalty_points
+            .expect_new_account()
+            .withf(|_, transactions| {
+                let orders: Vec<String> = transactions.iter().map(|t| t.order_number()).collect();
+
+                orders == ["SIGNUP-BONUS", "ORD1", "ORD2"]
+            })
+            .times(1)
+            .returning(|_, _| Ok(()));
+        loyalty_points.expect_add_transactions().never();
+
+        let events = [order("james", "ORD1", 100.0), order("james", "ORD2", 50.0)];
+
+        let results = handle_batch(&loyalty_points, &events).await;
+
+        assert!(results.iter().all(|result| result.is_ok()));
+    }
+
+    #[tokio::test]
+    async fn batch_should_earn_once_for_an_order_repeated_in_the_batch() {
+        let mut loyalty_points = MockLoyaltyPoints::new();
+        loyalty_points
+            .expect_retrieve()
+            .times(1)
+            .returning(|programme_id, customer_id| {
+                LoyaltyAccount::from(
+                    programme_id.to_string(),
+                    customer_id.to_string(),
+                    0.0,
+                    vec![],
+                )
+            });
+        loyalty_points
+            .expect_add_transactions()
+            .withf(|_, transactions| transactions.len() == 1)
+            .times(1)
+            .returning(|_, _| Ok(()));
+
+        let events = [order("james", "ORD1", 100.0), order("james", "ORD1", 100.0)];
+
+        let results = handle_batch(&loyalty_points, &events).await;
+
+        assert!(results.iter().all(|result| result.is_ok()));
+    }
+
+    #[tokio::test]
+    async fn batch_should_only_fail_the_orders_that_went_wrong() {
+        let mut loyalty_points = MockLoyaltyPoints::new();
+        loyalty_points
+            .expect_retrieve()
+            .returning(|programme_id, customer_id| {
+                LoyaltyAccount::from(
+                    programme_id.to_string(),
+                    customer_id.to_string(),
+                    0.0,
+                    vec![],
+                )
+            });
+        loyalty_points
+            .expect_add_transactions()
+            .withf(|account, _| account.customer_id() == "james")
+            .returning(|_, _| Err(LoyaltyErrors::DatabaseError("connection lost".to_string())));
+        loyalty_points
+            .expect_add_transactions()
+            .withf(|account, _| account.customer_id() == "sarah")
+            .returning(|_, _| Ok(()));
+
+        let events = [
+            order("james", "ORD1", 100.0),
+            order("sarah", "ORD2", 0.0),
+            order("sarah", "ORD3", 20.0),
+            order("james", "ORD4", 50.0),
+        ];
+
+        let results = handle_batch(&loyalty_points, &events).await;
+
+        assert!(matches!(results[0], Err(LoyaltyErrors::DatabaseError(_))));
+        assert!(matches!(results[1], Err(LoyaltyErrors::InvalidValues(_))));
+        assert!(results[2].is_ok());
+        assert!(matches!(results[3], Err(LoyaltyErrors::DatabaseError(_))));
+    }
+
+    #[tokio::test]
+    async fn batch_should_not_reward_referral_when_orders_are_not_saved() {
+        let mut loyalty_points = MockLoyaltyPoints::new();
+        loyalty_points
+            .expect_retrieve()
+            .returning(|programme_id, customer_id| {
+                LoyaltyAccount::from(
+                    programme_id.to_string(),
+                    customer_id.to_string(),
+                    0.0,
+                    vec![],
+                )
+            });
+        loyalty_points
+            .expect_add_transactions()
+            .returning(|_, _| Err(LoyaltyErrors::DatabaseError("connection lost".to_string())));
+
+        let mut referrals = MockReferrals::new();
+        referrals.expect_retrieve_referral().times(0);
+
+        let events = [order("james", "ORD1", 100.0)];
+        let events: Vec<&OrderConfirmed> = events.iter().collect();
+
+        let results = OrderConfirmedEventHandler::handle_batch(
+            &loyalty_points,
+            &default_programme(),
+            &no_campaigns(),
+            &referrals,
+            &no_household(),
+            &StaticExchangeRates::default(),
+            &VestingPolicy::default(),
+            &BonusPolicy::default(),
+            &SystemClock,
+            &events,
+        )
+        .await;
+
+        assert!(matches!(results[0], Err(LoyaltyErrors::DatabaseError(_))));
+    }
+
+    fn order(customer_id: &str, order_id: &str, order_value: f32) -> OrderConfirmed {
+        OrderConfirmed {
+            programme_id: DEFAULT_PROGRAMME_ID.to_string(),
+            customer_id: customer_id.to_string(),
+            order_id: order_id.to_string(),
+            order_value,
+            currency: None,
+            occurred_at: None,
+        }
+    }
+
+    async fn handle_batch(
+        loyalty_points: &MockLoyaltyPoints,
+        events: &[OrderConfirmed],
+    ) -> Vec<Result<(), LoyaltyErrors>> {
+        let events: Vec<&OrderConfirmed> = events.iter().collect();
+
+        OrderConfirmedEventHandler::handle_batch(
+            loyalty_points,
+            &default_programme(),
+            &no_campaigns(),
+            &no_referrals(),
+            &no_household(),
+            &StaticExchangeRates::default(),
+            &VestingPolicy::default(),
+            &BonusPolicy::default(),
+            &SystemClock,
+            &events,
+        )
+        .await
+    }
+
+    fn default_programme() -> MockProgrammes {
+        let mut programmes = MockProgrammes::new();
+        programmes
+            .expect_retrieve_programme()
+            .returning(|_| Ok(Programme::default()));
+        programmes
+    }
+
+    fn no_household() -> MockHouseholds {
+        let mut households = MockHouseholds::new();
+        households
+            .expect_household_for_member()
+            .returning(|_, customer_id| {
+                Err(LoyaltyErrors::HouseholdNotFound(customer_id.to_string()))
+            });
+        households
+    }
+
+    fn no_referrals() -> MockReferrals {
+        let mut referrals = MockReferrals::new();
+        referrals
+            .expect_retrieve_referral()
+            .returning(|_, referee_id| {
+                Err(LoyaltyErrors::ReferralNotFound(referee_id.to_string()))
+            });
+        referrals
+    }
+
+    fn no_campaigns() -> MockCampaigns {
+        let mut campaigns = MockCampaigns::new();
+        campaigns
+            .expect_active_campaigns()
+            .returning(|_, _| Ok(vec![]));
+        campaigns
+    }
 }
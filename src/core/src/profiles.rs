@@ -0,0 +1,322 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Datelike, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::loyalty::{LoyaltyAccount, LoyaltyAccountTransaction, LoyaltyErrors};
+
+#[cfg(any(test, feature = "mocks"))]
+use mockall::automock;
+
+/// The points granted for joining, on each birthday and to both sides of a referral, a value of
+/// zero disables the bonus.
+#[derive(Clone, Debug)]
+pub struct BonusPolicy {
+    signup_bonus: f32,
+    birthday_bonus: f32,
+    referrer_bonus: f32,
+    referee_bonus: f32,
+}
+
+impl BonusPolicy {
+    pub fn new(
+        signup_bonus: f32,
+        birthday_bonus: f32,
+        referrer_bonus: f32,
+        referee_bonus: f32,
+    ) -> Self {
+        Self {
+            signup_bonus,
+            birthday_bonus,
+            referrer_bonus,
+            referee_bonus,
+        }
+    }
+
+    pub fn signup_bonus(&self) -> f32 {
+        self.signup_bonus
+    }
+
+    pub fn birthday_bonus(&self) -> f32 {
+        self.birthday_bonus
+    }
+
+    pub fn referrer_bonus(&self) -> f32 {
+        self.referrer_bonus
+    }
+
+    pub fn referee_bonus(&self) -> f32 {
+        self.referee_bonus
+    }
+
+    pub(crate) fn apply_signup_bonus(
+        &self,
+        account: &mut LoyaltyAccount,
+        at: DateTime<Utc>,
+    ) -> Result<Option<LoyaltyAccountTransaction>, LoyaltyErrors> {
+        if self.signup_bonus <= 0.0 {
+            return Ok(None);
+        }
+
+        account
+            .adjust_points("SIGNUP-BONUS", self.signup_bonus, at)
+            .map(Some)
+    }
+
+    pub(crate) fn apply_birthday_bonus(
+        &self,
+        account: &mut LoyaltyAccount,
+        today: NaiveDate,
+        at: DateTime<Utc>,
+    ) -> Result<Option<LoyaltyAccountTransaction>, LoyaltyErrors> {
+        if self.birthday_bonus <= 0.0 {
+            return Ok(None);
+        }
+
+        account
+            .adjust_points(
+                &format!("BIRTHDAY-{}", today.year()),
+                self.birthday_bonus,
+                at,
+            )
+            .map(Some)
+    }
+}
+
+impl Default for BonusPolicy {
+    fn default() -> Self {
+        Self::new(100.0, 50.0, 200.0, 100.0)
+    }
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct CustomerProfileDto {
+    pub programme_id: String,
+    pub customer_id: String,
+    pub display_name: String,
+    pub email: String,
+    pub birthday: Option<NaiveDate>,
+    pub marketing_opt_in: bool,
+    pub joined_at: DateTime<Utc>,
+}
+
+impl From<CustomerProfile> for CustomerProfileDto {
+    fn from(value: CustomerProfile) -> Self {
+        CustomerProfileDto {
+            programme_id: value.programme_id,
+            customer_id: value.customer_id,
+            display_name: value.display_name,
+            email: value.email,
+            birthday: value.birthday,
+            marketing_opt_in: value.marketing_opt_in,
+            joined_at: value.joined_at,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct CustomerProfile {
+    programme_id: String,
+    customer_id: String,
+    display_name: String,
+    email: String,
+    birthday: Option<NaiveDate>,
+    marketing_opt_in: bool,
+    joined_at: DateTime<Utc>,
+}
+
+impl CustomerProfile {
+    pub(crate) fn new(
+        programme_id: String,
+        customer_id: String,
+        display_name: String,
+        email: String,
+        birthday: Option<NaiveDate>,
+        marketing_opt_in: bool,
+        joined_at: DateTime<Utc>,
+    ) -> Result<Self, LoyaltyErrors> {
+        if customer_id.is_empty() {
+            return Err(LoyaltyErrors::InvalidValues(
+                "CustomerID cannot be empty".to_string(),
+            ));
+        }
+
+        let mut profile = Self {
+            programme_id,
+            customer_id,
+            display_name: String::new(),
+            email: String::new(),
+            birthday: None,
+            marketing_opt_in: false,
+            joined_at,
+        };
+
+        profile.update(display_name, email, birthday, marketing_opt_in)?;
+
+        Ok(profile)
+    }
+
+    pub fn from(
+        programme_id: String,
+        customer_id: String,
+        display_name: String,
+        email: String,
+        birthday: Option<NaiveDate>,
+        marketing_opt_in: bool,
+        joined_at: DateTime<Utc>,
+    ) -> Self {
+        Self {
+            programme_id,
+            customer_id,
+            display_name,
+            email,
+            birthday,
+            marketing_opt_in,
+            joined_at,
+        }
+    }
+
+    pub fn programme_id(&self) -> &str {
+        &self.programme_id
+    }
+    pub fn customer_id(&self) -> &str {
+        &self.customer_id
+    }
+    pub fn display_name(&self) -> &str {
+        &self.display_name
+    }
+    pub fn email(&self) -> &str {
+        &self.email
+    }
+    pub fn birthday(&self) -> Option<NaiveDate> {
+        self.birthday
+    }
+    pub fn marketing_opt_in(&self) -> bool {
+        self.marketing_opt_in
+    }
+    pub fn joined_at(&self) -> DateTime<Utc> {
+        self.joined_at
+    }
+
+    pub(crate) fn update(
+        &mut self,
+        display_name: String,
+        email: String,
+        birthday: Option<NaiveDate>,
+        marketing_opt_in: bool,
+    ) -> Result<(), LoyaltyErrors> {
+        if !email.is_empty() && !email.contains('@') {
+            return Err(LoyaltyErrors::InvalidValues(format!(
+                "'{}' is not a valid email address",
+                email
+            )));
+        }
+
+        if birthday.is_some_and(|birthday| birthday > Utc::now().date_naive()) {
+            return Err(LoyaltyErrors::InvalidValues(
+                "Birthday cannot be in the future".to_string(),
+            ));
+        }
+
+        self.display_name = display_name;
+        self.email = email;
+        self.birthday = birthday;
+        self.marketing_opt_in = marketing_opt_in;
+
+        Ok(())
+    }
+
+    /// Customers born on 29th February celebrate on the 28th in years that are not leap years.
+    pub(crate) fn celebrates_birthday_on(&self, date: NaiveDate) -> bool {
+        self.birthday.is_some_and(|birthday| {
+            birthday_dates(date).contains(&(birthday.month(), birthday.day()))
+        })
+    }
+}
+
+/// The (month, day) birthdays that are celebrated on `date`.
+pub(crate) fn birthday_dates(date: NaiveDate) -> Vec<(u32, u32)> {
+    let mut dates = vec![(date.month(), date.day())];
+
+    if date.month() == 2 && date.day() == 28 && NaiveDate::from_ymd_opt(date.year(), 2, 29).is_none() {
+        dates.push((2, 29));
+    }
+
+    dates
+}
+
+#[cfg_attr(any(test, feature = "mocks"), automock)]
+#[async_trait]
+pub trait CustomerProfiles {
+    async fn save_profile(&self, profile: &CustomerProfile) -> Result<(), LoyaltyErrors>;
+    async fn retrieve_profile(
+        &self,
+        programme_id: &str,
+        customer_id: &str,
+    ) -> Result<CustomerProfile, LoyaltyErrors>;
+    /// Profiles celebrating on the given day across every programme.
+    async fn profiles_with_birthday(
+        &self,
+        month: u32,
+        day: u32,
+    ) -> Result<Vec<CustomerProfile>, LoyaltyErrors>;
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::programmes::DEFAULT_PROGRAMME_ID;
+
+    use super::*;
+
+    fn profile(birthday: NaiveDate) -> CustomerProfile {
+        CustomerProfile::from(
+            DEFAULT_PROGRAMME_ID.to_string(),
+            "james".to_string(),
+            "James".to_string(),
+            "james@example.com".to_string(),
+            Some(birthday),
+            true,
+            Utc::now(),
+        )
+    }
+
+    #[test]
+    fn leap_day_birthday_is_celebrated_on_28th_in_other_years() {
+        let profile = profile(NaiveDate::from_ymd_opt(2000, 2, 29).unwrap());
+
+        assert!(profile.celebrates_birthday_on(NaiveDate::from_ymd_opt(2027, 2, 28).unwrap()));
+        assert!(!profile.celebrates_birthday_on(NaiveDate::from_ymd_opt(2028, 2, 28).unwrap()));
+        assert!(profile.celebrates_birthday_on(NaiveDate::from_ymd_opt(2028, 2, 29).unwrap()));
+    }
+
+    #[test]
+    fn profile_with_invalid_email_should_error() {
+        let result = CustomerProfile::new(
+            DEFAULT_PROGRAMME_ID.to_string(),
+            "james".to_string(),
+            "James".to_string(),
+            "not-an-email".to_string(),
+            None,
+            false,
+            Utc::now(),
+        );
+
+        assert!(matches!(result, Err(LoyaltyErrors::InvalidValues(_))));
+    }
+
+    #[test]
+    fn birthday_bonus_is_only_granted_once_a_year() {
+        let mut account =
+            LoyaltyAccount::new(DEFAULT_PROGRAMME_ID.to_string(), "james".to_string()).unwrap();
+        let today = NaiveDate::from_ymd_opt(2026, 10, 18).unwrap();
+        let policy = BonusPolicy::default();
+
+        assert!(policy
+            .apply_birthday_bonus(&mut account, today, Utc::now())
+            .unwrap()
+            .is_some());
+        assert!(policy
+            .apply_birthday_bonus(&mut account, today, Utc::now())
+            .is_err());
+        assert_eq!(*account.current_points(), 50.0);
+    }
+}
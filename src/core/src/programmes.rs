@@ -0,0 +1,235 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::loyalty::LoyaltyErrors;
+
+#[cfg(any(test, feature = "mocks"))]
+use mockall::automock;
+
+/// The programme used by deployments, events and cached accounts that predate programmes.
+pub const DEFAULT_PROGRAMME_ID: &str = "default";
+
+#[derive(Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProgrammeDto {
+    pub programme_id: String,
+    pub name: String,
+    pub earn_rate: f32,
+    pub base_currency: String,
+    pub created_at: DateTime<Utc>,
+}
+
+impl From<Programme> for ProgrammeDto {
+    fn from(value: Programme) -> Self {
+        ProgrammeDto {
+            programme_id: value.programme_id,
+            name: value.name,
+            earn_rate: value.earn_rate,
+            base_currency: value.base_currency,
+            created_at: value.created_at,
+        }
+    }
+}
+
+/// A loyalty programme run for a single brand, with its own customers and earning rules.
+#[derive(Clone, Debug)]
+pub struct Programme {
+    programme_id: String,
+    name: String,
+    /// Points earned for every unit of order value.
+    earn_rate: f32,
+    /// ISO 4217 code that order values are converted into before earning rules apply.
+    base_currency: String,
+    created_at: DateTime<Utc>,
+}
+
+impl Programme {
+    pub(crate) fn new(
+        programme_id: String,
+        name: String,
+        earn_rate: f32,
+        base_currency: String,
+        created_at: DateTime<Utc>,
+    ) -> Result<Self, LoyaltyErrors> {
+        // Programme ids end up in routes, topic names and cache keys so they are kept to a
+        // simple slug
+        let valid_id = !programme_id.is_empty()
+            && programme_id
+                .chars()
+                .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-');
+
+        if !valid_id {
+            return Err(LoyaltyErrors::InvalidValues(format!(
+                "'{}' is not a valid programme id, use lowercase letters, digits and dashes",
+                programme_id
+            )));
+        }
+
+        let programme = Self {
+            programme_id,
+            name,
+            earn_rate,
+            base_currency,
+            created_at,
+        };
+
+        programme.validate()?;
+
+        Ok(programme)
+    }
+
+    pub fn from(
+        programme_id: String,
+        name: String,
+        earn_rate: f32,
+        base_currency: String,
+        created_at: DateTime<Utc>,
+    ) -> Self {
+        Self {
+            programme_id,
+            name,
+            earn_rate,
+            base_currency,
+            created_at,
+        }
+    }
+
+    pub fn programme_id(&self) -> &str {
+        &self.programme_id
+    }
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+    pub fn earn_rate(&self) -> f32 {
+        self.earn_rate
+    }
+    pub fn base_currency(&self) -> &str {
+        &self.base_currency
+    }
+    pub fn created_at(&self) -> DateTime<Utc> {
+        self.created_at
+    }
+
+    pub(crate) fn update(&mut self, name: String, earn_rate: f32) -> Result<(), LoyaltyErrors> {
+        let mut updated = self.clone();
+        updated.name = name;
+        updated.earn_rate = earn_rate;
+
+        updated.validate()?;
+
+        *self = updated;
+
+        Ok(())
+    }
+
+    fn validate(&self) -> Result<(), LoyaltyErrors> {
+        if self.name.trim().is_empty() {
+            return Err(LoyaltyErrors::InvalidValues(
+                "Programme name cannot be empty".to_string(),
+            ));
+        }
+
+        if !self.earn_rate.is_finite() || self.earn_rate < 0.0 {
+            return Err(LoyaltyErrors::InvalidValues(
+                "Programme earn rate cannot be negative".to_string(),
+            ));
+        }
+
+        let valid_currency = self.base_currency.len() == 3
+            && self.base_currency.chars().all(|c| c.is_ascii_uppercase());
+
+        if !valid_currency {
+            return Err(LoyaltyErrors::InvalidValues(format!(
+                "'{}' is not a valid ISO 4217 currency code",
+                self.base_currency
+            )));
+        }
+
+        Ok(())
+    }
+
+    pub(crate) fn points_for_order(&self, order_value: f32) -> f32 {
+        order_value * self.earn_rate
+    }
+}
+
+impl Default for Programme {
+    fn default() -> Self {
+        Self::from(
+            DEFAULT_PROGRAMME_ID.to_string(),
+            "Default".to_string(),
+            0.5,
+            "USD".to_string(),
+            DateTime::UNIX_EPOCH,
+        )
+    }
+}
+
+#[cfg_attr(any(test, feature = "mocks"), automock)]
+#[async_trait]
+pub trait Programmes {
+    async fn save_programme(&self, programme: &Programme) -> Result<(), LoyaltyErrors>;
+    async fn retrieve_programme(&self, programme_id: &str) -> Result<Programme, LoyaltyErrors>;
+    async fn list_programmes(&self) -> Result<Vec<Programme>, LoyaltyErrors>;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn programme_earns_at_its_own_rate() {
+        let programme = Programme::new(
+            "acme".to_string(),
+            "Acme Rewards".to_string(),
+            2.0,
+            "USD".to_string(),
+            Utc::now(),
+        )
+        .unwrap();
+
+        assert_eq!(programme.points_for_order(100.0), 200.0);
+        assert_eq!(Programme::default().points_for_order(100.0), 50.0);
+    }
+
+    #[test]
+    fn programme_id_must_be_a_slug() {
+        for programme_id in ["", "Acme", "acme.eu", "acme#1"] {
+            let result = Programme::new(
+                programme_id.to_string(),
+                "Acme Rewards".to_string(),
+                1.0,
+                "USD".to_string(),
+                Utc::now(),
+            );
+
+            assert!(matches!(result, Err(LoyaltyErrors::InvalidValues(_))));
+        }
+    }
+
+    #[test]
+    fn programme_base_currency_must_be_a_currency_code() {
+        for base_currency in ["", "usd", "US", "EURO"] {
+            let result = Programme::new(
+                "acme".to_string(),
+                "Acme Rewards".to_string(),
+                1.0,
+                base_currency.to_string(),
+                Utc::now(),
+            );
+
+            assert!(matches!(result, Err(LoyaltyErrors::InvalidValues(_))));
+        }
+    }
+
+    #[test]
+    fn programme_earn_rate_cannot_be_negative() {
+        let mut programme = Programme::default();
+
+        let result = programme.update("Default".to_string(), -1.0);
+
+        assert!(result.is_err());
+        assert_eq!(programme.earn_rate(), 0.5);
+    }
+}
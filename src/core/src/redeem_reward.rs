@@ -0,0 +1,386 @@
+use serde::Deserialize;
+
+use crate::{
+    clock::Clock,
+    loyalty::{retrieve_active_account, LoyaltyErrors, LoyaltyPoints},
+    rewards::{
+        FulfilmentStatus, RedemptionDto, RewardEvents, RewardOutbox, RewardRedeemed, Rewards,
+    },
+    validation::bind_path_id,
+};
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RedeemRewardCommand {
+    #[serde(default)]
+    programme_id: String,
+    #[serde(default)]
+    customer_id: String,
+    reward_id: String,
+    /// Chosen by the client so a retried request can't redeem twice, generated if not given.
+    #[serde(default)]
+    redemption_id: Option<String>,
+}
+
+impl RedeemRewardCommand {
+    pub fn bind_path(
+        &mut self,
+        programme_id: String,
+        customer_id: String,
+    ) -> Result<(), LoyaltyErrors> {
+        bind_path_id("programmeId", &mut self.programme_id, programme_id)?;
+        bind_path_id("customerId", &mut self.customer_id, customer_id)?;
+
+        Ok(())
+    }
+}
+
+pub struct RedeemRewardCommandHandler;
+
+impl RedeemRewardCommandHandler {
+    #[tracing::instrument(name = "handle_redeem_reward", skip(loyalty_points, rewards, clock, command), fields(programme_id=command.programme_id, customer_id=command.customer_id, reward_id=command.reward_id, redemption_id=command.redemption_id))]
+    pub async fn handle<T: LoyaltyPoints, R: Rewards, K: Clock>(
+        loyalty_points: &T,
+        rewards: &R,
+        clock: &K,
+        command: RedeemRewardCommand,
+    ) -> anyhow::Result<RedemptionDto, LoyaltyErrors> {
+        if let Some(redemption_id) = &command.redemption_id {
+            match rewards
+                .retrieve_redemption(&command.programme_id, redemption_id)
+                .await
+            {
+                Err(LoyaltyErrors::RedemptionNotFound(_)) => {}
+                Ok(_) => {
+                    return Err(LoyaltyErrors::RedemptionExists(format!(
+                        "Redemption {} already exists",
+                        redemption_id
+                    )))
+                }
+                Err(e) => return Err(e),
+            }
+        }
+
+        let mut account =
+            retrieve_active_account(loyalty_points, &command.programme_id, &command.customer_id)
+                .await?;
+
+        let reward = rewards
+            .retrieve_reward(account.programme_id(), &command.reward_id)
+            .await?;
+
+        let (redemption, transaction) =
+            reward.redeem(&mut account, command.redemption_id, clock.now())?;
+
+        // The event goes into the outbox with the redemption, so it is published even if the
+        // process stops straight after committing
+        let event = RewardRedeemed::new(&reward, &redemption);
+
+        rewards
+            .redeem(&account, transaction, &redemption, &event)
+            .await?;
+
+        Ok(redemption.into())
+    }
+}
+
+/// Publishes the redemption events waiting in the outbox, oldest first.
+pub struct PublishRewardOutboxHandler;
+
+impl PublishRewardOutboxHandler {
+    /// Publishes up to `limit` events, removing each from the outbox once published. Stops at the
+    /// first event that can't be published so events for a customer stay in order, returning how
+    /// many were published.
+    #[tracing::instrument(name = "handle_publish_reward_outbox", skip(outbox, events))]
+    pub async fn handle<O: RewardOutbox, E: RewardEvents>(
+        outbox: &O,
+        events: &E,
+        limit: i64,
+    ) -> anyhow::Result<usize, LoyaltyErrors> {
+        let pending = outbox.pending_reward_redeemed(limit).await?;
+
+        for (published, evt) in pending.iter().enumerate() {
+            if let Err(e) = events.publish_reward_redeemed(evt).await {
+                tracing::error!(
+                    "Failure publishing redemption {}: {:?}",
+                    evt.redemption_id,
+                    e
+                );
+
+                return match published {
+                    0 => Err(e),
+                    _ => Ok(published),
+                };
+            }
+
+            outbox.remove_reward_redeemed(evt).await?;
+        }
+
+        Ok(pending.len())
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UpdateRedemptionStatusCommand {
+    #[serde(default)]
+    programme_id: String,
+    #[serde(default)]
+    redemption_id: String,
+    status: FulfilmentStatus,
+}
+
+impl UpdateRedemptionStatusCommand {
+    pub fn bind_path(
+        &mut self,
+        programme_id: String,
+        redemption_id: String,
+    ) -> Result<(), LoyaltyErrors> {
+        bind_path_id("programmeId", &mut self.programme_id, programme_id)?;
+        bind_path_id("redemptionId", &mut self.redemption_id, redemption_id)?;
+
+        Ok(())
+    }
+}
+
+pub struct UpdateRedemptionStatusCommandHandler;
+
+impl UpdateRedemptionStatusCommandHandler {
+    #[tracing::instrument(name = "handle_update_redemption_status", skip(rewards, command), fields(programme_id=command.programme_id, redemption_id=command.redemption_id, status=command.status.to_string()))]
+    pub async fn handle<R: Rewards>(
+        rewards: &R,
+        command: UpdateRedemptionStatusCommand,
+    ) -> anyhow::Result<RedemptionDto, LoyaltyErrors> {
+        let mut redemption = rewards
+            .retrieve_redemption(&command.programme_id, &command.redemption_id)
+            .await?;
+
+        redemption.update_status(command.status)?;
+
+        rewards.update_redemption_status(&redemption).await?;
+
+        Ok(redemption.into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Utc;
+
+    use crate::{
+        clock::SystemClock,
+        loyalty::{LoyaltyAccount, MockLoyaltyPoints},
+        programmes::DEFAULT_PROGRAMME_ID,
+        rewards::{MockRewardEvents, MockRewardOutbox, MockRewards, Redemption, Reward},
+    };
+
+    use super::*;
+
+    fn loyalty_points(current_points: f32) -> MockLoyaltyPoints {
+        let mut loyalty_points = MockLoyaltyPoints::new();
+        loyalty_points
+            .expect_retrieve()
+            .returning(move |programme_id, customer_id| {
+                LoyaltyAccount::from(
+                    programme_id.to_string(),
+                    customer_id.to_string(),
+                    current_points,
+                    vec![],
+                )
+            });
+        loyalty_points
+    }
+
+    fn rewards(stock: i32) -> MockRewards {
+        let mut rewards = MockRewards::new();
+        rewards
+            .expect_retrieve_reward()
+            .returning(move |programme_id, reward_id| {
+                Ok(Reward::from(
+                    programme_id.to_string(),
+                    reward_id.to_string(),
+                    "Coffee".to_string(),
+                    "A free coffee".to_string(),
+                    50.0,
+                    stock,
+                    None,
+                    None,
+                ))
+            });
+        rewards
+    }
+
+    fn command(redemption_id: Option<&str>) -> RedeemRewardCommand {
+        RedeemRewardCommand {
+            programme_id: DEFAULT_PROGRAMME_ID.to_string(),
+            customer_id: "james".to_string(),
+            reward_id: "RWD-1".to_string(),
+            redemption_id: redemption_id.map(str::to_string),
+        }
+    }
+
+    fn reward_redeemed(redemption_id: &str) -> RewardRedeemed {
+        RewardRedeemed {
+            programme_id: DEFAULT_PROGRAMME_ID.to_string(),
+            redemption_id: redemption_id.to_string(),
+            customer_id: "james".to_string(),
+            reward_id: "RWD-1".to_string(),
+            reward_name: "Coffee".to_string(),
+            points: 50.0,
+            redeemed_at: Utc::now(),
+        }
+    }
+
+    #[tokio::test]
+    async fn on_valid_redemption_points_should_be_taken_and_event_written_to_outbox() {
+        let mut rewards = rewards(10);
+        rewards
+            .expect_retrieve_redemption()
+            .returning(|_, redemption_id| {
+                Err(LoyaltyErrors::RedemptionNotFound(redemption_id.to_string()))
+            });
+        rewards
+            .expect_redeem()
+            .withf(|account, transaction, redemption, evt| {
+                *account.current_points() == 50.0
+                    && transaction.change() == -50.0
+                    && redemption.redemption_id() == "RDM-client-1"
+                    && evt.redemption_id == "RDM-client-1"
+                    && evt.customer_id == "james"
+                    && evt.points == 50.0
+            })
+            .times(1)
+            .returning(|_, _, _, _| Ok(()));
+
+        let result = RedeemRewardCommandHandler::handle(
+            &loyalty_points(100.0),
+            &rewards,
+            &SystemClock,
+            command(Some("RDM-client-1")),
+        )
+        .await;
+
+        let redemption = result.unwrap();
+
+        assert_eq!(redemption.redemption_id, "RDM-client-1");
+        assert_eq!(redemption.status, FulfilmentStatus::Pending);
+        assert_eq!(redemption.points, 50.0);
+    }
+
+    #[tokio::test]
+    async fn on_redemption_with_a_used_id_should_error() {
+        let mut rewards = rewards(10);
+        rewards
+            .expect_retrieve_redemption()
+            .returning(|programme_id, redemption_id| {
+                Ok(Redemption::from(
+                    programme_id.to_string(),
+                    redemption_id.to_string(),
+                    "james".to_string(),
+                    "RWD-1".to_string(),
+                    50.0,
+                    FulfilmentStatus::Pending,
+                    Utc::now(),
+                ))
+            });
+        rewards.expect_redeem().times(0);
+
+        let result = RedeemRewardCommandHandler::handle(
+            &loyalty_points(100.0),
+            &rewards,
+            &SystemClock,
+            command(Some("RDM-client-1")),
+        )
+        .await;
+
+        assert!(matches!(result, Err(LoyaltyErrors::RedemptionExists(_))));
+    }
+
+    #[tokio::test]
+    async fn on_redemption_without_enough_points_should_error() {
+        let mut rewards = rewards(10);
+        rewards.expect_redeem().times(0);
+
+        let result = RedeemRewardCommandHandler::handle(
+            &loyalty_points(10.0),
+            &rewards,
+            &SystemClock,
+            command(None),
+        )
+        .await;
+
+        assert!(matches!(result, Err(LoyaltyErrors::PointsNotAvailable(_))));
+    }
+
+    #[tokio::test]
+    async fn on_redemption_when_stock_runs_out_should_error() {
+        let mut rewards = rewards(1);
+        rewards.expect_redeem().times(1).returning(|_, _, _, _| {
+            Err(LoyaltyErrors::RewardUnavailable("Out of stock".to_string()))
+        });
+
+        let result = RedeemRewardCommandHandler::handle(
+            &loyalty_points(100.0),
+            &rewards,
+            &SystemClock,
+            command(None),
+        )
+        .await;
+
+        assert!(matches!(result, Err(LoyaltyErrors::RewardUnavailable(_))));
+    }
+
+    #[tokio::test]
+    async fn outbox_events_should_be_removed_once_published() {
+        let mut outbox = MockRewardOutbox::new();
+        outbox
+            .expect_pending_reward_redeemed()
+            .returning(|_| Ok(vec![reward_redeemed("RDM-1"), reward_redeemed("RDM-2")]));
+        outbox
+            .expect_remove_reward_redeemed()
+            .times(2)
+            .returning(|_| Ok(()));
+
+        let mut events = MockRewardEvents::new();
+        events
+            .expect_publish_reward_redeemed()
+            .times(2)
+            .returning(|_| Ok(()));
+
+        let published = PublishRewardOutboxHandler::handle(&outbox, &events, 10).await;
+
+        assert_eq!(published.unwrap(), 2);
+    }
+
+    #[tokio::test]
+    async fn outbox_should_stop_at_the_first_event_that_cannot_be_published() {
+        let mut outbox = MockRewardOutbox::new();
+        outbox.expect_pending_reward_redeemed().returning(|_| {
+            Ok(vec![
+                reward_redeemed("RDM-1"),
+                reward_redeemed("RDM-2"),
+                reward_redeemed("RDM-3"),
+            ])
+        });
+        outbox
+            .expect_remove_reward_redeemed()
+            .withf(|evt| evt.redemption_id == "RDM-1")
+            .times(1)
+            .returning(|_| Ok(()));
+
+        let mut events = MockRewardEvents::new();
+        events
+            .expect_publish_reward_redeemed()
+            .withf(|evt| evt.redemption_id == "RDM-1")
+            .returning(|_| Ok(()));
+        events
+            .expect_publish_reward_redeemed()
+            .withf(|evt| evt.redemption_id == "RDM-2")
+            .times(1)
+            .returning(|_| Err(LoyaltyErrors::DatabaseError("Broker down".to_string())));
+
+        let published = PublishRewardOutboxHandler::handle(&outbox, &events, 10).await;
+
+        assert_eq!(published.unwrap(), 1);
+    }
+}
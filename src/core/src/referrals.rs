@@ -0,0 +1,437 @@
+use std::{fmt::Display, str::FromStr};
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::{
+    loyalty::{LoyaltyAccount, LoyaltyAccountTransaction, LoyaltyErrors},
+    profiles::BonusPolicy,
+};
+
+#[cfg(any(test, feature = "mocks"))]
+use mockall::automock;
+
+/// The longest chain of referrers followed when checking a new referral for loops.
+const MAX_REFERRAL_DEPTH: usize = 100;
+
+#[derive(Deserialize, Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ReferralStatus {
+    /// The referred customer has registered but not yet placed an order.
+    Pending,
+    /// The referred customer's first order has been processed and both bonuses granted.
+    Rewarded,
+}
+
+impl Display for ReferralStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let status = match self {
+            ReferralStatus::Pending => "Pending",
+            ReferralStatus::Rewarded => "Rewarded",
+        };
+
+        write!(f, "{}", status)
+    }
+}
+
+impl FromStr for ReferralStatus {
+    type Err = LoyaltyErrors;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "Pending" => Ok(ReferralStatus::Pending),
+            "Rewarded" => Ok(ReferralStatus::Rewarded),
+            _ => Err(LoyaltyErrors::InvalidValues(format!(
+                "'{}' is not a valid referral status",
+                s
+            ))),
+        }
+    }
+}
+
+/// Generates a new shareable code, the code is only assigned once it has been stored.
+pub(crate) fn new_referral_code() -> String {
+    let id = Uuid::new_v4().simple().to_string().to_uppercase();
+
+    format!("REF-{}", &id[..8])
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct ReferralCodeDto {
+    pub programme_id: String,
+    pub customer_id: String,
+    pub referral_code: String,
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct ReferralDto {
+    pub programme_id: String,
+    pub referrer_id: String,
+    pub referee_id: String,
+    pub referral_code: String,
+    pub status: ReferralStatus,
+    pub created_at: DateTime<Utc>,
+    pub rewarded_at: Option<DateTime<Utc>>,
+}
+
+impl From<Referral> for ReferralDto {
+    fn from(value: Referral) -> Self {
+        ReferralDto {
+            programme_id: value.programme_id,
+            referrer_id: value.referrer_id,
+            referee_id: value.referee_id,
+            referral_code: value.referral_code,
+            status: value.status,
+            created_at: value.created_at,
+            rewarded_at: value.rewarded_at,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Referral {
+    programme_id: String,
+    referrer_id: String,
+    referee_id: String,
+    referral_code: String,
+    status: ReferralStatus,
+    created_at: DateTime<Utc>,
+    rewarded_at: Option<DateTime<Utc>>,
+}
+
+impl Referral {
+    /// `referrer_chain` holds the referrer's own referrer, their referrer and so on, a referral is
+    /// rejected if the new customer already appears anywhere in it.
+    pub(crate) fn new(
+        programme_id: String,
+        referrer_id: String,
+        referee_id: String,
+        referral_code: String,
+        referrer_chain: &[String],
+        created_at: DateTime<Utc>,
+    ) -> Result<Self, LoyaltyErrors> {
+        if referrer_id == referee_id {
+            return Err(LoyaltyErrors::InvalidValues(
+                "Customers cannot refer themselves".to_string(),
+            ));
+        }
+
+        if referrer_chain.contains(&referee_id) {
+            return Err(LoyaltyErrors::InvalidValues(format!(
+                "Referral of {} by {} would create a referral loop",
+                referee_id, referrer_id
+            )));
+        }
+
+        Ok(Self {
+            programme_id,
+            referrer_id,
+            referee_id,
+            referral_code,
+            status: ReferralStatus::Pending,
+            created_at,
+            rewarded_at: None,
+        })
+    }
+
+    pub fn from(
+        programme_id: String,
+        referrer_id: String,
+        referee_id: String,
+        referral_code: String,
+        status: ReferralStatus,
+        created_at: DateTime<Utc>,
+        rewarded_at: Option<DateTime<Utc>>,
+    ) -> Self {
+        Self {
+            programme_id,
+            referrer_id,
+            referee_id,
+            referral_code,
+            status,
+            created_at,
+            rewarded_at,
+        }
+    }
+
+    pub fn programme_id(&self) -> &str {
+        &self.programme_id
+    }
+    pub fn referrer_id(&self) -> &str {
+        &self.referrer_id
+    }
+    pub fn referee_id(&self) -> &str {
+        &self.referee_id
+    }
+    pub fn referral_code(&self) -> &str {
+        &self.referral_code
+    }
+    pub fn status(&self) -> ReferralStatus {
+        self.status
+    }
+    pub fn created_at(&self) -> DateTime<Utc> {
+        self.created_at
+    }
+    pub fn rewarded_at(&self) -> Option<DateTime<Utc>> {
+        self.rewarded_at
+    }
+
+    /// Grants the configured bonus to both sides of the referral. A referrer whose account can no
+    /// longer be adjusted misses out, but the referred customer is still rewarded.
+    pub(crate) fn reward(
+        &mut self,
+        bonuses: &BonusPolicy,
+        referee: &mut LoyaltyAccount,
+        referrer: Option<&mut LoyaltyAccount>,
+        at: DateTime<Utc>,
+    ) -> Result<ReferralBonuses, LoyaltyErrors> {
+        if self.status != ReferralStatus::Pending {
+            return Err(LoyaltyErrors::InvalidValues(format!(
+                "Referral of {} has already been rewarded",
+                self.referee_id
+            )));
+        }
+
+        let reference = format!("REFERRAL-{}", self.referee_id);
+
+        let referee_bonus = match bonuses.referee_bonus() {
+            points if points > 0.0 => Some(referee.adjust_points(&reference, points, at)?),
+            _ => None,
+        };
+
+        let referrer_bonus = match (referrer, bonuses.referrer_bonus()) {
+            (Some(referrer), points) if points > 0.0 => {
+                referrer.adjust_points(&reference, points, at).ok()
+            }
+            _ => None,
+        };
+
+        self.status = ReferralStatus::Rewarded;
+        self.rewarded_at = Some(at);
+
+        Ok(ReferralBonuses {
+            referee_bonus,
+            referrer_bonus,
+        })
+    }
+}
+
+/// The transactions created when a referral is rewarded, either side is empty when its bonus is
+/// disabled.
+#[derive(Clone)]
+pub struct ReferralBonuses {
+    pub referee_bonus: Option<LoyaltyAccountTransaction>,
+    pub referrer_bonus: Option<LoyaltyAccountTransaction>,
+}
+
+/// Validates and stores a referral for a newly registered customer.
+pub(crate) async fn register_referral<R: Referrals>(
+    referrals: &R,
+    programme_id: &str,
+    referee_id: &str,
+    referral_code: &str,
+    at: DateTime<Utc>,
+) -> Result<Referral, LoyaltyErrors> {
+    let referrer_id = referrals
+        .customer_for_referral_code(programme_id, referral_code)
+        .await?;
+
+    match referrals.retrieve_referral(programme_id, referee_id).await {
+        Ok(_) => {
+            return Err(LoyaltyErrors::InvalidValues(format!(
+                "{} has already been referred",
+                referee_id
+            )))
+        }
+        Err(LoyaltyErrors::ReferralNotFound(_)) => {}
+        Err(e) => return Err(e),
+    }
+
+    let mut referrer_chain: Vec<String> = vec![];
+    let mut current = referrer_id.clone();
+
+    while referrer_chain.len() < MAX_REFERRAL_DEPTH {
+        match referrals.retrieve_referral(programme_id, &current).await {
+            Ok(referral) => {
+                if referrer_chain.contains(&referral.referrer_id) {
+                    break;
+                }
+
+                current = referral.referrer_id.clone();
+                referrer_chain.push(referral.referrer_id);
+            }
+            Err(LoyaltyErrors::ReferralNotFound(_)) => break,
+            Err(e) => return Err(e),
+        }
+    }
+
+    let referral = Referral::new(
+        programme_id.to_string(),
+        referrer_id,
+        referee_id.to_string(),
+        referral_code.to_string(),
+        &referrer_chain,
+        at,
+    )?;
+
+    referrals.save_referral(&referral).await?;
+
+    Ok(referral)
+}
+
+#[cfg_attr(any(test, feature = "mocks"), automock)]
+#[async_trait]
+pub trait Referrals {
+    /// Stores `referral_code` for the customer unless they already have one, returning whichever
+    /// code is assigned to them.
+    async fn assign_referral_code(
+        &self,
+        programme_id: &str,
+        customer_id: &str,
+        referral_code: &str,
+    ) -> Result<String, LoyaltyErrors>;
+    async fn customer_for_referral_code(
+        &self,
+        programme_id: &str,
+        referral_code: &str,
+    ) -> Result<String, LoyaltyErrors>;
+    async fn save_referral(&self, referral: &Referral) -> Result<(), LoyaltyErrors>;
+    /// Retrieves the referral that brought `referee_id` in, if there is one.
+    async fn retrieve_referral(
+        &self,
+        programme_id: &str,
+        referee_id: &str,
+    ) -> Result<Referral, LoyaltyErrors>;
+    async fn list_referrals(
+        &self,
+        programme_id: &str,
+        referrer_id: &str,
+    ) -> Result<Vec<Referral>, LoyaltyErrors>;
+    /// Marks the referral rewarded and applies both bonuses in a single atomic write. Returns
+    /// false without writing anything if the referral has already been rewarded.
+    async fn reward_referral<'a>(
+        &self,
+        referral: &Referral,
+        referee: &LoyaltyAccount,
+        referrer: Option<&'a LoyaltyAccount>,
+        bonuses: ReferralBonuses,
+    ) -> Result<bool, LoyaltyErrors>;
+}
+
+#[cfg(test)]
+mod tests {
+    use mockall::predicate;
+
+    use crate::programmes::DEFAULT_PROGRAMME_ID;
+
+    use super::*;
+
+    fn pending_referral(referrer_id: &str, referee_id: &str) -> Referral {
+        Referral::from(
+            DEFAULT_PROGRAMME_ID.to_string(),
+            referrer_id.to_string(),
+            referee_id.to_string(),
+            "REF-1".to_string(),
+            ReferralStatus::Pending,
+            Utc::now(),
+            None,
+        )
+    }
+
+    #[test]
+    fn customer_cannot_refer_themselves() {
+        let result = Referral::new(
+            DEFAULT_PROGRAMME_ID.to_string(),
+            "james".to_string(),
+            "james".to_string(),
+            "REF-1".to_string(),
+            &[],
+            Utc::now(),
+        );
+
+        assert!(matches!(result, Err(LoyaltyErrors::InvalidValues(_))));
+    }
+
+    #[test]
+    fn referral_loop_should_be_rejected() {
+        let result = Referral::new(
+            DEFAULT_PROGRAMME_ID.to_string(),
+            "bob".to_string(),
+            "james".to_string(),
+            "REF-1".to_string(),
+            &["alice".to_string(), "james".to_string()],
+            Utc::now(),
+        );
+
+        assert!(matches!(result, Err(LoyaltyErrors::InvalidValues(_))));
+    }
+
+    #[test]
+    fn rewarding_should_credit_both_sides_once() {
+        let mut referral = pending_referral("alice", "james");
+        let mut referee = LoyaltyAccount::from(
+            DEFAULT_PROGRAMME_ID.to_string(),
+            "james".to_string(),
+            0.0,
+            vec![],
+        )
+        .unwrap();
+        let mut referrer = LoyaltyAccount::from(
+            DEFAULT_PROGRAMME_ID.to_string(),
+            "alice".to_string(),
+            0.0,
+            vec![],
+        )
+        .unwrap();
+
+        let bonuses = referral
+            .reward(
+                &BonusPolicy::default(),
+                &mut referee,
+                Some(&mut referrer),
+                Utc::now(),
+            )
+            .unwrap();
+
+        assert_eq!(*referee.current_points(), 100.0);
+        assert_eq!(*referrer.current_points(), 200.0);
+        assert!(bonuses.referee_bonus.is_some() && bonuses.referrer_bonus.is_some());
+        assert_eq!(referral.status(), ReferralStatus::Rewarded);
+        assert!(referral
+            .reward(&BonusPolicy::default(), &mut referee, None, Utc::now())
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn registering_referral_that_closes_a_loop_should_fail() {
+        let mut referrals = MockReferrals::new();
+        referrals
+            .expect_customer_for_referral_code()
+            .returning(|_, _| Ok("bob".to_string()));
+        referrals
+            .expect_retrieve_referral()
+            .with(predicate::eq(DEFAULT_PROGRAMME_ID), predicate::eq("james"))
+            .returning(|_, id| Err(LoyaltyErrors::ReferralNotFound(id.to_string())));
+        referrals
+            .expect_retrieve_referral()
+            .with(predicate::eq(DEFAULT_PROGRAMME_ID), predicate::eq("bob"))
+            .returning(|_, _| Ok(pending_referral("alice", "bob")));
+        referrals
+            .expect_retrieve_referral()
+            .with(predicate::eq(DEFAULT_PROGRAMME_ID), predicate::eq("alice"))
+            .returning(|_, _| Ok(pending_referral("james", "alice")));
+        referrals.expect_save_referral().times(0);
+
+        let result = register_referral(
+            &referrals,
+            DEFAULT_PROGRAMME_ID,
+            "james",
+            "REF-BOB",
+            Utc::now(),
+        )
+        .await;
+
+        assert!(matches!(result, Err(LoyaltyErrors::InvalidValues(_))));
+    }
+}
@@ -0,0 +1,135 @@
+use serde::Deserialize;
+
+use crate::{
+    clock::Clock,
+    loyalty::{retrieve_active_account, LoyaltyErrors, LoyaltyPoints},
+    validation::bind_path_id,
+    LoyaltyDto,
+};
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RefundOrderCommand {
+    #[serde(default)]
+    programme_id: String,
+    #[serde(default)]
+    customer_id: String,
+    order_number: String,
+}
+
+impl RefundOrderCommand {
+    pub fn bind_path(
+        &mut self,
+        programme_id: String,
+        customer_id: String,
+    ) -> Result<(), LoyaltyErrors> {
+        bind_path_id("programmeId", &mut self.programme_id, programme_id)?;
+        bind_path_id("customerId", &mut self.customer_id, customer_id)?;
+
+        Ok(())
+    }
+}
+
+pub struct RefundOrderCommandHandler;
+
+impl RefundOrderCommandHandler {
+    #[tracing::instrument(name = "handle_refund_order", skip(loyalty_points, clock, command), fields(programme_id=command.programme_id, customer_id=command.customer_id, order_number=command.order_number))]
+    pub async fn handle<T: LoyaltyPoints, K: Clock>(
+        loyalty_points: &T,
+        clock: &K,
+        command: RefundOrderCommand,
+    ) -> anyhow::Result<LoyaltyDto, LoyaltyErrors> {
+        let mut account =
+            retrieve_active_account(loyalty_points, &command.programme_id, &command.customer_id)
+                .await?;
+
+        let transaction = account.refund_order(&command.order_number, clock.now())?;
+
+        loyalty_points
+            .add_transaction(&account, transaction)
+            .await?;
+
+        Ok(account.into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{Duration, Utc};
+
+    use crate::{
+        clock::SystemClock,
+        loyalty::{LoyaltyAccount, LoyaltyAccountTransaction, MockLoyaltyPoints},
+        programmes::DEFAULT_PROGRAMME_ID,
+    };
+
+    use super::*;
+
+    #[tokio::test]
+    async fn on_refund_of_pending_order_pending_points_should_be_cancelled() {
+        let mut loyalty_points = MockLoyaltyPoints::new();
+        loyalty_points
+            .expect_retrieve()
+            .times(1)
+            .returning(|programme_id, customer_id| {
+                LoyaltyAccount::from(
+                    programme_id.to_string(),
+                    customer_id.to_string(),
+                    60.0,
+                    vec![
+                        LoyaltyAccountTransaction::new(Utc::now(), "ORD1".to_string(), 50.0)
+                            .with_vests_at(Some(Utc::now() + Duration::days(14))),
+                    ],
+                )
+            });
+        loyalty_points
+            .expect_add_transaction()
+            .withf(|_, transaction| {
+                transaction.change() == -50.0 && transaction.vests_at().is_some()
+            })
+            .times(1)
+            .returning(|_, _| Ok(()));
+
+        let command = RefundOrderCommand {
+            programme_id: DEFAULT_PROGRAMME_ID.to_string(),
+            customer_id: "james".to_string(),
+            order_number: "ORD1".to_string(),
+        };
+
+        let account = RefundOrderCommandHandler::handle(&loyalty_points, &SystemClock, command)
+            .await
+            .unwrap();
+
+        assert_eq!(account.current_points, 10.0);
+        assert_eq!(account.pending_points, 0.0);
+        assert_eq!(account.available_points, 10.0);
+    }
+
+    #[tokio::test]
+    async fn on_refund_of_unknown_order_should_error() {
+        let mut loyalty_points = MockLoyaltyPoints::new();
+        loyalty_points
+            .expect_retrieve()
+            .times(1)
+            .returning(|programme_id, customer_id| {
+                LoyaltyAccount::from(
+                    programme_id.to_string(),
+                    customer_id.to_string(),
+                    10.0,
+                    vec![],
+                )
+            });
+        loyalty_points.expect_add_transaction().times(0);
+
+        let command = RefundOrderCommand {
+            programme_id: DEFAULT_PROGRAMME_ID.to_string(),
+            customer_id: "james".to_string(),
+            order_number: "ORD1".to_string(),
+        };
+
+        let result =
+            RefundOrderCommandHandler::handle(&loyalty_points, &SystemClock, command).await;
+
+        assert!(matches!(result, Err(LoyaltyErrors::InvalidValues(_))));
+    }
+}
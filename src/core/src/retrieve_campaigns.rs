@@ -0,0 +1,35 @@
+use crate::{
+    campaigns::{CampaignDto, Campaigns},
+    loyalty::LoyaltyErrors,
+};
+
+pub struct ListCampaignsQueryHandler;
+
+impl ListCampaignsQueryHandler {
+    #[tracing::instrument(name = "handle_list_campaigns", skip(campaigns))]
+    pub async fn handle<C: Campaigns>(
+        campaigns: &C,
+        programme_id: String,
+    ) -> anyhow::Result<Vec<CampaignDto>, LoyaltyErrors> {
+        let campaigns = campaigns.list_campaigns(&programme_id).await?;
+
+        Ok(campaigns.into_iter().map(|campaign| campaign.into()).collect())
+    }
+}
+
+pub struct RetrieveCampaignQueryHandler;
+
+impl RetrieveCampaignQueryHandler {
+    #[tracing::instrument(name = "handle_retrieve_campaign", skip(campaigns))]
+    pub async fn handle<C: Campaigns>(
+        campaigns: &C,
+        programme_id: String,
+        campaign_id: String,
+    ) -> anyhow::Result<CampaignDto, LoyaltyErrors> {
+        let campaign = campaigns
+            .retrieve_campaign(&programme_id, &campaign_id)
+            .await?;
+
+        Ok(campaign.into())
+    }
+}
@@ -1,18 +1,42 @@
-use crate::{loyalty::LoyaltyPoints, LoyaltyDto};
+use crate::{
+    households::Households,
+    loyalty::{retrieve_active_account, LoyaltyErrors, LoyaltyPoints},
+    LoyaltyDto,
+};
 
 pub struct RetrieveLoyaltyAccountQueryHandler;
 
 impl RetrieveLoyaltyAccountQueryHandler {
-    #[tracing::instrument(name = "handle_retrieve_loyalty_account", skip(loyalty_points))]
-    pub async fn handle<T: LoyaltyPoints>(loyalty_points: &T, customer_id: String) -> Result<LoyaltyDto, ()> {
-        let loyalty_points = loyalty_points
-            .retrieve(&customer_id)
+    #[tracing::instrument(name = "handle_retrieve_loyalty_account", skip(loyalty_points, households))]
+    pub async fn handle<T: LoyaltyPoints, H: Households>(
+        loyalty_points: &T,
+        households: &H,
+        programme_id: String,
+        customer_id: String,
+    ) -> Result<LoyaltyDto, ()> {
+        let loyalty_points = retrieve_active_account(loyalty_points, &programme_id, &customer_id)
             .await
             .map_err(|e| {
                 tracing::error!("Failure retrieving loyalty points: {:?}", e);
                 
             })?;
 
-        Ok(loyalty_points.into())
+        let household = match households
+            .household_for_member(loyalty_points.programme_id(), loyalty_points.customer_id())
+            .await
+        {
+            Ok(household) => household.membership(loyalty_points.customer_id()),
+            Err(LoyaltyErrors::HouseholdNotFound(_)) => None,
+            Err(e) => {
+                tracing::error!("Failure retrieving household: {:?}", e);
+
+                return Err(());
+            }
+        };
+
+        Ok(LoyaltyDto {
+            household,
+            ..loyalty_points.into()
+        })
     }
 }
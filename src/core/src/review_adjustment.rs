@@ -0,0 +1,217 @@
+use serde::Deserialize;
+
+use crate::{
+    adjustments::{PointsAdjustmentDto, PointsAdjustments},
+    clock::Clock,
+    loyalty::{retrieve_active_account, LoyaltyErrors, LoyaltyPoints},
+    validation::bind_path_id,
+};
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReviewAdjustmentCommand {
+    #[serde(default)]
+    programme_id: String,
+    #[serde(default)]
+    adjustment_id: String,
+    /// The authenticated operator reviewing the adjustment, never taken from the body.
+    #[serde(skip)]
+    operator_id: String,
+}
+
+impl ReviewAdjustmentCommand {
+    pub fn bind_path(
+        &mut self,
+        programme_id: String,
+        adjustment_id: String,
+    ) -> Result<(), LoyaltyErrors> {
+        bind_path_id("programmeId", &mut self.programme_id, programme_id)?;
+        bind_path_id("adjustmentId", &mut self.adjustment_id, adjustment_id)?;
+
+        Ok(())
+    }
+
+    pub fn bind_operator(&mut self, operator_id: String) {
+        self.operator_id = operator_id;
+    }
+}
+
+pub struct ApproveAdjustmentCommandHandler;
+
+impl ApproveAdjustmentCommandHandler {
+    #[tracing::instrument(name = "handle_approve_adjustment", skip(loyalty_points, adjustments, clock, command), fields(programme_id=command.programme_id, adjustment_id=command.adjustment_id, operator_id=command.operator_id))]
+    pub async fn handle<T: LoyaltyPoints, A: PointsAdjustments, K: Clock>(
+        loyalty_points: &T,
+        adjustments: &A,
+        clock: &K,
+        command: ReviewAdjustmentCommand,
+    ) -> anyhow::Result<PointsAdjustmentDto, LoyaltyErrors> {
+        let mut adjustment = adjustments
+            .retrieve_adjustment(&command.programme_id, &command.adjustment_id)
+            .await?;
+
+        let mut account = retrieve_active_account(
+            loyalty_points,
+            adjustment.programme_id(),
+            adjustment.customer_id(),
+        )
+        .await?;
+
+        let transaction = adjustment.approve(&mut account, command.operator_id, clock.now())?;
+
+        adjustments
+            .apply_adjustment(&account, transaction, &adjustment)
+            .await?;
+
+        Ok(adjustment.into())
+    }
+}
+
+pub struct RejectAdjustmentCommandHandler;
+
+impl RejectAdjustmentCommandHandler {
+    #[tracing::instrument(name = "handle_reject_adjustment", skip(adjustments, clock, command), fields(programme_id=command.programme_id, adjustment_id=command.adjustment_id, operator_id=command.operator_id))]
+    pub async fn handle<A: PointsAdjustments, K: Clock>(
+        adjustments: &A,
+        clock: &K,
+        command: ReviewAdjustmentCommand,
+    ) -> anyhow::Result<PointsAdjustmentDto, LoyaltyErrors> {
+        let mut adjustment = adjustments
+            .retrieve_adjustment(&command.programme_id, &command.adjustment_id)
+            .await?;
+
+        adjustment.reject(command.operator_id, clock.now())?;
+
+        adjustments.save_adjustment(&adjustment).await?;
+
+        Ok(adjustment.into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Utc;
+
+    use crate::{
+        adjustments::{AdjustmentStatus, MockPointsAdjustments, PointsAdjustment, ReasonCode},
+        clock::SystemClock,
+        loyalty::{LoyaltyAccount, MockLoyaltyPoints},
+        programmes::DEFAULT_PROGRAMME_ID,
+    };
+
+    use super::*;
+
+    fn pending_adjustment() -> PointsAdjustment {
+        PointsAdjustment::from(
+            DEFAULT_PROGRAMME_ID.to_string(),
+            "ADJ-1".to_string(),
+            "james".to_string(),
+            1000.0,
+            ReasonCode::Compensation,
+            "Damaged parcel".to_string(),
+            "agent-1".to_string(),
+            AdjustmentStatus::Pending,
+            None,
+            Utc::now(),
+            None,
+        )
+    }
+
+    fn command(operator_id: &str) -> ReviewAdjustmentCommand {
+        ReviewAdjustmentCommand {
+            programme_id: DEFAULT_PROGRAMME_ID.to_string(),
+            adjustment_id: "ADJ-1".to_string(),
+            operator_id: operator_id.to_string(),
+        }
+    }
+
+    #[tokio::test]
+    async fn on_approval_by_second_operator_points_should_be_applied() {
+        let mut loyalty_points = MockLoyaltyPoints::new();
+        loyalty_points
+            .expect_retrieve()
+            .times(1)
+            .returning(|programme_id, customer_id| {
+                LoyaltyAccount::from(
+                    programme_id.to_string(),
+                    customer_id.to_string(),
+                    0.0,
+                    vec![],
+                )
+            });
+        let mut adjustments = MockPointsAdjustments::new();
+        adjustments
+            .expect_retrieve_adjustment()
+            .times(1)
+            .returning(|_, _| Ok(pending_adjustment()));
+        adjustments
+            .expect_apply_adjustment()
+            .times(1)
+            .returning(|_, _, _| Ok(()));
+
+        let result = ApproveAdjustmentCommandHandler::handle(
+            &loyalty_points,
+            &adjustments,
+            &SystemClock,
+            command("agent-2"),
+        )
+        .await;
+
+        let adjustment = result.unwrap();
+
+        assert_eq!(adjustment.status, AdjustmentStatus::Applied);
+        assert_eq!(adjustment.reviewed_by, Some("agent-2".to_string()));
+    }
+
+    #[tokio::test]
+    async fn on_approval_by_requesting_operator_should_error() {
+        let mut loyalty_points = MockLoyaltyPoints::new();
+        loyalty_points
+            .expect_retrieve()
+            .times(1)
+            .returning(|programme_id, customer_id| {
+                LoyaltyAccount::from(
+                    programme_id.to_string(),
+                    customer_id.to_string(),
+                    0.0,
+                    vec![],
+                )
+            });
+        let mut adjustments = MockPointsAdjustments::new();
+        adjustments
+            .expect_retrieve_adjustment()
+            .times(1)
+            .returning(|_, _| Ok(pending_adjustment()));
+        adjustments.expect_apply_adjustment().times(0);
+
+        let result = ApproveAdjustmentCommandHandler::handle(
+            &loyalty_points,
+            &adjustments,
+            &SystemClock,
+            command("agent-1"),
+        )
+        .await;
+
+        assert!(result.is_err());
+    }
+
+    #[tokio::test]
+    async fn on_rejection_adjustment_should_be_saved_as_rejected() {
+        let mut adjustments = MockPointsAdjustments::new();
+        adjustments
+            .expect_retrieve_adjustment()
+            .times(1)
+            .returning(|_, _| Ok(pending_adjustment()));
+        adjustments
+            .expect_save_adjustment()
+            .withf(|adjustment| adjustment.status() == AdjustmentStatus::Rejected)
+            .times(1)
+            .returning(|_| Ok(()));
+
+        let result =
+            RejectAdjustmentCommandHandler::handle(&adjustments, &SystemClock, command("agent-2"))
+                .await;
+
+        assert_eq!(result.unwrap().status, AdjustmentStatus::Rejected);
+    }
+}
@@ -0,0 +1,524 @@
+use std::{fmt::Display, str::FromStr};
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::loyalty::{LoyaltyAccount, LoyaltyAccountTransaction, LoyaltyErrors};
+
+#[cfg(any(test, feature = "mocks"))]
+use mockall::automock;
+
+#[derive(Deserialize, Serialize, Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum FulfilmentStatus {
+    /// The points have been taken and the reward is waiting to be fulfilled.
+    Pending,
+    /// The reward has been sent to the customer.
+    Dispatched,
+    /// The customer has received the reward.
+    Fulfilled,
+}
+
+impl Display for FulfilmentStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let status = match self {
+            FulfilmentStatus::Pending => "Pending",
+            FulfilmentStatus::Dispatched => "Dispatched",
+            FulfilmentStatus::Fulfilled => "Fulfilled",
+        };
+
+        write!(f, "{}", status)
+    }
+}
+
+impl FromStr for FulfilmentStatus {
+    type Err = LoyaltyErrors;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "Pending" => Ok(FulfilmentStatus::Pending),
+            "Dispatched" => Ok(FulfilmentStatus::Dispatched),
+            "Fulfilled" => Ok(FulfilmentStatus::Fulfilled),
+            _ => Err(LoyaltyErrors::InvalidValues(format!(
+                "'{}' is not a valid fulfilment status",
+                s
+            ))),
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct RewardDto {
+    pub programme_id: String,
+    pub reward_id: String,
+    pub name: String,
+    pub description: String,
+    pub points_cost: f32,
+    pub stock: i32,
+    pub available_from: Option<DateTime<Utc>>,
+    pub available_until: Option<DateTime<Utc>>,
+}
+
+impl From<Reward> for RewardDto {
+    fn from(value: Reward) -> Self {
+        RewardDto {
+            programme_id: value.programme_id,
+            reward_id: value.reward_id,
+            name: value.name,
+            description: value.description,
+            points_cost: value.points_cost,
+            stock: value.stock,
+            available_from: value.available_from,
+            available_until: value.available_until,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Reward {
+    programme_id: String,
+    reward_id: String,
+    name: String,
+    description: String,
+    points_cost: f32,
+    stock: i32,
+    available_from: Option<DateTime<Utc>>,
+    available_until: Option<DateTime<Utc>>,
+}
+
+impl Reward {
+    pub(crate) fn new(
+        programme_id: String,
+        name: String,
+        description: String,
+        points_cost: f32,
+        stock: i32,
+        available_from: Option<DateTime<Utc>>,
+        available_until: Option<DateTime<Utc>>,
+    ) -> Result<Self, LoyaltyErrors> {
+        if name.trim().is_empty() {
+            return Err(LoyaltyErrors::InvalidValues(
+                "Reward name cannot be empty".to_string(),
+            ));
+        }
+
+        if !points_cost.is_finite() || points_cost <= 0.0 {
+            return Err(LoyaltyErrors::InvalidValues(
+                "Reward cost must be greater than zero".to_string(),
+            ));
+        }
+
+        if stock < 0 {
+            return Err(LoyaltyErrors::InvalidValues(
+                "Reward stock cannot be negative".to_string(),
+            ));
+        }
+
+        if let (Some(from), Some(until)) = (available_from, available_until) {
+            if until <= from {
+                return Err(LoyaltyErrors::InvalidValues(
+                    "Reward availability must end after it starts".to_string(),
+                ));
+            }
+        }
+
+        Ok(Self {
+            programme_id,
+            reward_id: format!("RWD-{}", Uuid::new_v4()),
+            name,
+            description,
+            points_cost,
+            stock,
+            available_from,
+            available_until,
+        })
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub fn from(
+        programme_id: String,
+        reward_id: String,
+        name: String,
+        description: String,
+        points_cost: f32,
+        stock: i32,
+        available_from: Option<DateTime<Utc>>,
+        available_until: Option<DateTime<Utc>>,
+    ) -> Self {
+        Self {
+            programme_id,
+            reward_id,
+            name,
+            description,
+            points_cost,
+            stock,
+            available_from,
+            available_until,
+        }
+    }
+
+    pub fn programme_id(&self) -> &str {
+        &self.programme_id
+    }
+    pub fn reward_id(&self) -> &str {
+        &self.reward_id
+    }
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+    pub fn description(&self) -> &str {
+        &self.description
+    }
+    pub fn points_cost(&self) -> f32 {
+        self.points_cost
+    }
+    pub fn stock(&self) -> i32 {
+        self.stock
+    }
+    pub fn available_from(&self) -> Option<DateTime<Utc>> {
+        self.available_from
+    }
+    pub fn available_until(&self) -> Option<DateTime<Utc>> {
+        self.available_until
+    }
+
+    pub(crate) fn ensure_available(&self, at: DateTime<Utc>) -> Result<(), LoyaltyErrors> {
+        if self.available_from.is_some_and(|from| at < from)
+            || self.available_until.is_some_and(|until| at >= until)
+        {
+            return Err(LoyaltyErrors::RewardUnavailable(format!(
+                "Reward {} is not available",
+                self.reward_id
+            )));
+        }
+
+        if self.stock <= 0 {
+            return Err(LoyaltyErrors::RewardUnavailable(format!(
+                "Reward {} is out of stock",
+                self.reward_id
+            )));
+        }
+
+        Ok(())
+    }
+
+    /// Takes the cost of the reward from the account, returning the redemption record and the
+    /// transaction that pays for it. The redemption id is generated unless the client chose one.
+    pub(crate) fn redeem(
+        &self,
+        account: &mut LoyaltyAccount,
+        redemption_id: Option<String>,
+        at: DateTime<Utc>,
+    ) -> Result<(Redemption, LoyaltyAccountTransaction), LoyaltyErrors> {
+        self.ensure_available(at)?;
+
+        let redemption_id = match redemption_id {
+            Some(redemption_id) if redemption_id.trim().is_empty() => {
+                return Err(LoyaltyErrors::InvalidValues(
+                    "Redemption id cannot be empty".to_string(),
+                ))
+            }
+            Some(redemption_id) => redemption_id,
+            None => format!("RDM-{}", Uuid::new_v4()),
+        };
+
+        let redemption = Redemption {
+            programme_id: self.programme_id.clone(),
+            redemption_id,
+            customer_id: account.customer_id().to_string(),
+            reward_id: self.reward_id.clone(),
+            points: self.points_cost,
+            status: FulfilmentStatus::Pending,
+            created_at: at,
+        };
+
+        let transaction = account.spend_points(&redemption.redemption_id, &self.points_cost, at)?;
+
+        Ok((redemption, transaction))
+    }
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct RedemptionDto {
+    pub programme_id: String,
+    pub redemption_id: String,
+    pub customer_id: String,
+    pub reward_id: String,
+    pub points: f32,
+    pub status: FulfilmentStatus,
+    pub created_at: DateTime<Utc>,
+}
+
+impl From<Redemption> for RedemptionDto {
+    fn from(value: Redemption) -> Self {
+        RedemptionDto {
+            programme_id: value.programme_id,
+            redemption_id: value.redemption_id,
+            customer_id: value.customer_id,
+            reward_id: value.reward_id,
+            points: value.points,
+            status: value.status,
+            created_at: value.created_at,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Redemption {
+    programme_id: String,
+    redemption_id: String,
+    customer_id: String,
+    reward_id: String,
+    points: f32,
+    status: FulfilmentStatus,
+    created_at: DateTime<Utc>,
+}
+
+impl Redemption {
+    pub fn from(
+        programme_id: String,
+        redemption_id: String,
+        customer_id: String,
+        reward_id: String,
+        points: f32,
+        status: FulfilmentStatus,
+        created_at: DateTime<Utc>,
+    ) -> Self {
+        Self {
+            programme_id,
+            redemption_id,
+            customer_id,
+            reward_id,
+            points,
+            status,
+            created_at,
+        }
+    }
+
+    pub fn programme_id(&self) -> &str {
+        &self.programme_id
+    }
+    pub fn redemption_id(&self) -> &str {
+        &self.redemption_id
+    }
+    pub fn customer_id(&self) -> &str {
+        &self.customer_id
+    }
+    pub fn reward_id(&self) -> &str {
+        &self.reward_id
+    }
+    pub fn points(&self) -> f32 {
+        self.points
+    }
+    pub fn status(&self) -> FulfilmentStatus {
+        self.status
+    }
+    pub fn created_at(&self) -> DateTime<Utc> {
+        self.created_at
+    }
+
+    /// Fulfilment only ever moves forward, Pending -> Dispatched -> Fulfilled.
+    pub(crate) fn update_status(&mut self, status: FulfilmentStatus) -> Result<(), LoyaltyErrors> {
+        if status <= self.status {
+            return Err(LoyaltyErrors::InvalidValues(format!(
+                "Redemption {} cannot move from {} to {}",
+                self.redemption_id, self.status, status
+            )));
+        }
+
+        self.status = status;
+
+        Ok(())
+    }
+}
+
+/// Published once a redemption has been committed so fulfilment can pick it up.
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq)]
+pub struct RewardRedeemed {
+    pub programme_id: String,
+    pub redemption_id: String,
+    pub customer_id: String,
+    pub reward_id: String,
+    pub reward_name: String,
+    pub points: f32,
+    pub redeemed_at: DateTime<Utc>,
+}
+
+impl RewardRedeemed {
+    pub(crate) fn new(reward: &Reward, redemption: &Redemption) -> Self {
+        Self {
+            programme_id: redemption.programme_id.clone(),
+            redemption_id: redemption.redemption_id.clone(),
+            customer_id: redemption.customer_id.clone(),
+            reward_id: reward.reward_id.clone(),
+            reward_name: reward.name.clone(),
+            points: redemption.points,
+            redeemed_at: redemption.created_at,
+        }
+    }
+}
+
+#[cfg_attr(any(test, feature = "mocks"), automock)]
+#[async_trait]
+pub trait Rewards {
+    async fn save_reward(&self, reward: &Reward) -> Result<(), LoyaltyErrors>;
+    async fn retrieve_reward(
+        &self,
+        programme_id: &str,
+        reward_id: &str,
+    ) -> Result<Reward, LoyaltyErrors>;
+    async fn list_rewards(&self, programme_id: &str) -> Result<Vec<Reward>, LoyaltyErrors>;
+    /// Takes the points, decrements the reward stock, records the redemption and adds `event` to
+    /// the outbox in a single atomic write. Fails with `RewardUnavailable` if the stock has
+    /// already run out, and `RedemptionExists` if the redemption id has been used before.
+    async fn redeem(
+        &self,
+        account: &LoyaltyAccount,
+        transaction: LoyaltyAccountTransaction,
+        redemption: &Redemption,
+        event: &RewardRedeemed,
+    ) -> Result<(), LoyaltyErrors>;
+    async fn retrieve_redemption(
+        &self,
+        programme_id: &str,
+        redemption_id: &str,
+    ) -> Result<Redemption, LoyaltyErrors>;
+    async fn update_redemption_status(&self, redemption: &Redemption) -> Result<(), LoyaltyErrors>;
+}
+
+#[cfg_attr(any(test, feature = "mocks"), automock)]
+#[async_trait]
+pub trait RewardEvents {
+    async fn publish_reward_redeemed(&self, evt: &RewardRedeemed) -> Result<(), LoyaltyErrors>;
+}
+
+/// Redemption events written to the outbox with their redemption, waiting to be published.
+#[cfg_attr(any(test, feature = "mocks"), automock)]
+#[async_trait]
+pub trait RewardOutbox {
+    /// The oldest events not yet published, at most `limit` of them.
+    async fn pending_reward_redeemed(
+        &self,
+        limit: i64,
+    ) -> Result<Vec<RewardRedeemed>, LoyaltyErrors>;
+    /// Removes an event from the outbox once it has been published.
+    async fn remove_reward_redeemed(&self, evt: &RewardRedeemed) -> Result<(), LoyaltyErrors>;
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Duration;
+
+    use crate::programmes::DEFAULT_PROGRAMME_ID;
+
+    use super::*;
+
+    fn reward(stock: i32) -> Reward {
+        Reward::from(
+            DEFAULT_PROGRAMME_ID.to_string(),
+            "RWD-1".to_string(),
+            "Coffee".to_string(),
+            "A free coffee".to_string(),
+            50.0,
+            stock,
+            None,
+            None,
+        )
+    }
+
+    #[test]
+    fn redeeming_should_take_points_from_account() {
+        let mut account = LoyaltyAccount::from(
+            DEFAULT_PROGRAMME_ID.to_string(),
+            "james".to_string(),
+            100.0,
+            vec![],
+        )
+        .unwrap();
+
+        let (redemption, transaction) = reward(1).redeem(&mut account, None, Utc::now()).unwrap();
+
+        assert_eq!(*account.current_points(), 50.0);
+        assert_eq!(transaction.change(), -50.0);
+        assert_eq!(
+            transaction.order_number(),
+            redemption.redemption_id().to_string()
+        );
+        assert_eq!(redemption.status(), FulfilmentStatus::Pending);
+    }
+
+    #[test]
+    fn redemption_keeps_the_id_the_client_chose() {
+        let mut account = LoyaltyAccount::from(
+            DEFAULT_PROGRAMME_ID.to_string(),
+            "james".to_string(),
+            100.0,
+            vec![],
+        )
+        .unwrap();
+
+        let (redemption, transaction) = reward(1)
+            .redeem(&mut account, Some("RDM-client-1".to_string()), Utc::now())
+            .unwrap();
+
+        assert_eq!(redemption.redemption_id(), "RDM-client-1");
+        assert_eq!(transaction.order_number(), "RDM-client-1");
+        assert!(reward(1)
+            .redeem(&mut account, Some(" ".to_string()), Utc::now())
+            .is_err());
+    }
+
+    #[test]
+    fn out_of_stock_reward_cannot_be_redeemed() {
+        let mut account = LoyaltyAccount::from(
+            DEFAULT_PROGRAMME_ID.to_string(),
+            "james".to_string(),
+            100.0,
+            vec![],
+        )
+        .unwrap();
+
+        let result = reward(0).redeem(&mut account, None, Utc::now());
+
+        assert!(matches!(result, Err(LoyaltyErrors::RewardUnavailable(_))));
+        assert_eq!(*account.current_points(), 100.0);
+    }
+
+    #[test]
+    fn reward_outside_availability_window_cannot_be_redeemed() {
+        let reward = Reward::from(
+            DEFAULT_PROGRAMME_ID.to_string(),
+            "RWD-1".to_string(),
+            "Coffee".to_string(),
+            "A free coffee".to_string(),
+            50.0,
+            10,
+            Some(Utc::now() + Duration::days(1)),
+            None,
+        );
+
+        assert!(reward.ensure_available(Utc::now()).is_err());
+        assert!(reward
+            .ensure_available(Utc::now() + Duration::days(2))
+            .is_ok());
+    }
+
+    #[test]
+    fn fulfilment_status_cannot_move_backwards() {
+        let mut redemption = Redemption::from(
+            DEFAULT_PROGRAMME_ID.to_string(),
+            "RDM-1".to_string(),
+            "james".to_string(),
+            "RWD-1".to_string(),
+            50.0,
+            FulfilmentStatus::Dispatched,
+            Utc::now(),
+        );
+
+        assert!(redemption.update_status(FulfilmentStatus::Pending).is_err());
+        assert!(redemption
+            .update_status(FulfilmentStatus::Fulfilled)
+            .is_ok());
+    }
+}
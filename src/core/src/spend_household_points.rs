@@ -0,0 +1,149 @@
+use serde::Deserialize;
+
+use crate::{
+    clock::Clock,
+    households::{HouseholdDto, Households},
+    loyalty::{retrieve_active_account, AccountStatus, LoyaltyErrors, LoyaltyPoints},
+    validation::bind_path_id,
+};
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SpendHouseholdPointsCommand {
+    #[serde(default)]
+    programme_id: String,
+    #[serde(default)]
+    customer_id: String,
+    order_number: String,
+    spend: f32,
+    /// The owner approving a spend above the member's own limit.
+    approved_by: Option<String>,
+}
+
+impl SpendHouseholdPointsCommand {
+    pub fn bind_path(
+        &mut self,
+        programme_id: String,
+        customer_id: String,
+    ) -> Result<(), LoyaltyErrors> {
+        bind_path_id("programmeId", &mut self.programme_id, programme_id)?;
+        bind_path_id("customerId", &mut self.customer_id, customer_id)?;
+
+        Ok(())
+    }
+}
+
+pub struct SpendHouseholdPointsCommandHandler;
+
+impl SpendHouseholdPointsCommandHandler {
+    #[tracing::instrument(name = "handle_spend_household_points", skip(loyalty_points, households, clock, command), fields(programme_id=command.programme_id, customer_id=command.customer_id, order_number=command.order_number, spend=command.spend))]
+    pub async fn handle<T: LoyaltyPoints, H: Households, K: Clock>(
+        loyalty_points: &T,
+        households: &H,
+        clock: &K,
+        command: SpendHouseholdPointsCommand,
+    ) -> anyhow::Result<HouseholdDto, LoyaltyErrors> {
+        let account =
+            retrieve_active_account(loyalty_points, &command.programme_id, &command.customer_id)
+                .await?;
+
+        if account.status() != AccountStatus::Active {
+            return Err(LoyaltyErrors::AccountNotActive(format!(
+                "Account is {} and cannot spend points",
+                account.status()
+            )));
+        }
+
+        let mut household = households
+            .household_for_member(account.programme_id(), account.customer_id())
+            .await?;
+
+        let transaction = household.spend_points(
+            account.customer_id(),
+            &command.order_number,
+            command.spend,
+            command.approved_by.as_deref(),
+            clock.now(),
+        )?;
+
+        households
+            .spend(household.household_id(), account.customer_id(), transaction)
+            .await?;
+
+        Ok(household.into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Utc;
+
+    use crate::{
+        clock::SystemClock,
+        households::{Household, HouseholdMember, HouseholdRole, MockHouseholds},
+        loyalty::{LoyaltyAccount, MockLoyaltyPoints},
+        programmes::DEFAULT_PROGRAMME_ID,
+    };
+
+    use super::*;
+
+    #[tokio::test]
+    async fn on_valid_command_pool_should_be_debited() {
+        let mut loyalty_points = MockLoyaltyPoints::new();
+        loyalty_points
+            .expect_retrieve()
+            .returning(|programme_id, customer_id| {
+                LoyaltyAccount::from(
+                    programme_id.to_string(),
+                    customer_id.to_string(),
+                    0.0,
+                    vec![],
+                )
+            });
+
+        let mut households = MockHouseholds::new();
+        households
+            .expect_household_for_member()
+            .returning(|programme_id, customer_id| {
+                Ok(Household::from(
+                    programme_id.to_string(),
+                    "HH-1".to_string(),
+                    "Smiths".to_string(),
+                    100.0,
+                    vec![HouseholdMember::from(
+                        customer_id.to_string(),
+                        HouseholdRole::Owner,
+                        None,
+                        0.0,
+                    )],
+                    Utc::now(),
+                ))
+            });
+        households
+            .expect_spend()
+            .withf(|household_id, customer_id, transaction| {
+                household_id == "HH-1" && customer_id == "james" && transaction.change() == -40.0
+            })
+            .times(1)
+            .returning(|_, _, _| Ok(()));
+
+        let command = SpendHouseholdPointsCommand {
+            programme_id: DEFAULT_PROGRAMME_ID.to_string(),
+            customer_id: "james".to_string(),
+            order_number: "ORD1".to_string(),
+            spend: 40.0,
+            approved_by: None,
+        };
+
+        let result = SpendHouseholdPointsCommandHandler::handle(
+            &loyalty_points,
+            &households,
+            &SystemClock,
+            command,
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(result.points, 60.0);
+    }
+}
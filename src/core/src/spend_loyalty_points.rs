@@ -1,34 +1,80 @@
-
 use serde::Deserialize;
 
 use crate::{
-    loyalty::{LoyaltyErrors, LoyaltyPoints},
+    clock::Clock,
+    loyalty::{retrieve_active_account, LoyaltyErrors, LoyaltyPoints},
+    spend_risk::{FlaggedSpend, FlaggedSpends, RiskDecision, SpendRiskPolicy},
+    validation::bind_path_id,
     LoyaltyDto,
 };
 
 #[derive(Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct SpendLoyaltyPointsCommand {
+    #[serde(default)]
+    programme_id: String,
+    #[serde(default)]
     customer_id: String,
     order_number: String,
     spend: f32,
 }
 
+impl SpendLoyaltyPointsCommand {
+    pub fn bind_path(
+        &mut self,
+        programme_id: String,
+        customer_id: String,
+    ) -> Result<(), LoyaltyErrors> {
+        bind_path_id("programmeId", &mut self.programme_id, programme_id)?;
+        bind_path_id("customerId", &mut self.customer_id, customer_id)?;
+
+        Ok(())
+    }
+}
+
 pub struct SpendLoyaltyPointsCommandHandler;
 
 impl SpendLoyaltyPointsCommandHandler {
-    #[tracing::instrument(name = "handle_spend_loyalty_points", skip(loyalty_points, command), fields(customer_id=command.customer_id, order_number=command.order_number, spend=command.spend))]
-    pub async fn handle<T: LoyaltyPoints>(
-        loyalty_points: &T, 
+    #[tracing::instrument(name = "handle_spend_loyalty_points", skip(loyalty_points, flagged_spends, risk_policy, clock, command), fields(programme_id=command.programme_id, customer_id=command.customer_id, order_number=command.order_number, spend=command.spend))]
+    pub async fn handle<T: LoyaltyPoints, F: FlaggedSpends, K: Clock>(
+        loyalty_points: &T,
+        flagged_spends: &F,
+        risk_policy: &SpendRiskPolicy,
+        clock: &K,
         command: SpendLoyaltyPointsCommand,
     ) -> anyhow::Result<LoyaltyDto, LoyaltyErrors> {
-        let mut account = loyalty_points.retrieve(&command.customer_id).await?;
+        let mut account =
+            retrieve_active_account(loyalty_points, &command.programme_id, &command.customer_id)
+                .await?;
 
-        let transaction = account.spend_points(&command.order_number, &command.spend)?;
+        let now = clock.now();
 
-        loyalty_points
-            .add_transaction(&account, transaction)
-            .await?;
+        let reasons = match risk_policy.assess(&account, command.spend, now) {
+            RiskDecision::Block(reason) => return Err(LoyaltyErrors::SpendBlocked(reason)),
+            RiskDecision::Flag(reasons) => reasons,
+            RiskDecision::Allow => vec![],
+        };
+
+        let transaction = account.spend_points(&command.order_number, &command.spend, now)?;
+
+        if reasons.is_empty() {
+            loyalty_points
+                .add_transaction(&account, transaction)
+                .await?;
+        } else {
+            let flagged = FlaggedSpend::from(
+                command.programme_id,
+                command.customer_id,
+                command.order_number,
+                command.spend,
+                reasons,
+                now,
+            );
+
+            flagged_spends
+                .spend_flagged(&account, transaction, &flagged)
+                .await?;
+        }
 
         Ok(account.into())
     }
@@ -36,7 +82,14 @@ impl SpendLoyaltyPointsCommandHandler {
 
 #[cfg(test)]
 mod tests {
-    use crate::loyalty::{LoyaltyAccount, MockLoyaltyPoints};
+    use chrono::Utc;
+
+    use crate::{
+        clock::SystemClock,
+        loyalty::{LoyaltyAccount, LoyaltyAccountTransaction, MockLoyaltyPoints},
+        programmes::DEFAULT_PROGRAMME_ID,
+        spend_risk::{MockFlaggedSpends, RiskAction, SpendRiskRule},
+    };
 
     use super::*;
     use mockall::predicate;
@@ -50,10 +103,18 @@ mod tests {
         let mut loyalty_points = MockLoyaltyPoints::new();
         loyalty_points
             .expect_retrieve()
-            .with(predicate::eq(test_customer_id))
+            .with(
+                predicate::eq(DEFAULT_PROGRAMME_ID),
+                predicate::eq(test_customer_id),
+            )
             .times(1)
-            .returning(move |customer_id| {
-                LoyaltyAccount::from(customer_id.to_string(), customer_existing_points, vec![])
+            .returning(move |programme_id, customer_id| {
+                LoyaltyAccount::from(
+                    programme_id.to_string(),
+                    customer_id.to_string(),
+                    customer_existing_points,
+                    vec![],
+                )
             });
         loyalty_points
             .expect_add_transaction()
@@ -61,12 +122,20 @@ mod tests {
             .returning(|_, _| Ok(()));
 
         let command = SpendLoyaltyPointsCommand {
+            programme_id: DEFAULT_PROGRAMME_ID.to_string(),
             customer_id: test_customer_id.to_string(),
             order_number: "ORD123".to_string(),
             spend: customer_spend,
         };
 
-        let result = SpendLoyaltyPointsCommandHandler::handle(&loyalty_points, command).await;
+        let result = SpendLoyaltyPointsCommandHandler::handle(
+            &loyalty_points,
+            &MockFlaggedSpends::new(),
+            &SpendRiskPolicy::default(),
+            &SystemClock,
+            command,
+        )
+        .await;
 
         let account = result.unwrap();
 
@@ -83,20 +152,36 @@ mod tests {
         let mut loyalty_points = MockLoyaltyPoints::new();
         loyalty_points
             .expect_retrieve()
-            .with(predicate::eq(test_customer_id))
+            .with(
+                predicate::eq(DEFAULT_PROGRAMME_ID),
+                predicate::eq(test_customer_id),
+            )
             .times(1)
-            .returning(move |customer_id| {
-                LoyaltyAccount::from(customer_id.to_string(), customer_existing_points, vec![])
+            .returning(move |programme_id, customer_id| {
+                LoyaltyAccount::from(
+                    programme_id.to_string(),
+                    customer_id.to_string(),
+                    customer_existing_points,
+                    vec![],
+                )
             });
         loyalty_points.expect_add_transaction().times(0);
 
         let command = SpendLoyaltyPointsCommand {
+            programme_id: DEFAULT_PROGRAMME_ID.to_string(),
             customer_id: test_customer_id.to_string(),
             order_number: "ORD123".to_string(),
             spend: customer_spend,
         };
 
-        let result = SpendLoyaltyPointsCommandHandler::handle(&loyalty_points, command).await;
+        let result = SpendLoyaltyPointsCommandHandler::handle(
+            &loyalty_points,
+            &MockFlaggedSpends::new(),
+            &SpendRiskPolicy::default(),
+            &SystemClock,
+            command,
+        )
+        .await;
 
         assert!(result.is_err());
     }
@@ -109,19 +194,135 @@ mod tests {
         let mut loyalty_points = MockLoyaltyPoints::new();
         loyalty_points
             .expect_retrieve()
-            .with(predicate::eq(test_customer_id))
+            .with(
+                predicate::eq(DEFAULT_PROGRAMME_ID),
+                predicate::eq(test_customer_id),
+            )
             .times(1)
-            .returning(move |_| Err(LoyaltyErrors::AccountNotFound()));
+            .returning(move |_, _| Err(LoyaltyErrors::AccountNotFound()));
         loyalty_points.expect_add_transaction().times(0);
 
         let command = SpendLoyaltyPointsCommand {
+            programme_id: DEFAULT_PROGRAMME_ID.to_string(),
             customer_id: test_customer_id.to_string(),
             order_number: "ORD123".to_string(),
             spend: customer_spend,
         };
-        
-        let result = SpendLoyaltyPointsCommandHandler::handle(&loyalty_points, command).await;
+
+        let result = SpendLoyaltyPointsCommandHandler::handle(
+            &loyalty_points,
+            &MockFlaggedSpends::new(),
+            &SpendRiskPolicy::default(),
+            &SystemClock,
+            command,
+        )
+        .await;
 
         assert!(result.is_err());
     }
+
+    fn account_with_spends(spends: usize) -> LoyaltyAccount {
+        let transactions = (0..spends)
+            .map(|i| LoyaltyAccountTransaction::new(Utc::now(), format!("ORD{}", i), -10.0))
+            .collect();
+
+        LoyaltyAccount::from(
+            DEFAULT_PROGRAMME_ID.to_string(),
+            "james".to_string(),
+            1000.0,
+            transactions,
+        )
+        .unwrap()
+    }
+
+    fn spend_command(spend: f32) -> SpendLoyaltyPointsCommand {
+        SpendLoyaltyPointsCommand {
+            programme_id: DEFAULT_PROGRAMME_ID.to_string(),
+            customer_id: "james".to_string(),
+            order_number: "ORD123".to_string(),
+            spend,
+        }
+    }
+
+    #[tokio::test]
+    async fn on_blocked_spend_points_should_not_be_spent() {
+        let mut loyalty_points = MockLoyaltyPoints::new();
+        loyalty_points
+            .expect_retrieve()
+            .times(1)
+            .returning(|_, _| Ok(account_with_spends(3)));
+        loyalty_points.expect_add_transaction().times(0);
+        let policy = SpendRiskPolicy::new(vec![SpendRiskRule::MaxSpendsPerHour {
+            limit: 3,
+            action: RiskAction::Block,
+        }]);
+
+        let result = SpendLoyaltyPointsCommandHandler::handle(
+            &loyalty_points,
+            &MockFlaggedSpends::new(),
+            &policy,
+            &SystemClock,
+            spend_command(10.0),
+        )
+        .await;
+
+        assert!(matches!(result, Err(LoyaltyErrors::SpendBlocked(_))));
+    }
+
+    #[tokio::test]
+    async fn on_flagged_spend_points_should_be_spent_and_queued_for_review() {
+        let mut loyalty_points = MockLoyaltyPoints::new();
+        loyalty_points
+            .expect_retrieve()
+            .times(1)
+            .returning(|_, _| Ok(account_with_spends(3)));
+        loyalty_points.expect_add_transaction().times(0);
+        let mut flagged_spends = MockFlaggedSpends::new();
+        flagged_spends
+            .expect_spend_flagged()
+            .withf(|_, transaction, flagged| {
+                transaction.change() == -500.0
+                    && flagged.order_number() == "ORD123"
+                    && flagged.reasons().len() == 1
+            })
+            .times(1)
+            .returning(|_, _, _| Ok(()));
+
+        let result = SpendLoyaltyPointsCommandHandler::handle(
+            &loyalty_points,
+            &flagged_spends,
+            &SpendRiskPolicy::default(),
+            &SystemClock,
+            spend_command(500.0),
+        )
+        .await;
+
+        assert_eq!(result.unwrap().current_points, 500.0);
+    }
+
+    #[tokio::test]
+    async fn on_flagged_spend_when_review_cannot_be_queued_should_error() {
+        let mut loyalty_points = MockLoyaltyPoints::new();
+        loyalty_points
+            .expect_retrieve()
+            .times(1)
+            .returning(|_, _| Ok(account_with_spends(3)));
+        loyalty_points.expect_add_transaction().times(0);
+        let mut flagged_spends = MockFlaggedSpends::new();
+        flagged_spends
+            .expect_spend_flagged()
+            .times(1)
+            .returning(|_, _, _| Err(LoyaltyErrors::DatabaseError("unavailable".to_string())));
+
+        let result = SpendLoyaltyPointsCommandHandler::handle(
+            &loyalty_points,
+            &flagged_spends,
+            &SpendRiskPolicy::default(),
+            &SystemClock,
+            spend_command(500.0),
+        )
+        .await;
+
+        assert!(matches!(result, Err(LoyaltyErrors::DatabaseError(_))));
+    }
 }
@@ -0,0 +1,323 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::loyalty::{LoyaltyAccount, LoyaltyAccountTransaction, LoyaltyErrors};
+
+#[cfg(any(test, feature = "mocks"))]
+use mockall::automock;
+
+/// What happens to a spend that trips a rule.
+#[derive(Deserialize, Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RiskAction {
+    /// The spend goes through and is queued for an operator to review.
+    Flag,
+    /// The spend is rejected.
+    Block,
+}
+
+/// A single check run against a customer's recent spending before a spend is accepted.
+#[derive(Deserialize, Serialize, Clone, Debug)]
+#[serde(
+    tag = "rule",
+    rename_all = "camelCase",
+    rename_all_fields = "camelCase"
+)]
+pub enum SpendRiskRule {
+    /// Trips when the spend would be more than `limit` spends within the last hour.
+    MaxSpendsPerHour { limit: usize, action: RiskAction },
+    /// Trips when the spend would take the points spent in the last 24 hours above `limit`.
+    MaxPointsPerDay { limit: f32, action: RiskAction },
+    /// Trips when the spend is more than `multiplier` times the customer's average spend. Only
+    /// applies once the customer has at least `min_history` earlier spends to compare against.
+    UnusualSpendSize {
+        multiplier: f32,
+        min_history: usize,
+        action: RiskAction,
+    },
+}
+
+impl SpendRiskRule {
+    /// Returns the reason the rule tripped, if it did.
+    fn check(
+        &self,
+        spends: &[&LoyaltyAccountTransaction],
+        spend: f32,
+        at: DateTime<Utc>,
+    ) -> Option<String> {
+        match self {
+            SpendRiskRule::MaxSpendsPerHour { limit, .. } => {
+                let last_hour = spends
+                    .iter()
+                    .filter(|t| t.date > at - Duration::hours(1))
+                    .count();
+
+                (last_hour + 1 > *limit).then(|| format!("More than {} spends in an hour", limit))
+            }
+            SpendRiskRule::MaxPointsPerDay { limit, .. } => {
+                let last_day: f32 = spends
+                    .iter()
+                    .filter(|t| t.date > at - Duration::days(1))
+                    .map(|t| -t.change)
+                    .sum();
+
+                (last_day + spend > *limit)
+                    .then(|| format!("More than {} points spent in a day", limit))
+            }
+            SpendRiskRule::UnusualSpendSize {
+                multiplier,
+                min_history,
+                ..
+            } => {
+                if spends.is_empty() || spends.len() < *min_history {
+                    return None;
+                }
+
+                let average = spends.iter().map(|t| -t.change).sum::<f32>() / spends.len() as f32;
+
+                (spend > average * multiplier).then(|| {
+                    format!(
+                        "Spend of {} is more than {} times the average spend of {}",
+                        spend, multiplier, average
+                    )
+                })
+            }
+        }
+    }
+
+    fn action(&self) -> RiskAction {
+        match self {
+            SpendRiskRule::MaxSpendsPerHour { action, .. }
+            | SpendRiskRule::MaxPointsPerDay { action, .. }
+            | SpendRiskRule::UnusualSpendSize { action, .. } => *action,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub(crate) enum RiskDecision {
+    Allow,
+    Flag(Vec<String>),
+    Block(String),
+}
+
+/// The rules every spend is checked against. A spend tripping any blocking rule is rejected,
+/// one tripping only flagging rules goes through and is queued for review.
+#[derive(Clone, Debug)]
+pub struct SpendRiskPolicy {
+    rules: Vec<SpendRiskRule>,
+}
+
+impl SpendRiskPolicy {
+    pub fn new(rules: Vec<SpendRiskRule>) -> Self {
+        Self { rules }
+    }
+
+    pub(crate) fn assess(
+        &self,
+        account: &LoyaltyAccount,
+        spend: f32,
+        at: DateTime<Utc>,
+    ) -> RiskDecision {
+        // Transfers and refund reversals move points without the customer spending them
+        let spends: Vec<&LoyaltyAccountTransaction> = account
+            .transactions()
+            .iter()
+            .filter(|t| {
+                t.change < 0.0 && t.transfer_id.is_none() && !t.order_number.starts_with("REFUND-")
+            })
+            .collect();
+
+        let mut flags = vec![];
+
+        for rule in &self.rules {
+            let Some(reason) = rule.check(&spends, spend, at) else {
+                continue;
+            };
+
+            match rule.action() {
+                RiskAction::Block => return RiskDecision::Block(reason),
+                RiskAction::Flag => flags.push(reason),
+            }
+        }
+
+        match flags.is_empty() {
+            true => RiskDecision::Allow,
+            false => RiskDecision::Flag(flags),
+        }
+    }
+}
+
+impl Default for SpendRiskPolicy {
+    fn default() -> Self {
+        Self::new(vec![
+            SpendRiskRule::MaxSpendsPerHour {
+                limit: 10,
+                action: RiskAction::Block,
+            },
+            SpendRiskRule::MaxPointsPerDay {
+                limit: 5000.0,
+                action: RiskAction::Flag,
+            },
+            SpendRiskRule::UnusualSpendSize {
+                multiplier: 10.0,
+                min_history: 3,
+                action: RiskAction::Flag,
+            },
+        ])
+    }
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct FlaggedSpendDto {
+    pub programme_id: String,
+    pub customer_id: String,
+    pub order_number: String,
+    pub spend: f32,
+    pub reasons: Vec<String>,
+    pub flagged_at: DateTime<Utc>,
+}
+
+impl From<FlaggedSpend> for FlaggedSpendDto {
+    fn from(value: FlaggedSpend) -> Self {
+        FlaggedSpendDto {
+            programme_id: value.programme_id,
+            customer_id: value.customer_id,
+            order_number: value.order_number,
+            spend: value.spend,
+            reasons: value.reasons,
+            flagged_at: value.flagged_at,
+        }
+    }
+}
+
+/// A spend that went through but tripped one or more flagging rules.
+pub struct FlaggedSpend {
+    programme_id: String,
+    customer_id: String,
+    order_number: String,
+    spend: f32,
+    reasons: Vec<String>,
+    flagged_at: DateTime<Utc>,
+}
+
+impl FlaggedSpend {
+    pub fn from(
+        programme_id: String,
+        customer_id: String,
+        order_number: String,
+        spend: f32,
+        reasons: Vec<String>,
+        flagged_at: DateTime<Utc>,
+    ) -> Self {
+        Self {
+            programme_id,
+            customer_id,
+            order_number,
+            spend,
+            reasons,
+            flagged_at,
+        }
+    }
+
+    pub fn programme_id(&self) -> &str {
+        &self.programme_id
+    }
+    pub fn customer_id(&self) -> &str {
+        &self.customer_id
+    }
+    pub fn order_number(&self) -> &str {
+        &self.order_number
+    }
+    pub fn spend(&self) -> f32 {
+        self.spend
+    }
+    pub fn reasons(&self) -> &[String] {
+        &self.reasons
+    }
+    pub fn flagged_at(&self) -> DateTime<Utc> {
+        self.flagged_at
+    }
+}
+
+#[cfg_attr(any(test, feature = "mocks"), automock)]
+#[async_trait]
+pub trait FlaggedSpends {
+    /// Records the spend `transaction` against `account` and queues `flagged_spend` for review in
+    /// a single atomic write, so a flagged spend is never taken without being reviewed.
+    async fn spend_flagged(
+        &self,
+        account: &LoyaltyAccount,
+        transaction: LoyaltyAccountTransaction,
+        flagged_spend: &FlaggedSpend,
+    ) -> Result<(), LoyaltyErrors>;
+    async fn list_flagged_spends(
+        &self,
+        programme_id: &str,
+    ) -> Result<Vec<FlaggedSpend>, LoyaltyErrors>;
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::programmes::DEFAULT_PROGRAMME_ID;
+
+    use super::*;
+
+    fn account_with_spends(spends: &[(f32, Duration)]) -> LoyaltyAccount {
+        let now = Utc::now();
+        let transactions = spends
+            .iter()
+            .enumerate()
+            .map(|(i, (points, ago))| {
+                LoyaltyAccountTransaction::new(now - *ago, format!("ORD{}", i), -points)
+            })
+            .collect();
+
+        LoyaltyAccount::from(
+            DEFAULT_PROGRAMME_ID.to_string(),
+            "james".to_string(),
+            1000.0,
+            transactions,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn too_many_spends_in_an_hour_should_block() {
+        let account = account_with_spends(&[
+            (5.0, Duration::minutes(5)),
+            (5.0, Duration::minutes(10)),
+            (5.0, Duration::hours(2)),
+        ]);
+        let policy = SpendRiskPolicy::new(vec![SpendRiskRule::MaxSpendsPerHour {
+            limit: 2,
+            action: RiskAction::Block,
+        }]);
+
+        let decision = policy.assess(&account, 5.0, Utc::now());
+
+        assert!(matches!(decision, RiskDecision::Block(_)));
+    }
+
+    #[test]
+    fn unusually_large_spend_should_be_flagged() {
+        let account = account_with_spends(&[
+            (10.0, Duration::days(3)),
+            (20.0, Duration::days(2)),
+            (30.0, Duration::days(1)),
+        ]);
+
+        let decision = SpendRiskPolicy::default().assess(&account, 500.0, Utc::now());
+
+        assert!(matches!(decision, RiskDecision::Flag(reasons) if reasons.len() == 1));
+    }
+
+    #[test]
+    fn spend_within_limits_should_be_allowed() {
+        let account = account_with_spends(&[(100.0, Duration::hours(30))]);
+
+        let decision = SpendRiskPolicy::default().assess(&account, 500.0, Utc::now());
+
+        assert_eq!(decision, RiskDecision::Allow);
+    }
+}
@@ -0,0 +1,301 @@
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use tracing::info;
+
+use crate::{
+    clock::Clock,
+    loyalty::{retrieve_active_account, LoyaltyAccount, LoyaltyErrors, LoyaltyPoints},
+    validation::bind_path_id,
+    LoyaltyDto,
+};
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TransferPointsCommand {
+    #[serde(default)]
+    programme_id: String,
+    #[serde(default)]
+    from_customer_id: String,
+    to_customer_id: String,
+    transfer_id: String,
+    points: f32,
+}
+
+impl TransferPointsCommand {
+    pub fn bind_path(
+        &mut self,
+        programme_id: String,
+        customer_id: String,
+    ) -> Result<(), LoyaltyErrors> {
+        bind_path_id("programmeId", &mut self.programme_id, programme_id)?;
+        bind_path_id("fromCustomerId", &mut self.from_customer_id, customer_id)?;
+
+        Ok(())
+    }
+}
+
+/// Limits applied to every transfer, checked against the sending account.
+#[derive(Clone, Debug)]
+pub struct TransferLimits {
+    min_points: f32,
+    max_points: f32,
+    daily_cap: f32,
+}
+
+impl TransferLimits {
+    pub fn new(min_points: f32, max_points: f32, daily_cap: f32) -> Self {
+        Self {
+            min_points,
+            max_points,
+            daily_cap,
+        }
+    }
+
+    fn check(
+        &self,
+        account: &LoyaltyAccount,
+        points: f32,
+        at: DateTime<Utc>,
+    ) -> Result<(), LoyaltyErrors> {
+        if !points.is_finite() || points <= 0.0 {
+            return Err(LoyaltyErrors::InvalidValues(
+                "Transfer points must be greater than 0".to_string(),
+            ));
+        }
+
+        if points < self.min_points || points > self.max_points {
+            return Err(LoyaltyErrors::TransferLimitExceeded(format!(
+                "Transfers must be between {} and {} points",
+                self.min_points, self.max_points
+            )));
+        }
+
+        let today = at.date_naive();
+        let transferred_today: f32 = account
+            .transactions()
+            .iter()
+            .filter(|t| t.transfer_id.is_some() && t.change < 0.0 && t.date.date_naive() == today)
+            .map(|t| -t.change)
+            .sum();
+
+        if transferred_today + points > self.daily_cap {
+            return Err(LoyaltyErrors::TransferLimitExceeded(format!(
+                "Transfer would exceed the daily cap of {} points",
+                self.daily_cap
+            )));
+        }
+
+        Ok(())
+    }
+}
+
+impl Default for TransferLimits {
+    fn default() -> Self {
+        Self::new(1.0, 1000.0, 2000.0)
+    }
+}
+
+pub struct TransferPointsCommandHandler;
+
+impl TransferPointsCommandHandler {
+    #[tracing::instrument(name = "handle_transfer_points", skip(loyalty_points, limits, clock, command), fields(programme_id=command.programme_id, from_customer_id=command.from_customer_id, to_customer_id=command.to_customer_id, transfer_id=command.transfer_id, points=command.points))]
+    pub async fn handle<T: LoyaltyPoints, K: Clock>(
+        loyalty_points: &T,
+        limits: &TransferLimits,
+        clock: &K,
+        command: TransferPointsCommand,
+    ) -> anyhow::Result<LoyaltyDto, LoyaltyErrors> {
+        let mut source = retrieve_active_account(
+            loyalty_points,
+            &command.programme_id,
+            &command.from_customer_id,
+        )
+        .await?;
+
+        if source.has_transfer(&command.transfer_id) {
+            info!("Transfer {} already processed", command.transfer_id);
+
+            return Ok(source.into());
+        }
+
+        let now = clock.now();
+
+        limits.check(&source, command.points, now)?;
+
+        let mut target = retrieve_active_account(
+            loyalty_points,
+            source.programme_id(),
+            &command.to_customer_id,
+        )
+        .await?;
+
+        let (debit, credit) =
+            source.transfer_to(&mut target, &command.transfer_id, command.points, now)?;
+
+        loyalty_points
+            .transfer(&source, &target, debit, credit)
+            .await?;
+
+        Ok(source.into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        clock::SystemClock,
+        loyalty::{LoyaltyAccountTransaction, MockLoyaltyPoints},
+        programmes::DEFAULT_PROGRAMME_ID,
+    };
+
+    use super::*;
+    use mockall::predicate;
+
+    fn command(points: f32) -> TransferPointsCommand {
+        TransferPointsCommand {
+            programme_id: DEFAULT_PROGRAMME_ID.to_string(),
+            from_customer_id: "james".to_string(),
+            to_customer_id: "christina".to_string(),
+            transfer_id: "TRF123".to_string(),
+            points,
+        }
+    }
+
+    #[tokio::test]
+    async fn on_valid_command_points_should_be_transferred() {
+        let mut loyalty_points = MockLoyaltyPoints::new();
+        loyalty_points
+            .expect_retrieve()
+            .times(2)
+            .returning(|programme_id, customer_id| {
+                LoyaltyAccount::from(
+                    programme_id.to_string(),
+                    customer_id.to_string(),
+                    100.0,
+                    vec![],
+                )
+            });
+        loyalty_points
+            .expect_transfer()
+            .withf(|source, target, debit, credit| {
+                source.customer_id() == "james"
+                    && target.customer_id() == "christina"
+                    && *target.current_points() == 125.0
+                    && debit.change() == -25.0
+                    && credit.change() == 25.0
+                    && debit.transfer_id() == credit.transfer_id()
+            })
+            .times(1)
+            .returning(|_, _, _, _| Ok(()));
+
+        let result = TransferPointsCommandHandler::handle(
+            &loyalty_points,
+            &TransferLimits::default(),
+            &SystemClock,
+            command(25.0),
+        )
+        .await;
+
+        let account = result.unwrap();
+
+        assert_eq!(account.current_points, 75.0);
+        assert_eq!(account.transactions.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn on_repeated_transfer_id_should_not_transfer_again() {
+        let mut loyalty_points = MockLoyaltyPoints::new();
+        loyalty_points
+            .expect_retrieve()
+            .with(predicate::eq(DEFAULT_PROGRAMME_ID), predicate::eq("james"))
+            .times(1)
+            .returning(|programme_id, customer_id| {
+                let existing =
+                    LoyaltyAccountTransaction::new(Utc::now(), "TRF123".to_string(), -25.0)
+                        .with_transfer_id(Some("TRF123".to_string()));
+
+                LoyaltyAccount::from(
+                    programme_id.to_string(),
+                    customer_id.to_string(),
+                    75.0,
+                    vec![existing],
+                )
+            });
+        loyalty_points.expect_transfer().times(0);
+
+        let result = TransferPointsCommandHandler::handle(
+            &loyalty_points,
+            &TransferLimits::default(),
+            &SystemClock,
+            command(25.0),
+        )
+        .await;
+
+        assert_eq!(result.unwrap().current_points, 75.0);
+    }
+
+    #[tokio::test]
+    async fn on_transfer_above_daily_cap_should_error() {
+        let mut loyalty_points = MockLoyaltyPoints::new();
+        loyalty_points
+            .expect_retrieve()
+            .with(predicate::eq(DEFAULT_PROGRAMME_ID), predicate::eq("james"))
+            .times(1)
+            .returning(|programme_id, customer_id| {
+                let earlier =
+                    LoyaltyAccountTransaction::new(Utc::now(), "TRF001".to_string(), -40.0)
+                        .with_transfer_id(Some("TRF001".to_string()));
+
+                LoyaltyAccount::from(
+                    programme_id.to_string(),
+                    customer_id.to_string(),
+                    100.0,
+                    vec![earlier],
+                )
+            });
+        loyalty_points.expect_transfer().times(0);
+
+        let result = TransferPointsCommandHandler::handle(
+            &loyalty_points,
+            &TransferLimits::new(1.0, 50.0, 50.0),
+            &SystemClock,
+            command(25.0),
+        )
+        .await;
+
+        assert!(matches!(
+            result,
+            Err(LoyaltyErrors::TransferLimitExceeded(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn on_transfer_below_minimum_should_error() {
+        let mut loyalty_points = MockLoyaltyPoints::new();
+        loyalty_points
+            .expect_retrieve()
+            .times(1)
+            .returning(|programme_id, customer_id| {
+                LoyaltyAccount::from(
+                    programme_id.to_string(),
+                    customer_id.to_string(),
+                    100.0,
+                    vec![],
+                )
+            });
+        loyalty_points.expect_transfer().times(0);
+
+        let result = TransferPointsCommandHandler::handle(
+            &loyalty_points,
+            &TransferLimits::new(10.0, 50.0, 100.0),
+            &SystemClock,
+            command(5.0),
+        )
+        .await;
+
+        assert!(matches!(
+            result,
+            Err(LoyaltyErrors::TransferLimitExceeded(_))
+        ));
+    }
+}
@@ -0,0 +1,103 @@
+use std::fmt::Display;
+
+use serde::Serialize;
+
+use crate::loyalty::LoyaltyErrors;
+
+/// A single field that failed validation, and why.
+#[derive(Serialize, Clone, Debug, PartialEq, Eq)]
+pub struct FieldError {
+    pub field: String,
+    pub message: String,
+}
+
+/// Every field of an incoming event that failed validation. Events that fail validation can never
+/// succeed, so consumers dead-letter them rather than retrying.
+#[derive(Serialize, Clone, Debug, Default, PartialEq, Eq)]
+pub struct ValidationError {
+    pub errors: Vec<FieldError>,
+}
+
+impl ValidationError {
+    pub(crate) fn check(&mut self, valid: bool, field: &str, message: &str) {
+        if !valid {
+            self.errors.push(FieldError {
+                field: field.to_string(),
+                message: message.to_string(),
+            });
+        }
+    }
+
+    pub(crate) fn into_result(self) -> Result<(), ValidationError> {
+        match self.errors.is_empty() {
+            true => Ok(()),
+            false => Err(self),
+        }
+    }
+}
+
+impl Display for ValidationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let errors: Vec<String> = self
+            .errors
+            .iter()
+            .map(|error| format!("{} {}", error.field, error.message))
+            .collect();
+
+        write!(f, "Validation failed: {}", errors.join(", "))
+    }
+}
+
+impl std::error::Error for ValidationError {}
+
+/// Takes an id from the request path. A body that leaves it out is given the path's, one that
+/// names a different id is rejected so a request can only act on the resource its URL addresses.
+pub(crate) fn bind_path_id(
+    field: &str,
+    body: &mut String,
+    path: String,
+) -> Result<(), LoyaltyErrors> {
+    if !body.is_empty() && *body != path {
+        return Err(LoyaltyErrors::InvalidValues(format!(
+            "'{}' does not match the request path",
+            field
+        )));
+    }
+
+    *body = path;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn missing_id_is_taken_from_path() {
+        let mut body = String::new();
+
+        bind_path_id("programmeId", &mut body, "default".to_string()).unwrap();
+
+        assert_eq!(body, "default");
+    }
+
+    #[test]
+    fn matching_id_is_accepted() {
+        let mut body = "default".to_string();
+
+        let result = bind_path_id("programmeId", &mut body, "default".to_string());
+
+        assert!(result.is_ok());
+    }
+
+    #[test]
+    fn id_different_to_path_is_rejected() {
+        let mut body = "other".to_string();
+
+        let result = bind_path_id("programmeId", &mut body, "default".to_string());
+
+        assert!(matches!(result, Err(LoyaltyErrors::InvalidValues(_))));
+        assert_eq!(body, "other");
+    }
+}
@@ -2,14 +2,18 @@ use axum::http::StatusCode;
 use axum::Router;
 use axum::routing::get;
 use rand::Rng;
-use rdkafka::{
-    producer::{FutureProducer, FutureRecord},
-    ClientConfig,
-};
+use rdkafka::producer::{FutureProducer, FutureRecord};
 use serde::Serialize;
 use std::time::Duration;
 use tokio::signal;
-use tracing::info;
+use tracing::{debug, info, Instrument};
+
+use loyalty_adapters::{
+    configure_instrumentation, configure_kafka_client, shutdown_instrumentation,
+};
+use trace_context::{produce_span, trace_headers};
+
+mod trace_context;
 
 #[derive(Serialize)]
 pub struct OrderConfirmed {
@@ -19,32 +23,17 @@ pub struct OrderConfirmed {
 }
 
 async fn product_order_completed_message(customer_ids: Vec<String>) {
-    let username = std::env::var("KAFKA_USERNAME");
-    let password = std::env::var("KAFKA_PASSWORD");
     let broker = std::env::var("BROKER").expect("Broker should be set");
     let events_per_second = std::env::var("EVENTS_PER_SECOND").unwrap_or("2".to_string());
     let per_second = 1000 / events_per_second.parse::<u64>().unwrap_or(2);
 
-    let producer: FutureProducer = match username {
-        Ok(username) => {
-            // info!("Publishing to {}", &broker);
-            ClientConfig::new()
-                .set("bootstrap.servers", broker)
-                .set("security.protocol", "SASL_SSL")
-                .set("sasl.mechanisms", "PLAIN")
-                .set("sasl.username", username)
-                .set(
-                    "sasl.password",
-                    password.expect("Password should be set if user is"),
-                )
-                .create()
-                .expect("Producer creation failed")
-        }
-        Err(_) => ClientConfig::new()
-            .set("bootstrap.servers", broker)
-            .create()
-            .expect("Producer creation failed"),
-    };
+    let topic =
+        std::env::var("ORDER_COMPLETED_TOPIC").unwrap_or("order-completed".to_string());
+
+    let producer: FutureProducer = configure_kafka_client(&broker)
+        .expect("Kafka client should be configured")
+        .create()
+        .expect("Producer creation failed");
 
     loop {
         let customer_id_for_call = rand::thread_rng().gen_range(1..customer_ids.len());
@@ -64,13 +53,19 @@ async fn product_order_completed_message(customer_ids: Vec<String>) {
 
         let serialized = serde_json::to_string(&data).unwrap();
 
+        let span = produce_span(&topic);
+
+        debug!(parent: &span, "Producing {}", data.order_id);
+
         let _ = producer
             .send(
-                FutureRecord::to("order-completed")
+                FutureRecord::to(&topic)
                     .payload(&serialized)
-                    .key(customer),
+                    .key(customer)
+                    .headers(trace_headers(&span)),
                 Duration::from_secs(0),
             )
+            .instrument(span)
             .await;
 
         std::thread::sleep(Duration::from_millis(per_second));
@@ -98,7 +93,7 @@ async fn get_loyalty_points(api_endpoint: &str, customer_ids: Vec<String>) {
 
 #[tokio::main]
 async fn main() {
-    tracing_subscriber::fmt().init();
+    let _ = configure_instrumentation();
 
     let lambda_api_endpoint = std::env::var("LAMBDA_API_ENDPOINT");
     let fargate_api_endpoint = std::env::var("FARGATE_API_ENDPOINT");
@@ -188,6 +183,8 @@ async fn main() {
     }
 
     info!("Shutting down");
+
+    shutdown_instrumentation(Duration::from_secs(2));
 }
 
 fn get_customer_list() -> Vec<String> {
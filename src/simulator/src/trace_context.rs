@@ -0,0 +1,39 @@
+use std::collections::HashMap;
+
+use opentelemetry::global;
+use rdkafka::message::{Header, OwnedHeaders};
+use tracing::Span;
+use tracing_opentelemetry::OpenTelemetrySpanExt;
+
+/// The span an order is published in, standing in for the order service's producer span so the
+/// backend's processing of it continues a trace the way it would in production.
+pub fn produce_span(topic: &str) -> Span {
+    tracing::info_span!(
+        "produce",
+        otel.name = %format!("{} publish", topic),
+        otel.kind = "producer",
+        messaging.system = "kafka",
+        messaging.operation = "publish",
+        messaging.destination.name = %topic,
+    )
+}
+
+/// The headers carrying `span`'s context, as written by the global propagator: W3C `traceparent`,
+/// plus Datadog's `x-datadog-*` headers when traces are sent to Datadog. Empty when no tracer is
+/// configured, as the span then has no context to pass on.
+pub fn trace_headers(span: &Span) -> OwnedHeaders {
+    let mut carrier = HashMap::new();
+
+    global::get_text_map_propagator(|propagator| {
+        propagator.inject_context(&span.context(), &mut carrier)
+    });
+
+    carrier
+        .iter()
+        .fold(OwnedHeaders::new(), |headers, (key, value)| {
+            headers.insert(Header {
+                key,
+                value: Some(value),
+            })
+        })
+}
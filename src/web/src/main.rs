@@ -1,19 +1,46 @@
-use std::sync::Arc;
+use std::{sync::Arc, time::Duration};
 
+use anyhow::Context;
 use axum::{
-    extract::{Path, State},
-    http::StatusCode,
-    routing::{get, post},
+    async_trait,
+    extract::{FromRequestParts, Path, State},
+    http::{request::Parts, StatusCode},
+    middleware::from_extractor_with_state,
+    routing::{delete, get, post, put},
     Json, Router,
 };
 use axum_tracing_opentelemetry::middleware::{OtelAxumLayer, OtelInResponseLayer};
 use lambda_http::run;
-use loyalty_adapters::{configure_instrumentation, ApplicationAdapters, PostgresLoyaltyPoints};
+use loyalty_adapters::{
+    configure_instrumentation, ApplicationAdapters, KafkaEventPublisher, PostgresLoyaltyPoints,
+};
 use loyalty_core::{
-    LoyaltyDto, LoyaltyErrors, LoyaltyPoints,RetrieveLoyaltyAccountQueryHandler, SpendLoyaltyPointsCommand,
-    SpendLoyaltyPointsCommandHandler,
+    AddHouseholdMemberCommand, AddHouseholdMemberCommandHandler, AdjustPointsCommand,
+    AdjustPointsCommandHandler, AdjustmentPolicy, ApproveAdjustmentCommandHandler, CampaignDto,
+    Campaigns, ChangeAccountStatusCommand, ChangeAccountStatusCommandHandler,
+    CreateCampaignCommand, CreateCampaignCommandHandler, CreateHouseholdCommand,
+    CreateHouseholdCommandHandler, CreateProgrammeCommand, CreateProgrammeCommandHandler,
+    CreateRewardCommand, CreateRewardCommandHandler, CustomerProfileDto, CustomerProfiles,
+    DeleteCampaignCommandHandler, EraseCustomerDataCommand, EraseCustomerDataCommandHandler,
+    FlaggedSpendDto, FlaggedSpends, HouseholdDto, Households, ListCampaignsQueryHandler,
+    ListFlaggedSpendsQueryHandler, ListPendingAdjustmentsQueryHandler, ListProgrammesQueryHandler,
+    ListReferralsQueryHandler, ListRewardsQueryHandler, LoyaltyDto, LoyaltyErrors, LoyaltyPoints,
+    MergeLoyaltyAccountsCommand, MergeLoyaltyAccountsCommandHandler, PointsAdjustmentDto,
+    PointsAdjustments, ProgrammeDto, Programmes, PublishRewardOutboxHandler, RedeemRewardCommand,
+    RedeemRewardCommandHandler, RedemptionDto, ReferralCodeDto, ReferralDto, Referrals,
+    RefundOrderCommand, RefundOrderCommandHandler, RejectAdjustmentCommandHandler,
+    RemoveHouseholdMemberCommandHandler, RetrieveCampaignQueryHandler,
+    RetrieveCustomerProfileQueryHandler, RetrieveHouseholdQueryHandler,
+    RetrieveLoyaltyAccountQueryHandler, RetrieveProgrammeQueryHandler,
+    RetrieveReferralCodeQueryHandler, ReviewAdjustmentCommand, RewardDto, RewardOutbox, Rewards,
+    SpendHouseholdPointsCommand, SpendHouseholdPointsCommandHandler, SpendLoyaltyPointsCommand,
+    SpendLoyaltyPointsCommandHandler, SpendRiskPolicy, SystemClock, TransferLimits,
+    TransferPointsCommand, TransferPointsCommandHandler, UpdateCampaignCommand,
+    UpdateCampaignCommandHandler, UpdateCustomerProfileCommand,
+    UpdateCustomerProfileCommandHandler, UpdateProgrammeCommand, UpdateProgrammeCommandHandler,
+    UpdateRedemptionStatusCommand, UpdateRedemptionStatusCommandHandler, DEFAULT_PROGRAMME_ID,
 };
-use tracing::info;
+use tracing::{info, warn};
 
 pub enum HostingOption {
     Lambda,
@@ -46,8 +73,67 @@ impl HostingOption {
     }
 }
 
+/// Set by the API gateway to the id of the operator it authenticated, admin requests are rejected
+/// without it.
+const OPERATOR_ID_HEADER: &str = "x-operator-id";
+
+/// Set by the API gateway to the secret it shares with this service, `ADMIN_GATEWAY_SECRET`. Only
+/// requests carrying it came through the gateway, so only their operator id can be trusted.
+const GATEWAY_SECRET_HEADER: &str = "x-gateway-secret";
+
+/// The authenticated operator making an admin request. Every admin route requires one, admin
+/// requests are rejected unless they came through the gateway.
+struct Operator(String);
+
+#[async_trait]
+impl<T: LoyaltyPoints + Send + Sync> FromRequestParts<Arc<AppState<T>>> for Operator {
+    type Rejection = StatusCode;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &Arc<AppState<T>>,
+    ) -> Result<Self, Self::Rejection> {
+        let header = |name: &str| {
+            parts
+                .headers
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+                .filter(|value| !value.is_empty())
+        };
+
+        let from_gateway = match (&state.gateway_secret, header(GATEWAY_SECRET_HEADER)) {
+            (Some(secret), Some(given)) => constant_time_eq(secret.as_bytes(), given.as_bytes()),
+            _ => false,
+        };
+
+        if !from_gateway {
+            return Err(StatusCode::UNAUTHORIZED);
+        }
+
+        header(OPERATOR_ID_HEADER)
+            .map(|value| Operator(value.to_string()))
+            .ok_or(StatusCode::UNAUTHORIZED)
+    }
+}
+
+/// Compares every byte, so how long it takes doesn't reveal how much of the secret was guessed.
+fn constant_time_eq(expected: &[u8], given: &[u8]) -> bool {
+    expected.len() == given.len()
+        && expected
+            .iter()
+            .zip(given)
+            .fold(0, |diff, (a, b)| diff | (a ^ b))
+            == 0
+}
+
 pub struct AppState<T: LoyaltyPoints + Send + Sync> {
     pub application: ApplicationAdapters<T>,
+    pub transfer_limits: TransferLimits,
+    pub adjustment_policy: AdjustmentPolicy,
+    pub spend_risk_policy: SpendRiskPolicy,
+    pub event_publisher: KafkaEventPublisher,
+    pub clock: SystemClock,
+    pub gateway_secret: Option<String>,
 }
 
 #[tokio::main]
@@ -60,11 +146,142 @@ async fn main() -> Result<(), anyhow::Error> {
 
     let shared_state = Arc::new(AppState {
         application: application_adapters,
+        transfer_limits: get_transfer_limits(),
+        adjustment_policy: AdjustmentPolicy::new(
+            env_f32("ADJUSTMENT_APPROVAL_THRESHOLD").unwrap_or(500.0),
+        ),
+        spend_risk_policy: get_spend_risk_policy()?,
+        event_publisher: KafkaEventPublisher::new()?,
+        clock: SystemClock,
+        gateway_secret: get_gateway_secret(),
     });
 
+    tokio::spawn(publish_reward_outbox(shared_state.clone()));
+
+    // The gateway authenticates operators, admin requests from anywhere else are rejected
+    let admin_routes = Router::new()
+        .route(
+            "/admin/programmes",
+            post(create_programme).get(list_programmes),
+        )
+        .route(
+            "/admin/programmes/:programme_id",
+            get(get_programme).put(update_programme),
+        )
+        .route(
+            "/admin/programmes/:programme_id/loyalty/merge",
+            post(merge_loyalty_accounts),
+        )
+        .route(
+            "/admin/programmes/:programme_id/loyalty/:customer_id/status",
+            put(change_account_status),
+        )
+        .route(
+            "/admin/programmes/:programme_id/loyalty/:customer_id/erase",
+            post(erase_customer_data),
+        )
+        .route(
+            "/admin/programmes/:programme_id/adjustments",
+            post(adjust_points),
+        )
+        .route(
+            "/admin/programmes/:programme_id/adjustments/pending",
+            get(list_pending_adjustments),
+        )
+        .route(
+            "/admin/programmes/:programme_id/adjustments/:adjustment_id/approve",
+            post(approve_adjustment),
+        )
+        .route(
+            "/admin/programmes/:programme_id/adjustments/:adjustment_id/reject",
+            post(reject_adjustment),
+        )
+        .route(
+            "/admin/programmes/:programme_id/spends/flagged",
+            get(list_flagged_spends),
+        )
+        .route(
+            "/admin/programmes/:programme_id/campaigns",
+            post(create_campaign).get(list_campaigns),
+        )
+        .route(
+            "/admin/programmes/:programme_id/campaigns/:campaign_id",
+            get(get_campaign)
+                .put(update_campaign)
+                .delete(delete_campaign),
+        )
+        .route(
+            "/admin/programmes/:programme_id/households",
+            post(create_household),
+        )
+        .route(
+            "/admin/programmes/:programme_id/households/:household_id",
+            get(get_household),
+        )
+        .route(
+            "/admin/programmes/:programme_id/households/:household_id/members",
+            post(add_household_member),
+        )
+        .route(
+            "/admin/programmes/:programme_id/households/:household_id/members/:customer_id",
+            delete(remove_household_member),
+        )
+        .route(
+            "/admin/programmes/:programme_id/rewards",
+            post(create_reward),
+        )
+        .route(
+            "/admin/programmes/:programme_id/redemptions/:redemption_id/status",
+            put(update_redemption_status),
+        )
+        .route_layer(from_extractor_with_state::<Operator, _>(
+            shared_state.clone(),
+        ));
+
     let app = Router::new()
-        .route("/loyalty/:customer_id", get(get_loyalty_points))
-        .route("/loyalty/:customer_id/spend", post(spend_loyalty_points))
+        .route("/loyalty/:customer_id", get(get_default_loyalty_points))
+        .route(
+            "/loyalty/:customer_id/spend",
+            post(spend_default_loyalty_points),
+        )
+        .route(
+            "/programmes/:programme_id/loyalty/:customer_id",
+            get(get_loyalty_points),
+        )
+        .route(
+            "/programmes/:programme_id/loyalty/:customer_id/spend",
+            post(spend_loyalty_points),
+        )
+        .route(
+            "/programmes/:programme_id/loyalty/:customer_id/transfer",
+            post(transfer_loyalty_points),
+        )
+        .route(
+            "/programmes/:programme_id/loyalty/:customer_id/redeem",
+            post(redeem_reward),
+        )
+        .route(
+            "/programmes/:programme_id/loyalty/:customer_id/refund",
+            post(refund_order),
+        )
+        .route(
+            "/programmes/:programme_id/loyalty/:customer_id/profile",
+            get(get_customer_profile).put(update_customer_profile),
+        )
+        .route(
+            "/programmes/:programme_id/loyalty/:customer_id/referral-code",
+            get(get_referral_code),
+        )
+        .route(
+            "/programmes/:programme_id/loyalty/:customer_id/referrals",
+            get(list_referrals),
+        )
+        .route(
+            "/programmes/:programme_id/loyalty/:customer_id/household/spend",
+            post(spend_household_points),
+        )
+        .route("/programmes/:programme_id/rewards", get(list_rewards))
+        .merge(admin_routes)
         .layer(OtelInResponseLayer)
         .layer(OtelAxumLayer::default())
         .with_state(shared_state);
@@ -76,12 +293,19 @@ async fn main() -> Result<(), anyhow::Error> {
 }
 
 #[tracing::instrument(name = "get_loyalty_points", skip(state, path))]
-async fn get_loyalty_points<T: LoyaltyPoints + Send + Sync>(
+async fn get_loyalty_points<T: LoyaltyPoints + Households + Send + Sync>(
     State(state): State<Arc<AppState<T>>>,
-    path: Path<String>,
+    path: Path<(String, String)>,
 ) -> (StatusCode, Json<Option<LoyaltyDto>>) {
-    let loyalty_points =
-        RetrieveLoyaltyAccountQueryHandler::handle(&state.application.loyalty_points, path.0).await;
+    let (programme_id, customer_id) = path.0;
+
+    let loyalty_points = RetrieveLoyaltyAccountQueryHandler::handle(
+        &state.application.loyalty_points,
+        &state.application.loyalty_points,
+        programme_id,
+        customer_id,
+    )
+    .await;
 
     match loyalty_points {
         Ok(loyalty) => (StatusCode::OK, (Json(Some(loyalty)))),
@@ -89,21 +313,767 @@ async fn get_loyalty_points<T: LoyaltyPoints + Send + Sync>(
     }
 }
 
-#[tracing::instrument(name = "spend_loyalty_points", skip(state, payload), fields(span.kind="server"))]
-async fn spend_loyalty_points<T: LoyaltyPoints + Send + Sync>(
+#[tracing::instrument(name = "spend_loyalty_points", skip(state, path, payload), fields(span.kind="server"))]
+async fn spend_loyalty_points<T: LoyaltyPoints + FlaggedSpends + Send + Sync>(
     State(state): State<Arc<AppState<T>>>,
-    Json(payload): Json<SpendLoyaltyPointsCommand>,
+    path: Path<(String, String)>,
+    Json(mut payload): Json<SpendLoyaltyPointsCommand>,
 ) -> (StatusCode, Json<Option<LoyaltyDto>>) {
-    let loyalty_points =
-        SpendLoyaltyPointsCommandHandler::handle(&state.application.loyalty_points, payload).await;
+    let (programme_id, customer_id) = path.0;
+
+    if let Err(e) = payload.bind_path(programme_id, customer_id) {
+        return (error_status_code(&e), Json(None));
+    }
+
+    let loyalty_points = SpendLoyaltyPointsCommandHandler::handle(
+        &state.application.loyalty_points,
+        &state.application.loyalty_points,
+        &state.spend_risk_policy,
+        &state.clock,
+        payload,
+    )
+    .await;
 
     match loyalty_points {
         Ok(account) => (StatusCode::OK, (Json(Some(account)))),
-        Err(e) => match e {
-            LoyaltyErrors::PointsNotAvailable(_) => (StatusCode::BAD_REQUEST, (Json(None))),
-            LoyaltyErrors::AccountNotFound() => (StatusCode::NOT_FOUND, (Json(None))),
-            _ => (StatusCode::INTERNAL_SERVER_ERROR, (Json(None))),
-        },
+        Err(e) => (error_status_code(&e), Json(None)),
+    }
+}
+
+/// Kept for clients from before programmes were introduced, acts on the default programme.
+async fn get_default_loyalty_points<T: LoyaltyPoints + Households + Send + Sync>(
+    state: State<Arc<AppState<T>>>,
+    Path(customer_id): Path<String>,
+) -> (StatusCode, Json<Option<LoyaltyDto>>) {
+    get_loyalty_points(state, Path((DEFAULT_PROGRAMME_ID.to_string(), customer_id))).await
+}
+
+/// Kept for clients from before programmes were introduced, acts on the default programme.
+async fn spend_default_loyalty_points<T: LoyaltyPoints + FlaggedSpends + Send + Sync>(
+    state: State<Arc<AppState<T>>>,
+    Path(customer_id): Path<String>,
+    payload: Json<SpendLoyaltyPointsCommand>,
+) -> (StatusCode, Json<Option<LoyaltyDto>>) {
+    spend_loyalty_points(
+        state,
+        Path((DEFAULT_PROGRAMME_ID.to_string(), customer_id)),
+        payload,
+    )
+    .await
+}
+
+#[tracing::instrument(name = "transfer_loyalty_points", skip(state, path, payload), fields(span.kind="server"))]
+async fn transfer_loyalty_points<T: LoyaltyPoints + Send + Sync>(
+    State(state): State<Arc<AppState<T>>>,
+    path: Path<(String, String)>,
+    Json(mut payload): Json<TransferPointsCommand>,
+) -> (StatusCode, Json<Option<LoyaltyDto>>) {
+    let (programme_id, customer_id) = path.0;
+
+    if let Err(e) = payload.bind_path(programme_id, customer_id) {
+        return (error_status_code(&e), Json(None));
+    }
+
+    let result = TransferPointsCommandHandler::handle(
+        &state.application.loyalty_points,
+        &state.transfer_limits,
+        &state.clock,
+        payload,
+    )
+    .await;
+
+    match result {
+        Ok(account) => (StatusCode::OK, (Json(Some(account)))),
+        Err(e) => (error_status_code(&e), Json(None)),
+    }
+}
+
+#[tracing::instrument(name = "refund_order", skip(state, path, payload), fields(span.kind="server"))]
+async fn refund_order<T: LoyaltyPoints + Send + Sync>(
+    State(state): State<Arc<AppState<T>>>,
+    path: Path<(String, String)>,
+    Json(mut payload): Json<RefundOrderCommand>,
+) -> (StatusCode, Json<Option<LoyaltyDto>>) {
+    let (programme_id, customer_id) = path.0;
+
+    if let Err(e) = payload.bind_path(programme_id, customer_id) {
+        return (error_status_code(&e), Json(None));
+    }
+
+    let result =
+        RefundOrderCommandHandler::handle(&state.application.loyalty_points, &state.clock, payload)
+            .await;
+
+    match result {
+        Ok(account) => (StatusCode::OK, (Json(Some(account)))),
+        Err(e) => (error_status_code(&e), Json(None)),
+    }
+}
+
+#[tracing::instrument(name = "get_customer_profile", skip(state, path), fields(span.kind="server"))]
+async fn get_customer_profile<T: LoyaltyPoints + CustomerProfiles + Send + Sync>(
+    State(state): State<Arc<AppState<T>>>,
+    path: Path<(String, String)>,
+) -> (StatusCode, Json<Option<CustomerProfileDto>>) {
+    let (programme_id, customer_id) = path.0;
+
+    let result = RetrieveCustomerProfileQueryHandler::handle(
+        &state.application.loyalty_points,
+        programme_id,
+        customer_id,
+    )
+    .await;
+
+    match result {
+        Ok(profile) => (StatusCode::OK, Json(Some(profile))),
+        Err(e) => (error_status_code(&e), Json(None)),
+    }
+}
+
+#[tracing::instrument(name = "update_customer_profile", skip(state, path, payload), fields(span.kind="server"))]
+async fn update_customer_profile<T: LoyaltyPoints + CustomerProfiles + Send + Sync>(
+    State(state): State<Arc<AppState<T>>>,
+    path: Path<(String, String)>,
+    Json(mut payload): Json<UpdateCustomerProfileCommand>,
+) -> (StatusCode, Json<Option<CustomerProfileDto>>) {
+    let (programme_id, customer_id) = path.0;
+
+    if let Err(e) = payload.bind_path(programme_id, customer_id) {
+        return (error_status_code(&e), Json(None));
+    }
+
+    let result =
+        UpdateCustomerProfileCommandHandler::handle(&state.application.loyalty_points, payload)
+            .await;
+
+    match result {
+        Ok(profile) => (StatusCode::OK, Json(Some(profile))),
+        Err(e) => (error_status_code(&e), Json(None)),
+    }
+}
+
+#[tracing::instrument(name = "get_referral_code", skip(state, path), fields(span.kind="server"))]
+async fn get_referral_code<T: LoyaltyPoints + Referrals + Send + Sync>(
+    State(state): State<Arc<AppState<T>>>,
+    path: Path<(String, String)>,
+) -> (StatusCode, Json<Option<ReferralCodeDto>>) {
+    let (programme_id, customer_id) = path.0;
+
+    let result = RetrieveReferralCodeQueryHandler::handle(
+        &state.application.loyalty_points,
+        &state.application.loyalty_points,
+        programme_id,
+        customer_id,
+    )
+    .await;
+
+    match result {
+        Ok(referral_code) => (StatusCode::OK, Json(Some(referral_code))),
+        Err(e) => (error_status_code(&e), Json(None)),
+    }
+}
+
+#[tracing::instrument(name = "list_referrals", skip(state, path), fields(span.kind="server"))]
+async fn list_referrals<T: LoyaltyPoints + Referrals + Send + Sync>(
+    State(state): State<Arc<AppState<T>>>,
+    path: Path<(String, String)>,
+) -> (StatusCode, Json<Vec<ReferralDto>>) {
+    let (programme_id, customer_id) = path.0;
+
+    let result = ListReferralsQueryHandler::handle(
+        &state.application.loyalty_points,
+        programme_id,
+        customer_id,
+    )
+    .await;
+
+    match result {
+        Ok(referrals) => (StatusCode::OK, Json(referrals)),
+        Err(e) => (error_status_code(&e), Json(vec![])),
+    }
+}
+
+#[tracing::instrument(name = "spend_household_points", skip(state, path, payload), fields(span.kind="server"))]
+async fn spend_household_points<T: LoyaltyPoints + Households + Send + Sync>(
+    State(state): State<Arc<AppState<T>>>,
+    path: Path<(String, String)>,
+    Json(mut payload): Json<SpendHouseholdPointsCommand>,
+) -> (StatusCode, Json<Option<HouseholdDto>>) {
+    let (programme_id, customer_id) = path.0;
+
+    if let Err(e) = payload.bind_path(programme_id, customer_id) {
+        return (error_status_code(&e), Json(None));
+    }
+
+    let result = SpendHouseholdPointsCommandHandler::handle(
+        &state.application.loyalty_points,
+        &state.application.loyalty_points,
+        &state.clock,
+        payload,
+    )
+    .await;
+
+    match result {
+        Ok(household) => (StatusCode::OK, Json(Some(household))),
+        Err(e) => (error_status_code(&e), Json(None)),
+    }
+}
+
+#[tracing::instrument(name = "change_account_status", skip(state, path, payload), fields(span.kind="server"))]
+async fn change_account_status<T: LoyaltyPoints + Send + Sync>(
+    State(state): State<Arc<AppState<T>>>,
+    path: Path<(String, String)>,
+    Json(mut payload): Json<ChangeAccountStatusCommand>,
+) -> (StatusCode, Json<Option<LoyaltyDto>>) {
+    let (programme_id, customer_id) = path.0;
+
+    if let Err(e) = payload.bind_path(programme_id, customer_id) {
+        return (error_status_code(&e), Json(None));
+    }
+
+    let result =
+        ChangeAccountStatusCommandHandler::handle(&state.application.loyalty_points, payload).await;
+
+    match result {
+        Ok(account) => (StatusCode::OK, (Json(Some(account)))),
+        Err(e) => (error_status_code(&e), Json(None)),
+    }
+}
+
+#[tracing::instrument(name = "merge_loyalty_accounts", skip(state, path, payload), fields(span.kind="server"))]
+async fn merge_loyalty_accounts<T: LoyaltyPoints + Send + Sync>(
+    State(state): State<Arc<AppState<T>>>,
+    path: Path<String>,
+    Json(mut payload): Json<MergeLoyaltyAccountsCommand>,
+) -> (StatusCode, Json<Option<LoyaltyDto>>) {
+    if let Err(e) = payload.bind_path(path.0) {
+        return (error_status_code(&e), Json(None));
+    }
+
+    let result =
+        MergeLoyaltyAccountsCommandHandler::handle(&state.application.loyalty_points, payload)
+            .await;
+
+    match result {
+        Ok(account) => (StatusCode::OK, (Json(Some(account)))),
+        Err(e) => (error_status_code(&e), Json(None)),
+    }
+}
+
+#[tracing::instrument(name = "erase_customer_data", skip(state, path, payload), fields(span.kind="server"))]
+async fn erase_customer_data<T: LoyaltyPoints + Send + Sync>(
+    State(state): State<Arc<AppState<T>>>,
+    path: Path<(String, String)>,
+    Json(mut payload): Json<EraseCustomerDataCommand>,
+) -> (StatusCode, Json<Option<LoyaltyDto>>) {
+    let (programme_id, customer_id) = path.0;
+
+    if let Err(e) = payload.bind_path(programme_id, customer_id) {
+        return (error_status_code(&e), Json(None));
+    }
+
+    let result =
+        EraseCustomerDataCommandHandler::handle(&state.application.loyalty_points, payload).await;
+
+    match result {
+        Ok(account) => (StatusCode::OK, (Json(Some(account)))),
+        Err(e) => (error_status_code(&e), Json(None)),
+    }
+}
+
+#[tracing::instrument(name = "adjust_points", skip(state, path, operator_id, payload), fields(span.kind="server"))]
+async fn adjust_points<T: LoyaltyPoints + PointsAdjustments + Send + Sync>(
+    State(state): State<Arc<AppState<T>>>,
+    path: Path<String>,
+    Operator(operator_id): Operator,
+    Json(mut payload): Json<AdjustPointsCommand>,
+) -> (StatusCode, Json<Option<PointsAdjustmentDto>>) {
+    if let Err(e) = payload.bind_path(path.0) {
+        return (error_status_code(&e), Json(None));
+    }
+
+    payload.bind_operator(operator_id);
+
+    let result = AdjustPointsCommandHandler::handle(
+        &state.application.loyalty_points,
+        &state.application.loyalty_points,
+        &state.adjustment_policy,
+        &state.clock,
+        payload,
+    )
+    .await;
+
+    match result {
+        Ok(adjustment) => (StatusCode::OK, (Json(Some(adjustment)))),
+        Err(e) => (error_status_code(&e), Json(None)),
+    }
+}
+
+#[tracing::instrument(name = "list_pending_adjustments", skip(state, path), fields(span.kind="server"))]
+async fn list_pending_adjustments<T: LoyaltyPoints + PointsAdjustments + Send + Sync>(
+    State(state): State<Arc<AppState<T>>>,
+    path: Path<String>,
+) -> (StatusCode, Json<Vec<PointsAdjustmentDto>>) {
+    let result =
+        ListPendingAdjustmentsQueryHandler::handle(&state.application.loyalty_points, path.0).await;
+
+    match result {
+        Ok(adjustments) => (StatusCode::OK, Json(adjustments)),
+        Err(e) => (error_status_code(&e), Json(vec![])),
+    }
+}
+
+#[tracing::instrument(name = "list_flagged_spends", skip(state, path), fields(span.kind="server"))]
+async fn list_flagged_spends<T: LoyaltyPoints + FlaggedSpends + Send + Sync>(
+    State(state): State<Arc<AppState<T>>>,
+    path: Path<String>,
+) -> (StatusCode, Json<Vec<FlaggedSpendDto>>) {
+    let result =
+        ListFlaggedSpendsQueryHandler::handle(&state.application.loyalty_points, path.0).await;
+
+    match result {
+        Ok(flagged_spends) => (StatusCode::OK, Json(flagged_spends)),
+        Err(e) => (error_status_code(&e), Json(vec![])),
+    }
+}
+
+#[tracing::instrument(name = "approve_adjustment", skip(state, path, operator_id, payload), fields(span.kind="server"))]
+async fn approve_adjustment<T: LoyaltyPoints + PointsAdjustments + Send + Sync>(
+    State(state): State<Arc<AppState<T>>>,
+    path: Path<(String, String)>,
+    Operator(operator_id): Operator,
+    Json(mut payload): Json<ReviewAdjustmentCommand>,
+) -> (StatusCode, Json<Option<PointsAdjustmentDto>>) {
+    let (programme_id, adjustment_id) = path.0;
+
+    if let Err(e) = payload.bind_path(programme_id, adjustment_id) {
+        return (error_status_code(&e), Json(None));
+    }
+
+    payload.bind_operator(operator_id);
+
+    let result = ApproveAdjustmentCommandHandler::handle(
+        &state.application.loyalty_points,
+        &state.application.loyalty_points,
+        &state.clock,
+        payload,
+    )
+    .await;
+
+    match result {
+        Ok(adjustment) => (StatusCode::OK, (Json(Some(adjustment)))),
+        Err(e) => (error_status_code(&e), Json(None)),
+    }
+}
+
+#[tracing::instrument(name = "reject_adjustment", skip(state, path, operator_id, payload), fields(span.kind="server"))]
+async fn reject_adjustment<T: LoyaltyPoints + PointsAdjustments + Send + Sync>(
+    State(state): State<Arc<AppState<T>>>,
+    path: Path<(String, String)>,
+    Operator(operator_id): Operator,
+    Json(mut payload): Json<ReviewAdjustmentCommand>,
+) -> (StatusCode, Json<Option<PointsAdjustmentDto>>) {
+    let (programme_id, adjustment_id) = path.0;
+
+    if let Err(e) = payload.bind_path(programme_id, adjustment_id) {
+        return (error_status_code(&e), Json(None));
+    }
+
+    payload.bind_operator(operator_id);
+
+    let result = RejectAdjustmentCommandHandler::handle(
+        &state.application.loyalty_points,
+        &state.clock,
+        payload,
+    )
+    .await;
+
+    match result {
+        Ok(adjustment) => (StatusCode::OK, (Json(Some(adjustment)))),
+        Err(e) => (error_status_code(&e), Json(None)),
+    }
+}
+
+#[tracing::instrument(name = "create_campaign", skip(state, path, payload), fields(span.kind="server"))]
+async fn create_campaign<T: LoyaltyPoints + Campaigns + Send + Sync>(
+    State(state): State<Arc<AppState<T>>>,
+    path: Path<String>,
+    Json(mut payload): Json<CreateCampaignCommand>,
+) -> (StatusCode, Json<Option<CampaignDto>>) {
+    if let Err(e) = payload.bind_path(path.0) {
+        return (error_status_code(&e), Json(None));
+    }
+
+    let result =
+        CreateCampaignCommandHandler::handle(&state.application.loyalty_points, payload).await;
+
+    match result {
+        Ok(campaign) => (StatusCode::CREATED, (Json(Some(campaign)))),
+        Err(e) => (error_status_code(&e), Json(None)),
+    }
+}
+
+#[tracing::instrument(name = "list_campaigns", skip(state, path), fields(span.kind="server"))]
+async fn list_campaigns<T: LoyaltyPoints + Campaigns + Send + Sync>(
+    State(state): State<Arc<AppState<T>>>,
+    path: Path<String>,
+) -> (StatusCode, Json<Vec<CampaignDto>>) {
+    let result = ListCampaignsQueryHandler::handle(&state.application.loyalty_points, path.0).await;
+
+    match result {
+        Ok(campaigns) => (StatusCode::OK, Json(campaigns)),
+        Err(e) => (error_status_code(&e), Json(vec![])),
+    }
+}
+
+#[tracing::instrument(name = "get_campaign", skip(state, path), fields(span.kind="server"))]
+async fn get_campaign<T: LoyaltyPoints + Campaigns + Send + Sync>(
+    State(state): State<Arc<AppState<T>>>,
+    path: Path<(String, String)>,
+) -> (StatusCode, Json<Option<CampaignDto>>) {
+    let (programme_id, campaign_id) = path.0;
+
+    let result = RetrieveCampaignQueryHandler::handle(
+        &state.application.loyalty_points,
+        programme_id,
+        campaign_id,
+    )
+    .await;
+
+    match result {
+        Ok(campaign) => (StatusCode::OK, (Json(Some(campaign)))),
+        Err(e) => (error_status_code(&e), Json(None)),
+    }
+}
+
+#[tracing::instrument(name = "update_campaign", skip(state, path, payload), fields(span.kind="server"))]
+async fn update_campaign<T: LoyaltyPoints + Campaigns + Send + Sync>(
+    State(state): State<Arc<AppState<T>>>,
+    path: Path<(String, String)>,
+    Json(mut payload): Json<UpdateCampaignCommand>,
+) -> (StatusCode, Json<Option<CampaignDto>>) {
+    let (programme_id, campaign_id) = path.0;
+
+    if let Err(e) = payload.bind_path(programme_id, campaign_id) {
+        return (error_status_code(&e), Json(None));
+    }
+
+    let result =
+        UpdateCampaignCommandHandler::handle(&state.application.loyalty_points, payload).await;
+
+    match result {
+        Ok(campaign) => (StatusCode::OK, (Json(Some(campaign)))),
+        Err(e) => (error_status_code(&e), Json(None)),
+    }
+}
+
+#[tracing::instrument(name = "delete_campaign", skip(state, path), fields(span.kind="server"))]
+async fn delete_campaign<T: LoyaltyPoints + Campaigns + Send + Sync>(
+    State(state): State<Arc<AppState<T>>>,
+    path: Path<(String, String)>,
+) -> StatusCode {
+    let (programme_id, campaign_id) = path.0;
+
+    let result = DeleteCampaignCommandHandler::handle(
+        &state.application.loyalty_points,
+        programme_id,
+        campaign_id,
+    )
+    .await;
+
+    match result {
+        Ok(_) => StatusCode::NO_CONTENT,
+        Err(e) => error_status_code(&e),
+    }
+}
+
+#[tracing::instrument(name = "list_rewards", skip(state, path), fields(span.kind="server"))]
+async fn list_rewards<T: LoyaltyPoints + Rewards + Send + Sync>(
+    State(state): State<Arc<AppState<T>>>,
+    path: Path<String>,
+) -> (StatusCode, Json<Vec<RewardDto>>) {
+    let result = ListRewardsQueryHandler::handle(&state.application.loyalty_points, path.0).await;
+
+    match result {
+        Ok(rewards) => (StatusCode::OK, Json(rewards)),
+        Err(e) => (error_status_code(&e), Json(vec![])),
+    }
+}
+
+#[tracing::instrument(name = "create_household", skip(state, path, payload), fields(span.kind="server"))]
+async fn create_household<T: LoyaltyPoints + Households + Send + Sync>(
+    State(state): State<Arc<AppState<T>>>,
+    path: Path<String>,
+    Json(mut payload): Json<CreateHouseholdCommand>,
+) -> (StatusCode, Json<Option<HouseholdDto>>) {
+    if let Err(e) = payload.bind_path(path.0) {
+        return (error_status_code(&e), Json(None));
+    }
+
+    let result = CreateHouseholdCommandHandler::handle(
+        &state.application.loyalty_points,
+        &state.application.loyalty_points,
+        payload,
+    )
+    .await;
+
+    match result {
+        Ok(household) => (StatusCode::CREATED, Json(Some(household))),
+        Err(e) => (error_status_code(&e), Json(None)),
+    }
+}
+
+#[tracing::instrument(name = "get_household", skip(state, path), fields(span.kind="server"))]
+async fn get_household<T: LoyaltyPoints + Households + Send + Sync>(
+    State(state): State<Arc<AppState<T>>>,
+    path: Path<(String, String)>,
+) -> (StatusCode, Json<Option<HouseholdDto>>) {
+    let (programme_id, household_id) = path.0;
+
+    let result = RetrieveHouseholdQueryHandler::handle(
+        &state.application.loyalty_points,
+        programme_id,
+        household_id,
+    )
+    .await;
+
+    match result {
+        Ok(household) => (StatusCode::OK, Json(Some(household))),
+        Err(e) => (error_status_code(&e), Json(None)),
+    }
+}
+
+#[tracing::instrument(name = "add_household_member", skip(state, path, payload), fields(span.kind="server"))]
+async fn add_household_member<T: LoyaltyPoints + Households + Send + Sync>(
+    State(state): State<Arc<AppState<T>>>,
+    path: Path<(String, String)>,
+    Json(mut payload): Json<AddHouseholdMemberCommand>,
+) -> (StatusCode, Json<Option<HouseholdDto>>) {
+    let (programme_id, household_id) = path.0;
+
+    if let Err(e) = payload.bind_path(programme_id, household_id) {
+        return (error_status_code(&e), Json(None));
+    }
+
+    let result = AddHouseholdMemberCommandHandler::handle(
+        &state.application.loyalty_points,
+        &state.application.loyalty_points,
+        payload,
+    )
+    .await;
+
+    match result {
+        Ok(household) => (StatusCode::OK, Json(Some(household))),
+        Err(e) => (error_status_code(&e), Json(None)),
+    }
+}
+
+#[tracing::instrument(name = "remove_household_member", skip(state, path), fields(span.kind="server"))]
+async fn remove_household_member<T: LoyaltyPoints + Households + Send + Sync>(
+    State(state): State<Arc<AppState<T>>>,
+    path: Path<(String, String, String)>,
+) -> (StatusCode, Json<Option<HouseholdDto>>) {
+    let (programme_id, household_id, customer_id) = path.0;
+
+    let result = RemoveHouseholdMemberCommandHandler::handle(
+        &state.application.loyalty_points,
+        programme_id,
+        household_id,
+        customer_id,
+    )
+    .await;
+
+    match result {
+        Ok(household) => (StatusCode::OK, Json(Some(household))),
+        Err(e) => (error_status_code(&e), Json(None)),
+    }
+}
+
+#[tracing::instrument(name = "create_reward", skip(state, path, payload), fields(span.kind="server"))]
+async fn create_reward<T: LoyaltyPoints + Rewards + Send + Sync>(
+    State(state): State<Arc<AppState<T>>>,
+    path: Path<String>,
+    Json(mut payload): Json<CreateRewardCommand>,
+) -> (StatusCode, Json<Option<RewardDto>>) {
+    if let Err(e) = payload.bind_path(path.0) {
+        return (error_status_code(&e), Json(None));
+    }
+
+    let result =
+        CreateRewardCommandHandler::handle(&state.application.loyalty_points, payload).await;
+
+    match result {
+        Ok(reward) => (StatusCode::CREATED, (Json(Some(reward)))),
+        Err(e) => (error_status_code(&e), Json(None)),
+    }
+}
+
+#[tracing::instrument(name = "redeem_reward", skip(state, path, payload), fields(span.kind="server"))]
+async fn redeem_reward<T: LoyaltyPoints + Rewards + RewardOutbox + Send + Sync>(
+    State(state): State<Arc<AppState<T>>>,
+    path: Path<(String, String)>,
+    Json(mut payload): Json<RedeemRewardCommand>,
+) -> (StatusCode, Json<Option<RedemptionDto>>) {
+    let (programme_id, customer_id) = path.0;
+
+    if let Err(e) = payload.bind_path(programme_id, customer_id) {
+        return (error_status_code(&e), Json(None));
+    }
+
+    let result = RedeemRewardCommandHandler::handle(
+        &state.application.loyalty_points,
+        &state.application.loyalty_points,
+        &state.clock,
+        payload,
+    )
+    .await;
+
+    match result {
+        Ok(redemption) => {
+            // Published straight away where possible, the background publisher retries the rest
+            let _ = PublishRewardOutboxHandler::handle(
+                &state.application.loyalty_points,
+                &state.event_publisher,
+                OUTBOX_BATCH_SIZE,
+            )
+            .await;
+
+            (StatusCode::OK, (Json(Some(redemption))))
+        }
+        Err(e) => (error_status_code(&e), Json(None)),
+    }
+}
+
+/// How many redemption events are published from the outbox at a time.
+const OUTBOX_BATCH_SIZE: i64 = 100;
+
+/// Publishes redemption events left in the outbox, by a publish that failed or a process that
+/// stopped after committing, every `REWARD_OUTBOX_INTERVAL_SECONDS` (5 by default).
+async fn publish_reward_outbox<T: LoyaltyPoints + RewardOutbox + Send + Sync>(
+    state: Arc<AppState<T>>,
+) {
+    let interval = std::env::var("REWARD_OUTBOX_INTERVAL_SECONDS")
+        .ok()
+        .and_then(|seconds| seconds.parse().ok())
+        .map(Duration::from_secs)
+        .unwrap_or(Duration::from_secs(5));
+
+    loop {
+        tokio::time::sleep(interval).await;
+
+        let _ = PublishRewardOutboxHandler::handle(
+            &state.application.loyalty_points,
+            &state.event_publisher,
+            OUTBOX_BATCH_SIZE,
+        )
+        .await;
+    }
+}
+
+#[tracing::instrument(name = "update_redemption_status", skip(state, path, payload), fields(span.kind="server"))]
+async fn update_redemption_status<T: LoyaltyPoints + Rewards + Send + Sync>(
+    State(state): State<Arc<AppState<T>>>,
+    path: Path<(String, String)>,
+    Json(mut payload): Json<UpdateRedemptionStatusCommand>,
+) -> (StatusCode, Json<Option<RedemptionDto>>) {
+    let (programme_id, redemption_id) = path.0;
+
+    if let Err(e) = payload.bind_path(programme_id, redemption_id) {
+        return (error_status_code(&e), Json(None));
+    }
+
+    let result =
+        UpdateRedemptionStatusCommandHandler::handle(&state.application.loyalty_points, payload)
+            .await;
+
+    match result {
+        Ok(redemption) => (StatusCode::OK, (Json(Some(redemption)))),
+        Err(e) => (error_status_code(&e), Json(None)),
+    }
+}
+
+#[tracing::instrument(name = "create_programme", skip(state, payload), fields(span.kind="server"))]
+async fn create_programme<T: LoyaltyPoints + Programmes + Send + Sync>(
+    State(state): State<Arc<AppState<T>>>,
+    Json(payload): Json<CreateProgrammeCommand>,
+) -> (StatusCode, Json<Option<ProgrammeDto>>) {
+    let result =
+        CreateProgrammeCommandHandler::handle(&state.application.loyalty_points, payload).await;
+
+    match result {
+        Ok(programme) => (StatusCode::CREATED, Json(Some(programme))),
+        Err(e) => (error_status_code(&e), Json(None)),
+    }
+}
+
+#[tracing::instrument(name = "list_programmes", skip(state), fields(span.kind="server"))]
+async fn list_programmes<T: LoyaltyPoints + Programmes + Send + Sync>(
+    State(state): State<Arc<AppState<T>>>,
+) -> (StatusCode, Json<Vec<ProgrammeDto>>) {
+    let result = ListProgrammesQueryHandler::handle(&state.application.loyalty_points).await;
+
+    match result {
+        Ok(programmes) => (StatusCode::OK, Json(programmes)),
+        Err(e) => (error_status_code(&e), Json(vec![])),
+    }
+}
+
+#[tracing::instrument(name = "get_programme", skip(state, path), fields(span.kind="server"))]
+async fn get_programme<T: LoyaltyPoints + Programmes + Send + Sync>(
+    State(state): State<Arc<AppState<T>>>,
+    path: Path<String>,
+) -> (StatusCode, Json<Option<ProgrammeDto>>) {
+    let result =
+        RetrieveProgrammeQueryHandler::handle(&state.application.loyalty_points, path.0).await;
+
+    match result {
+        Ok(programme) => (StatusCode::OK, Json(Some(programme))),
+        Err(e) => (error_status_code(&e), Json(None)),
+    }
+}
+
+#[tracing::instrument(name = "update_programme", skip(state, path, payload), fields(span.kind="server"))]
+async fn update_programme<T: LoyaltyPoints + Programmes + Send + Sync>(
+    State(state): State<Arc<AppState<T>>>,
+    path: Path<String>,
+    Json(mut payload): Json<UpdateProgrammeCommand>,
+) -> (StatusCode, Json<Option<ProgrammeDto>>) {
+    if let Err(e) = payload.bind_path(path.0) {
+        return (error_status_code(&e), Json(None));
+    }
+
+    let result =
+        UpdateProgrammeCommandHandler::handle(&state.application.loyalty_points, payload).await;
+
+    match result {
+        Ok(programme) => (StatusCode::OK, Json(Some(programme))),
+        Err(e) => (error_status_code(&e), Json(None)),
+    }
+}
+
+fn error_status_code(error: &LoyaltyErrors) -> StatusCode {
+    match error {
+        LoyaltyErrors::InvalidValues(_)
+        | LoyaltyErrors::PointsNotAvailable(_)
+        | LoyaltyErrors::TransferLimitExceeded(_) => StatusCode::BAD_REQUEST,
+        LoyaltyErrors::AccountNotFound()
+        | LoyaltyErrors::AdjustmentNotFound(_)
+        | LoyaltyErrors::CampaignNotFound(_)
+        | LoyaltyErrors::RewardNotFound(_)
+        | LoyaltyErrors::RedemptionNotFound(_)
+        | LoyaltyErrors::ProfileNotFound(_)
+        | LoyaltyErrors::ReferralNotFound(_)
+        | LoyaltyErrors::HouseholdNotFound(_)
+        | LoyaltyErrors::ProgrammeNotFound(_) => StatusCode::NOT_FOUND,
+        LoyaltyErrors::SpendNotPermitted(_) | LoyaltyErrors::SpendBlocked(_) => {
+            StatusCode::FORBIDDEN
+        }
+        LoyaltyErrors::AccountNotActive(_)
+        | LoyaltyErrors::TransactionExistsForOrder(_)
+        | LoyaltyErrors::RewardUnavailable(_)
+        | LoyaltyErrors::RedemptionExists(_) => StatusCode::CONFLICT,
+        LoyaltyErrors::DatabaseError(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        LoyaltyErrors::PublishError(_) => StatusCode::SERVICE_UNAVAILABLE,
     }
 }
 
@@ -145,6 +1115,46 @@ async fn shutdown_signal() {
     }
 }
 
+fn get_transfer_limits() -> TransferLimits {
+    TransferLimits::new(
+        env_f32("TRANSFER_MIN_POINTS").unwrap_or(1.0),
+        env_f32("TRANSFER_MAX_POINTS").unwrap_or(1000.0),
+        env_f32("TRANSFER_DAILY_CAP").unwrap_or(2000.0),
+    )
+}
+
+/// Reads the spend risk rules from a JSON array in `SPEND_RISK_RULES`, for example
+/// `[{"rule": "maxSpendsPerHour", "limit": 5, "action": "Block"}]`.
+fn get_spend_risk_policy() -> Result<SpendRiskPolicy, anyhow::Error> {
+    let Ok(rules) = std::env::var("SPEND_RISK_RULES") else {
+        return Ok(SpendRiskPolicy::default());
+    };
+
+    let rules =
+        serde_json::from_str(&rules).context("'SPEND_RISK_RULES' is not a JSON array of rules")?;
+
+    Ok(SpendRiskPolicy::new(rules))
+}
+
+/// Admin routes reject every request until `ADMIN_GATEWAY_SECRET` is set.
+fn get_gateway_secret() -> Option<String> {
+    let secret = std::env::var("ADMIN_GATEWAY_SECRET")
+        .ok()
+        .filter(|secret| !secret.is_empty());
+
+    if secret.is_none() {
+        warn!("'ADMIN_GATEWAY_SECRET' is not set, admin requests will be rejected");
+    }
+
+    secret
+}
+
+fn env_f32(name: &str) -> Option<f32> {
+    std::env::var(name)
+        .ok()
+        .and_then(|value| value.parse().ok())
+}
+
 fn get_hosting_option() -> HostingOption {
     match std::env::var("LAMBDA_TASK_ROOT") {
         Ok(_) => HostingOption::Lambda,